@@ -0,0 +1,68 @@
+//! Peak heap use of the blood type analysis, which borrows the one-hot vectors
+//!
+//! Runs once rather than under criterion, as a counting allocator measures the peak of a
+//! single run: `cargo bench --bench analysis_memory`
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use fhe_mini_project::computations::run_biosample_analysis;
+use fhe_mini_project::data_generator::generate_biosample_data;
+use fhe_mini_project::encryption::{encrypt_biosample_data, BiosampleFHE, ScaleConfig};
+use fhe_mini_project::fields;
+
+/// Number of records analysed
+const NUM_RECORDS: usize = 1000;
+
+/// Allocator that keeps track of the largest number of bytes allocated at once
+struct PeakAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+fn main() {
+    let fhe = BiosampleFHE::new();
+    let records = generate_biosample_data(NUM_RECORDS, 42).unwrap();
+    let (mut encrypted_data, _) =
+        encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+    encrypted_data
+        .retain(|key, _| key == fields::CHOLESTEROL || key.starts_with(fields::BLOOD_TYPE_PREFIX));
+    let blood_type_bytes: usize = encrypted_data
+        .iter()
+        .filter(|(key, _)| key.starts_with(fields::BLOOD_TYPE_PREFIX))
+        .flat_map(|(_, vector)| &vector.data)
+        .map(Vec::len)
+        .sum();
+
+    // Measure the analysis alone, on top of the encrypted data it borrows
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let results = run_biosample_analysis(&encrypted_data, fhe.server_key()).unwrap();
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+    drop(results);
+
+    println!("Blood type analysis of {} records", NUM_RECORDS);
+    println!(
+        "  one-hot blood type vectors: {:>12} bytes",
+        blood_type_bytes
+    );
+    println!("  peak heap use of analysis:  {:>12} bytes", peak);
+}
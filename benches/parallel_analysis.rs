@@ -0,0 +1,55 @@
+//! Benchmarks of the full analysis on thread pools of different sizes
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use fhe_mini_project::computations::run_biosample_analysis;
+use fhe_mini_project::data_generator::generate_biosample_data;
+use fhe_mini_project::encryption::{encrypt_biosample_data, BiosampleFHE, ScaleConfig};
+
+/// Number of records analysed by each benchmark
+const NUM_RECORDS: usize = 16;
+
+fn bench_parallel_analysis(c: &mut Criterion) {
+    let fhe = BiosampleFHE::new();
+    let records = generate_biosample_data(NUM_RECORDS, 42).unwrap();
+    let (encrypted_data, context) =
+        encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+    // A single thread against one per core, which must decrypt to the same results
+    let max_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let pools: Vec<rayon::ThreadPool> = [1, max_threads]
+        .iter()
+        .map(|&threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+        })
+        .collect();
+    let analyses: Vec<_> = pools
+        .iter()
+        .map(|pool| {
+            pool.install(|| run_biosample_analysis(&encrypted_data, fhe.server_key()))
+                .unwrap()
+                .decrypt(&fhe, &context)
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(analyses[0], analyses[1]);
+
+    // Each analysis takes minutes on one thread, so keep the number of samples at
+    // criterion's minimum
+    let mut group = c.benchmark_group(format!("analysis_{}_records", NUM_RECORDS));
+    group.sample_size(10);
+    for pool in &pools {
+        group.bench_function(format!("{}_threads", pool.current_num_threads()), |b| {
+            b.iter(|| {
+                pool.install(|| run_biosample_analysis(&encrypted_data, fhe.server_key()))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parallel_analysis);
+criterion_main!(benches);
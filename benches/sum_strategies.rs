@@ -0,0 +1,34 @@
+//! Benchmarks of the strategies for summing an encrypted vector
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use fhe_mini_project::computations::{compute_encrypted_sum_with_strategy, SumStrategy};
+use fhe_mini_project::encryption::BiosampleFHE;
+
+/// Number of ciphertexts summed by each benchmark
+const NUM_CIPHERTEXTS: usize = 500;
+
+fn bench_sum_strategies(c: &mut Criterion) {
+    let fhe = BiosampleFHE::new();
+    let ages: Vec<f64> = (0..NUM_CIPHERTEXTS).map(|i| (18 + i % 70) as f64).collect();
+    let encrypted_ages = fhe.encrypt_f64_vector(&ages, 1.0).unwrap();
+
+    // Each sum takes seconds, so keep the number of samples at criterion's minimum
+    let mut group = c.benchmark_group(format!("sum_{}_ciphertexts", NUM_CIPHERTEXTS));
+    group.sample_size(10);
+    for strategy in [
+        SumStrategy::MultiOperand,
+        SumStrategy::Sequential,
+        SumStrategy::Tree,
+    ] {
+        group.bench_function(format!("{:?}", strategy), |b| {
+            b.iter(|| {
+                compute_encrypted_sum_with_strategy(&encrypted_ages, fhe.server_key(), strategy)
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sum_strategies);
+criterion_main!(benches);
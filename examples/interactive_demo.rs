@@ -1,7 +1,12 @@
 use fhe_mini_project::{
-    computations::compute_encrypted_mean,
-    data_generator::generate_biosample_data,
-    encryption::BiosampleFHE,
+    computations::{
+        compute_encrypted_mean, compute_encrypted_monthly_means, compute_encrypted_risk_scores,
+        compute_encrypted_threshold_count, verify, verify_computation_with_context, RiskModel,
+        Tolerance, VerificationReport, HIGH_CHOLESTEROL_THRESHOLD,
+    },
+    data_generator::{collection_day, generate_biosample_data},
+    encryption::{encrypt_biosample_data, BiosampleFHE, ScaleConfig},
+    fields,
     visualization::{plot_comparison, visualize_fhe_workflow},
 };
 
@@ -11,6 +16,9 @@ use std::io::{self, Write};
 use std::path::Path;
 use std::time::Instant;
 
+/// Length of the months the average glucose is charted over, in days
+const DAYS_PER_MONTH: i64 = 30;
+
 /// Prints a formatted header with the given text centered.
 ///
 /// The header consists of a line of '=' characters, the centered text,
@@ -33,6 +41,22 @@ fn pause() {
     io::stdin().read_line(&mut buffer).unwrap();
 }
 
+/// Prints the outcome of comparing a decrypted result with its plaintext counterpart.
+///
+/// The relative error is left out when the plaintext result is zero, since it
+/// would be undefined.
+fn print_verification(report: &VerificationReport) {
+    match report.rel_error {
+        Some(rel_error) => println!("Error: {:.4} ({:.2}%)", report.abs_error, rel_error * 100.0),
+        None => println!("Error: {:.4}", report.abs_error),
+    }
+    println!(
+        "Verification: {} (tolerance {:.4})",
+        if report.passed { "PASS" } else { "FAIL" },
+        report.tolerance_used
+    );
+}
+
 /// Runs an interactive demonstration of Fully Homomorphic Encryption (FHE) for biosample data analysis.
 ///
 /// This demo guides users through the process of:
@@ -41,6 +65,7 @@ fn pause() {
 /// 3. Performing statistical analysis on the encrypted data
 /// 4. Decrypting only the results (not the original data)
 /// 5. Verifying the accuracy against plaintext computations
+/// 6. Revealing a single record with the patient's consent
 ///
 /// The demo includes visualizations of the results and workflow, saved to the 'outputs' directory.
 ///
@@ -58,6 +83,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("3. Perform statistical analysis on the encrypted data");
     println!("4. Decrypt only the results (not the original data)");
     println!("5. Verify the accuracy against plaintext computations");
+    println!("6. Reveal a single record with the patient's consent");
 
     pause();
 
@@ -101,23 +127,20 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("\nEncrypting numerical data...");
     let start = Instant::now();
 
-    // Extract and scale numerical data
-    let scale = 100.0; // Scale for floating-point values
-
-    // Encrypt age
-    let ages: Vec<f64> = records.iter().map(|r| r.age as f64).collect();
-    let encrypted_age = fhe.encrypt_f64_vector(&ages, scale);
+    // Encrypt every field; the context remembers each field's scale and length
+    let (encrypted_data, context) =
+        encrypt_biosample_data(&fhe, &records, &ScaleConfig::default())?;
+    let encrypted_age = &encrypted_data[fields::AGE];
     println!("Encrypted ages");
-
-    // Encrypt glucose levels
-    let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
-    let encrypted_glucose = fhe.encrypt_f64_vector(&glucose, scale);
+    let encrypted_glucose = &encrypted_data[fields::GLUCOSE];
     println!("Encrypted glucose levels");
+    let encrypted_cholesterol = &encrypted_data[fields::CHOLESTEROL];
+    println!("Encrypted cholesterol values");
 
-    // Encrypt cholesterol
+    // Plaintext copies, only used to verify the results
+    let ages: Vec<f64> = records.iter().map(|r| r.age as f64).collect();
+    let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
     let cholesterol: Vec<f64> = records.iter().map(|r| r.cholesterol_level).collect();
-    let encrypted_cholesterol = fhe.encrypt_f64_vector(&cholesterol, scale);
-    println!("Encrypted cholesterol values");
 
     println!(
         "\nEncryption completed in {:.2} seconds",
@@ -135,28 +158,70 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Compute average age
     println!("\nComputing average age on encrypted data...");
     let compute_start = Instant::now();
-    let encrypted_avg_age = compute_encrypted_mean(&encrypted_age, fhe.server_key())?;
+    let encrypted_avg_age = compute_encrypted_mean(encrypted_age, fhe.server_key())?;
     let age_time = compute_start.elapsed();
     println!("Computation took {:.2} seconds", age_time.as_secs_f64());
 
     // Compute average glucose level
     println!("\nComputing average glucose level on encrypted data...");
     let compute_start = Instant::now();
-    let encrypted_avg_glucose = compute_encrypted_mean(&encrypted_glucose, fhe.server_key())?;
+    let encrypted_avg_glucose = compute_encrypted_mean(encrypted_glucose, fhe.server_key())?;
     let glucose_time = compute_start.elapsed();
     println!("Computation took {:.2} seconds", glucose_time.as_secs_f64());
 
-    // Compute average cholesterol samples
-    println!("\nComputing average cholesterol level on encrypted data...");
+    // Count patients with high cholesterol
+    println!(
+        "\nCounting patients with cholesterol above {:.0} on encrypted data...",
+        HIGH_CHOLESTEROL_THRESHOLD
+    );
     let compute_start = Instant::now();
-    let encrypted_high_cholesterol =
-        compute_encrypted_mean(&encrypted_cholesterol, fhe.server_key())?;
+    let threshold_scaled =
+        (HIGH_CHOLESTEROL_THRESHOLD * context.scale_for(fields::CHOLESTEROL)?).round() as i64;
+    let encrypted_high_cholesterol = compute_encrypted_threshold_count(
+        encrypted_cholesterol,
+        fhe.server_key(),
+        threshold_scaled,
+    )?;
     let cholesterol_time = compute_start.elapsed();
     println!(
         "Computation took {:.2} seconds",
         cholesterol_time.as_secs_f64()
     );
 
+    // Average glucose per month, bucketed on the encrypted collection dates
+    println!("\nComputing glucose sums and counts per month on encrypted collection dates...");
+    let compute_start = Instant::now();
+    // The study period is public; only the date of each sample stays encrypted
+    let collection_days = records
+        .iter()
+        .map(|r| collection_day(&r.collection_date))
+        .collect::<Result<Vec<i64>, _>>()?;
+    let first_day = *collection_days.iter().min().unwrap_or(&0);
+    let last_day = *collection_days.iter().max().unwrap_or(&0);
+    let monthly_glucose = compute_encrypted_monthly_means(
+        encrypted_glucose,
+        &encrypted_data[fields::COLLECTION_DAY],
+        first_day..last_day + 1,
+        DAYS_PER_MONTH,
+        fhe.server_key(),
+    )?;
+    let monthly_time = compute_start.elapsed();
+    println!("Computation took {:.2} seconds", monthly_time.as_secs_f64());
+
+    // Score every patient with a risk model and count the patients at risk
+    println!("\nScoring patients with a cardiometabolic risk model on encrypted data...");
+    let compute_start = Instant::now();
+    let risk_model = RiskModel::new(-5.0, 1.0)
+        .with_weight(fields::AGE, 0.03)
+        .with_weight(fields::GLUCOSE, 0.02)
+        .with_weight(fields::CHOLESTEROL, 0.01);
+    // Only the count goes back to the client; the individual scores stay on the server
+    let encrypted_at_risk =
+        compute_encrypted_risk_scores(&risk_model, &encrypted_data, fhe.server_key())?
+            .above_threshold;
+    let risk_time = compute_start.elapsed();
+    println!("Computation took {:.2} seconds", risk_time.as_secs_f64());
+
     println!(
         "\nAll computations completed in {:.2} seconds",
         start.elapsed().as_secs_f64()
@@ -175,48 +240,122 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Calculate plaintext results for comparison
     let plaintext_avg_age = ages.iter().sum::<f64>() / ages.len() as f64;
     let plaintext_avg_glucose = glucose.iter().sum::<f64>() / glucose.len() as f64;
-    let plaintext_high_cholesterol = cholesterol.iter().filter(|&&c| c > 200.0).count() as f64;
+    let plaintext_high_cholesterol = cholesterol
+        .iter()
+        .filter(|&&c| c > HIGH_CHOLESTEROL_THRESHOLD)
+        .count() as f64;
 
     // Decrypt and verify average age
     println!("\nDecrypting average age result...");
-    let decrypted_age_raw = fhe.decrypt_f64_vector(&encrypted_avg_age, scale);
-    let decrypted_avg_age = decrypted_age_raw[0] / records.len() as f64;
-    let age_error = (decrypted_avg_age - plaintext_avg_age).abs();
-    let age_error_pct = age_error / plaintext_avg_age * 100.0;
+    let decrypted_avg_age = fhe.decrypt_mean(&encrypted_avg_age, fields::AGE, &context)?;
+    let age_check = verify_computation_with_context(
+        decrypted_avg_age,
+        plaintext_avg_age,
+        fields::AGE,
+        &context,
+    )?;
 
     println!("Plaintext average age: {:.2}", plaintext_avg_age);
     println!("Encrypted+decrypted average age: {:.2}", decrypted_avg_age);
-    println!("Error: {:.4} ({:.2}%)", age_error, age_error_pct);
+    print_verification(&age_check);
 
     // Decrypt and verify average glucose
     println!("\nDecrypting average glucose result...");
-    let decrypted_glucose_raw = fhe.decrypt_f64_vector(&encrypted_avg_glucose, scale);
-    let decrypted_avg_glucose = decrypted_glucose_raw[0] / records.len() as f64;
-    let glucose_error = (decrypted_avg_glucose - plaintext_avg_glucose).abs();
-    let glucose_error_pct = glucose_error / plaintext_avg_glucose * 100.0;
+    let decrypted_avg_glucose =
+        fhe.decrypt_mean(&encrypted_avg_glucose, fields::GLUCOSE, &context)?;
+    let glucose_check = verify_computation_with_context(
+        decrypted_avg_glucose,
+        plaintext_avg_glucose,
+        fields::GLUCOSE,
+        &context,
+    )?;
 
     println!("Plaintext average glucose: {:.2}", plaintext_avg_glucose);
     println!(
         "Encrypted+decrypted average glucose: {:.2}",
         decrypted_avg_glucose
     );
-    println!("Error: {:.4} ({:.2}%)", glucose_error, glucose_error_pct);
+    print_verification(&glucose_check);
 
     // Decrypt and verify high cholesterol count
     println!("\nDecrypting high cholesterol count result...");
-    let decrypted_high_chol_raw = fhe.decrypt_f64_vector(&encrypted_high_cholesterol, scale);
-    let decrypted_high_cholesterol = decrypted_high_chol_raw[0];
-    let chol_error = (decrypted_high_cholesterol - plaintext_high_cholesterol).abs();
+    // Counts are plain integers, so they decrypt with a scale of 1
+    let decrypted_high_cholesterol = fhe.decrypt_element(&encrypted_high_cholesterol, 0, 1.0)?;
+    // Counts must match exactly
+    let chol_check = verify(
+        decrypted_high_cholesterol,
+        plaintext_high_cholesterol,
+        Tolerance::Absolute(0.0),
+    );
 
     println!(
         "Plaintext high cholesterol count: {:.0}",
         plaintext_high_cholesterol
     );
     println!(
-        "Encrypted+decrypted high cholesterol count: {:.2}",
+        "Encrypted+decrypted high cholesterol count: {:.0}",
         decrypted_high_cholesterol
     );
-    println!("Error: {:.4}", chol_error);
+    print_verification(&chol_check);
+
+    // Decrypt the glucose sum and count of each month and divide them
+    println!("\nDecrypting average glucose per month...");
+    let mut plaintext_monthly = HashMap::new();
+    let mut decrypted_monthly = HashMap::new();
+    println!(
+        "{:<10} {:>8} {:>12} {:>12}",
+        "Month", "Samples", "Plaintext", "Decrypted"
+    );
+    for (i, (start, sum, count)) in monthly_glucose.iter().enumerate() {
+        let decrypted_count = fhe.decrypt_element(count, 0, 1.0)?;
+        // Months without samples have no average
+        if decrypted_count == 0.0 {
+            continue;
+        }
+        let decrypted_mean = fhe.decrypt_element(sum, 0, sum.scale)? / decrypted_count;
+        let in_month: Vec<f64> = collection_days
+            .iter()
+            .zip(&glucose)
+            .filter(|(day, _)| (*start..start + DAYS_PER_MONTH).contains(*day))
+            .map(|(_, &g)| g)
+            .collect();
+        let plaintext_mean = in_month.iter().sum::<f64>() / in_month.len() as f64;
+
+        let label = format!("Month {:02}", i + 1);
+        println!(
+            "{:<10} {:>8.0} {:>12.2} {:>12.2}",
+            label, decrypted_count, plaintext_mean, decrypted_mean
+        );
+        plaintext_monthly.insert(label.clone(), plaintext_mean);
+        decrypted_monthly.insert(label, decrypted_mean);
+    }
+
+    // Decrypt the number of patients at risk; no individual score is ever decrypted
+    println!("\nDecrypting the number of patients at risk...");
+    let decrypted_at_risk = fhe.decrypt_element(&encrypted_at_risk, 0, 1.0)?;
+    let plaintext_at_risk = records
+        .iter()
+        .filter(|r| {
+            let score = risk_model.intercept
+                + risk_model.weights[fields::AGE] * r.age as f64
+                + risk_model.weights[fields::GLUCOSE] * r.glucose_level
+                + risk_model.weights[fields::CHOLESTEROL] * r.cholesterol_level;
+            score > risk_model.threshold
+        })
+        .count() as f64;
+    // Counts must match exactly
+    let risk_check = verify(
+        decrypted_at_risk,
+        plaintext_at_risk,
+        Tolerance::Absolute(0.0),
+    );
+
+    println!("Plaintext patients at risk: {:.0}", plaintext_at_risk);
+    println!(
+        "Encrypted+decrypted patients at risk: {:.0}",
+        decrypted_at_risk
+    );
+    print_verification(&risk_check);
 
     // Store results for visualization
     let mut plaintext_results = HashMap::new();
@@ -226,6 +365,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         "High Cholesterol Count".to_string(),
         plaintext_high_cholesterol,
     );
+    plaintext_results.insert("Patients at Risk".to_string(), plaintext_at_risk);
 
     let mut decrypted_results = HashMap::new();
     decrypted_results.insert("Average Age".to_string(), decrypted_avg_age);
@@ -234,11 +374,51 @@ fn main() -> Result<(), Box<dyn Error>> {
         "High Cholesterol Count".to_string(),
         decrypted_high_cholesterol,
     );
+    decrypted_results.insert("Patients at Risk".to_string(), decrypted_at_risk);
+
+    pause();
+
+    // Step 5: Reveal one record with consent
+    print_header("Step 5: Reveal One Record with Consent");
+
+    // Pick a record to spot-check, as if its patient had agreed to the disclosure
+    let consented_index = records.len() / 2;
+    let consented = &records[consented_index];
+    println!(
+        "Patient {} has consented to revealing their record.",
+        consented.patient_id
+    );
+    println!("Decrypting only their values; every other record stays encrypted...");
+
+    let revealed_age = fhe.decrypt_element(
+        encrypted_age,
+        consented_index,
+        context.scale_for(fields::AGE)?,
+    )?;
+    let revealed_glucose = fhe.decrypt_element(
+        encrypted_glucose,
+        consented_index,
+        context.scale_for(fields::GLUCOSE)?,
+    )?;
+    let revealed_cholesterol = fhe.decrypt_element(
+        encrypted_cholesterol,
+        consented_index,
+        context.scale_for(fields::CHOLESTEROL)?,
+    )?;
+
+    println!(
+        "\nRevealed: Age={:.0}, Glucose={:.1}, Cholesterol={:.1}",
+        revealed_age, revealed_glucose, revealed_cholesterol
+    );
+    println!(
+        "Original: Age={}, Glucose={:.1}, Cholesterol={:.1}",
+        consented.age, consented.glucose_level, consented.cholesterol_level
+    );
 
     pause();
 
-    // Step 5: Visualize results
-    print_header("Step 5: Visualize Results");
+    // Step 6: Visualize results
+    print_header("Step 6: Visualize Results");
 
     println!("Generating visualizations to show FHE results compared to plaintext...");
 
@@ -252,6 +432,17 @@ fn main() -> Result<(), Box<dyn Error>> {
         &decrypted_results,
         "FHE vs Plaintext Results",
         Path::new("outputs/interactive_results.png"),
+        None,
+    )?;
+
+    // Plot the average glucose per month
+    println!("Creating monthly glucose chart...");
+    plot_comparison(
+        &plaintext_monthly,
+        &decrypted_monthly,
+        "Average Glucose per Month",
+        Path::new("outputs/interactive_monthly_glucose.png"),
+        None,
     )?;
 
     // Visualize FHE workflow
@@ -260,6 +451,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     println!("\nVisualizations have been saved to the 'outputs' directory:");
     println!("- outputs/interactive_results.png");
+    println!("- outputs/interactive_monthly_glucose.png");
     println!("- outputs/interactive_workflow.png");
 
     // Final summary
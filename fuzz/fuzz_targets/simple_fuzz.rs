@@ -1,5 +1,8 @@
 use fhe_mini_project::data_generator::{generate_biosample_data, BiosampleRecord};
-use fhe_mini_project::encryption::{encrypt_biosample_data, BiosampleFHE};
+use fhe_mini_project::encryption::{encrypt_biosample_data, BiosampleFHE, ScaleConfig};
+
+/// Seed for the keys used by the fuzz harness, so a failing input reproduces exactly
+const FUZZ_KEY_SEED: [u8; 32] = [42; 32];
 
 fn main() {
     // Simple deterministic test cases
@@ -69,14 +72,14 @@ fn main() {
 fn run_simple_test(data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
     if data.is_empty() {
         // Test empty data handling
-        let fhe = BiosampleFHE::new();
+        let fhe = BiosampleFHE::from_seed(FUZZ_KEY_SEED);
         let empty_records: Vec<BiosampleRecord> = vec![];
-        let _encrypted = encrypt_biosample_data(&fhe, &empty_records)?;
+        let _encrypted = encrypt_biosample_data(&fhe, &empty_records, &ScaleConfig::default())?;
         return Ok(());
     }
 
     // Create FHE instance
-    let fhe = BiosampleFHE::new();
+    let fhe = BiosampleFHE::from_seed(FUZZ_KEY_SEED);
 
     // Test basic encryption/decryption with simple data
     let f64_values: Vec<f64> = data.iter().map(|&x| x as f64).collect();
@@ -87,7 +90,7 @@ fn run_simple_test(data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
 
     // Test f64 vector encryption/decryption
     let scale = 100.0;
-    let encrypted_f64 = fhe.encrypt_f64_vector(&f64_values, scale);
+    let encrypted_f64 = fhe.encrypt_f64_vector(&f64_values, scale)?;
     let decrypted_f64 = fhe.decrypt_f64_vector(&encrypted_f64, scale);
 
     // Verify decryption accuracy
@@ -99,7 +102,7 @@ fn run_simple_test(data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
 
     // Test bool vector encryption/decryption
     let encrypted_bool = fhe.encrypt_bool_vector(&bool_values);
-    let decrypted_bool = fhe.decrypt_bool_vector(&encrypted_bool);
+    let decrypted_bool = fhe.decrypt_bool_vector(&encrypted_bool)?;
 
     if bool_values != decrypted_bool {
         return Err("Bool decryption mismatch".into());
@@ -116,10 +119,10 @@ fn test_generated_data(num_samples: usize, seed: u64) -> Result<(), Box<dyn std:
     let records = generate_biosample_data(num_samples, seed)?;
 
     // Create FHE instance
-    let fhe = BiosampleFHE::new();
+    let fhe = BiosampleFHE::from_seed(FUZZ_KEY_SEED);
 
     // Encrypt the data
-    let encrypted_data = encrypt_biosample_data(&fhe, &records)?;
+    let (encrypted_data, _) = encrypt_biosample_data(&fhe, &records, &ScaleConfig::default())?;
 
     // Verify all expected fields are present
     let expected_fields = ["age", "glucose", "cholesterol", "marker"];
@@ -3,27 +3,79 @@
 /// The computations are performed using the TFHE library
 // Required libraries
 use std::collections::HashMap;
-use std::error::Error;
-use tfhe::integer::{ServerKey, SignedRadixCiphertext};
+use std::io::Read;
+use std::ops::Range;
+#[cfg(feature = "native")]
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tfhe::integer::{
+    BooleanBlock, IntegerCiphertext, RadixCiphertext, ServerKey, SignedRadixCiphertext,
+};
 
 // Import the encryption module
-use crate::encryption::EncryptedVector;
+#[cfg(feature = "native")]
+use crate::encryption::load_encrypted_fields;
+use crate::encryption::{
+    BiosampleFHE, EncryptedCategorical, EncryptedVector, EncryptedVectorReader, EncryptionContext,
+    EncryptionError, VectorKind, FHE_BOOL_BLOCKS, FHE_INT_BITS,
+};
+use crate::fields;
+
+/// Error returned by the computations on encrypted data
+///
+/// Converts into `Box<dyn Error>` with `?`, so callers that do not match on the variants
+/// can keep propagating it like any other error.
+#[derive(Debug, thiserror::Error)]
+#[allow(dead_code)]
+pub enum ComputationError {
+    #[error("{0}")]
+    EmptyInput(String), // An input that must hold values is empty
+    #[error("{0}")]
+    LengthMismatch(String), // Inputs that must be aligned differ in length
+    #[error("{0}")]
+    Overflow(String), // A value does not fit the integers it must be encoded in
+    #[error("{0}")]
+    Deserialization(String), // A serialized ciphertext cannot be decoded
+    #[error("{0}")]
+    KeyMismatch(String), // Inputs were encrypted under different client keys
+    #[error("{0}")]
+    Unsupported(String), // The computation does not apply to the kind of vector given
+    #[error("{0}")]
+    InvalidInput(String), // A parameter such as a bound, cutoff or count is out of range
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError), // The encrypted data cannot be accessed or decrypted
+}
+
+impl From<bincode::Error> for ComputationError {
+    fn from(error: bincode::Error) -> Self {
+        ComputationError::Deserialization(error.to_string())
+    }
+}
 
 /// Deserializes a vector of encrypted ciphertexts from an EncryptedVector
 ///
 /// This function converts the binary data in an EncryptedVector back into
-/// SignedRadixCiphertext objects that can be used for computation.
+/// SignedRadixCiphertext objects that can be used for computation. Compressed
+/// vectors are decompressed first.
 ///
 /// # Arguments
 /// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
 ///
 /// # Returns
-/// A vector of deserialized SignedRadixCiphertext objects
-fn deserialize_ciphertexts(encrypted_vector: &EncryptedVector) -> Vec<SignedRadixCiphertext> {
+/// A Result containing the deserialized SignedRadixCiphertext objects, or an error if the
+/// vector's data cannot be read or a ciphertext cannot be decoded
+fn deserialize_ciphertexts(
+    encrypted_vector: &EncryptedVector,
+) -> Result<Vec<SignedRadixCiphertext>, ComputationError> {
     encrypted_vector
-        .data
+        .ciphertext_bytes()?
         .iter()
-        .map(|data| bincode::deserialize(data).unwrap())
+        .map(|data| Ok(bincode::deserialize(data)?))
         .collect()
 }
 
@@ -33,218 +85,7430 @@ fn deserialize_ciphertexts(encrypted_vector: &EncryptedVector) -> Vec<SignedRadi
 /// that can be stored in an EncryptedVector for transmission or storage.
 ///
 /// # Arguments
-/// * `ciphertexts` - A vector of SignedRadixCiphertext objects to serialize
+/// * `ciphertexts` - The SignedRadixCiphertext objects to serialize
 ///
 /// # Returns
 /// An EncryptedVector containing the serialized ciphertexts
-fn serialize_ciphertexts(ciphertexts: Vec<SignedRadixCiphertext>) -> EncryptedVector {
+fn serialize_ciphertexts(ciphertexts: &[SignedRadixCiphertext]) -> EncryptedVector {
     let data: Vec<Vec<u8>> = ciphertexts
         .iter()
         .map(|ciphertext| bincode::serialize(ciphertext).unwrap())
         .collect();
-    EncryptedVector {
-        data,
-        length: ciphertexts.len(),
-    }
+    EncryptedVector::new(data, VectorKind::Numeric)
 }
 
-/// Computes the sum of encrypted values in a vector
+/// Widens a ciphertext holding a 0/1 indicator to the given number of blocks
 ///
-/// This function takes an encrypted vector, deserializes the ciphertexts,
-/// and computes their sum using homomorphic addition.
+/// Indicators are never negative, so the added most significant blocks are trivial
+/// encryptions of zero and no bootstrapping is required. Ciphertexts that are already
+/// wide enough are returned unchanged.
 ///
 /// # Arguments
-/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
-/// * `server_key` - The ServerKey used for homomorphic operations
+/// * `ciphertext` - The indicator ciphertext to widen
+/// * `num_blocks` - The number of blocks the result should have
+/// * `server_key` - The ServerKey used to create the trivial blocks
 ///
 /// # Returns
-/// * `Result<SignedRadixCiphertext, Box<dyn Error>>` - The encrypted sum or an error
-///   if the vector is empty or if addition fails
-pub fn compute_encrypted_sum(
-    encrypted_vector: &EncryptedVector,
+/// The widened SignedRadixCiphertext
+fn widen_indicator(
+    ciphertext: &SignedRadixCiphertext,
+    num_blocks: usize,
     server_key: &ServerKey,
-) -> Result<SignedRadixCiphertext, Box<dyn Error>> {
-    // Deserialize the ciphertexts
-    let ciphertexts = deserialize_ciphertexts(encrypted_vector);
+) -> SignedRadixCiphertext {
+    let missing_blocks = num_blocks.saturating_sub(ciphertext.blocks().len());
+    if missing_blocks == 0 {
+        return ciphertext.clone();
+    }
+
+    let mut blocks = ciphertext.blocks().to_vec();
+    blocks.extend_from_slice(
+        server_key
+            .create_trivial_zero_radix::<SignedRadixCiphertext>(missing_blocks)
+            .blocks(),
+    );
+    SignedRadixCiphertext::from_blocks(blocks)
+}
+
+/// An `EncryptedVector` whose ciphertexts have already been deserialized
+///
+/// Every computation on an `EncryptedVector` has to parse its ciphertexts first. Decoding
+/// a vector into a column once with `EncryptedVector::decode` lets several reductions run
+/// on the same parsed ciphertexts.
+#[derive(Clone)]
+pub struct EncryptedColumn {
+    pub ciphertexts: Vec<SignedRadixCiphertext>, // Deserialized ciphertexts
+    pub kind: VectorKind,                        // What the encrypted values represent
+    pub scale: f64, // Factor the values were multiplied by before encryption
+    pub key_fingerprint: Option<[u8; 32]>, // Fingerprint of the encrypting client key, if known
+}
+
+impl EncryptedVector {
+    /// Deserializes the ciphertexts of this vector into an `EncryptedColumn`
+    ///
+    /// Compressed vectors are decompressed first.
+    ///
+    /// # Returns
+    /// A Result containing an `EncryptedColumn` with the same kind, scale and key
+    /// fingerprint as this vector, or an error if a ciphertext cannot be decoded
+    pub fn decode(&self) -> Result<EncryptedColumn, ComputationError> {
+        Ok(EncryptedColumn {
+            ciphertexts: deserialize_ciphertexts(self)?,
+            kind: self.kind,
+            scale: self.scale,
+            key_fingerprint: self.key_fingerprint,
+        })
+    }
+
+    /// Stamps a computed vector with the key fingerprint of the inputs it was computed from
+    ///
+    /// # Arguments
+    /// * `key_fingerprint` - The fingerprint of the inputs, e.g. from `shared_key_fingerprint`
+    ///
+    /// # Returns
+    /// The vector with its fingerprint replaced
+    fn stamped(mut self, key_fingerprint: Option<[u8; 32]>) -> Self {
+        self.key_fingerprint = key_fingerprint;
+        self
+    }
+}
+
+/// Returns the key fingerprint shared by vectors that are combined in one computation
+///
+/// Vectors without a fingerprint, such as ones saved before fingerprints were recorded,
+/// are accepted alongside any other vector.
+///
+/// # Arguments
+/// * `vectors` - The vectors the computation combines
+///
+/// # Returns
+/// * `Result<Option<[u8; 32]>, ComputationError>` - The fingerprint of the vectors, if any
+///   has one, or a `KeyMismatch` error if two were encrypted under different client keys
+fn shared_key_fingerprint(
+    vectors: &[&EncryptedVector],
+) -> Result<Option<[u8; 32]>, ComputationError> {
+    let mut shared = None;
+    for fingerprint in vectors.iter().filter_map(|vector| vector.key_fingerprint) {
+        match shared {
+            Some(expected) if expected != fingerprint => {
+                return Err(ComputationError::KeyMismatch(
+                    "Encrypted vectors were encrypted under different client keys".to_string(),
+                ))
+            }
+            _ => shared = Some(fingerprint),
+        }
+    }
+    Ok(shared)
+}
+
+impl EncryptedColumn {
+    /// Serializes the column back into an `EncryptedVector`
+    ///
+    /// # Returns
+    /// An uncompressed `EncryptedVector` with the same kind, scale and key fingerprint as
+    /// this column
+    #[allow(dead_code)]
+    pub fn encode(&self) -> EncryptedVector {
+        let mut encoded = serialize_ciphertexts(&self.ciphertexts)
+            .with_scale(self.scale)
+            .stamped(self.key_fingerprint);
+        encoded.kind = self.kind;
+        encoded
+    }
+
+    /// Returns the number of values in the column
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.ciphertexts.len()
+    }
+
+    /// Returns whether the column holds no values
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.ciphertexts.is_empty()
+    }
+
+    /// Computes the sum of the values in the column with the default `SumStrategy`
+    ///
+    /// Every value is extended to `sum_blocks` blocks before summing, so the sum cannot
+    /// overflow however long the column is, and counts are not limited by the single-block
+    /// encoding of boolean indicators.
+    ///
+    /// # Arguments
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    ///
+    /// # Returns
+    /// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum, or an error if
+    ///   the column is empty or holds identifier hashes
+    pub fn sum(&self, server_key: &ServerKey) -> Result<SignedRadixCiphertext, ComputationError> {
+        self.sum_with_strategy(server_key, SumStrategy::default())
+    }
+
+    /// Computes the sum of the values in the column with the given strategy
+    ///
+    /// Every strategy computes the same sum; they differ in speed only.
+    ///
+    /// # Arguments
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    /// * `strategy` - How the additions are arranged
+    ///
+    /// # Returns
+    /// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum, or an error if
+    ///   the column is empty or holds identifier hashes
+    pub fn sum_with_strategy(
+        &self,
+        server_key: &ServerKey,
+        strategy: SumStrategy,
+    ) -> Result<SignedRadixCiphertext, ComputationError> {
+        self.sum_to_width(server_key, strategy, self.sum_blocks(server_key))
+    }
+
+    /// Returns the number of blocks that hold the sum of the column without overflow
+    ///
+    /// Numeric values may span the full width of their ciphertexts, so their sum needs
+    /// ceil(log2(length)) more bits than the widest value. Boolean indicators are summed at
+    /// least at the full integer width.
+    ///
+    /// # Arguments
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    ///
+    /// # Returns
+    /// * The number of blocks `sum` extends every value to
+    pub fn sum_blocks(&self, server_key: &ServerKey) -> usize {
+        self.sum_blocks_for(self.len(), server_key)
+    }
+
+    /// Returns the number of blocks that hold the sum of `length` values like the column's
+    fn sum_blocks_for(&self, length: usize, server_key: &ServerKey) -> usize {
+        match self.kind {
+            VectorKind::Boolean => sum_width_blocks(1, length, server_key).max(FHE_INT_BITS),
+            _ => sum_width_blocks(self.widest_blocks(), length, server_key),
+        }
+    }
 
-    // check if the ciphertexts are empty
-    if ciphertexts.is_empty() {
-        return Err("Cannot compute sum of empty vector".into());
+    /// Returns the number of blocks of the widest ciphertext in the column
+    fn widest_blocks(&self) -> usize {
+        self.ciphertexts
+            .iter()
+            .map(|ciphertext| ciphertext.blocks().len())
+            .max()
+            .unwrap_or(0)
     }
 
-    // Start with the first ciphertext
-    let mut sum = ciphertexts[0].clone();
+    /// Computes the sum of the values in the column at a given width
+    ///
+    /// Every value is extended to `num_blocks` blocks before summing, boolean indicators
+    /// with zero blocks and numeric values with sign blocks. `sum_blocks` is the width at
+    /// which the sum cannot overflow; a smaller width saves work when the values are known
+    /// to be small, but the sum may wrap around. Values that are already wider than
+    /// `num_blocks` keep their width.
+    ///
+    /// # Arguments
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    /// * `strategy` - How the additions are arranged
+    /// * `num_blocks` - The number of blocks to sum at
+    ///
+    /// # Returns
+    /// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum, or an error if
+    ///   the column is empty or holds identifier hashes
+    pub fn sum_to_width(
+        &self,
+        server_key: &ServerKey,
+        strategy: SumStrategy,
+        num_blocks: usize,
+    ) -> Result<SignedRadixCiphertext, ComputationError> {
+        if self.kind == VectorKind::Identifier {
+            return Err(ComputationError::Unsupported(
+                "Identifier hashes cannot be summed".to_string(),
+            ));
+        }
+
+        let num_blocks = num_blocks.max(self.widest_blocks());
+        let ciphertexts: Vec<SignedRadixCiphertext> = self
+            .ciphertexts
+            .iter()
+            .map(|ciphertext| match self.kind {
+                VectorKind::Boolean => widen_indicator(ciphertext, num_blocks, server_key),
+                _ => sign_extend(ciphertext, num_blocks, server_key),
+            })
+            .collect();
+
+        let sum = match strategy {
+            // The server key's multi-operand sum propagates carries as it accumulates, so any
+            // number of ciphertexts can be summed without exhausting the carry space
+            SumStrategy::MultiOperand => server_key.sum_ciphertexts_parallelized(&ciphertexts),
+            SumStrategy::Sequential => {
+                fold_ciphertexts(&ciphertexts, |a, b| server_key.add_parallelized(a, b))
+            }
+            SumStrategy::Tree => tree_sum(&ciphertexts, server_key),
+        };
+        sum.ok_or_else(|| {
+            ComputationError::EmptyInput("Cannot compute sum of empty vector".to_string())
+        })
+    }
 
-    // Iterate over the rest of the ciphertexts and add them to the sum
-    for ciphertext in &ciphertexts[1..] {
-        sum = server_key.checked_add(&sum, ciphertext)?;
+    /// Computes the smallest value in the column
+    ///
+    /// # Arguments
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    ///
+    /// # Returns
+    /// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted minimum, or an error
+    ///   if the column is empty
+    #[allow(dead_code)]
+    pub fn min(&self, server_key: &ServerKey) -> Result<SignedRadixCiphertext, ComputationError> {
+        fold_ciphertexts(&self.ciphertexts, |a, b| server_key.min_parallelized(a, b)).ok_or_else(
+            || ComputationError::EmptyInput("Cannot compute minimum of empty vector".to_string()),
+        )
     }
 
-    Ok(sum)
+    /// Computes the largest value in the column
+    ///
+    /// # Arguments
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    ///
+    /// # Returns
+    /// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted maximum, or an error
+    ///   if the column is empty
+    #[allow(dead_code)]
+    pub fn max(&self, server_key: &ServerKey) -> Result<SignedRadixCiphertext, ComputationError> {
+        fold_ciphertexts(&self.ciphertexts, |a, b| server_key.max_parallelized(a, b)).ok_or_else(
+            || ComputationError::EmptyInput("Cannot compute maximum of empty vector".to_string()),
+        )
+    }
 }
 
-/// Computes the mean of encrypted values in a vector
+/// Folds ciphertexts from left to right with a binary operation
+///
+/// The sequential sum, the minimum, the maximum and `reduce_encrypted` all share this fold.
+///
+/// # Arguments
+/// * `ciphertexts` - The ciphertexts to combine
+/// * `op` - The homomorphic operation combining the value so far with the next one
+///
+/// # Returns
+/// * The combined value, or `None` if there are no ciphertexts
+fn fold_ciphertexts<F>(
+    ciphertexts: &[SignedRadixCiphertext],
+    op: F,
+) -> Option<SignedRadixCiphertext>
+where
+    F: Fn(&SignedRadixCiphertext, &SignedRadixCiphertext) -> SignedRadixCiphertext,
+{
+    let (first, rest) = ciphertexts.split_first()?;
+    Some(
+        rest.iter()
+            .fold(first.clone(), |acc, ciphertext| op(&acc, ciphertext)),
+    )
+}
+
+/// Reduces the encrypted values of a vector to one with a custom binary operation
 ///
-/// This function calculates the sum of encrypted values and returns it
-/// in a serialized form. The actual division to compute the mean is performed
-/// after decryption, as homomorphic division is complex.
+/// The values are folded from left to right, so `f` is applied `length - 1` times, e.g.
+/// `|sk, a, b| sk.min_parallelized(a, b)` computes the minimum and
+/// `|sk, a, b| sk.bitand_parallelized(a, b)` the conjunction of indicators. The operands
+/// are passed at the width they were encrypted at, so an operation whose result can
+/// outgrow that width must extend them itself; sums are better computed with
+/// `compute_encrypted_sum`, which sizes the sum to the length of the vector and uses the
+/// faster multi-operand addition.
 ///
 /// # Arguments
 /// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
 /// * `server_key` - The ServerKey used for homomorphic operations
+/// * `f` - The homomorphic operation combining the value so far with the next value
 ///
 /// # Returns
-/// * `Result<EncryptedVector, Box<dyn Error>>` - The encrypted sum in a serialized form,
-///   or an error if computation fails
-pub fn compute_encrypted_mean(
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted result as a single-element
+///   vector with the scale of the values, or an error if the vector is empty or cannot be
+///   deserialized
+#[allow(dead_code)]
+pub fn reduce_encrypted<F>(
     encrypted_vector: &EncryptedVector,
     server_key: &ServerKey,
-) -> Result<EncryptedVector, Box<dyn Error>> {
-    // Compute the sum
-    let sum = compute_encrypted_sum(encrypted_vector, server_key)?;
+    f: F,
+) -> Result<EncryptedVector, ComputationError>
+where
+    F: Fn(&ServerKey, &SignedRadixCiphertext, &SignedRadixCiphertext) -> SignedRadixCiphertext,
+{
+    ensure_signed_radix(encrypted_vector, "reduction")?;
+    let ciphertexts = deserialize_ciphertexts(encrypted_vector)?;
+    let result = fold_ciphertexts(&ciphertexts, |a, b| f(server_key, a, b))
+        .ok_or_else(|| ComputationError::EmptyInput("Cannot reduce an empty vector".to_string()))?;
+    Ok(serialize_ciphertexts(&[result])
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
+}
+
+/// How the additions of an encrypted sum are arranged
+///
+/// Every strategy computes the same sum. `MultiOperand` leaves the arrangement to the
+/// server key's multi-operand sum. `Sequential` adds the values one after another, so
+/// each addition waits for the previous one. `Tree` adds them pairwise in a balanced
+/// binary tree, whose ceil(log2(n)) levels each consist of independent additions that
+/// run in parallel on the rayon thread pool when the `rayon` feature is enabled.
+///
+/// `MultiOperand` is the default because it needs far fewer carry propagations than
+/// pairwise additions; `Tree` only catches up with many cores. `cargo bench --bench
+/// sum_strategies` compares the three on the current machine.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SumStrategy {
+    /// The server key's multi-operand sum
+    #[default]
+    MultiOperand,
+    /// A left fold of pairwise additions
+    Sequential,
+    /// A balanced binary tree of pairwise additions, one parallel level at a time
+    Tree,
+}
 
-    // For division, we'll use a trick: instead of dividing the encrypted sum (which is complex),
-    // we'll return the sum and divide after decryption
-    // In a more advanced implementation, we would use bootstrapping and server-side division
+/// Sums ciphertexts pairwise in a balanced binary tree
+///
+/// Each level adds neighbouring pairs in parallel and carries an odd last ciphertext over
+/// to the next level unchanged.
+///
+/// # Arguments
+/// * `ciphertexts` - The ciphertexts to sum
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * The encrypted sum, or `None` if there are no ciphertexts
+fn tree_sum(
+    ciphertexts: &[SignedRadixCiphertext],
+    server_key: &ServerKey,
+) -> Option<SignedRadixCiphertext> {
+    let add_pairs = |level: &[SignedRadixCiphertext]| -> Vec<SignedRadixCiphertext> {
+        #[cfg(feature = "rayon")]
+        let pairs = level.par_chunks(2);
+        #[cfg(not(feature = "rayon"))]
+        let pairs = level.chunks(2);
+        pairs
+            .map(|pair| match pair {
+                [a, b] => server_key.add_parallelized(a, b),
+                [last] => last.clone(),
+                _ => unreachable!("chunks of two hold one or two ciphertexts"),
+            })
+            .collect()
+    };
 
-    Ok(serialize_ciphertexts(vec![sum]))
+    // The first level is added straight from the input, which is never copied
+    if ciphertexts.len() <= 1 {
+        return ciphertexts.first().cloned();
+    }
+    let mut level = add_pairs(ciphertexts);
+    while level.len() > 1 {
+        level = add_pairs(&level);
+    }
+    level.pop()
 }
 
-/*
-/// Count values in a vector that are approximately above a threshold
-/// Note: This is an approximation as direct comparisons are not easily done in FHE
-pub fn compute_encrypted_threshold_count(encrypted_vector: &EncryptedVector, server_key: &ServerKey, threshold_scaled: i64) -> Result<EncryptedVector, Box<dyn Error>> {
-    // Deserialize the ciphertexts
-    let ciphertexts = deserialize_ciphertexts(encrypted_vector);
+/// Returns the number of blocks that hold a sum of `length` values of `value_blocks` blocks
+///
+/// A sum of n values needs ceil(log2(n)) more bits than a single value, e.g. 1000
+/// cholesterol levels of about 22000 each need 10 more bits than one of them.
+///
+/// # Arguments
+/// * `value_blocks` - The number of blocks of each value
+/// * `length` - The number of values to sum
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * The number of blocks of a sum that cannot overflow
+pub fn sum_width_blocks(value_blocks: usize, length: usize, server_key: &ServerKey) -> usize {
+    let bits_per_block = server_key.message_modulus().0.ilog2();
+    let extra_bits = usize::BITS - length.saturating_sub(1).leading_zeros();
+    value_blocks + extra_bits.div_ceil(bits_per_block) as usize
+}
 
-    // For each value, we'll compute a score that's higher when the value exceeds the threshold
-    // This is a simplified approach and not a true comparison
+/// Sign-extends a ciphertext to `num_blocks` blocks, leaving wider ciphertexts unchanged
+fn sign_extend(
+    ciphertext: &SignedRadixCiphertext,
+    num_blocks: usize,
+    server_key: &ServerKey,
+) -> SignedRadixCiphertext {
+    let missing_blocks = num_blocks.saturating_sub(ciphertext.blocks().len());
+    server_key.extend_radix_with_sign_msb(ciphertext, missing_blocks)
+}
 
-    // Encrypt the threshold
-    let threshold_cipher = server_key.create_trivial_radix(threshold_scaled, 8);
+/// Computes the sum of encrypted values in a vector
+///
+/// This function takes an encrypted vector, deserializes the ciphertexts,
+/// and computes their sum using homomorphic addition. The ciphertexts are extended
+/// to `EncryptedColumn::sum_blocks` blocks before summing, enough to hold the length
+/// of the vector times its largest possible value, so the sum cannot wrap around.
+/// Use `compute_encrypted_sum_with_width` to sum at a different width.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum or an error
+///   if the vector is empty, holds identifier hashes or high-level ciphertexts, or if
+///   addition fails
+pub fn compute_encrypted_sum(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<SignedRadixCiphertext, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "sum")?;
 
-    // For each cipthertext, compute it is greater than the threshold
-    let mut count_ciphers = Vec::new();
+    encrypted_vector.decode()?.sum(server_key)
+}
 
-    for cipher in ciphertexts {
-        // Subtract the threshold from the ciphertext
-        let diff = server_key.checked_sub(&cipher, &threshold_cipher)?;
+/// Computes the sum of encrypted values in a vector at a given width
+///
+/// Behaves like `compute_encrypted_sum`, but extends the ciphertexts to `num_blocks`
+/// blocks instead of the width computed from the vector's length. See
+/// `EncryptedColumn::sum_to_width`.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+/// * `num_blocks` - The number of blocks to sum at
+///
+/// # Returns
+/// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum or an error
+///   if the vector is empty or cannot be summed
+#[allow(dead_code)]
+pub fn compute_encrypted_sum_with_width(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+    num_blocks: usize,
+) -> Result<SignedRadixCiphertext, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "sum")?;
 
-        // If difference is positive, it's above threshold
-        // We'll encode a "soft" count using the sign bit trick
-        // In real FHE, this would use more sophisticated polynomials
+    encrypted_vector
+        .decode()?
+        .sum_to_width(server_key, SumStrategy::default(), num_blocks)
+}
 
-        // This is a simplification - in practice you'd use a better approach
-        let shifted = server_key.unchecked_scalar_right_shift(&diff, 7);
-        count_ciphers.push(shifted);
-    }
+/// Computes the sum of encrypted values in a vector with the given strategy
+///
+/// Behaves like `compute_encrypted_sum`, but arranges the additions as `strategy` says.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+/// * `strategy` - How the additions are arranged
+///
+/// # Returns
+/// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum or an error
+///   if the vector is empty or cannot be summed
+#[allow(dead_code)]
+pub fn compute_encrypted_sum_with_strategy(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+    strategy: SumStrategy,
+) -> Result<SignedRadixCiphertext, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "sum")?;
 
-    // Sum the counts
-    let mut count_sum = count_ciphers[0].clone();
-    for cipher in &count_ciphers[1..] {
-        count_sum = server_key.checked_add(&count_sum, cipher)?;
-    }
+    encrypted_vector
+        .decode()?
+        .sum_with_strategy(server_key, strategy)
+}
 
-    Ok(serialize_ciphertexts(vec![count_sum]))
+/// Factor by which encrypted means are scaled beyond the scale of their values
+///
+/// The server divides a sum by its plaintext count, which truncates. Scaling the sum up
+/// first keeps two more decimal digits of the mean, and the factor is recorded in the
+/// scale of the mean.
+pub const MEAN_PRECISION: u64 = 100;
 
+/// Divides an encrypted sum by its plaintext count into a mean scaled by `MEAN_PRECISION`
+///
+/// The sum is sign-extended before it is scaled up, so the scaling cannot overflow.
+///
+/// # Arguments
+/// * `sum` - The encrypted sum
+/// * `count` - The number of values summed, which must not be zero
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * The encrypted mean, truncated towards zero
+fn divide_into_mean(
+    sum: &SignedRadixCiphertext,
+    count: usize,
+    server_key: &ServerKey,
+) -> SignedRadixCiphertext {
+    let bits_per_block = server_key.message_modulus().0.ilog2();
+    let extra_bits = MEAN_PRECISION.next_power_of_two().ilog2();
+    let num_blocks = sum.blocks().len() + extra_bits.div_ceil(bits_per_block) as usize;
+    let scaled = server_key
+        .scalar_mul_parallelized(&sign_extend(sum, num_blocks, server_key), MEAN_PRECISION);
+    server_key.signed_scalar_div_parallelized(&scaled, count as i64)
 }
-*/
 
-/// Computes the count of each category in a map of encrypted category vectors
+/// Computes the mean of encrypted values in a vector
+///
+/// The values are summed and the sum is divided by the number of values on the server, so
+/// the result decrypts directly to the mean. Use `compute_encrypted_sum` for the sum alone.
 ///
 /// # Arguments
-/// * `encrypted_categories` - A map of category names to encrypted vectors where each vector
-///   contains binary indicators (0 or 1) for category membership
-/// * `server_key` - The server key used for homomorphic operations
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
 ///
 /// # Returns
-/// * A map of category names to encrypted counts, where each count is the sum of the binary indicators
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted mean in a serialized form,
+///   with the scale of the values times `MEAN_PRECISION`, or an error if computation fails
+pub fn compute_encrypted_mean(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let sum = compute_encrypted_sum(encrypted_vector, server_key)?;
+    Ok(mean_from_sum(&sum, encrypted_vector, server_key))
+}
+
+/// Divides the sum of a vector's values into their encrypted mean
 ///
-/// # Errors
-/// * Returns an error if any of the homomorphic operations fail
-pub fn compute_encrypted_category_counts(
-    encrypted_categories: &HashMap<String, EncryptedVector>,
+/// # Arguments
+/// * `sum` - The encrypted sum of the values, as from `compute_encrypted_sum`
+/// * `encrypted_vector` - The EncryptedVector the sum was taken over
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * The encrypted mean in a serialized form, with the scale of the values times
+///   `MEAN_PRECISION`
+fn mean_from_sum(
+    sum: &SignedRadixCiphertext,
+    encrypted_vector: &EncryptedVector,
     server_key: &ServerKey,
-) -> Result<HashMap<String, EncryptedVector>, Box<dyn Error>> {
-    let mut category_counts = HashMap::new();
+) -> EncryptedVector {
+    let mean = divide_into_mean(sum, encrypted_vector.length, server_key);
+    serialize_ciphertexts(&[mean])
+        .with_scale(encrypted_vector.scale * MEAN_PRECISION as f64)
+        .stamped(encrypted_vector.key_fingerprint)
+}
 
-    for (category, encrypted_vector) in encrypted_categories {
-        if category.starts_with("blood_type_") {
-            let sum = compute_encrypted_sum(encrypted_vector, server_key)?;
-            category_counts.insert(category.clone(), serialize_ciphertexts(vec![sum]));
-        }
+/// Computes the mean of encrypted values as an exact quotient and remainder
+///
+/// `compute_encrypted_mean` truncates the mean to `MEAN_PRECISION` extra digits. This
+/// function instead divides the sum by `n` on the server and keeps both the quotient and
+/// the remainder, so the client can rebuild the mean as the exact fraction
+/// `quotient + remainder / n`, e.g. with `BiosampleFHE::decrypt_exact_mean`. The quotient
+/// is truncated towards zero and the remainder has the sign of the sum.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `n` - The number to divide the sum by, usually the length of the vector
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted quotient and remainder,
+///   in that order, with the scale of the values, or an error if `n` is zero or the
+///   vector cannot be summed
+#[allow(dead_code)]
+pub fn compute_encrypted_mean_exact(
+    encrypted_vector: &EncryptedVector,
+    n: usize,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    if n == 0 {
+        return Err(ComputationError::InvalidInput(
+            "Cannot divide a sum by a count of zero".to_string(),
+        ));
     }
 
-    Ok(category_counts)
+    let sum = compute_encrypted_sum(encrypted_vector, server_key)?;
+    let (quotient, remainder) = server_key.signed_scalar_div_rem_parallelized(&sum, n as i64);
+    Ok(serialize_ciphertexts(&[quotient, remainder])
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
 }
 
-/// Verifies that an encrypted computation result is close enough to the plaintext result
+/// Checks that a vector holds signed radix values that can be compared or multiplied
 ///
 /// # Arguments
-/// * `encrypted_result` - The result obtained through homomorphic encryption
-/// * `plaintext_result` - The expected result computed on plaintext data
-/// * `tolerance` - The relative error tolerance (as a fraction)
+/// * `encrypted_vector` - The EncryptedVector about to be used
+/// * `operation` - The name of the operation, for the error message
 ///
 /// # Returns
-/// * `true` if the encrypted result is within the specified tolerance of the plaintext result
-pub fn verify_computation(encrypted_result: f64, plaintext_result: f64, tolerance: f64) -> bool {
-    // Check if the encrypted result is within the tolerance of the plaintext result
-    (encrypted_result - plaintext_result).abs() <= tolerance * plaintext_result.abs()
+/// * `Result<(), ComputationError>` - An error if the vector holds identifier hashes or
+///   high-level ciphertexts
+fn ensure_signed_radix(
+    encrypted_vector: &EncryptedVector,
+    operation: &str,
+) -> Result<(), ComputationError> {
+    match encrypted_vector.kind {
+        VectorKind::HighLevelInt => Err(ComputationError::Unsupported(format!(
+            "The {} of high-level ciphertexts is not supported",
+            operation
+        ))),
+        VectorKind::Identifier => Err(ComputationError::Unsupported(format!(
+            "The {} of identifier hashes is meaningless",
+            operation
+        ))),
+        VectorKind::Numeric | VectorKind::Boolean => Ok(()),
+    }
 }
 
-/// Runs analysis on encrypted biosample data
+/// Adds a plaintext constant to every encrypted value in a vector
+///
+/// The constant is in the encoded units of the vector, so 1.5 is added to a column at
+/// scale 100 as 150. The values are sign-extended by enough blocks to hold the sum, so it
+/// cannot wrap around.
 ///
 /// # Arguments
-/// * `encrypted_data` - A map of feature names to encrypted vectors containing the data
-/// * `server_key` - The server key used for homomorphic operations
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `scalar` - The constant to add, multiplied by the scale of the vector
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted sums, with the length and
+///   scale of the input, or an error if the vector does not hold numeric values
+#[allow(dead_code)]
+pub fn scalar_add_encrypted_vector(
+    encrypted_vector: &EncryptedVector,
+    scalar: i64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    offset_encrypted_vector(
+        encrypted_vector,
+        scalar,
+        "added to",
+        |value| server_key.scalar_add_parallelized(value, scalar),
+        server_key,
+    )
+}
+
+/// Subtracts a plaintext constant from every encrypted value in a vector
+///
+/// This centers a column, e.g. on a mean that was decrypted earlier, without decrypting
+/// and re-encrypting the values. The constant is in the encoded units of the vector, so a
+/// mean of 42.5 is subtracted from a column at scale 100 as 4250. The values are
+/// sign-extended by enough blocks to hold the difference, so it cannot wrap around.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `scalar` - The constant to subtract, multiplied by the scale of the vector
+/// * `server_key` - The ServerKey used for homomorphic operations
 ///
 /// # Returns
-/// * A map of analysis results, including average age, glucose, cholesterol, and blood type counts
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted differences, with the
+///   length and scale of the input, or an error if the vector does not hold numeric values
+#[allow(dead_code)]
+pub fn scalar_sub_encrypted_vector(
+    encrypted_vector: &EncryptedVector,
+    scalar: i64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    offset_encrypted_vector(
+        encrypted_vector,
+        scalar,
+        "subtracted from",
+        |value| server_key.scalar_sub_parallelized(value, scalar),
+        server_key,
+    )
+}
+
+/// Applies the addition or subtraction of a constant to every value of a numeric vector
 ///
-/// # Errors
-/// * Returns an error if any of the homomorphic operations fail
-pub fn run_biosample_analysis(
-    encrypted_data: &HashMap<String, EncryptedVector>,
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `scalar` - The constant, in the encoded units of the vector
+/// * `operation` - How the constant is applied, for the error message
+/// * `offset` - Adds or subtracts the constant from a widened value
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted results, with the length
+///   and scale of the input, or an error if the vector does not hold numeric values
+fn offset_encrypted_vector(
+    encrypted_vector: &EncryptedVector,
+    scalar: i64,
+    operation: &str,
+    offset: impl Fn(&SignedRadixCiphertext) -> SignedRadixCiphertext,
     server_key: &ServerKey,
-) -> Result<HashMap<String, EncryptedVector>, Box<dyn Error>> {
-    let mut results = HashMap::new();
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_numeric(encrypted_vector, operation)?;
 
-    // Compute average age
-    if let Some(age_data) = encrypted_data.get("age") {
-        let mean = compute_encrypted_mean(age_data, server_key)?;
-        results.insert("avg_age".to_string(), mean);
-    }
+    // The result needs one more bit than the wider of the value and the constant
+    let column = encrypted_vector.decode()?;
+    let num_blocks = column
+        .widest_blocks()
+        .max(scalar_blocks(scalar, server_key))
+        + 1;
 
-    // Compute average glucose levels
-    if let Some(glucose_data) = encrypted_data.get("glucose") {
-        let mean = compute_encrypted_mean(glucose_data, server_key)?;
-        results.insert("avg_glucose".to_string(), mean);
-    }
+    let results: Vec<SignedRadixCiphertext> = column
+        .ciphertexts
+        .iter()
+        .map(|ciphertext| offset(&sign_extend(ciphertext, num_blocks, server_key)))
+        .collect();
 
-    // Compute average cholesterol levels
-    if let Some(cholesterol_data) = encrypted_data.get("cholesterol") {
-        let mean = compute_encrypted_mean(cholesterol_data, server_key)?;
-        results.insert("avg_cholesterol".to_string(), mean);
-    }
+    Ok(serialize_ciphertexts(&results)
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
+}
 
-    // Count blood types
-    let blood_type_keys: Vec<String> = encrypted_data
-        .keys()
-        .filter(|k| k.starts_with("blood_type_"))
-        .cloned()
+/// Multiplies every encrypted value in a vector by a plaintext factor
+///
+/// Ciphertexts can only be multiplied by integers, so the factor is multiplied by
+/// `precision` and rounded first, and the scale of the result is the scale of the vector
+/// times `precision`. A precision of 1 multiplies by a whole factor and keeps the scale.
+/// This converts units on the server, e.g. glucose levels from mg/dL to mmol/L with a
+/// factor of 1/18.016 at a precision of 10000. The values are sign-extended by enough
+/// blocks to hold the products, so they cannot wrap around.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `factor` - The factor to multiply by, which may be negative
+/// * `precision` - The factor that turns `factor` into an integer multiplier
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted products, with the length
+///   of the input and its scale times `precision`, or an error if the vector does not
+///   hold numeric values or the multiplier does not fit a 64-bit integer
+#[allow(dead_code)]
+pub fn scalar_mul_encrypted_vector(
+    encrypted_vector: &EncryptedVector,
+    factor: f64,
+    precision: f64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_numeric(encrypted_vector, "multiplied by")?;
+    let multiplier = integer_weight(factor, precision)?;
+
+    // The product needs as many bits as the value and the multiplier together
+    let column = encrypted_vector.decode()?;
+    let num_blocks = column.widest_blocks() + scalar_blocks(multiplier, server_key);
+
+    let products: Vec<SignedRadixCiphertext> = column
+        .ciphertexts
+        .iter()
+        .map(|ciphertext| {
+            let widened = sign_extend(ciphertext, num_blocks, server_key);
+            server_key.scalar_mul_parallelized(&widened, multiplier)
+        })
         .collect();
 
-    if !blood_type_keys.is_empty() {
-        let blood_type_data: HashMap<String, EncryptedVector> = blood_type_keys
-            .iter()
-            .map(|k| (k.clone(), encrypted_data[k].clone()))
-            .collect();
-        let blood_counts = compute_encrypted_category_counts(&blood_type_data, server_key)?;
+    Ok(serialize_ciphertexts(&products)
+        .with_scale(encrypted_vector.scale * precision)
+        .stamped(encrypted_vector.key_fingerprint))
+}
 
-        for (key, value) in blood_counts {
-            results.insert(key, value);
-        }
+/// Checks that a vector holds numeric values that constants can be applied to
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector to check
+/// * `operation` - How a constant is applied, for the error message, e.g. "added to"
+///
+/// # Returns
+/// * `Result<(), ComputationError>` - An error if the vector does not hold numeric values
+fn ensure_numeric(
+    encrypted_vector: &EncryptedVector,
+    operation: &str,
+) -> Result<(), ComputationError> {
+    ensure_signed_radix(encrypted_vector, "scalar arithmetic")?;
+    if encrypted_vector.kind != VectorKind::Numeric {
+        return Err(ComputationError::Unsupported(format!(
+            "Constants can only be {} numeric vectors",
+            operation
+        )));
     }
+    Ok(())
+}
 
-    Ok(results)
+/// Returns the number of blocks that hold a plaintext constant with its sign
+///
+/// # Arguments
+/// * `scalar` - The constant
+/// * `server_key` - The ServerKey whose block size is used
+///
+/// # Returns
+/// * The number of blocks of a signed ciphertext holding `scalar`
+fn scalar_blocks(scalar: i64, server_key: &ServerKey) -> usize {
+    let bits_per_block = server_key.message_modulus().0.ilog2();
+    let scalar_bits = u64::BITS - scalar.unsigned_abs().leading_zeros() + 1;
+    scalar_bits.div_ceil(bits_per_block) as usize
+}
+
+/// Computes the smallest of the encrypted values in a vector
+///
+/// The values are folded pairwise with the server key's encrypted minimum, so the result
+/// reveals neither the smallest value nor which record holds it.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted minimum as a single-element
+///   vector with the scale of the values, or an error if the vector is empty or cannot
+///   be compared
+#[allow(dead_code)]
+pub fn compute_encrypted_min(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "minimum")?;
+    let min = encrypted_vector.decode()?.min(server_key)?;
+    Ok(serialize_ciphertexts(&[min])
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Computes the largest of the encrypted values in a vector
+///
+/// The values are folded pairwise with the server key's encrypted maximum, so the result
+/// reveals neither the largest value nor which record holds it.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted maximum as a single-element
+///   vector with the scale of the values, or an error if the vector is empty or cannot
+///   be compared
+#[allow(dead_code)]
+pub fn compute_encrypted_max(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "maximum")?;
+    let max = encrypted_vector.decode()?.max(server_key)?;
+    Ok(serialize_ciphertexts(&[max])
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Computes the `k` largest of the encrypted values in a vector
+///
+/// The values are selected one at a time: each round takes the encrypted maximum of the
+/// remaining values, then replaces the first value equal to it with the smallest
+/// representable value, so that duplicates are kept and nothing reveals which record was
+/// selected. The values are returned largest first.
+///
+/// This is expensive: every round costs a comparison, an equality test and a selection
+/// per value, so selecting `k` of `n` values takes about `3 * k * n` homomorphic
+/// operations. Values equal to the smallest representable value cannot be told apart
+/// from already selected ones.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `k` - The number of values to select
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The `k` largest values with the scale of
+///   the input, or an error if `k` exceeds the number of values or they cannot be compared
+#[allow(dead_code)]
+pub fn compute_encrypted_top_k(
+    encrypted_vector: &EncryptedVector,
+    k: usize,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "top-k")?;
+    if k > encrypted_vector.length {
+        return Err(ComputationError::InvalidInput(format!(
+            "Cannot select the {} largest of {} values",
+            k, encrypted_vector.length
+        )));
+    }
+
+    let mut column = encrypted_vector.decode()?;
+    let mut selected = Vec::with_capacity(k);
+    for _ in 0..k {
+        let max = column.max(server_key)?;
+
+        // Mask out exactly one occurrence of the maximum
+        let num_blocks = max.blocks().len();
+        let bits = num_blocks as u32 * server_key.message_modulus().0.ilog2();
+        let masked: SignedRadixCiphertext =
+            server_key.create_trivial_radix(-(1i64 << (bits - 1)), num_blocks);
+        let mut found = server_key.create_trivial_boolean_block(false);
+        for value in column.ciphertexts.iter_mut() {
+            let is_max = server_key.eq_parallelized(value, &max);
+            let is_first = server_key.boolean_bitand(&is_max, &server_key.boolean_bitnot(&found));
+            *value = server_key.cmux_parallelized(&is_first, &masked, value);
+            found = server_key.boolean_bitor(&found, &is_first);
+        }
+
+        selected.push(max);
+    }
+
+    Ok(serialize_ciphertexts(&selected)
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Returns the compare-exchange pairs of Batcher's odd-even merge sort for `n` values
+///
+/// The network is built for the next power of two and truncated to `n`; the dropped
+/// inputs behave like values larger than any other, so the truncated network still sorts.
+///
+/// # Arguments
+/// * `n` - The number of values to sort
+///
+/// # Returns
+/// * The pairs `(i, j)` with `i < j`, in order, after each of which position `i` must hold
+///   the smaller and position `j` the larger of the two values
+fn sorting_network_pairs(n: usize) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let mut p = 1;
+    while p < n {
+        let mut k = p;
+        while k >= 1 {
+            let mut j = k % p;
+            while j + k < n {
+                for i in 0..k.min(n - j - k) {
+                    // Only compare values that belong to the same merge
+                    if (i + j) / (2 * p) == (i + j + k) / (2 * p) {
+                        pairs.push((i + j, i + j + k));
+                    }
+                }
+                j += 2 * k;
+            }
+            k /= 2;
+        }
+        p *= 2;
+    }
+    pairs
+}
+
+/// Sorts the encrypted values in a vector in non-decreasing order
+///
+/// The values pass through Batcher's odd-even merge sort network, in which every
+/// compare-exchange replaces two values with their encrypted minimum and maximum. The
+/// sequence of operations does not depend on the values, so the sort reveals nothing
+/// about them or about which record ends up where.
+///
+/// The network needs O(n log² n) compare-exchanges of two homomorphic operations each,
+/// which makes the sort practical only for small vectors of up to about 32 values.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The sorted values with the kind and scale
+///   of the input, or an error if the values cannot be compared
+#[allow(dead_code)]
+pub fn sort_encrypted_vector(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "sort")?;
+
+    let mut column = encrypted_vector.decode()?;
+    for (i, j) in sorting_network_pairs(column.len()) {
+        let (a, b) = (&column.ciphertexts[i], &column.ciphertexts[j]);
+        let min = server_key.min_parallelized(a, b);
+        let max = server_key.max_parallelized(a, b);
+        column.ciphertexts[i] = min;
+        column.ciphertexts[j] = max;
+    }
+
+    Ok(column.encode())
+}
+
+/// Computes the median of the encrypted values in a vector
+///
+/// The vector is sorted with `sort_encrypted_vector` and the middle element is selected.
+/// For an even number of values there is no single middle element and halving is not
+/// available on ciphertexts, so the result is the sum of the two middle elements and the
+/// client divides it by two after decryption. The sum is sign-extended by one block so
+/// that it cannot overflow. Sorting makes this practical only for small vectors.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted median (odd length) or twice
+///   the median (even length) with the scale of the values, or an error if the vector is
+///   empty or cannot be compared
+#[allow(dead_code)]
+pub fn compute_encrypted_median(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    if encrypted_vector.length == 0 {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute median of empty vector".to_string(),
+        ));
+    }
+
+    let sorted = sort_encrypted_vector(encrypted_vector, server_key)?.decode()?;
+    let middle = sorted.len() / 2;
+    let median = if sorted.len() % 2 == 1 {
+        sorted.ciphertexts[middle].clone()
+    } else {
+        let lower = server_key.extend_radix_with_sign_msb(&sorted.ciphertexts[middle - 1], 1);
+        let upper = server_key.extend_radix_with_sign_msb(&sorted.ciphertexts[middle], 1);
+        server_key.add_parallelized(&lower, &upper)
+    };
+
+    Ok(serialize_ciphertexts(&[median])
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Computes the sum of high-level `FheInt64` values in a vector
+///
+/// This is the `high-level-api` counterpart of `compute_encrypted_sum`, for vectors
+/// produced by `BiosampleFHE::encrypt_f64_vector_hl`. The sum is computed with the
+/// high-level `+` operator on 64-bit integers, so it does not overflow for realistic data.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized high-level ciphertexts
+/// * `server_key` - The high-level ServerKey, see `BiosampleFHE::high_level_server_key`
+///
+/// # Returns
+/// * `Result<FheInt64, ComputationError>` - The encrypted sum or an error if the vector is
+///   empty or does not hold high-level ciphertexts
+#[cfg(feature = "high-level-api")]
+#[allow(dead_code)]
+pub fn compute_encrypted_sum_hl(
+    encrypted_vector: &EncryptedVector,
+    server_key: &tfhe::ServerKey,
+) -> Result<tfhe::FheInt64, ComputationError> {
+    if encrypted_vector.kind != VectorKind::HighLevelInt {
+        return Err(ComputationError::Unsupported(format!(
+            "Expected high-level ciphertexts, got a {:?} vector",
+            encrypted_vector.kind
+        )));
+    }
+
+    // High-level operations use the server key installed on the current thread
+    tfhe::set_server_key(server_key.clone());
+
+    encrypted_vector
+        .ciphertext_bytes()?
+        .iter()
+        .map(|data| bincode::deserialize::<tfhe::FheInt64>(data))
+        .try_fold(None, |sum: Option<tfhe::FheInt64>, ciphertext| {
+            let ciphertext = ciphertext?;
+            Ok::<_, ComputationError>(Some(match sum {
+                Some(sum) => sum + ciphertext,
+                None => ciphertext,
+            }))
+        })?
+        .ok_or_else(|| {
+            ComputationError::EmptyInput("Cannot compute sum of empty vector".to_string())
+        })
+}
+
+/// Computes the mean of high-level `FheInt64` values in a vector
+///
+/// Like `compute_encrypted_mean`, the sum is scaled by `MEAN_PRECISION` and divided by the
+/// number of values on the server.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized high-level ciphertexts
+/// * `server_key` - The high-level ServerKey, see `BiosampleFHE::high_level_server_key`
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted sum as a high-level vector,
+///   or an error if computation fails
+#[cfg(feature = "high-level-api")]
+#[allow(dead_code)]
+pub fn compute_encrypted_mean_hl(
+    encrypted_vector: &EncryptedVector,
+    server_key: &tfhe::ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let sum = compute_encrypted_sum_hl(encrypted_vector, server_key)?;
+    let mean = sum * MEAN_PRECISION as i64 / encrypted_vector.length as i64;
+    Ok(
+        EncryptedVector::new(vec![bincode::serialize(&mean)?], VectorKind::HighLevelInt)
+            .with_scale(encrypted_vector.scale * MEAN_PRECISION as f64)
+            .stamped(encrypted_vector.key_fingerprint),
+    )
+}
+
+/// Computes the sum of encrypted values streamed from a reader, one chunk at a time
+///
+/// The stream is expected in the framing produced by `EncryptedVectorWriter` (for example
+/// by `BiosampleFHE::encrypt_f64_vector_chunked`). Each chunk is summed like
+/// `compute_encrypted_sum` and folded into a running total, so only one chunk of
+/// ciphertexts is held in memory at a time.
+///
+/// # Arguments
+/// * `source` - The reader providing the framed ciphertexts
+/// * `chunk_size` - The number of ciphertexts to load and sum at a time
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum or an error
+///   if the stream is empty or malformed, or if addition fails
+#[allow(dead_code)]
+pub fn compute_encrypted_sum_chunked(
+    source: &mut dyn Read,
+    chunk_size: usize,
+    server_key: &ServerKey,
+) -> Result<SignedRadixCiphertext, ComputationError> {
+    sum_chunked_with_count(source, chunk_size, server_key).map(|(sum, _, _)| sum)
+}
+
+/// Sums the ciphertexts streamed from a reader like `compute_encrypted_sum_chunked`
+///
+/// # Returns
+/// * `Result<(SignedRadixCiphertext, usize, EncryptedVector), ComputationError>` - The
+///   encrypted sum together with the number of values read and the last chunk, whose scale
+///   and key fingerprint are those of the stream, or an error if the stream is empty or
+///   malformed
+fn sum_chunked_with_count(
+    source: &mut dyn Read,
+    chunk_size: usize,
+    server_key: &ServerKey,
+) -> Result<(SignedRadixCiphertext, usize, EncryptedVector), ComputationError> {
+    let mut reader = EncryptedVectorReader::new(source);
+    let mut total: Option<SignedRadixCiphertext> = None;
+    let mut last_chunk = None;
+    let mut count = 0;
+
+    while let Some(chunk) = reader.read_chunk(chunk_size)? {
+        let column = chunk.decode()?;
+        last_chunk = Some(chunk);
+        let partial = column.sum(server_key)?;
+        count += column.len();
+
+        // Each partial sum is only as wide as its own chunk needs, so both operands are
+        // extended to hold the sum of every value read so far. The carry-propagating
+        // addition keeps the running total clean, so any number of partial sums can be
+        // folded in
+        total = Some(match total {
+            Some(total) => {
+                let num_blocks = column.sum_blocks_for(count, server_key);
+                server_key.add_parallelized(
+                    &sign_extend(&total, num_blocks, server_key),
+                    &sign_extend(&partial, num_blocks, server_key),
+                )
+            }
+            None => partial,
+        });
+    }
+
+    let (Some(total), Some(last_chunk)) = (total, last_chunk) else {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute sum of empty vector".to_string(),
+        ));
+    };
+    Ok((total, count, last_chunk))
+}
+
+/// A running encrypted sum of ciphertexts that are added one at a time
+///
+/// Only the running total and the ciphertext being added are held in memory, so a column
+/// of any length can be summed while its serialized ciphertexts are read, e.g. from disk.
+/// Like `sum_chunked_with_count`, the total is sign-extended as values are added so that
+/// it always holds the sum of every value added so far.
+#[derive(Default)]
+#[allow(dead_code)]
+pub struct EncryptedSumAccumulator {
+    total: Option<SignedRadixCiphertext>, // Sum of the ciphertexts added so far
+    count: usize,                         // Number of ciphertexts added so far
+}
+
+#[allow(dead_code)]
+impl EncryptedSumAccumulator {
+    /// Creates an accumulator holding no values
+    ///
+    /// # Returns
+    /// * An empty `EncryptedSumAccumulator`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deserializes a ciphertext and adds it to the running sum
+    ///
+    /// # Arguments
+    /// * `bytes` - A serialized ciphertext, e.g. one element of an uncompressed
+    ///   `EncryptedVector`
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    ///
+    /// # Returns
+    /// * `Result<(), ComputationError>` - An error if the bytes are not a valid ciphertext
+    pub fn add_serialized(
+        &mut self,
+        bytes: &[u8],
+        server_key: &ServerKey,
+    ) -> Result<(), ComputationError> {
+        let ciphertext: SignedRadixCiphertext = bincode::deserialize(bytes)?;
+        self.count += 1;
+
+        self.total = Some(match self.total.take() {
+            Some(total) => {
+                let num_blocks =
+                    sum_width_blocks(ciphertext.blocks().len(), self.count, server_key);
+                server_key.add_parallelized(
+                    &sign_extend(&total, num_blocks, server_key),
+                    &sign_extend(&ciphertext, num_blocks, server_key),
+                )
+            }
+            None => ciphertext,
+        });
+        Ok(())
+    }
+
+    /// Returns the number of ciphertexts added so far
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns whether no ciphertext has been added yet
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Finishes the sum
+    ///
+    /// Serialized ciphertexts do not record the scale of their values, so set it on the
+    /// result with `EncryptedVector::with_scale` before decrypting.
+    ///
+    /// # Returns
+    /// * `Result<EncryptedVector, ComputationError>` - The encrypted sum in a serialized form,
+    ///   or an error if no ciphertext was added
+    pub fn finalize(self) -> Result<EncryptedVector, ComputationError> {
+        Ok(serialize_ciphertexts(&[self.into_total()?]))
+    }
+
+    /// Finishes the sum as a ciphertext
+    ///
+    /// # Returns
+    /// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum, or an error
+    ///   if no ciphertext was added
+    fn into_total(self) -> Result<SignedRadixCiphertext, ComputationError> {
+        self.total.ok_or_else(|| {
+            ComputationError::EmptyInput("Cannot compute sum of empty vector".to_string())
+        })
+    }
+}
+
+/// Computes the sum of the ciphertexts of a stream, reading them one at a time
+///
+/// Each ciphertext is deserialized and folded into an `EncryptedSumAccumulator` before
+/// the next one is read, so only the running total and a single ciphertext are held in
+/// memory however long the stream is. Use `compute_encrypted_sum_chunked` to sum whole
+/// chunks at once instead, trading memory for fewer carry propagations.
+///
+/// # Arguments
+/// * `reader` - The reader providing the ciphertexts framed by an `EncryptedVectorWriter`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum, or an error if
+///   the stream is empty or malformed
+#[allow(dead_code)]
+pub fn compute_encrypted_sum_streaming<R: Read>(
+    reader: &mut EncryptedVectorReader<R>,
+    server_key: &ServerKey,
+) -> Result<SignedRadixCiphertext, ComputationError> {
+    let mut accumulator = EncryptedSumAccumulator::new();
+    while let Some(bytes) = reader.read_ciphertext()? {
+        accumulator.add_serialized(&bytes, server_key)?;
+    }
+    accumulator.into_total()
+}
+
+/// A running encrypted sum of a column that grows in batches, e.g. as a biobank accrues records
+///
+/// Each batch is summed with `compute_encrypted_sum` and folded into the running total,
+/// which is sign-extended to hold the sum of every record added so far, so earlier
+/// batches never have to be summed again. The accumulator serializes with serde to be
+/// persisted between runs; the server key is not part of it and must be attached again
+/// with `with_server_key` after deserializing.
+#[derive(Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct IncrementalSum {
+    #[serde(skip)]
+    server_key: Option<Arc<ServerKey>>, // Key for the additions, attached at run time
+    total: Option<SignedRadixCiphertext>, // Sum of the batches added so far
+    count: usize,                         // Number of records added so far
+    scale: Option<f64>,                   // Scale shared by the batches added so far
+    #[serde(default)]
+    key_fingerprint: Option<[u8; 32]>, // Client key fingerprint of the batches added so far
+}
+
+#[allow(dead_code)]
+impl IncrementalSum {
+    /// Creates an accumulator holding no records
+    ///
+    /// # Arguments
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    ///
+    /// # Returns
+    /// * An empty `IncrementalSum`
+    pub fn new(server_key: Arc<ServerKey>) -> Self {
+        IncrementalSum {
+            server_key: Some(server_key),
+            total: None,
+            count: 0,
+            scale: None,
+            key_fingerprint: None,
+        }
+    }
+
+    /// Attaches the server key to an accumulator, e.g. after deserializing it
+    ///
+    /// # Arguments
+    /// * `server_key` - The ServerKey used for homomorphic operations
+    ///
+    /// # Returns
+    /// * The accumulator, ready to add batches
+    pub fn with_server_key(mut self, server_key: Arc<ServerKey>) -> Self {
+        self.server_key = Some(server_key);
+        self
+    }
+
+    /// Adds the records of a batch to the running sum
+    ///
+    /// # Arguments
+    /// * `batch` - The EncryptedVector holding the new records, at the scale of the batches
+    ///   added before it
+    ///
+    /// # Returns
+    /// * `Result<(), ComputationError>` - An error if no server key is attached, the batch
+    ///   is not numeric, its scale or client key differs from earlier batches or it cannot
+    ///   be summed
+    pub fn add_batch(&mut self, batch: &EncryptedVector) -> Result<(), ComputationError> {
+        let server_key = self.server_key.as_deref().ok_or_else(|| {
+            ComputationError::InvalidInput(
+                "Attach a server key with IncrementalSum::with_server_key before adding batches"
+                    .to_string(),
+            )
+        })?;
+        if batch.kind != VectorKind::Numeric {
+            return Err(ComputationError::Unsupported(format!(
+                "Batches must be numeric, got a {:?} batch",
+                batch.kind
+            )));
+        }
+        if self.scale.is_some_and(|scale| scale != batch.scale) {
+            return Err(ComputationError::InvalidInput(format!(
+                "Batch scale {} differs from the scale {} of earlier batches",
+                batch.scale,
+                self.scale.unwrap_or_default()
+            )));
+        }
+        let key_fingerprint = match (self.key_fingerprint, batch.key_fingerprint) {
+            (Some(expected), Some(fingerprint)) if expected != fingerprint => {
+                return Err(ComputationError::KeyMismatch(
+                    "Batch was encrypted under a different client key than earlier batches"
+                        .to_string(),
+                ))
+            }
+            (expected, fingerprint) => expected.or(fingerprint),
+        };
+        if batch.length == 0 {
+            return Ok(());
+        }
+
+        let column = batch.decode()?;
+        let partial = column.sum(server_key)?;
+        self.count += column.len();
+        self.scale = Some(batch.scale);
+        self.key_fingerprint = key_fingerprint;
+
+        self.total = Some(match self.total.take() {
+            Some(total) => {
+                let num_blocks = column
+                    .sum_blocks_for(self.count, server_key)
+                    .max(total.blocks().len());
+                server_key.add_parallelized(
+                    &sign_extend(&total, num_blocks, server_key),
+                    &sign_extend(&partial, num_blocks, server_key),
+                )
+            }
+            None => partial,
+        });
+        Ok(())
+    }
+
+    /// Returns the number of records added so far
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns whether no record has been added yet
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the sum of the records added so far, leaving the accumulator open for more
+    ///
+    /// # Returns
+    /// * `Result<EncryptedVector, ComputationError>` - The encrypted sum at the scale of the
+    ///   batches, or an error if no record was added
+    pub fn finish(&self) -> Result<EncryptedVector, ComputationError> {
+        let total = self.total.as_ref().ok_or_else(|| {
+            ComputationError::EmptyInput("Cannot compute sum of empty vector".to_string())
+        })?;
+        Ok(serialize_ciphertexts(std::slice::from_ref(total))
+            .with_scale(self.scale.unwrap_or(1.0))
+            .stamped(self.key_fingerprint))
+    }
+}
+
+/// Computes the mean of encrypted values streamed from a reader, one chunk at a time
+///
+/// Like `compute_encrypted_mean`, the sum is divided by the number of values read on the
+/// server. The mean carries the scale and key fingerprint recorded in the stream, with the
+/// scale multiplied by `MEAN_PRECISION`.
+///
+/// # Arguments
+/// * `source` - The reader providing the framed ciphertexts
+/// * `chunk_size` - The number of ciphertexts to load and sum at a time
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted mean in a serialized form,
+///   or an error if computation fails
+pub fn compute_encrypted_mean_chunked(
+    source: &mut dyn Read,
+    chunk_size: usize,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let (sum, count, last_chunk) = sum_chunked_with_count(source, chunk_size, server_key)?;
+    let mean = divide_into_mean(&sum, count, server_key);
+    Ok(serialize_ciphertexts(&[mean])
+        .with_scale(last_chunk.scale * MEAN_PRECISION as f64)
+        .stamped(last_chunk.key_fingerprint))
+}
+
+/// Counts the true values of an encrypted boolean vector
+///
+/// The 0/1 indicators are summed like any other column, so the sum is widened to hold
+/// the length of the vector and the count does not wrap around.
+///
+/// # Arguments
+/// * `encrypted_bool_vector` - The boolean EncryptedVector, e.g. `fields::MARKER`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted count, which decrypts with a
+///   scale of 1, or an error if the vector is not boolean or is empty
+pub fn compute_encrypted_count_true(
+    encrypted_bool_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    if encrypted_bool_vector.kind != VectorKind::Boolean {
+        return Err(ComputationError::Unsupported(format!(
+            "Only boolean vectors can be counted, got a {:?} vector",
+            encrypted_bool_vector.kind
+        )));
+    }
+
+    let count = compute_encrypted_sum(encrypted_bool_vector, server_key)?;
+    Ok(serialize_ciphertexts(&[count]).stamped(encrypted_bool_vector.key_fingerprint))
+}
+
+/// An encrypted count of true values together with the number of values it was taken over
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct EncryptedProportion {
+    pub count: EncryptedVector, // Encrypted number of true values
+    pub total: usize,           // Number of values counted over
+}
+
+impl EncryptedProportion {
+    /// Decrypts the count and divides it by the total into a rate
+    ///
+    /// # Arguments
+    /// * `fhe` - The BiosampleFHE instance holding the client key
+    ///
+    /// # Returns
+    /// * `Result<f64, ComputationError>` - The fraction of true values, between 0 and 1, or
+    ///   an error if the count cannot be decrypted
+    #[allow(dead_code)]
+    pub fn decrypt_rate(&self, fhe: &BiosampleFHE) -> Result<f64, ComputationError> {
+        let count = fhe.decrypt_element(&self.count, 0, 1.0)?;
+        Ok(count / self.total as f64)
+    }
+}
+
+/// Computes the proportion of true values of an encrypted boolean vector
+///
+/// The length of a vector is not secret, so it is paired with the encrypted count from
+/// `compute_encrypted_count_true` and the client divides after decryption.
+///
+/// # Arguments
+/// * `encrypted_bool_vector` - The boolean EncryptedVector, e.g. `fields::MARKER`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedProportion, ComputationError>` - The encrypted count and the length of
+///   the vector, or an error if the vector is not boolean or is empty
+#[allow(dead_code)]
+pub fn compute_encrypted_proportion(
+    encrypted_bool_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedProportion, ComputationError> {
+    Ok(EncryptedProportion {
+        count: compute_encrypted_count_true(encrypted_bool_vector, server_key)?,
+        total: encrypted_bool_vector.length,
+    })
+}
+
+/// Cholesterol level above which a patient counts towards the analysis' high cholesterol count
+pub const HIGH_CHOLESTEROL_THRESHOLD: f64 = 200.0;
+
+/// Builds an encrypted mask of the values of a vector that satisfy an encrypted comparison
+///
+/// Each ciphertext is compared by `predicate`, and the encrypted result is cast to a
+/// single-block indicator like the ones produced by `encrypt_bool_vector`.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `operation` - The name of the operation, for the error on vectors that cannot be compared
+/// * `predicate` - The comparison of a single ciphertext
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - An encrypted boolean mask with the same
+///   length as the input vector, or an error if the values cannot be compared
+fn mask_where(
+    encrypted_vector: &EncryptedVector,
+    operation: &str,
+    predicate: impl Fn(&SignedRadixCiphertext) -> BooleanBlock,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(encrypted_vector, operation)?;
+    let indicators: Vec<SignedRadixCiphertext> = deserialize_ciphertexts(encrypted_vector)?
+        .iter()
+        .map(|ciphertext| predicate(ciphertext).into_radix(1, server_key))
+        .collect();
+
+    let mut mask = serialize_ciphertexts(&indicators).stamped(encrypted_vector.key_fingerprint);
+    mask.kind = VectorKind::Boolean;
+    Ok(mask)
+}
+
+/// Counts the values of a vector that satisfy an encrypted comparison
+///
+/// The mask of `mask_where` is summed, so neither the values nor which records satisfy
+/// the comparison are revealed.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `operation` - The name of the operation, for the error on vectors that cannot be compared
+/// * `predicate` - The comparison of a single ciphertext
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted count, which decrypts with
+///   a scale of 1, or an error if the vector is empty or cannot be compared
+fn count_where(
+    encrypted_vector: &EncryptedVector,
+    operation: &str,
+    predicate: impl Fn(&SignedRadixCiphertext) -> BooleanBlock,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let mask = mask_where(encrypted_vector, operation, predicate, server_key)?;
+    let count = compute_encrypted_sum(&mask, server_key)?;
+    Ok(serialize_ciphertexts(&[count]).stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Counts the encrypted values in a vector that are strictly above a threshold
+///
+/// Each ciphertext is compared with the plaintext threshold, the encrypted comparison
+/// result is cast to a single-block indicator, and the indicators are summed. A threshold
+/// beyond the range of the ciphertexts compares correctly instead of wrapping. Neither the
+/// values nor which records exceed the threshold are revealed.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+/// * `threshold_scaled` - The threshold, in the same scaled-integer units used at
+///   encryption time
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted count in a serialized form,
+///   or an error if the vector is empty or cannot be compared
+pub fn compute_encrypted_threshold_count(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+    threshold_scaled: i64,
+) -> Result<EncryptedVector, ComputationError> {
+    count_where(
+        encrypted_vector,
+        "threshold count",
+        |ciphertext| server_key.scalar_gt_parallelized(ciphertext, threshold_scaled),
+        server_key,
+    )
+}
+
+/// Counts the encrypted values in a vector that fall inside a half-open range
+///
+/// Each value is compared with both bounds, the two encrypted comparison results are
+/// ANDed, and the resulting indicators are summed, so a query such as "how many patients
+/// have glucose between 100 and 125" is answered without revealing any value.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `low` - The inclusive lower bound, in real units
+/// * `high` - The exclusive upper bound, in real units
+/// * `scale` - The scaling factor used during encryption
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted count of values `x` with
+///   `low <= x < high`, or an error if the bounds are inverted or the vector is empty
+#[allow(dead_code)]
+pub fn compute_encrypted_range_count(
+    encrypted_vector: &EncryptedVector,
+    low: f64,
+    high: f64,
+    scale: f64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    if low > high {
+        return Err(ComputationError::InvalidInput(format!(
+            "Inverted range: lower bound {} is above upper bound {}",
+            low, high
+        )));
+    }
+
+    // Bounds in the same scaled-integer units as the values
+    let low_scaled = (low * scale).round() as i64;
+    let high_scaled = (high * scale).round() as i64;
+
+    count_where(
+        encrypted_vector,
+        "range count",
+        |ciphertext| in_range(ciphertext, low_scaled, high_scaled, server_key),
+        server_key,
+    )
+}
+
+/// Builds an encrypted mask of the values of a vector that fall inside a half-open range
+///
+/// Each value is compared with both bounds and the two encrypted comparison results are
+/// ANDed, so the mask selects e.g. the samples collected in a date range from the
+/// `fields::COLLECTION_DAY` ordinals without revealing any date. Like `eq_scalar_mask`,
+/// the mask can be summed to count the selected records or passed to `masked_sum`.
+///
+/// The values in range cannot be compacted into a shorter vector without revealing how
+/// many there are, so the mask keeps one entry per value. A client that wants only the
+/// values in range decrypts the mask and the vector and keeps the selected values with
+/// `apply_mask_after_decrypt`. The values are integers, so the closed range `[lo, hi]`
+/// is the half-open range `lo..hi + 1`.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `low` - The inclusive lower bound, in the same scaled-integer units used at
+///   encryption time
+/// * `high` - The exclusive upper bound, in the same units
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - An encrypted boolean mask of the values
+///   `x` with `low <= x < high`, or an error if the bounds are inverted or the values
+///   cannot be compared
+#[allow(dead_code)]
+pub fn range_mask(
+    encrypted_vector: &EncryptedVector,
+    low: i64,
+    high: i64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    if low > high {
+        return Err(ComputationError::InvalidInput(format!(
+            "Inverted range: lower bound {} is above upper bound {}",
+            low, high
+        )));
+    }
+
+    mask_where(
+        encrypted_vector,
+        "range mask",
+        |ciphertext| in_range(ciphertext, low, high, server_key),
+        server_key,
+    )
+}
+
+/// Compares an encrypted value with both bounds of a half-open range
+///
+/// # Arguments
+/// * `ciphertext` - The encrypted value
+/// * `low` - The inclusive lower bound, in the units of the value
+/// * `high` - The exclusive upper bound, in the same units
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * The encrypted result of `low <= value < high`
+fn in_range(
+    ciphertext: &SignedRadixCiphertext,
+    low: i64,
+    high: i64,
+    server_key: &ServerKey,
+) -> BooleanBlock {
+    let above_low = server_key.scalar_ge_parallelized(ciphertext, low);
+    let below_high = server_key.scalar_lt_parallelized(ciphertext, high);
+    server_key.boolean_bitand(&above_low, &below_high)
+}
+
+/// Keeps the decrypted values whose decrypted mask entry is set, on the client
+///
+/// This is the compaction that `range_mask` cannot do on the server: the server returns
+/// the encrypted mask, and the client applies it once both are decrypted, e.g. with
+/// `BiosampleFHE::decrypt_bool_vector` and `BiosampleFHE::decrypt_f64_vector`.
+///
+/// # Arguments
+/// * `values` - The decrypted values
+/// * `mask` - The decrypted mask, with one entry per value
+///
+/// # Returns
+/// * `Result<Vec<T>, ComputationError>` - The selected values in their original order, or
+///   an error if the mask and the values have different lengths
+#[allow(dead_code)]
+pub fn apply_mask_after_decrypt<T: Clone>(
+    values: &[T],
+    mask: &[bool],
+) -> Result<Vec<T>, ComputationError> {
+    if values.len() != mask.len() {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} values but {} mask entries",
+            values.len(),
+            mask.len()
+        )));
+    }
+
+    Ok(values
+        .iter()
+        .zip(mask)
+        .filter(|(_, &selected)| selected)
+        .map(|(value, _)| value.clone())
+        .collect())
+}
+
+/// Encrypted outlier flags of a column and their total
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct OutlierFlags {
+    pub flags: EncryptedVector, // Boolean flag of each record, 1 if the value is an outlier
+    pub count: EncryptedVector, // Number of outliers
+}
+
+/// Flags the encrypted values of a column that lie more than `k` standard deviations from
+/// the mean
+///
+/// This is the second phase of z-score anomaly detection. In the first, the client
+/// decrypts the column's mean and variance from `compute_encrypted_mean` and
+/// `compute_encrypted_variance`, so only those aggregates are revealed. Here the cutoffs
+/// `mean − k·std` and `mean + k·std` are trivially encrypted and every value is compared
+/// with both, so neither the values nor which records are outliers are revealed.
+///
+/// # Arguments
+/// * `field` - The EncryptedVector containing the column
+/// * `mean` - The decrypted mean of the column, in real units
+/// * `std` - The decrypted standard deviation of the column, in real units
+/// * `k` - The number of standard deviations beyond which a value is an outlier
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<OutlierFlags, ComputationError>` - The encrypted flag of each value `x` with
+///   `x < mean − k·std` or `x > mean + k·std`, and their encrypted count, or an error if
+///   `std` or `k` is negative or not finite, or the values cannot be compared
+#[allow(dead_code)]
+pub fn compute_encrypted_outlier_flags(
+    field: &EncryptedVector,
+    mean: f64,
+    std: f64,
+    k: f64,
+    server_key: &ServerKey,
+) -> Result<OutlierFlags, ComputationError> {
+    if !(std.is_finite() && std >= 0.0 && k.is_finite() && k >= 0.0) {
+        return Err(ComputationError::InvalidInput(format!(
+            "Invalid cutoff: {} standard deviations of {}",
+            k, std
+        )));
+    }
+
+    // Cutoffs in the same scaled-integer units as the values
+    let low = ((mean - k * std) * field.scale).round() as i64;
+    let high = ((mean + k * std) * field.scale).round() as i64;
+
+    let flags = mask_where(
+        field,
+        "outlier flags",
+        |ciphertext| {
+            // A cutoff beyond the range of the ciphertext would wrap around, and no value
+            // can lie beyond it anyway
+            let num_blocks = ciphertext.blocks().len();
+            let bits = num_blocks as u32 * server_key.message_modulus().0.ilog2();
+            let (min, max) = (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1);
+            let low: SignedRadixCiphertext =
+                server_key.create_trivial_radix(low.clamp(min, max), num_blocks);
+            let high: SignedRadixCiphertext =
+                server_key.create_trivial_radix(high.clamp(min, max), num_blocks);
+
+            let below = server_key.lt_parallelized(ciphertext, &low);
+            let above = server_key.gt_parallelized(ciphertext, &high);
+            server_key.boolean_bitor(&below, &above)
+        },
+        server_key,
+    )?;
+    let count = compute_encrypted_sum(&flags, server_key)?;
+    Ok(OutlierFlags {
+        flags,
+        count: serialize_ciphertexts(&[count]).stamped(field.key_fingerprint),
+    })
+}
+
+/// Counts the encrypted values in a vector that are strictly below a pivot
+///
+/// This is the server-side step of `compute_encrypted_median_approx`: it only needs the
+/// server key and returns an encrypted count, which the client decrypts to choose the
+/// next pivot.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `pivot_scaled` - The pivot, in the same scaled-integer units used at encryption time
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted count of values below the
+///   pivot, or an error if the vector is empty or cannot be compared
+#[allow(dead_code)]
+pub fn compute_encrypted_count_below(
+    encrypted_vector: &EncryptedVector,
+    pivot_scaled: i64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    count_where(
+        encrypted_vector,
+        "count below a pivot",
+        |ciphertext| server_key.scalar_lt_parallelized(ciphertext, pivot_scaled),
+        server_key,
+    )
+}
+
+/// Counts the encrypted values in a vector that are at most a threshold
+///
+/// Sorting is too expensive for large vectors, so percentiles are estimated by ranking
+/// candidate thresholds instead: the client binary-searches for the smallest threshold
+/// whose decrypted rank reaches the wanted fraction of the vector length.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `threshold_scaled` - The threshold, in the same scaled-integer units used at
+///   encryption time
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted count of values less than
+///   or equal to the threshold, which decrypts with a scale of 1, or an error if the vector
+///   is empty or cannot be compared
+#[allow(dead_code)]
+pub fn compute_encrypted_percentile_rank(
+    encrypted_vector: &EncryptedVector,
+    threshold_scaled: i64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    count_where(
+        encrypted_vector,
+        "percentile rank",
+        |ciphertext| server_key.scalar_le_parallelized(ciphertext, threshold_scaled),
+        server_key,
+    )
+}
+
+/// Counts the nonzero values of an encrypted vector
+///
+/// Each ciphertext is compared with zero and the encrypted results are summed as
+/// single-block indicators, e.g. to count the positive markers of an integer column
+/// without revealing which records they belong to.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted count of nonzero values,
+///   which decrypts with a scale of 1, or an error if the vector is empty or cannot be
+///   compared
+#[allow(dead_code)]
+pub fn compute_encrypted_nonzero_count(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    count_where(
+        encrypted_vector,
+        "nonzero count",
+        |ciphertext| server_key.scalar_ne_parallelized(ciphertext, 0),
+        server_key,
+    )
+}
+
+/// Approximates the median of encrypted values by an interactive binary search over counts
+///
+/// The client proposes a pivot, the server counts the values below it with
+/// `compute_encrypted_count_below`, and the client decrypts only that count to halve the
+/// search interval. No value is ever decrypted. For an even number of values the two
+/// middle values are searched separately and averaged.
+///
+/// The decrypted counts are the leakage: every iteration reveals how many values lie
+/// below one pivot, so the client learns up to `iterations` counts per searched value
+/// (twice that for an even number of values). After enough iterations to narrow the
+/// interval to a single scaled unit, about log2((max - min) * scale), the result is exact;
+/// fewer iterations return the middle of the remaining interval.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing the encrypted values
+/// * `min` - A lower bound on the values, in real units
+/// * `max` - An upper bound on the values, in real units
+/// * `iterations` - The largest number of counts to decrypt per searched value
+/// * `fhe` - The BiosampleFHE instance holding the client and server keys
+///
+/// # Returns
+/// * `Result<f64, ComputationError>` - The approximate median in real units, or an error if
+///   the vector is empty or the bounds are inverted
+#[allow(dead_code)]
+pub fn compute_encrypted_median_approx(
+    encrypted_vector: &EncryptedVector,
+    min: f64,
+    max: f64,
+    iterations: usize,
+    fhe: &BiosampleFHE,
+) -> Result<f64, ComputationError> {
+    if encrypted_vector.length == 0 {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute median of empty vector".to_string(),
+        ));
+    }
+    if min > max {
+        return Err(ComputationError::InvalidInput(format!(
+            "Inverted bounds: minimum {} is above maximum {}",
+            min, max
+        )));
+    }
+
+    let scale = encrypted_vector.scale;
+    let n = encrypted_vector.length;
+
+    // Finds the k-th smallest value (1-based): the smallest p with at least k values <= p
+    let search = |k: usize| -> Result<f64, ComputationError> {
+        let mut low = (min * scale).floor() as i64;
+        let mut high = (max * scale).ceil() as i64;
+        for _ in 0..iterations {
+            if low >= high {
+                break;
+            }
+            let pivot = low + (high - low) / 2;
+            let count =
+                compute_encrypted_count_below(encrypted_vector, pivot + 1, fhe.server_key())?;
+            // Counts are plain integers, so they decrypt with a scale of 1
+            if fhe.decrypt_element(&count, 0, 1.0)? >= k as f64 {
+                high = pivot;
+            } else {
+                low = pivot + 1;
+            }
+        }
+        Ok((low + high) as f64 / 2.0 / scale)
+    };
+
+    let lower = search(n.div_ceil(2))?;
+    if n % 2 == 1 {
+        return Ok(lower);
+    }
+    let upper = search(n / 2 + 1)?;
+    Ok((lower + upper) / 2.0)
+}
+
+/// Builds an encrypted equality mask of a vector against a plaintext scalar
+///
+/// Each element of the returned vector encrypts 1 if the corresponding value equals
+/// `scalar` and 0 otherwise. The comparison is performed homomorphically, so the mask
+/// can be summed with `compute_encrypted_sum` to count matching records.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `scalar` - The plaintext value to compare against, in the same scaled-integer units
+///   used at encryption time
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - An encrypted boolean mask with the same
+///   length as the input vector, or an error if the values cannot be compared
+#[allow(dead_code)]
+pub fn eq_scalar_mask(
+    encrypted_vector: &EncryptedVector,
+    scalar: i64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    // Compare each ciphertext with the scalar, keeping each result as a
+    // single-block indicator like the ones produced by `encrypt_bool_vector`
+    mask_where(
+        encrypted_vector,
+        "equality mask",
+        |ciphertext| server_key.scalar_eq_parallelized(ciphertext, scalar),
+        server_key,
+    )
+}
+
+/// Counts the encrypted values of a vector that equal a plaintext value
+///
+/// Each value is compared with the target using the server key's scalar equality, and the
+/// resulting indicators are widened and summed, so neither the values nor which records
+/// match are revealed. This answers e.g. "how many records have facility_id == 3" on an
+/// integer-coded column.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `target` - The plaintext value to count, in real units
+/// * `scale` - The scale the values were encrypted at, 1 for integer-coded fields
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted number of matching values,
+///   which decrypts with a scale of 1, or an error if the vector is empty or cannot be
+///   compared, or the target does not fit a 64-bit integer at the given scale
+#[allow(dead_code)]
+pub fn compute_encrypted_equality_count(
+    encrypted_vector: &EncryptedVector,
+    target: f64,
+    scale: f64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let target = integer_weight(target, scale)?;
+    count_where(
+        encrypted_vector,
+        "equality count",
+        |ciphertext| server_key.scalar_eq_parallelized(ciphertext, target),
+        server_key,
+    )
+}
+
+/// Combines two encrypted indicator vectors with a logical AND
+///
+/// Each pair of indicators is multiplied, so a record is selected only if both indicators
+/// encrypt 1, e.g. "marker positive AND blood type O+". The indicators must encrypt 0 or 1,
+/// as those from `encrypt_bool_vector`, the one-hot blood type vectors and masks such as
+/// `eq_scalar_mask` do; other values give meaningless results. The combined indicator can
+/// be counted with `compute_encrypted_count_true` or used as a mask for `masked_sum`.
+///
+/// # Arguments
+/// * `a` - The first boolean EncryptedVector
+/// * `b` - The second boolean EncryptedVector, aligned with `a`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted 0/1 indicator of each
+///   record, or an error if a vector is not boolean or the lengths differ
+#[allow(dead_code)]
+pub fn indicator_and(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    combine_indicators(
+        a,
+        b,
+        "AND",
+        |x, y| server_key.mul_parallelized(x, y),
+        server_key,
+    )
+}
+
+/// Combines two encrypted indicator vectors with a logical OR
+///
+/// Each pair of indicators is combined as `a + b − ab`, so a record is selected if either
+/// indicator encrypts 1. Like `indicator_and`, this assumes the indicators encrypt 0 or 1.
+///
+/// # Arguments
+/// * `a` - The first boolean EncryptedVector
+/// * `b` - The second boolean EncryptedVector, aligned with `a`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted 0/1 indicator of each
+///   record, or an error if a vector is not boolean or the lengths differ
+#[allow(dead_code)]
+pub fn indicator_or(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    combine_indicators(
+        a,
+        b,
+        "OR",
+        |x, y| {
+            let sum = server_key.add_parallelized(x, y);
+            server_key.sub_parallelized(&sum, &server_key.mul_parallelized(x, y))
+        },
+        server_key,
+    )
+}
+
+/// Combines two aligned indicator vectors into a new indicator
+///
+/// The indicators are widened by one block, so intermediate values such as `a + b` cannot
+/// wrap around, and each result is cut back to `FHE_BOOL_BLOCKS` blocks like the
+/// indicators from `encrypt_bool_vector`.
+///
+/// # Arguments
+/// * `a` - The first boolean EncryptedVector
+/// * `b` - The second boolean EncryptedVector
+/// * `operation` - The name of the combination, for error messages
+/// * `combine` - Combines two widened indicators into a 0/1 value
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The combined indicators, or an error if
+///   a vector is not boolean, the lengths differ or the client keys differ
+fn combine_indicators(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    operation: &str,
+    combine: impl Fn(&SignedRadixCiphertext, &SignedRadixCiphertext) -> SignedRadixCiphertext,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    for indicator in [a, b] {
+        if indicator.kind != VectorKind::Boolean {
+            return Err(ComputationError::Unsupported(format!(
+                "Only boolean vectors can be combined with {}, got a {:?} vector",
+                operation, indicator.kind
+            )));
+        }
+    }
+    if a.length != b.length {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} indicators in the first vector but {} in the second",
+            a.length, b.length
+        )));
+    }
+    let key_fingerprint = shared_key_fingerprint(&[a, b])?;
+
+    let num_blocks = FHE_BOOL_BLOCKS + 1;
+    let combined: Vec<SignedRadixCiphertext> = deserialize_ciphertexts(a)?
+        .iter()
+        .zip(deserialize_ciphertexts(b)?.iter())
+        .map(|(x, y)| {
+            let result = combine(
+                &widen_indicator(x, num_blocks, server_key),
+                &widen_indicator(y, num_blocks, server_key),
+            );
+            // The result is 0 or 1, so the added block holds nothing but zeros
+            SignedRadixCiphertext::from_blocks(result.blocks()[..FHE_BOOL_BLOCKS].to_vec())
+        })
+        .collect();
+
+    let mut combined = serialize_ciphertexts(&combined).stamped(key_fingerprint);
+    combined.kind = VectorKind::Boolean;
+    Ok(combined)
+}
+
+/// Computes the sum of the encrypted values selected by an encrypted boolean mask
+///
+/// Each value is selected by its mask bit (as produced by, e.g., `eq_scalar_mask`) with an
+/// encrypted multiplexer and the selections are summed, so records whose mask encrypts 0
+/// contribute nothing. The values are sign-extended to hold the sum of the whole column
+/// first, so the total cannot wrap around. Neither the mask nor the selected values are
+/// revealed.
+///
+/// # Arguments
+/// * `values` - The EncryptedVector containing the values to sum
+/// * `mask` - A boolean EncryptedVector of 0/1 indicators with the same length as `values`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted masked sum in a serialized form,
+///   or an error if the vectors are empty or have different lengths, the values cannot be
+///   summed, the mask is not boolean or the vectors were encrypted under different keys
+#[allow(dead_code)]
+pub fn masked_sum(
+    values: &EncryptedVector,
+    mask: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(values, "masked sum")?;
+    if mask.kind != VectorKind::Boolean {
+        return Err(ComputationError::Unsupported(format!(
+            "The mask of a masked sum must be boolean, got a {:?} vector",
+            mask.kind
+        )));
+    }
+    if values.length != mask.length {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} values but {} mask entries",
+            values.length, mask.length
+        )));
+    }
+    let key_fingerprint = shared_key_fingerprint(&[values, mask])?;
+
+    // Room for the sum of every value, so the selected total cannot wrap around
+    let column = values.decode()?;
+    let value_blocks = column.widest_blocks().max(FHE_INT_BITS);
+    let num_blocks = sum_width_blocks(value_blocks, values.length, server_key);
+    let widened: Vec<SignedRadixCiphertext> = column
+        .ciphertexts
+        .iter()
+        .map(|ciphertext| sign_extend(ciphertext, num_blocks, server_key))
+        .collect();
+    let zero: SignedRadixCiphertext = server_key.create_trivial_zero_radix(num_blocks);
+
+    let sum = selected_sum(&widened, mask, &zero, server_key)?;
+    Ok(serialize_ciphertexts(&[sum])
+        .with_scale(values.scale)
+        .stamped(key_fingerprint))
+}
+
+/// Computes the encrypted sum and count of a field for each facility
+///
+/// The `facility_<id>` one-hot masks in `encrypted_data` are the categories of
+/// `compute_encrypted_means_by_category`, so each facility's values are selected with an
+/// encrypted multiplexer and summed at a width that holds the whole column, and each mask
+/// is summed to count the facility's records. Neither the values nor the facility of any
+/// record is revealed. Division is not supported on ciphertexts, so each facility's mean is
+/// obtained after decryption as `decrypted_sum / decrypted_count`.
+///
+/// # Arguments
+/// * `values` - The EncryptedVector containing the values to average, e.g. glucose levels
+/// * `encrypted_data` - A map containing the facility masks, such as the output of
+///   `encrypt_biosample_data`; entries without the facility prefix are ignored
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<HashMap<u32, (EncryptedVector, EncryptedVector)>, ComputationError>` - A map of
+///   facility ids to their encrypted sum and encrypted count, or an error if a mask does
+///   not match the values or a facility id cannot be parsed
+#[allow(dead_code)]
+pub fn group_by_facility_mean(
+    values: &EncryptedVector,
+    encrypted_data: &HashMap<String, EncryptedVector>,
+    server_key: &ServerKey,
+) -> Result<HashMap<u32, (EncryptedVector, EncryptedVector)>, ComputationError> {
+    let mut facilities: HashMap<&str, &EncryptedVector> = HashMap::new();
+    let mut facility_ids: HashMap<&str, u32> = HashMap::new();
+    for (key, mask) in encrypted_data {
+        let Some(facility) = key.strip_prefix(fields::FACILITY_PREFIX) else {
+            continue;
+        };
+        let facility_id: u32 = facility.parse().map_err(|_| {
+            ComputationError::InvalidInput(format!("Invalid facility id in key {}", key))
+        })?;
+        facilities.insert(facility, mask);
+        facility_ids.insert(facility, facility_id);
+    }
+    if facilities.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let groups = compute_encrypted_means_by_category(values, &facilities, server_key)?;
+    Ok(groups
+        .into_iter()
+        .map(|(facility, terms)| {
+            // The terms hold the sum, with the scale of the values, and then the count
+            let sum = EncryptedVector::new(vec![terms.data[0].clone()], VectorKind::Numeric)
+                .with_scale(values.scale)
+                .stamped(terms.key_fingerprint);
+            let count = EncryptedVector::new(vec![terms.data[1].clone()], VectorKind::Numeric)
+                .stamped(terms.key_fingerprint);
+            (facility_ids[facility.as_str()], (sum, count))
+        })
+        .collect())
+}
+
+/// Sums the values whose boolean indicator is 1, selecting them with an encrypted multiplexer
+///
+/// # Arguments
+/// * `values` - The values, already sign-extended to hold their sum
+/// * `indicator` - The boolean EncryptedVector selecting the values, aligned with `values`
+/// * `zero` - A trivial zero as wide as the values, taken in place of unselected values
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted sum of the selected
+///   values, or an error if there are no values
+fn selected_sum(
+    values: &[SignedRadixCiphertext],
+    indicator: &EncryptedVector,
+    zero: &SignedRadixCiphertext,
+    server_key: &ServerKey,
+) -> Result<SignedRadixCiphertext, ComputationError> {
+    // A boolean indicator is a single block holding 0 or 1
+    let selected: Vec<SignedRadixCiphertext> = deserialize_ciphertexts(indicator)?
+        .iter()
+        .zip(values.iter())
+        .map(|(bit, value)| {
+            let bit = BooleanBlock::new_unchecked(bit.blocks()[0].clone());
+            server_key.cmux_parallelized(&bit, value, zero)
+        })
+        .collect();
+    server_key
+        .sum_ciphertexts_parallelized(&selected)
+        .ok_or_else(|| {
+            ComputationError::EmptyInput("Cannot compute sum of empty vector".to_string())
+        })
+}
+
+/// Computes the encrypted sum and count of a field for each category of a one-hot field
+///
+/// For every category, the category's indicator selects the values of its records with an
+/// encrypted multiplexer, which is much cheaper than multiplying by the indicator, and the
+/// selected values are summed after sign-extending them to hold the sum of the whole
+/// column. The indicators themselves are summed to count the category's records, so
+/// neither the values nor the category of any record is revealed. Division is not
+/// supported on ciphertexts, so each category's mean is obtained after decryption with
+/// `BiosampleFHE::decrypt_conditional_mean`, which leaves out categories without records.
+///
+/// # Arguments
+/// * `field` - The EncryptedVector containing the values to average, e.g. cholesterol levels
+/// * `categories` - A map of category names, e.g. "A+", to their boolean one-hot indicators,
+///   borrowed so that the indicators are not copied
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<HashMap<String, EncryptedVector>, ComputationError>` - A map of category names to
+///   their encrypted sum and count, with the scale of `field`, or an error if an indicator
+///   is not boolean or does not match the values or their client key
+#[allow(dead_code)]
+pub fn compute_encrypted_means_by_category(
+    field: &EncryptedVector,
+    categories: &HashMap<&str, &EncryptedVector>,
+    server_key: &ServerKey,
+) -> Result<HashMap<String, EncryptedVector>, ComputationError> {
+    means_by_category_with_counts(field, categories, server_key, |_, indicator| {
+        compute_encrypted_sum(indicator, server_key)
+    })
+}
+
+/// Computes the encrypted sum and count of a field for each category, taking the counts
+/// from `count`
+///
+/// # Arguments
+/// * `field` - The EncryptedVector containing the values to average
+/// * `categories` - A map of category names to their boolean one-hot indicators
+/// * `server_key` - The ServerKey used for homomorphic operations
+/// * `count` - Returns the encrypted number of records of a category, given its name and
+///   indicator
+///
+/// # Returns
+/// * `Result<HashMap<String, EncryptedVector>, ComputationError>` - The sums and counts of
+///   `compute_encrypted_means_by_category`, or an error if an indicator is not boolean or
+///   does not match the values
+fn means_by_category_with_counts(
+    field: &EncryptedVector,
+    categories: &HashMap<&str, &EncryptedVector>,
+    server_key: &ServerKey,
+    count: impl Fn(&str, &EncryptedVector) -> Result<SignedRadixCiphertext, ComputationError>,
+) -> Result<HashMap<String, EncryptedVector>, ComputationError> {
+    ensure_signed_radix(field, "category means")?;
+    if field.length == 0 {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute category means of empty vector".to_string(),
+        ));
+    }
+    let inputs: Vec<&EncryptedVector> = std::iter::once(field)
+        .chain(categories.values().copied())
+        .collect();
+    let key_fingerprint = shared_key_fingerprint(&inputs)?;
+
+    let num_blocks = sum_width_blocks(FHE_INT_BITS, field.length, server_key);
+    let values = sign_extend_to(field, num_blocks, server_key)?;
+    let zero: SignedRadixCiphertext = server_key.create_trivial_zero_radix(num_blocks);
+
+    let mut groups = HashMap::new();
+    for (&category, &indicator) in categories {
+        if indicator.kind != VectorKind::Boolean {
+            return Err(ComputationError::Unsupported(format!(
+                "Indicator of category {} must be boolean",
+                category
+            )));
+        }
+        if indicator.length != field.length {
+            return Err(ComputationError::LengthMismatch(format!(
+                "Length mismatch: {} values but {} indicators for category {}",
+                field.length, indicator.length, category
+            )));
+        }
+
+        let sum = selected_sum(&values, indicator, &zero, server_key)?;
+        let count = count(category, indicator)?;
+        groups.insert(
+            category.to_string(),
+            serialize_ciphertexts(&[sum, count])
+                .with_scale(field.scale)
+                .stamped(key_fingerprint),
+        );
+    }
+
+    Ok(groups)
+}
+
+/// Computes the encrypted sum and count of a field in each time bucket of the collection dates
+///
+/// The day range is split into consecutive buckets of `bucket_days` days, the last one
+/// possibly shorter. For each bucket, `range_mask` compares every encrypted day with the
+/// bucket's bounds, and the values of the records inside are summed like in
+/// `compute_encrypted_means_by_category`, so neither the dates nor the values are revealed.
+/// The client divides each sum by its count after decryption, e.g. to chart the average
+/// glucose level per month.
+///
+/// # Arguments
+/// * `field` - The EncryptedVector containing the values to average, e.g. glucose levels
+/// * `day_offsets` - The EncryptedVector containing the collection day of each record,
+///   e.g. the `fields::COLLECTION_DAY` ordinals
+/// * `days` - The range of days to cover, in the units of `day_offsets`
+/// * `bucket_days` - The number of days in each bucket
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<Vec<(i64, EncryptedVector, EncryptedVector)>, ComputationError>` - The first
+///   day of each bucket with the encrypted sum of its values, at the scale of `field`, and
+///   the encrypted number of its records, or an error if the range or bucket size is empty
+///   or the vectors are empty or have different lengths
+#[allow(dead_code)]
+pub fn compute_encrypted_monthly_means(
+    field: &EncryptedVector,
+    day_offsets: &EncryptedVector,
+    days: Range<i64>,
+    bucket_days: i64,
+    server_key: &ServerKey,
+) -> Result<Vec<(i64, EncryptedVector, EncryptedVector)>, ComputationError> {
+    ensure_signed_radix(field, "time bucket means")?;
+    if bucket_days <= 0 {
+        return Err(ComputationError::InvalidInput(format!(
+            "Buckets must span at least one day, not {}",
+            bucket_days
+        )));
+    }
+    if days.is_empty() {
+        return Err(ComputationError::InvalidInput(format!(
+            "Empty day range {}..{}",
+            days.start, days.end
+        )));
+    }
+    if field.length != day_offsets.length {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} values but {} collection days",
+            field.length, day_offsets.length
+        )));
+    }
+    if field.length == 0 {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute time bucket means of empty vector".to_string(),
+        ));
+    }
+    let key_fingerprint = shared_key_fingerprint(&[field, day_offsets])?;
+
+    let num_blocks = sum_width_blocks(FHE_INT_BITS, field.length, server_key);
+    let values = sign_extend_to(field, num_blocks, server_key)?;
+    let zero: SignedRadixCiphertext = server_key.create_trivial_zero_radix(num_blocks);
+
+    let mut buckets = Vec::new();
+    for start in days.clone().step_by(bucket_days as usize) {
+        let end = (start + bucket_days).min(days.end);
+        let inside = range_mask(day_offsets, start, end, server_key)?;
+        let sum = selected_sum(&values, &inside, &zero, server_key)?;
+        let count = compute_encrypted_sum(&inside, server_key)?;
+        buckets.push((
+            start,
+            serialize_ciphertexts(&[sum])
+                .with_scale(field.scale)
+                .stamped(key_fingerprint),
+            serialize_ciphertexts(&[count]).stamped(key_fingerprint),
+        ));
+    }
+
+    Ok(buckets)
+}
+
+/// Counts the encrypted values falling into each bin of a histogram
+///
+/// Consecutive edges delimit the bins, so `n` edges define `n - 1` bins, and bin `i`
+/// covers the left-closed, right-open range `[bin_edges[i], bin_edges[i + 1])`: a value
+/// exactly on an edge falls into the bin that edge opens. Values outside all bins are not
+/// counted; see `compute_encrypted_histogram_with_overflow` to count them too. Neither the
+/// values nor their bins are revealed.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `bin_edges` - The strictly increasing bin edges in real units, e.g. mg/dL
+/// * `scale` - The scale the values were encrypted at
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted count of each bin in
+///   order, which decrypt with a scale of 1, or an error if there are fewer than two
+///   edges, the scaled edges are not strictly increasing or the vector is empty
+#[allow(dead_code)]
+pub fn compute_encrypted_histogram(
+    encrypted_vector: &EncryptedVector,
+    bin_edges: &[f64],
+    scale: f64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let mut counts = histogram_counts(encrypted_vector, bin_edges, scale, server_key)?;
+    counts.remove(0);
+    counts.pop();
+    Ok(serialize_ciphertexts(&counts).stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Counts the encrypted values falling into each bin of a histogram, plus overflow bins
+///
+/// Behaves like `compute_encrypted_histogram`, with one more bin on each side: the first
+/// count is of the values below the first edge, and the last of the values at or above
+/// the last edge. The counts therefore add up to the length of the vector.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `bin_edges` - The strictly increasing bin edges in real units, e.g. mg/dL
+/// * `scale` - The scale the values were encrypted at
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted counts of the
+///   `bin_edges.len() + 1` bins in order, or an error as for `compute_encrypted_histogram`
+#[allow(dead_code)]
+pub fn compute_encrypted_histogram_with_overflow(
+    encrypted_vector: &EncryptedVector,
+    bin_edges: &[f64],
+    scale: f64,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let counts = histogram_counts(encrypted_vector, bin_edges, scale, server_key)?;
+    Ok(serialize_ciphertexts(&counts).stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Counts the values of each histogram bin, including the two overflow bins
+///
+/// Every value is compared once with each edge. As the number of values at or above an
+/// edge can only shrink from one edge to the next, the count of a bin is the difference
+/// between the counts at its two edges.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `bin_edges` - The strictly increasing bin edges in real units
+/// * `scale` - The scale the values were encrypted at
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<Vec<SignedRadixCiphertext>, ComputationError>` - The `bin_edges.len() + 1`
+///   encrypted counts, from the values below the first edge to those at or above the last
+fn histogram_counts(
+    encrypted_vector: &EncryptedVector,
+    bin_edges: &[f64],
+    scale: f64,
+    server_key: &ServerKey,
+) -> Result<Vec<SignedRadixCiphertext>, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "histogram")?;
+    if bin_edges.len() < 2 {
+        return Err(ComputationError::InvalidInput(
+            "A histogram needs at least two bin edges".to_string(),
+        ));
+    }
+    let scaled_edges = bin_edges
+        .iter()
+        .map(|&edge| integer_weight(edge, scale))
+        .collect::<Result<Vec<i64>, _>>()?;
+    if scaled_edges.windows(2).any(|edges| edges[0] >= edges[1]) {
+        return Err(ComputationError::InvalidInput(format!(
+            "Bin edges must be strictly increasing at scale {}: {:?}",
+            scale, bin_edges
+        )));
+    }
+
+    // Count the values at or above each edge
+    let ciphertexts = deserialize_ciphertexts(encrypted_vector)?;
+    let at_or_above = scaled_edges
+        .iter()
+        .map(|&edge| {
+            let mask: Vec<SignedRadixCiphertext> = ciphertexts
+                .iter()
+                .map(|ciphertext| {
+                    server_key
+                        .scalar_ge_parallelized(ciphertext, edge)
+                        .into_radix(1, server_key)
+                })
+                .collect();
+            let mut mask = serialize_ciphertexts(&mask);
+            mask.kind = VectorKind::Boolean;
+            compute_encrypted_sum(&mask, server_key)
+        })
+        .collect::<Result<Vec<SignedRadixCiphertext>, _>>()?;
+
+    let num_blocks = at_or_above[0].blocks().len();
+    let total: SignedRadixCiphertext =
+        server_key.create_trivial_radix(ciphertexts.len() as i64, num_blocks);
+    let mut counts = Vec::with_capacity(at_or_above.len() + 1);
+    counts.push(server_key.sub_parallelized(&total, &at_or_above[0]));
+    for edges in at_or_above.windows(2) {
+        counts.push(server_key.sub_parallelized(&edges[0], &edges[1]));
+    }
+    counts.extend(at_or_above.last().cloned());
+    Ok(counts)
+}
+
+/// Counts the positions at which two encrypted identifier vectors hold the same identifier
+///
+/// The vectors are compared element by element, so the records of both datasets must be
+/// aligned, e.g. by enrolment order. Each pair of hashes is compared homomorphically and
+/// the resulting indicators are summed, so neither the identifiers nor which positions
+/// matched are revealed, only the total. Because the identifiers are truncated hashes,
+/// distinct identifiers collide with a probability of about 2^-32 per pair.
+///
+/// # Arguments
+/// * `a` - The encrypted identifiers of the first dataset, from `BiosampleFHE::encrypt_id_vector`
+/// * `b` - The encrypted identifiers of the second dataset, encrypted with the same keys
+/// * `server_key` - The server key used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - A single encrypted count with a scale of 1,
+///   or an error if the vectors are not identifier vectors of equal, non-zero length
+#[allow(dead_code)]
+pub fn compute_encrypted_id_match_count(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    if a.kind != VectorKind::Identifier || b.kind != VectorKind::Identifier {
+        return Err(ComputationError::Unsupported(
+            "Both vectors must hold encrypted identifiers".to_string(),
+        ));
+    }
+    if a.length != b.length {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Identifier vectors must have the same length, got {} and {}",
+            a.length, b.length
+        )));
+    }
+    let key_fingerprint = shared_key_fingerprint(&[a, b])?;
+
+    let a_bytes = a.ciphertext_bytes()?;
+    let b_bytes = b.ciphertext_bytes()?;
+
+    // Flag equal identifiers as single-block indicators
+    let matches = a_bytes
+        .iter()
+        .zip(b_bytes.iter())
+        .map(|(a_data, b_data)| {
+            let a_id: RadixCiphertext = bincode::deserialize(a_data)?;
+            let b_id: RadixCiphertext = bincode::deserialize(b_data)?;
+            Ok(server_key
+                .eq_parallelized(&a_id, &b_id)
+                .into_radix(1, server_key))
+        })
+        .collect::<Result<Vec<SignedRadixCiphertext>, ComputationError>>()?;
+
+    let mut matches = serialize_ciphertexts(&matches);
+    matches.kind = VectorKind::Boolean;
+    let count = compute_encrypted_sum(&matches, server_key)?;
+    Ok(serialize_ciphertexts(&[count]).stamped(key_fingerprint))
+}
+
+/// Returns the number of blocks that hold a sum of `length` products of two values
+///
+/// A product needs twice the width of its operands, and the sum needs ceil(log2(length))
+/// more bits on top.
+fn product_sum_blocks(length: usize, server_key: &ServerKey) -> usize {
+    sum_width_blocks(2 * FHE_INT_BITS, length, server_key)
+}
+
+/// Deserializes the ciphertexts of a vector and sign-extends them to `num_blocks` blocks
+fn sign_extend_to(
+    vector: &EncryptedVector,
+    num_blocks: usize,
+    server_key: &ServerKey,
+) -> Result<Vec<SignedRadixCiphertext>, ComputationError> {
+    Ok(deserialize_ciphertexts(vector)?
+        .iter()
+        .map(|ciphertext| sign_extend(ciphertext, num_blocks, server_key))
+        .collect())
+}
+
+/// Computes the dot product of two encrypted vectors
+///
+/// Both vectors are sign-extended to hold a sum of `length` products, multiplied element
+/// by element and summed. With a boolean indicator as one operand this is the sum of the
+/// other column over the selected records, e.g. the total glucose of marker-positive
+/// patients. The result carries the product of the two scales, so it decrypts to real units.
+///
+/// # Arguments
+/// * `a` - The EncryptedVector containing the first operand
+/// * `b` - The EncryptedVector containing the second operand, aligned with `a`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted dot product as a
+///   single-element vector, or an error if the vectors are empty, have different lengths
+///   or cannot be multiplied
+pub fn compute_encrypted_dot_product(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(a, "dot product")?;
+    ensure_signed_radix(b, "dot product")?;
+    if a.length != b.length {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} values in the first vector but {} in the second",
+            a.length, b.length
+        )));
+    }
+    if a.length == 0 {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute dot product of empty vectors".to_string(),
+        ));
+    }
+    let key_fingerprint = shared_key_fingerprint(&[a, b])?;
+
+    let num_blocks = product_sum_blocks(a.length, server_key);
+    let a_values = sign_extend_to(a, num_blocks, server_key)?;
+    let b_values = sign_extend_to(b, num_blocks, server_key)?;
+    let products: Vec<SignedRadixCiphertext> = a_values
+        .iter()
+        .zip(b_values.iter())
+        .map(|(x, y)| server_key.mul_parallelized(x, y))
+        .collect();
+
+    let sum = server_key
+        .sum_ciphertexts_parallelized(&products)
+        .ok_or_else(|| {
+            ComputationError::EmptyInput("Cannot compute sum of empty vector".to_string())
+        })?;
+    Ok(serialize_ciphertexts(&[sum])
+        .with_scale(a.scale * b.scale)
+        .stamped(key_fingerprint))
+}
+
+/// Multiplies two encrypted vectors element by element
+///
+/// The product of two values needs as many bits as both of them together, so each pair is
+/// sign-extended to the combined width of its operands before it is multiplied and the
+/// product cannot wrap around. The result carries the product of the two scales, e.g. a
+/// glucose level at scale 100 times an age at scale 1 decrypts with a scale of 100.
+///
+/// # Arguments
+/// * `a` - The EncryptedVector containing the first factor of each record
+/// * `b` - The EncryptedVector containing the second factor of each record, aligned with `a`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted product of each record, or
+///   an error if the vectors are empty, have different lengths or cannot be multiplied
+pub fn compute_encrypted_elementwise_product(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(a, "elementwise product")?;
+    ensure_signed_radix(b, "elementwise product")?;
+    if a.length != b.length {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} values in the first vector but {} in the second",
+            a.length, b.length
+        )));
+    }
+    if a.length == 0 {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute elementwise product of empty vectors".to_string(),
+        ));
+    }
+    let key_fingerprint = shared_key_fingerprint(&[a, b])?;
+
+    let a_column = a.decode()?;
+    let b_column = b.decode()?;
+    let num_blocks = a_column.widest_blocks() + b_column.widest_blocks();
+    let products: Vec<SignedRadixCiphertext> = a_column
+        .ciphertexts
+        .iter()
+        .zip(b_column.ciphertexts.iter())
+        .map(|(x, y)| {
+            server_key.mul_parallelized(
+                &sign_extend(x, num_blocks, server_key),
+                &sign_extend(y, num_blocks, server_key),
+            )
+        })
+        .collect();
+
+    Ok(serialize_ciphertexts(&products)
+        .with_scale(a.scale * b.scale)
+        .stamped(key_fingerprint))
+}
+
+/// Adds two encrypted vectors element by element
+///
+/// Each pair is sign-extended by one block more than the wider operand, so the sum
+/// cannot wrap around. Both vectors must have the same scale, which the sums keep, e.g.
+/// two readings of the same field at scale 100.
+///
+/// # Arguments
+/// * `a` - The EncryptedVector containing the first term of each record
+/// * `b` - The EncryptedVector containing the second term of each record, aligned with `a`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted sum of each record, or an
+///   error if the vectors have different lengths or scales or cannot be added
+#[allow(dead_code)]
+pub fn vector_add(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    combine_elementwise(
+        a,
+        b,
+        "elementwise sum",
+        |x, y| server_key.add_parallelized(x, y),
+        server_key,
+    )
+}
+
+/// Subtracts an encrypted vector from another element by element
+///
+/// This computes e.g. each patient's cholesterol level minus an encrypted per-patient
+/// baseline. Each pair is sign-extended by one block more than the wider operand, so the
+/// difference cannot wrap around. Both vectors must have the same scale, which the
+/// differences keep.
+///
+/// # Arguments
+/// * `a` - The EncryptedVector containing the minuend of each record
+/// * `b` - The EncryptedVector containing the subtrahend of each record, aligned with `a`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted difference of each record,
+///   or an error if the vectors have different lengths or scales or cannot be subtracted
+#[allow(dead_code)]
+pub fn vector_sub(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    combine_elementwise(
+        a,
+        b,
+        "elementwise difference",
+        |x, y| server_key.sub_parallelized(x, y),
+        server_key,
+    )
+}
+
+/// Negates every encrypted value in a vector
+///
+/// The values are sign-extended by one block first, so negating the most negative value
+/// of their width cannot wrap around. The negations keep the scale of the vector.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted negations, or an error if
+///   the vector cannot be negated
+#[allow(dead_code)]
+pub fn vector_negate(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(encrypted_vector, "negation")?;
+
+    let column = encrypted_vector.decode()?;
+    let num_blocks = column.widest_blocks() + 1;
+    let negations: Vec<SignedRadixCiphertext> = column
+        .ciphertexts
+        .iter()
+        .map(|ciphertext| {
+            server_key.neg_parallelized(&sign_extend(ciphertext, num_blocks, server_key))
+        })
+        .collect();
+
+    Ok(serialize_ciphertexts(&negations)
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Subtracts an encrypted mean from every value of a field
+///
+/// The single encrypted mean is broadcast over the records, so each patient's deviation
+/// from the mean is computed without the client seeing the mean or any value, e.g. to
+/// center a field before further computations. A mean from `compute_encrypted_mean` has
+/// the scale of the field times `MEAN_PRECISION`, so the values are multiplied by the
+/// ratio of the two scales first, and the deviations have the scale of the mean.
+///
+/// # Arguments
+/// * `field` - The EncryptedVector containing the values of each record
+/// * `encrypted_mean` - The single-element EncryptedVector holding the mean, at a whole
+///   multiple of the field's scale
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted deviation of each record,
+///   or an error if the mean does not hold exactly one value, its scale is not a whole
+///   multiple of the field's or the vectors cannot be subtracted
+#[allow(dead_code)]
+pub fn compute_encrypted_deviations(
+    field: &EncryptedVector,
+    encrypted_mean: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(field, "deviation")?;
+    ensure_signed_radix(encrypted_mean, "deviation")?;
+    if encrypted_mean.length != 1 {
+        return Err(ComputationError::LengthMismatch(format!(
+            "The mean must hold a single value, not {}",
+            encrypted_mean.length
+        )));
+    }
+    let ratio = encrypted_mean.scale / field.scale;
+    if !ratio.is_finite() || ratio < 1.0 || ratio.fract() != 0.0 || ratio >= i64::MAX as f64 {
+        return Err(ComputationError::InvalidInput(format!(
+            "The scale {} of the mean is not a whole multiple of the scale {} of the field",
+            encrypted_mean.scale, field.scale
+        )));
+    }
+    let multiplier = ratio as i64;
+    let key_fingerprint = shared_key_fingerprint(&[field, encrypted_mean])?;
+
+    // Room for a value times the multiplier, and one more block for the difference
+    let column = field.decode()?;
+    let mean = deserialize_ciphertexts(encrypted_mean)?.remove(0);
+    let num_blocks = (column.widest_blocks() + scalar_blocks(multiplier, server_key))
+        .max(mean.blocks().len())
+        + 1;
+    let mean = sign_extend(&mean, num_blocks, server_key);
+
+    let deviations: Vec<SignedRadixCiphertext> = column
+        .ciphertexts
+        .iter()
+        .map(|ciphertext| {
+            let widened = sign_extend(ciphertext, num_blocks, server_key);
+            let rescaled = if multiplier == 1 {
+                widened
+            } else {
+                server_key.scalar_mul_parallelized(&widened, multiplier)
+            };
+            server_key.sub_parallelized(&rescaled, &mean)
+        })
+        .collect();
+
+    Ok(serialize_ciphertexts(&deviations)
+        .with_scale(encrypted_mean.scale)
+        .stamped(key_fingerprint))
+}
+
+/// Combines two aligned vectors of the same scale element by element
+///
+/// # Arguments
+/// * `a` - The EncryptedVector containing the first operand of each record
+/// * `b` - The EncryptedVector containing the second operand of each record
+/// * `operation` - The name of the combination, for error messages
+/// * `combine` - Combines two operands widened to the same number of blocks
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The combined values with the scale of
+///   the operands, or an error if the vectors have different lengths, scales or client keys
+fn combine_elementwise(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    operation: &str,
+    combine: impl Fn(&SignedRadixCiphertext, &SignedRadixCiphertext) -> SignedRadixCiphertext,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    ensure_signed_radix(a, operation)?;
+    ensure_signed_radix(b, operation)?;
+    if a.length != b.length {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} values in the first vector but {} in the second",
+            a.length, b.length
+        )));
+    }
+    if a.scale != b.scale {
+        return Err(ComputationError::InvalidInput(format!(
+            "Scale mismatch: the first vector is scaled by {} but the second by {}",
+            a.scale, b.scale
+        )));
+    }
+    let key_fingerprint = shared_key_fingerprint(&[a, b])?;
+
+    let a_column = a.decode()?;
+    let b_column = b.decode()?;
+    let num_blocks = a_column.widest_blocks().max(b_column.widest_blocks()) + 1;
+    let results: Vec<SignedRadixCiphertext> = a_column
+        .ciphertexts
+        .iter()
+        .zip(b_column.ciphertexts.iter())
+        .map(|(x, y)| {
+            combine(
+                &sign_extend(x, num_blocks, server_key),
+                &sign_extend(y, num_blocks, server_key),
+            )
+        })
+        .collect();
+
+    Ok(serialize_ciphertexts(&results)
+        .with_scale(a.scale)
+        .stamped(key_fingerprint))
+}
+
+/// Computes the mean of the per-record products of two encrypted vectors
+///
+/// The products from `compute_encrypted_elementwise_product` are averaged like any other
+/// column, so the result decrypts directly to the mean product, e.g. the mean of
+/// glucose × age from which a glucose-age index follows.
+///
+/// # Arguments
+/// * `a` - The EncryptedVector containing the first factor of each record
+/// * `b` - The EncryptedVector containing the second factor of each record, aligned with `a`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted mean product, with the
+///   product of the two scales times `MEAN_PRECISION`, or an error if the vectors are
+///   empty, have different lengths or cannot be multiplied
+pub fn compute_encrypted_product_mean(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let products = compute_encrypted_elementwise_product(a, b, server_key)?;
+    compute_encrypted_mean(&products, server_key)
+}
+
+/// Computes the encrypted sums from which the covariance of two columns follows
+///
+/// The covariance needs a division by the number of values, which is not available on
+/// ciphertexts, so only the sums are computed homomorphically: the dot product `Σxy` with
+/// `compute_encrypted_dot_product`, and `Σx` and `Σy` with `compute_encrypted_sum`. The
+/// client finishes `Σxy/n − (Σx/n)(Σy/n)` after decryption, and with the
+/// variance terms of both columns the Pearson correlation.
+///
+/// The returned vector carries the scale of its first element, `Σxy`, which is
+/// `x.scale * y.scale`. The second element, `Σx`, decrypts with the scale of `x` and the
+/// third, `Σy`, with the scale of `y`. `BiosampleFHE::decrypt_covariance` does all three.
+///
+/// # Arguments
+/// * `x` - The EncryptedVector containing the first column
+/// * `y` - The EncryptedVector containing the second column, aligned with `x`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted `Σxy`, `Σx` and `Σy`, or an
+///   error if the vectors are empty, have different lengths or client keys or cannot be
+///   multiplied
+#[allow(dead_code)]
+pub fn compute_encrypted_covariance(
+    x: &EncryptedVector,
+    y: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    shared_key_fingerprint(&[x, y])?;
+    let sum_x = compute_encrypted_sum(x, server_key)?;
+    let sum_y = compute_encrypted_sum(y, server_key)?;
+    covariance_from_sums(x, y, &sum_x, &sum_y, server_key)
+}
+
+/// Computes the encrypted covariance terms of two columns whose sums are already known
+///
+/// # Arguments
+/// * `x` - The EncryptedVector containing the first column
+/// * `y` - The EncryptedVector containing the second column, aligned with `x`
+/// * `sum_x` - The encrypted sum of `x`, as from `compute_encrypted_sum`
+/// * `sum_y` - The encrypted sum of `y`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The terms of `compute_encrypted_covariance`,
+///   or an error if the vectors are empty or have different lengths
+fn covariance_from_sums(
+    x: &EncryptedVector,
+    y: &EncryptedVector,
+    sum_x: &SignedRadixCiphertext,
+    sum_y: &SignedRadixCiphertext,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let sum_xy = compute_encrypted_dot_product(x, y, server_key)?;
+    let mut terms = sum_xy.decode()?.ciphertexts;
+    terms.extend([sum_x.clone(), sum_y.clone()]);
+
+    Ok(serialize_ciphertexts(&terms)
+        .with_scale(x.scale * y.scale)
+        .stamped(sum_xy.key_fingerprint))
+}
+
+/// Computes the encrypted sums from which the mean of a column in two groups follows
+///
+/// The groups are the records whose boolean `indicator` is 1 and those whose indicator is
+/// 0, e.g. marker-positive and marker-negative patients. The first group's sum is the dot
+/// product `Σ field·indicator`. The complement `1 − indicator` of every record is obtained
+/// by subtracting its indicator from a trivial 1, and the second group's sum
+/// `Σ field·(1 − indicator)` as the column total minus the first group's sum, which saves
+/// a second round of multiplications. The client divides each sum by its group's count.
+///
+/// The returned vector holds `Σ field·indicator`, `Σ indicator`, `Σ field·(1 − indicator)`
+/// and `Σ (1 − indicator)`, and carries the scale of the two sums, which is that of
+/// `field`. The two counts decrypt with a scale of 1. `BiosampleFHE::decrypt_group_means`
+/// does all four.
+///
+/// # Arguments
+/// * `field` - The EncryptedVector containing the column to average
+/// * `indicator` - The boolean EncryptedVector assigning each record to a group
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted sums and counts of both
+///   groups, or an error if the indicator is not boolean or the vectors are empty or have
+///   different lengths or client keys
+#[allow(dead_code)]
+pub fn compute_encrypted_group_means(
+    field: &EncryptedVector,
+    indicator: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    shared_key_fingerprint(&[field, indicator])?;
+    let total = compute_encrypted_sum(field, server_key)?;
+    let positive_count = compute_encrypted_sum(indicator, server_key)?;
+    group_means_from_sums(field, indicator, &total, &positive_count, server_key)
+}
+
+/// Computes the encrypted group sums and counts of a column whose sums are already known
+///
+/// # Arguments
+/// * `field` - The EncryptedVector containing the column to average
+/// * `indicator` - The boolean EncryptedVector assigning each record to a group
+/// * `total` - The encrypted sum of `field`, as from `compute_encrypted_sum`
+/// * `positive_count` - The encrypted sum of `indicator`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The terms of
+///   `compute_encrypted_group_means`, or an error if the indicator is not boolean or the
+///   vectors are empty or have different lengths
+fn group_means_from_sums(
+    field: &EncryptedVector,
+    indicator: &EncryptedVector,
+    total: &SignedRadixCiphertext,
+    positive_count: &SignedRadixCiphertext,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    if indicator.kind != VectorKind::Boolean {
+        return Err(ComputationError::Unsupported(
+            "Group indicator must be a boolean vector".to_string(),
+        ));
+    }
+
+    let positive_sums = compute_encrypted_dot_product(field, indicator, server_key)?;
+    let key_fingerprint = positive_sums.key_fingerprint;
+    let mut positive_sums = positive_sums.decode()?.ciphertexts;
+    let positive_sum = positive_sums.remove(0);
+    let total = sign_extend(total, positive_sum.blocks().len(), server_key);
+    let negative_sum = server_key.sub_parallelized(&total, &positive_sum);
+
+    // The records outside the group are all records but the group's, at the width of a
+    // count of every record
+    let num_blocks = sum_width_blocks(FHE_BOOL_BLOCKS, indicator.length, server_key)
+        .max(positive_count.blocks().len());
+    let length: SignedRadixCiphertext =
+        server_key.create_trivial_radix(indicator.length as i64, num_blocks);
+    let negative_count = server_key.sub_parallelized(
+        &length,
+        &sign_extend(positive_count, num_blocks, server_key),
+    );
+
+    let terms = vec![
+        positive_sum,
+        positive_count.clone(),
+        negative_sum,
+        negative_count,
+    ];
+    Ok(serialize_ciphertexts(&terms)
+        .with_scale(field.scale * indicator.scale)
+        .stamped(key_fingerprint))
+}
+
+/// Computes a per-record weighted sum of several encrypted fields with plaintext weights
+///
+/// Each weight is turned into an integer by multiplying it by `scale` and rounding, each
+/// value is multiplied by its field's integer weight with a scalar multiplication, and
+/// the products are added record by record, e.g. `0.3 * glucose + 0.7 * cholesterol`.
+/// The ciphertexts are sign-extended first so that neither the products nor their sum
+/// can overflow.
+///
+/// All fields must share one value scale. The scores then carry the combined scale
+/// `value scale * scale`, which is stored in the returned vector's metadata, so they
+/// decrypt to real units with `BiosampleFHE::decrypt_f64_vector(&scores, scores.scale)`.
+///
+/// # Arguments
+/// * `fields` - The encrypted fields, each with its plaintext weight
+/// * `server_key` - The ServerKey used for homomorphic operations
+/// * `scale` - The factor that turns the weights into integers, e.g. 10 for one decimal
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - One encrypted score per record, or an
+///   error if there are no fields or they differ in length, scale or client key
+#[allow(dead_code)]
+pub fn compute_encrypted_weighted_sum(
+    fields: &[(&EncryptedVector, f64)],
+    server_key: &ServerKey,
+    scale: f64,
+) -> Result<EncryptedVector, ComputationError> {
+    let (first, _) = fields.first().ok_or_else(|| {
+        ComputationError::EmptyInput("Cannot compute weighted sum of no fields".to_string())
+    })?;
+    for (field, _) in fields {
+        ensure_signed_radix(field, "weighted sum")?;
+        if field.length != first.length {
+            return Err(ComputationError::LengthMismatch(format!(
+                "Length mismatch: {} values in one field but {} in another",
+                first.length, field.length
+            )));
+        }
+        if field.scale != first.scale {
+            return Err(ComputationError::InvalidInput(format!(
+                "Scale mismatch: fields are scaled by {} and {}",
+                first.scale, field.scale
+            )));
+        }
+    }
+    let inputs: Vec<&EncryptedVector> = fields.iter().map(|(field, _)| *field).collect();
+    let key_fingerprint = shared_key_fingerprint(&inputs)?;
+
+    let weighted = fields
+        .iter()
+        .map(|(field, weight)| Ok((*field, integer_weight(*weight, scale)?)))
+        .collect::<Result<Vec<_>, ComputationError>>()?;
+    let scores = integer_weighted_sums(&weighted, 0, server_key)?;
+
+    Ok(serialize_ciphertexts(&scores)
+        .with_scale(first.scale * scale)
+        .stamped(key_fingerprint))
+}
+
+/// Turns a plaintext weight into an integer by multiplying it by `scale` and rounding
+///
+/// # Arguments
+/// * `weight` - The plaintext weight
+/// * `scale` - The factor that turns the weight into an integer
+///
+/// # Returns
+/// * `Result<i64, ComputationError>` - The integer weight, or an error if it is not finite
+///   or does not fit a 64-bit integer
+fn integer_weight(weight: f64, scale: f64) -> Result<i64, ComputationError> {
+    let scaled = (weight * scale).round();
+    if !scaled.is_finite() || scaled.abs() >= i64::MAX as f64 {
+        return Err(ComputationError::Overflow(format!(
+            "Weight {} does not fit a 64-bit integer at scale {}",
+            weight, scale
+        )));
+    }
+    Ok(scaled as i64)
+}
+
+/// Computes the per-record sums of encrypted fields times integer weights, plus a constant
+///
+/// The ciphertexts are sign-extended first so that neither the products, their sum nor
+/// the added constant can overflow. The fields must have the same length.
+///
+/// # Arguments
+/// * `fields` - The encrypted fields, each with its integer weight
+/// * `constant` - The integer added to every sum
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<Vec<SignedRadixCiphertext>, ComputationError>` - One encrypted sum per record,
+///   or an error if there are no fields
+fn integer_weighted_sums(
+    fields: &[(&EncryptedVector, i64)],
+    constant: i64,
+    server_key: &ServerKey,
+) -> Result<Vec<SignedRadixCiphertext>, ComputationError> {
+    let (first, _) = fields.first().ok_or_else(|| {
+        ComputationError::EmptyInput("Cannot compute sum of no fields".to_string())
+    })?;
+
+    // Room for a value times the largest weight, summed over all fields and the constant
+    let bits_per_block = server_key.message_modulus().0.ilog2();
+    let largest_weight = fields
+        .iter()
+        .map(|(_, w)| w.unsigned_abs())
+        .max()
+        .unwrap_or(0);
+    let weight_bits = u64::BITS - largest_weight.leading_zeros();
+    let sum_bits = usize::BITS - fields.len().saturating_sub(1).leading_zeros();
+    let value_bits = FHE_INT_BITS as u32 * bits_per_block + weight_bits + sum_bits;
+    let total_bits = if constant == 0 {
+        value_bits
+    } else {
+        let constant_bits = u64::BITS - constant.unsigned_abs().leading_zeros() + 1;
+        value_bits.max(constant_bits) + 1
+    };
+    let num_blocks = total_bits.div_ceil(bits_per_block) as usize;
+
+    let products: Vec<Vec<SignedRadixCiphertext>> = fields
+        .iter()
+        .map(|(field, weight)| {
+            Ok(sign_extend_to(field, num_blocks, server_key)?
+                .iter()
+                .map(|value| server_key.scalar_mul_parallelized(value, *weight))
+                .collect())
+        })
+        .collect::<Result<_, ComputationError>>()?;
+
+    // Move each record's products out of the columns instead of copying them
+    let mut columns: Vec<_> = products.into_iter().map(Vec::into_iter).collect();
+    (0..first.length)
+        .map(|_| {
+            let terms: Vec<SignedRadixCiphertext> =
+                columns.iter_mut().filter_map(Iterator::next).collect();
+            let sum = server_key
+                .sum_ciphertexts_parallelized(&terms)
+                .ok_or_else(|| {
+                    ComputationError::EmptyInput("Cannot compute sum of no fields".to_string())
+                })?;
+            Ok(if constant == 0 {
+                sum
+            } else {
+                server_key.scalar_add_parallelized(&sum, constant)
+            })
+        })
+        .collect()
+}
+
+/// Computes the cohort total of a weighted sum of several encrypted fields
+///
+/// The per-record scores of `compute_encrypted_weighted_sum` are summed, so
+/// the total carries the same combined scale `value scale * scale`.
+///
+/// # Arguments
+/// * `fields` - The encrypted fields, each with its plaintext weight
+/// * `server_key` - The ServerKey used for homomorphic operations
+/// * `scale` - The factor that turns the weights into integers, e.g. 10 for one decimal
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted total as a single-element
+///   vector, or an error if there are no fields, no records, or the fields differ in
+///   length or scale
+#[allow(dead_code)]
+pub fn compute_encrypted_weighted_total(
+    fields: &[(&EncryptedVector, f64)],
+    server_key: &ServerKey,
+    scale: f64,
+) -> Result<EncryptedVector, ComputationError> {
+    let scores = compute_encrypted_weighted_sum(fields, server_key, scale)?;
+    let total = compute_encrypted_sum(&scores, server_key)?;
+    Ok(serialize_ciphertexts(&[total])
+        .with_scale(scores.scale)
+        .stamped(scores.key_fingerprint))
+}
+
+/// Factor by which the weights of a `RiskModel` are turned into integers
+///
+/// Weights keep three decimals, which is finer than any clinically meaningful weight.
+pub const RISK_WEIGHT_SCALE: f64 = 1000.0;
+
+/// A linear risk model over encrypted biosample fields
+///
+/// A patient's score is `intercept + Σ weight · value` over the weighted fields, and the
+/// patient counts as at risk when the score is above `threshold`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RiskModel {
+    pub weights: HashMap<String, f64>, // Weight of each field, by field name
+    pub intercept: f64,                // Constant added to every score
+    pub threshold: f64,                // Score above which a patient is at risk
+}
+
+impl RiskModel {
+    /// Creates a model without weighted fields
+    ///
+    /// # Arguments
+    /// * `intercept` - The constant added to every score
+    /// * `threshold` - The score above which a patient is at risk
+    ///
+    /// # Returns
+    /// A new `RiskModel`, to which `with_weight` adds fields
+    #[allow(dead_code)]
+    pub fn new(intercept: f64, threshold: f64) -> Self {
+        Self {
+            weights: HashMap::new(),
+            intercept,
+            threshold,
+        }
+    }
+
+    /// Sets the weight of a field
+    ///
+    /// # Arguments
+    /// * `field` - The name of the field, e.g. `fields::GLUCOSE`
+    /// * `weight` - The weight of the field's values in real units
+    ///
+    /// # Returns
+    /// The updated `RiskModel`
+    #[allow(dead_code)]
+    pub fn with_weight(mut self, field: &str, weight: f64) -> Self {
+        self.weights.insert(field.to_string(), weight);
+        self
+    }
+}
+
+/// Encrypted risk scores of a cohort and the number of patients at risk
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct EncryptedRiskScores {
+    pub scores: EncryptedVector, // Score of each patient, with the scale of the scores
+    pub above_threshold: EncryptedVector, // Number of patients whose score is above the threshold
+}
+
+/// Scores every patient with a linear risk model and counts the patients at risk
+///
+/// The fields may have different scales. The scores are computed at the largest of them
+/// times `RISK_WEIGHT_SCALE`, so each weight is turned into an integer for its field's
+/// scale, multiplied in with a scalar multiplication, and the integer intercept is added.
+/// Every score is then compared with the integer threshold, and the comparison results
+/// are summed. The client only needs to decrypt the count; the scores never have to leave
+/// the server.
+///
+/// # Arguments
+/// * `model` - The weights, intercept and threshold of the model
+/// * `dataset` - The encrypted fields, by name, e.g. from `encrypt_biosample_data`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedRiskScores, ComputationError>` - The encrypted score of each patient
+///   and the encrypted number of scores above the threshold, or an error if the model has
+///   no weights, a weighted field is missing, or the fields differ in length or client key
+#[allow(dead_code)]
+pub fn compute_encrypted_risk_scores(
+    model: &RiskModel,
+    dataset: &HashMap<String, EncryptedVector>,
+    server_key: &ServerKey,
+) -> Result<EncryptedRiskScores, ComputationError> {
+    let mut fields = Vec::with_capacity(model.weights.len());
+    for (name, &weight) in &model.weights {
+        let field = dataset
+            .get(name)
+            .ok_or_else(|| EncryptionError::MissingField(name.clone()))?;
+        ensure_signed_radix(field, "risk score")?;
+        fields.push((field, weight));
+    }
+    let (first, _) = fields.first().ok_or_else(|| {
+        ComputationError::EmptyInput("Cannot compute risk scores without weights".to_string())
+    })?;
+    if let Some((field, _)) = fields
+        .iter()
+        .find(|(field, _)| field.length != first.length)
+    {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} values in one field but {} in another",
+            first.length, field.length
+        )));
+    }
+    let inputs: Vec<&EncryptedVector> = fields.iter().map(|(field, _)| *field).collect();
+    let key_fingerprint = shared_key_fingerprint(&inputs)?;
+
+    // Each weight makes up for the difference between its field's scale and the score's
+    let value_scale = fields
+        .iter()
+        .map(|(field, _)| field.scale)
+        .fold(f64::MIN, f64::max);
+    let score_scale = value_scale * RISK_WEIGHT_SCALE;
+    let weighted = fields
+        .iter()
+        .map(|(field, weight)| Ok((*field, integer_weight(*weight, score_scale / field.scale)?)))
+        .collect::<Result<Vec<_>, ComputationError>>()?;
+    let intercept = integer_weight(model.intercept, score_scale)?;
+    let threshold = integer_weight(model.threshold, score_scale)?;
+    let scores = integer_weighted_sums(&weighted, intercept, server_key)?;
+
+    let above: Vec<SignedRadixCiphertext> = scores
+        .iter()
+        .map(|score| {
+            server_key
+                .scalar_gt_parallelized(score, threshold)
+                .into_radix(1, server_key)
+        })
+        .collect();
+    let mut above = serialize_ciphertexts(&above);
+    above.kind = VectorKind::Boolean;
+    let count = compute_encrypted_sum(&above, server_key)?;
+
+    Ok(EncryptedRiskScores {
+        scores: serialize_ciphertexts(&scores)
+            .with_scale(score_scale)
+            .stamped(key_fingerprint),
+        above_threshold: serialize_ciphertexts(&[count]).stamped(key_fingerprint),
+    })
+}
+
+/// Computes the encrypted sum and sum of squares of a vector, from which the variance follows
+///
+/// The variance needs a division by the number of values, which is not available on
+/// ciphertexts, so only the two sums are computed homomorphically and the client finishes
+/// `E[x²] − E[x]²` after decryption. The ciphertexts are sign-extended before squaring,
+/// since a square needs twice the width of its value.
+///
+/// The returned vector carries the scale of the input values. Its first element is the sum,
+/// which decrypts with that scale; its second element is the sum of squares, which must be
+/// decrypted with the square of that scale. `BiosampleFHE::decrypt_variance` does both.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing the encrypted values
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted sum and sum of squares, or an
+///   error if the vector is empty or does not hold numeric values
+pub fn compute_encrypted_variance(
+    encrypted_vector: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let sum = compute_encrypted_sum(encrypted_vector, server_key)?;
+    variance_from_sum(encrypted_vector, &sum, server_key)
+}
+
+/// Computes the encrypted variance terms of a vector whose sum is already known
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+/// * `sum` - The encrypted sum of its values, as from `compute_encrypted_sum`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The sum and sum of squares, as from
+///   `compute_encrypted_variance`, or an error if the vector is not numeric or is empty
+fn variance_from_sum(
+    encrypted_vector: &EncryptedVector,
+    sum: &SignedRadixCiphertext,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    if encrypted_vector.kind != VectorKind::Numeric {
+        return Err(ComputationError::Unsupported(format!(
+            "Variance needs numeric values, got a {:?} vector",
+            encrypted_vector.kind
+        )));
+    }
+    if encrypted_vector.length == 0 {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute variance of empty vector".to_string(),
+        ));
+    }
+
+    let num_blocks = product_sum_blocks(encrypted_vector.length, server_key);
+    let values = sign_extend_to(encrypted_vector, num_blocks, server_key)?;
+    let squares: Vec<SignedRadixCiphertext> = values
+        .iter()
+        .map(|value| server_key.mul_parallelized(value, value))
+        .collect();
+
+    let sum = sign_extend(sum, num_blocks, server_key);
+    let sum_of_squares = server_key
+        .sum_ciphertexts_parallelized(&squares)
+        .ok_or_else(|| {
+            ComputationError::EmptyInput("Cannot compute sum of empty vector".to_string())
+        })?;
+    Ok(serialize_ciphertexts(&[sum, sum_of_squares])
+        .with_scale(encrypted_vector.scale)
+        .stamped(encrypted_vector.key_fingerprint))
+}
+
+/// Encrypted sums from which the difference between the means of two cohorts follows
+///
+/// Each cohort's vector holds its sum and sum of squares, as from
+/// `compute_encrypted_variance`. The lengths of the cohorts are not secret and are kept
+/// in the clear. After decryption, `decrypt_difference` finishes the difference and its
+/// standard error.
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct MeanDifferenceTerms {
+    pub cohort_a: EncryptedVector, // Sum and sum of squares of the first cohort
+    pub cohort_b: EncryptedVector, // Sum and sum of squares of the second cohort
+    pub length_a: usize,           // Number of values in the first cohort
+    pub length_b: usize,           // Number of values in the second cohort
+}
+
+impl MeanDifferenceTerms {
+    /// Decrypts the sums and finishes the difference in means and its standard error
+    ///
+    /// The standard error is the naive `sqrt(s_a²/n_a + s_b²/n_b)` with the sample
+    /// variance of each cohort, which does not assume equal variances.
+    ///
+    /// # Arguments
+    /// * `fhe` - The BiosampleFHE instance holding the client key
+    ///
+    /// # Returns
+    /// * `Result<(f64, f64), ComputationError>` - The mean of the first cohort minus the mean
+    ///   of the second, and its standard error, or an error if a cohort has fewer than two
+    ///   values or the sums cannot be decrypted
+    #[allow(dead_code)]
+    pub fn decrypt_difference(&self, fhe: &BiosampleFHE) -> Result<(f64, f64), ComputationError> {
+        let cohort = |terms: &EncryptedVector, length: usize| -> Result<_, ComputationError> {
+            if length < 2 {
+                return Err(ComputationError::InvalidInput(format!(
+                    "A standard error needs at least two values per cohort, got {}",
+                    length
+                )));
+            }
+            let n = length as f64;
+            let sum = fhe.decrypt_element(terms, 0, terms.scale)?;
+            let sum_of_squares = fhe.decrypt_element(terms, 1, terms.scale * terms.scale)?;
+            let mean = sum / n;
+            let sample_variance = ((sum_of_squares - n * mean * mean) / (n - 1.0)).max(0.0);
+            Ok((mean, sample_variance / n))
+        };
+
+        let (mean_a, squared_error_a) = cohort(&self.cohort_a, self.length_a)?;
+        let (mean_b, squared_error_b) = cohort(&self.cohort_b, self.length_b)?;
+        Ok((mean_a - mean_b, (squared_error_a + squared_error_b).sqrt()))
+    }
+}
+
+/// Computes the encrypted sums from which the difference between two cohorts' means follows
+///
+/// Each cohort, e.g. the patients of one facility, is reduced to its sum and sum of squares
+/// with `compute_encrypted_variance`, so facilities can compare cohorts without pooling
+/// their records. Both cohorts must be encrypted under the same key and scale.
+///
+/// # Arguments
+/// * `cohort_a` - The EncryptedVector containing the first cohort's values
+/// * `cohort_b` - The EncryptedVector containing the second cohort's values
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<MeanDifferenceTerms, ComputationError>` - The encrypted sums and the lengths of
+///   both cohorts, or an error if a cohort is empty or not numeric, or the cohorts were
+///   encrypted with different scales or client keys
+#[allow(dead_code)]
+pub fn compute_encrypted_mean_difference(
+    cohort_a: &EncryptedVector,
+    cohort_b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<MeanDifferenceTerms, ComputationError> {
+    shared_key_fingerprint(&[cohort_a, cohort_b])?;
+    if cohort_a.scale != cohort_b.scale {
+        return Err(ComputationError::InvalidInput(format!(
+            "Scale mismatch: the first cohort has scale {} but the second {}",
+            cohort_a.scale, cohort_b.scale
+        )));
+    }
+
+    Ok(MeanDifferenceTerms {
+        cohort_a: compute_encrypted_variance(cohort_a, server_key)?,
+        cohort_b: compute_encrypted_variance(cohort_b, server_key)?,
+        length_a: cohort_a.length,
+        length_b: cohort_b.length,
+    })
+}
+
+/// Encrypted sums from which the Pearson correlation of two columns is assembled
+///
+/// The correlation needs a division and a square root, which are not available on
+/// ciphertexts, so only these sums are computed homomorphically. After decrypting each
+/// term with its own scale, pass them to `pearson_from_terms`.
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct CorrelationTerms {
+    pub length: usize,             // Number of value pairs
+    pub sum_a: EncryptedVector,    // Sum of the first column
+    pub sum_b: EncryptedVector,    // Sum of the second column
+    pub sum_ab: EncryptedVector,   // Sum of the pairwise products
+    pub sum_a_sq: EncryptedVector, // Sum of the squares of the first column
+    pub sum_b_sq: EncryptedVector, // Sum of the squares of the second column
+}
+
+/// Computes the encrypted sums needed for the Pearson correlation of two columns
+///
+/// The ciphertexts are first sign-extended to hold a sum of `length` products, since a
+/// product of two values needs twice their width. Each returned term carries the scale of
+/// its values (e.g. `a.scale * b.scale` for `sum_ab`), so it decrypts to real units.
+///
+/// # Arguments
+/// * `a` - The EncryptedVector containing the first column
+/// * `b` - The EncryptedVector containing the second column, aligned with `a`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<CorrelationTerms, ComputationError>` - The encrypted sums, or an error if the
+///   vectors are empty or have different lengths or client keys
+#[allow(dead_code)]
+pub fn compute_encrypted_correlation_terms(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<CorrelationTerms, ComputationError> {
+    shared_key_fingerprint(&[a, b])?;
+    let sum_a = compute_encrypted_sum(a, server_key)?;
+    let sum_b = compute_encrypted_sum(b, server_key)?;
+    correlation_terms_from_sums(a, b, &sum_a, &sum_b, server_key)
+}
+
+/// Computes the encrypted correlation terms of two columns whose sums are already known
+///
+/// # Arguments
+/// * `a` - The EncryptedVector containing the first column
+/// * `b` - The EncryptedVector containing the second column, aligned with `a`
+/// * `sum_a` - The encrypted sum of `a`, as from `compute_encrypted_sum`
+/// * `sum_b` - The encrypted sum of `b`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<CorrelationTerms, ComputationError>` - The encrypted sums, or an error if the
+///   vectors are empty or have different lengths or client keys
+fn correlation_terms_from_sums(
+    a: &EncryptedVector,
+    b: &EncryptedVector,
+    sum_a: &SignedRadixCiphertext,
+    sum_b: &SignedRadixCiphertext,
+    server_key: &ServerKey,
+) -> Result<CorrelationTerms, ComputationError> {
+    if a.length != b.length {
+        return Err(ComputationError::LengthMismatch(format!(
+            "Length mismatch: {} values in the first column but {} in the second",
+            a.length, b.length
+        )));
+    }
+    if a.length == 0 {
+        return Err(ComputationError::EmptyInput(
+            "Cannot compute correlation of empty vectors".to_string(),
+        ));
+    }
+    let key_fingerprint = shared_key_fingerprint(&[a, b])?;
+
+    let num_blocks = product_sum_blocks(a.length, server_key);
+    let a_values = sign_extend_to(a, num_blocks, server_key)?;
+    let b_values = sign_extend_to(b, num_blocks, server_key)?;
+
+    let products = |x: &[SignedRadixCiphertext], y: &[SignedRadixCiphertext]| {
+        x.iter()
+            .zip(y.iter())
+            .map(|(x, y)| server_key.mul_parallelized(x, y))
+            .collect::<Vec<_>>()
+    };
+    let sum = |values: &[SignedRadixCiphertext], scale: f64| -> Result<_, ComputationError> {
+        let sum = server_key
+            .sum_ciphertexts_parallelized(values)
+            .ok_or_else(|| {
+                ComputationError::EmptyInput("Cannot compute sum of empty vector".to_string())
+            })?;
+        Ok(serialize_ciphertexts(&[sum])
+            .with_scale(scale)
+            .stamped(key_fingerprint))
+    };
+
+    let known_sum = |sum: &SignedRadixCiphertext, scale: f64| {
+        serialize_ciphertexts(&[sign_extend(sum, num_blocks, server_key)])
+            .with_scale(scale)
+            .stamped(key_fingerprint)
+    };
+
+    Ok(CorrelationTerms {
+        length: a.length,
+        sum_a: known_sum(sum_a, a.scale),
+        sum_b: known_sum(sum_b, b.scale),
+        sum_ab: sum(&products(&a_values, &b_values), a.scale * b.scale)?,
+        sum_a_sq: sum(&products(&a_values, &a_values), a.scale * a.scale)?,
+        sum_b_sq: sum(&products(&b_values, &b_values), b.scale * b.scale)?,
+    })
+}
+
+/// Assembles the Pearson correlation coefficient from decrypted correlation terms
+///
+/// # Arguments
+/// * `n` - The number of value pairs
+/// * `sum_a`, `sum_b` - The decrypted sums of each column
+/// * `sum_ab` - The decrypted sum of the pairwise products
+/// * `sum_a_sq`, `sum_b_sq` - The decrypted sums of the squares of each column
+///
+/// # Returns
+/// * The correlation coefficient, or `None` if either column has zero variance
+#[allow(dead_code)]
+pub fn pearson_from_terms(
+    n: usize,
+    sum_a: f64,
+    sum_b: f64,
+    sum_ab: f64,
+    sum_a_sq: f64,
+    sum_b_sq: f64,
+) -> Option<f64> {
+    let n = n as f64;
+    let covariance = n * sum_ab - sum_a * sum_b;
+    let variance_a = n * sum_a_sq - sum_a * sum_a;
+    let variance_b = n * sum_b_sq - sum_b * sum_b;
+    if variance_a <= 0.0 || variance_b <= 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_a * variance_b).sqrt())
+}
+
+/// Computes the encrypted sums from which a simple linear regression of `y` on `x` follows
+///
+/// Fitting `y = slope·x + intercept` by least squares needs divisions, so only the sums
+/// are computed homomorphically, with the widening and products of
+/// `compute_encrypted_correlation_terms`. The client decrypts them and finishes the fit
+/// with `finish_regression`.
+///
+/// The returned vector holds `Σx`, `Σy`, `Σxy`, `Σx²` and `Σy²`, the last one for the
+/// correlation coefficient of the fit. It carries the scale of its first element, which is
+/// that of `x`; the others decrypt with the scales of `y`, `x·y`, `x²` and `y²`.
+///
+/// # Arguments
+/// * `x` - The EncryptedVector containing the explanatory column, e.g. ages
+/// * `y` - The EncryptedVector containing the response column, aligned with `x`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The five encrypted sums, or an error if
+///   the vectors are empty or have different lengths or client keys
+#[allow(dead_code)]
+pub fn compute_encrypted_linear_regression(
+    x: &EncryptedVector,
+    y: &EncryptedVector,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let terms = compute_encrypted_correlation_terms(x, y, server_key)?;
+    regression_from_terms(terms, x.scale)
+}
+
+/// Packs the correlation terms of two columns into the regression sums
+///
+/// # Arguments
+/// * `terms` - The encrypted correlation terms of the regressor and the response
+/// * `scale` - The scale of the regressor
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The sums of
+///   `compute_encrypted_linear_regression` in a single vector, or an error if a sum cannot
+///   be decoded
+fn regression_from_terms(
+    terms: CorrelationTerms,
+    scale: f64,
+) -> Result<EncryptedVector, ComputationError> {
+    let key_fingerprint = terms.sum_a.key_fingerprint;
+    let sums = [
+        terms.sum_a,
+        terms.sum_b,
+        terms.sum_ab,
+        terms.sum_a_sq,
+        terms.sum_b_sq,
+    ];
+    let mut ciphertexts = Vec::with_capacity(sums.len());
+    for sum in &sums {
+        ciphertexts.extend(sum.decode()?.ciphertexts);
+    }
+    Ok(serialize_ciphertexts(&ciphertexts)
+        .with_scale(scale)
+        .stamped(key_fingerprint))
+}
+
+/// Finishes a simple linear regression from its decrypted sums
+///
+/// # Arguments
+/// * `decrypted` - The decrypted `Σx`, `Σy`, `Σxy`, `Σx²` and `Σy²` from
+///   `compute_encrypted_linear_regression`, each decrypted with its own scale
+/// * `n` - The number of value pairs
+///
+/// # Returns
+/// * The slope and intercept of the least-squares line and the correlation coefficient `r`,
+///   or `None` if either column has zero variance
+#[allow(dead_code)]
+pub fn finish_regression(decrypted: [f64; 5], n: usize) -> Option<(f64, f64, f64)> {
+    let [sum_x, sum_y, sum_xy, sum_x_sq, sum_y_sq] = decrypted;
+    let r = pearson_from_terms(n, sum_x, sum_y, sum_xy, sum_x_sq, sum_y_sq)?;
+
+    let n = n as f64;
+    let slope = (n * sum_xy - sum_x * sum_y) / (n * sum_x_sq - sum_x * sum_x);
+    let intercept = (sum_y - slope * sum_x) / n;
+    Some((slope, intercept, r))
+}
+
+/// Computes the count of each category in a map of encrypted category vectors
+///
+/// # Arguments
+/// * `encrypted_categories` - A map of category names to encrypted vectors where each vector
+///   contains binary indicators (0 or 1) for category membership
+/// * `server_key` - The server key used for homomorphic operations
+///
+/// # Returns
+/// * A map of category names to encrypted counts, where each count is the sum of the binary indicators
+///
+/// # Errors
+/// * Returns an error if any of the homomorphic operations fail
+pub fn compute_encrypted_category_counts(
+    encrypted_categories: &HashMap<String, EncryptedVector>,
+    server_key: &ServerKey,
+) -> Result<HashMap<String, EncryptedVector>, ComputationError> {
+    compute_encrypted_counts_with_prefix(
+        encrypted_categories,
+        fields::BLOOD_TYPE_PREFIX,
+        server_key,
+    )
+}
+
+/// Computes the number of records of each facility
+///
+/// # Arguments
+/// * `encrypted_data` - A map of field names to encrypted vectors, of which the
+///   `facility_*` one-hot vectors are counted
+/// * `server_key` - The server key used for homomorphic operations
+///
+/// # Returns
+/// * A map of facility keys, e.g. "facility_3", to encrypted record counts
+///
+/// # Errors
+/// * Returns an error if any of the homomorphic operations fail
+#[allow(dead_code)]
+pub fn compute_encrypted_facility_counts(
+    encrypted_data: &HashMap<String, EncryptedVector>,
+    server_key: &ServerKey,
+) -> Result<HashMap<String, EncryptedVector>, ComputationError> {
+    compute_encrypted_counts_with_prefix(encrypted_data, fields::FACILITY_PREFIX, server_key)
+}
+
+/// Sums the one-hot vectors whose keys start with a prefix into encrypted counts
+///
+/// # Arguments
+/// * `encrypted_categories` - A map of keys to encrypted vectors of binary indicators
+/// * `prefix` - The prefix of the keys to count, e.g. `fields::FACILITY_PREFIX`
+/// * `server_key` - The server key used for homomorphic operations
+///
+/// # Returns
+/// * A map of the matching keys to encrypted counts
+///
+/// # Errors
+/// * Returns an error if any of the homomorphic operations fail
+fn compute_encrypted_counts_with_prefix(
+    encrypted_categories: &HashMap<String, EncryptedVector>,
+    prefix: &str,
+    server_key: &ServerKey,
+) -> Result<HashMap<String, EncryptedVector>, ComputationError> {
+    let mut category_counts = HashMap::new();
+
+    for (category, encrypted_vector) in encrypted_categories {
+        if category.starts_with(prefix) {
+            let sum = compute_encrypted_sum(encrypted_vector, server_key)?;
+            category_counts.insert(
+                category.clone(),
+                serialize_ciphertexts(&[sum]).stamped(encrypted_vector.key_fingerprint),
+            );
+        }
+    }
+
+    Ok(category_counts)
+}
+
+/// Counts how many blood types occur at least once, without revealing which
+///
+/// Each `blood_type_*` mask is summed and the sum compared with zero, giving an encrypted
+/// "any" indicator per blood type. The indicators are summed into the distinct count, so
+/// neither the per-type counts nor the present types are revealed.
+///
+/// # Arguments
+/// * `encrypted_categories` - A map of field names to encrypted vectors, of which the
+///   `blood_type_*` one-hot masks are used
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted number of distinct blood
+///   types, which decrypts with a scale of 1, or an error if the map holds no blood type
+///   masks or they were encrypted under different client keys
+#[allow(dead_code)]
+pub fn compute_encrypted_distinct_count(
+    encrypted_categories: &HashMap<String, EncryptedVector>,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let mut blood_types: Vec<&String> = encrypted_categories
+        .keys()
+        .filter(|key| key.starts_with(fields::BLOOD_TYPE_PREFIX))
+        .collect();
+    if blood_types.is_empty() {
+        return Err(ComputationError::EmptyInput(
+            "No blood type masks to count distinct values over".to_string(),
+        ));
+    }
+    blood_types.sort();
+    let masks: Vec<&EncryptedVector> = blood_types
+        .into_iter()
+        .map(|key| &encrypted_categories[key])
+        .collect();
+    let key_fingerprint = shared_key_fingerprint(&masks)?;
+
+    let counts = masks
+        .into_iter()
+        .map(|mask| compute_encrypted_sum(mask, server_key))
+        .collect::<Result<Vec<SignedRadixCiphertext>, ComputationError>>()?;
+    let distinct = count_nonempty(&counts, server_key)?;
+    Ok(serialize_ciphertexts(&[distinct]).stamped(key_fingerprint))
+}
+
+/// Counts how many categories of an encrypted categorical variable occur at least once
+///
+/// Each category's one-hot vector is summed into its count, and the count is compared
+/// with a trivial encryption of zero into an encrypted "count > 0" flag. Summing the
+/// flags tells e.g. how many distinct blood types appear in a cohort without revealing
+/// which, or how many patients have each.
+///
+/// # Arguments
+/// * `categorical` - The encrypted categorical variable, e.g. from `encrypt_categorical`
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<EncryptedVector, ComputationError>` - The encrypted number of non-empty
+///   categories, which decrypts with a scale of 1, or an error if the variable has no
+///   categories, a category cannot be summed, or the categories were encrypted under
+///   different client keys
+#[allow(dead_code)]
+pub fn compute_encrypted_nonempty_category_count(
+    categorical: &EncryptedCategorical,
+    server_key: &ServerKey,
+) -> Result<EncryptedVector, ComputationError> {
+    let vectors: Vec<&EncryptedVector> = categorical.vectors.iter().collect();
+    let key_fingerprint = shared_key_fingerprint(&vectors)?;
+    let counts = vectors
+        .into_iter()
+        .map(|vector| compute_encrypted_sum(vector, server_key))
+        .collect::<Result<Vec<SignedRadixCiphertext>, ComputationError>>()?;
+    let nonempty = count_nonempty(&counts, server_key)?;
+    Ok(serialize_ciphertexts(&[nonempty]).stamped(key_fingerprint))
+}
+
+/// Counts how many encrypted counts are above zero
+///
+/// # Arguments
+/// * `counts` - The encrypted counts, e.g. the sums of one-hot vectors
+/// * `server_key` - The ServerKey used for homomorphic operations
+///
+/// # Returns
+/// * `Result<SignedRadixCiphertext, ComputationError>` - The encrypted number of non-zero
+///   counts, or an error if there are no counts
+fn count_nonempty(
+    counts: &[SignedRadixCiphertext],
+    server_key: &ServerKey,
+) -> Result<SignedRadixCiphertext, ComputationError> {
+    if counts.is_empty() {
+        return Err(ComputationError::EmptyInput(
+            "No category counts to check for presence".to_string(),
+        ));
+    }
+
+    let present: Vec<SignedRadixCiphertext> = counts
+        .iter()
+        .map(|count| {
+            let zero: SignedRadixCiphertext =
+                server_key.create_trivial_zero_radix(count.blocks().len());
+            server_key
+                .gt_parallelized(count, &zero)
+                .into_radix(FHE_BOOL_BLOCKS, server_key)
+        })
+        .collect();
+
+    let mut present = serialize_ciphertexts(&present);
+    present.kind = VectorKind::Boolean;
+    compute_encrypted_sum(&present, server_key)
+}
+
+/// Plaintext magnitude below which verification compares absolute rather than relative error
+const NEAR_ZERO_THRESHOLD: f64 = 1e-9;
+
+/// How close a decrypted result must be to the expected value to pass verification
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+pub enum Tolerance {
+    Absolute(f64),                         // Largest accepted absolute error
+    Relative(f64), // Largest accepted error as a fraction of the expected value
+    Both { absolute: f64, relative: f64 }, // Whichever of the two bounds is looser
+}
+
+impl Tolerance {
+    /// Returns the largest absolute error accepted for an expected value
+    ///
+    /// A relative bound on its own would only accept an exact match when the expected value
+    /// is near zero, so it is then applied to the absolute error instead. Combined with an
+    /// absolute bound, the absolute bound covers that case.
+    ///
+    /// # Arguments
+    /// * `expected` - The expected value
+    ///
+    /// # Returns
+    /// * The largest accepted absolute error
+    pub fn bound_for(&self, expected: f64) -> f64 {
+        match *self {
+            Tolerance::Absolute(absolute) => absolute,
+            Tolerance::Relative(relative) if expected.abs() < NEAR_ZERO_THRESHOLD => relative,
+            Tolerance::Relative(relative) => relative * expected.abs(),
+            Tolerance::Both { absolute, relative } => absolute.max(relative * expected.abs()),
+        }
+    }
+}
+
+/// Outcome of comparing a decrypted result with its expected plaintext counterpart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerificationReport {
+    /// Whether the error is within the tolerance, `false` if either value is NaN
+    pub passed: bool,
+    /// Absolute difference between the decrypted and expected results
+    pub abs_error: f64,
+    /// Absolute error relative to the expected result, `None` when the expected result is zero
+    pub rel_error: Option<f64>,
+    /// Largest absolute error the tolerance accepted for the expected result
+    pub tolerance_used: f64,
+}
+
+/// Compares a decrypted result with the expected result and reports the error
+///
+/// # Arguments
+/// * `decrypted` - The result obtained through homomorphic encryption
+/// * `expected` - The expected result computed on plaintext data
+/// * `tolerance` - The absolute and/or relative error to accept
+///
+/// # Returns
+/// * A `VerificationReport` with the errors, the bound applied and whether the check passed
+pub fn verify(decrypted: f64, expected: f64, tolerance: Tolerance) -> VerificationReport {
+    let abs_error = (decrypted - expected).abs();
+    let rel_error = if expected == 0.0 {
+        None
+    } else {
+        Some(abs_error / expected.abs())
+    };
+    let tolerance_used = tolerance.bound_for(expected);
+
+    VerificationReport {
+        // NaN compares false, so a NaN on either side fails
+        passed: abs_error <= tolerance_used,
+        abs_error,
+        rel_error,
+        tolerance_used,
+    }
+}
+
+/// Verifies that an encrypted computation result is close enough to the plaintext result
+///
+/// # Arguments
+/// * `encrypted_result` - The result obtained through homomorphic encryption
+/// * `plaintext_result` - The expected result computed on plaintext data
+/// * `tolerance` - The relative error tolerance (as a fraction), or the absolute
+///   tolerance when the plaintext result is near zero
+///
+/// # Returns
+/// * `true` if the encrypted result is within the specified tolerance of the plaintext result
+#[allow(dead_code)]
+pub fn verify_computation(encrypted_result: f64, plaintext_result: f64, tolerance: f64) -> bool {
+    verify(
+        encrypted_result,
+        plaintext_result,
+        Tolerance::Relative(tolerance),
+    )
+    .passed
+}
+
+/// Compares a decrypted mean with the plaintext mean using the tolerance its encoding allows
+///
+/// Each value is rounded to a multiple of `1 / scale` before encryption, so a mean computed
+/// on the encrypted values is off by at most half of that step, plus the step of
+/// `1 / (scale * MEAN_PRECISION)` the server-side division truncates to. The field's scale
+/// is taken from the context, so callers do not have to choose a tolerance.
+///
+/// # Arguments
+/// * `decrypted_mean` - The mean decrypted with `BiosampleFHE::decrypt_mean`
+/// * `plaintext_mean` - The expected mean computed on plaintext data
+/// * `field` - The name of the averaged field
+/// * `context` - The context produced when the field was encrypted
+///
+/// # Returns
+/// * `Result<VerificationReport, ComputationError>` - The outcome of the comparison, or an error
+///   if the field is not part of the context
+#[allow(dead_code)]
+pub fn verify_computation_with_context(
+    decrypted_mean: f64,
+    plaintext_mean: f64,
+    field: &str,
+    context: &EncryptionContext,
+) -> Result<VerificationReport, ComputationError> {
+    // Half a quantization step, one step of the truncating server-side division, and some
+    // slack for floating-point error
+    let scale = context.field(field)?.scale;
+    let abs_tolerance = 0.5 / scale + 1.0 / (scale * MEAN_PRECISION as f64) + 1e-9;
+    Ok(verify(
+        decrypted_mean,
+        plaintext_mean,
+        Tolerance::Absolute(abs_tolerance),
+    ))
+}
+
+/// Encrypted results of `run_biosample_analysis`
+///
+/// Each analysis is `None`, or each map empty, when the fields it needs were not encrypted.
+/// The whole result serializes into a single blob, so the server can ship it back to the
+/// client in one message, and `decrypt` turns it into a `DecryptedAnalysis`.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct AnalysisResult {
+    pub avg_age: Option<EncryptedVector>,         // Mean age
+    pub avg_glucose: Option<EncryptedVector>,     // Mean glucose level
+    pub avg_cholesterol: Option<EncryptedVector>, // Mean cholesterol level
+    pub blood_type_counts: HashMap<String, EncryptedVector>, // Patient count of each blood type, e.g. "A+"
+    pub var_glucose: Option<EncryptedVector>,                // Glucose sum and sum of squares
+    pub var_cholesterol: Option<EncryptedVector>,            // Cholesterol sum and sum of squares
+    pub cov_glucose_cholesterol: Option<EncryptedVector>,    // Glucose-cholesterol covariance terms
+    pub glucose_vs_age_regression: Option<EncryptedVector>, // Sums of the regression of glucose on age
+    pub glucose_by_marker: Option<EncryptedVector>, // Glucose sums and counts of marker-positive and -negative patients
+    pub marker_positive_count: Option<EncryptedVector>, // Number of marker-positive patients
+    pub high_cholesterol_count: Option<EncryptedVector>, // Number of patients with high cholesterol
+    pub cholesterol_by_blood_type: HashMap<String, EncryptedVector>, // Cholesterol sum and count of each blood type
+    pub mean_glucose_age_product: Option<EncryptedVector>, // Mean of glucose level times age
+    #[serde(default)]
+    pub facility_counts: HashMap<String, EncryptedVector>, // Record count of each facility, e.g. "3"
+    #[serde(default)]
+    pub distinct_blood_types: Option<EncryptedVector>, // Number of blood types with at least one patient
+}
+
+/// Decrypted and finished results of a biosample analysis
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecryptedAnalysis {
+    pub avg_age: Option<f64>,                                    // Mean age
+    pub avg_glucose: Option<f64>,                                // Mean glucose level
+    pub avg_cholesterol: Option<f64>,                            // Mean cholesterol level
+    pub blood_type_counts: HashMap<String, f64>,                 // Patient count of each blood type
+    pub var_glucose: Option<(f64, f64)>, // Variance and standard deviation of glucose levels
+    pub var_cholesterol: Option<(f64, f64)>, // Variance and standard deviation of cholesterol levels
+    pub cov_glucose_cholesterol: Option<f64>, // Covariance of glucose and cholesterol levels
+    pub glucose_vs_age_regression: Option<(f64, f64, f64)>, // Slope, intercept and r of glucose on age
+    pub glucose_by_marker: Option<(Option<f64>, Option<f64>)>, // Mean glucose of marker-positive and -negative patients
+    pub marker_positive_count: Option<f64>,                    // Number of marker-positive patients
+    pub high_cholesterol_count: Option<f64>, // Number of patients with high cholesterol
+    pub cholesterol_by_blood_type: HashMap<String, Option<f64>>, // Mean cholesterol of each blood type, `None` without patients
+    pub mean_glucose_age_product: Option<f64>,                   // Mean of glucose level times age
+    pub facility_counts: HashMap<String, f64>,                   // Record count of each facility
+    pub distinct_blood_types: Option<f64>, // Number of blood types with at least one patient
+}
+
+impl DecryptedAnalysis {
+    /// Returns the Pearson correlation of glucose and cholesterol levels
+    ///
+    /// # Returns
+    /// * The covariance divided by both standard deviations, or `None` if a term was not
+    ///   computed
+    pub fn glucose_cholesterol_correlation(&self) -> Option<f64> {
+        let covariance = self.cov_glucose_cholesterol?;
+        let (_, glucose_sd) = self.var_glucose?;
+        let (_, cholesterol_sd) = self.var_cholesterol?;
+        Some(covariance / (glucose_sd * cholesterol_sd))
+    }
+}
+
+impl AnalysisResult {
+    /// Decrypts every computed analysis and finishes it on the client
+    ///
+    /// # Arguments
+    /// * `fhe` - The BiosampleFHE instance holding the client key
+    /// * `context` - The context produced when the analysed data was encrypted
+    ///
+    /// # Returns
+    /// * `Result<DecryptedAnalysis, ComputationError>` - The decrypted results, or an error if
+    ///   a result cannot be decrypted or its fields are not part of the context
+    pub fn decrypt(
+        &self,
+        fhe: &BiosampleFHE,
+        context: &EncryptionContext,
+    ) -> Result<DecryptedAnalysis, ComputationError> {
+        let mean = |terms: &Option<EncryptedVector>, field| {
+            terms
+                .as_ref()
+                .map(|terms| fhe.decrypt_mean(terms, field, context))
+                .transpose()
+        };
+        let variance = |terms: &Option<EncryptedVector>, field| {
+            terms
+                .as_ref()
+                .map(|terms| fhe.decrypt_variance(terms, field, context))
+                .transpose()
+        };
+        let count = |terms: &EncryptedVector| fhe.decrypt_element(terms, 0, 1.0);
+
+        let regression = match &self.glucose_vs_age_regression {
+            Some(terms) => {
+                // Each sum decrypts with the scale of its products
+                let age = context.scale_for(fields::AGE)?;
+                let glucose = context.scale_for(fields::GLUCOSE)?;
+                let scales = [age, glucose, age * glucose, age * age, glucose * glucose];
+                let mut decrypted = [0.0; 5];
+                for (i, scale) in scales.into_iter().enumerate() {
+                    decrypted[i] = fhe.decrypt_element(terms, i, scale)?;
+                }
+                finish_regression(decrypted, context.field(fields::AGE)?.length)
+            }
+            None => None,
+        };
+
+        Ok(DecryptedAnalysis {
+            avg_age: mean(&self.avg_age, fields::AGE)?,
+            avg_glucose: mean(&self.avg_glucose, fields::GLUCOSE)?,
+            avg_cholesterol: mean(&self.avg_cholesterol, fields::CHOLESTEROL)?,
+            blood_type_counts: self
+                .blood_type_counts
+                .iter()
+                .map(|(blood_type, terms)| Ok((blood_type.clone(), count(terms)?)))
+                .collect::<Result<_, ComputationError>>()?,
+            var_glucose: variance(&self.var_glucose, fields::GLUCOSE)?,
+            var_cholesterol: variance(&self.var_cholesterol, fields::CHOLESTEROL)?,
+            cov_glucose_cholesterol: self
+                .cov_glucose_cholesterol
+                .as_ref()
+                .map(|terms| {
+                    fhe.decrypt_covariance(terms, fields::GLUCOSE, fields::CHOLESTEROL, context)
+                })
+                .transpose()?,
+            glucose_vs_age_regression: regression,
+            glucose_by_marker: self
+                .glucose_by_marker
+                .as_ref()
+                .map(|terms| fhe.decrypt_group_means(terms, fields::GLUCOSE, context))
+                .transpose()?,
+            marker_positive_count: self.marker_positive_count.as_ref().map(count).transpose()?,
+            high_cholesterol_count: self
+                .high_cholesterol_count
+                .as_ref()
+                .map(count)
+                .transpose()?,
+            cholesterol_by_blood_type: self
+                .cholesterol_by_blood_type
+                .iter()
+                .map(|(blood_type, terms)| {
+                    let mean = fhe.decrypt_conditional_mean(terms, fields::CHOLESTEROL, context)?;
+                    Ok((blood_type.clone(), mean))
+                })
+                .collect::<Result<_, ComputationError>>()?,
+            mean_glucose_age_product: mean(&self.mean_glucose_age_product, fields::GLUCOSE)?,
+            facility_counts: self
+                .facility_counts
+                .iter()
+                .map(|(facility, terms)| Ok((facility.clone(), count(terms)?)))
+                .collect::<Result<_, ComputationError>>()?,
+            distinct_blood_types: self.distinct_blood_types.as_ref().map(count).transpose()?,
+        })
+    }
+}
+
+/// Returns the serialized size of the ciphertexts of a vector, in bytes
+///
+/// Homomorphic operations widen their results, e.g. a sum holds more blocks than each
+/// value, so the size shows how much a chain of operations has grown the ciphertexts.
+/// Compressed vectors report their compressed size, as stored or sent.
+///
+/// # Arguments
+/// * `encrypted_vector` - The EncryptedVector containing serialized ciphertexts
+///
+/// # Returns
+/// * The total number of bytes of the serialized ciphertexts
+#[allow(dead_code)]
+pub fn ciphertext_byte_size(encrypted_vector: &EncryptedVector) -> usize {
+    encrypted_vector.data.iter().map(Vec::len).sum()
+}
+
+/// Serialized sizes of the input and result of a homomorphic operation
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct OperationStats {
+    pub operation: String,         // Name of the operation
+    pub input_bytes: usize,        // Serialized size of the input ciphertexts
+    pub input_ciphertexts: usize,  // Number of input ciphertexts
+    pub output_bytes: usize,       // Serialized size of the result ciphertexts
+    pub output_ciphertexts: usize, // Number of result ciphertexts
+}
+
+impl OperationStats {
+    /// Returns how many times larger a result ciphertext is than an input ciphertext
+    ///
+    /// # Returns
+    /// * The average size of a result ciphertext over that of an input ciphertext, or
+    ///   `None` if either side has no ciphertexts
+    #[allow(dead_code)]
+    pub fn growth_per_ciphertext(&self) -> Option<f64> {
+        if self.input_ciphertexts == 0 || self.output_ciphertexts == 0 {
+            return None;
+        }
+        let input = self.input_bytes as f64 / self.input_ciphertexts as f64;
+        let output = self.output_bytes as f64 / self.output_ciphertexts as f64;
+        Some(output / input)
+    }
+}
+
+/// Runs an operation on a vector and logs the serialized size before and after it
+///
+/// The sizes are logged at debug level and returned, so a caller can decide when a chain
+/// of operations has grown the ciphertexts enough to have the client re-encrypt them.
+///
+/// # Arguments
+/// * `operation` - The name of the operation, for the log
+/// * `input` - The EncryptedVector the operation takes
+/// * `compute` - The operation
+///
+/// # Returns
+/// * `Result<(EncryptedVector, OperationStats), ComputationError>` - The result of the
+///   operation and the sizes, or the error of the operation
+#[allow(dead_code)]
+pub fn log_operation_stats(
+    operation: &str,
+    input: &EncryptedVector,
+    compute: impl FnOnce(&EncryptedVector) -> Result<EncryptedVector, ComputationError>,
+) -> Result<(EncryptedVector, OperationStats), ComputationError> {
+    let result = compute(input)?;
+    let stats = OperationStats {
+        operation: operation.to_string(),
+        input_bytes: ciphertext_byte_size(input),
+        input_ciphertexts: input.data.len(),
+        output_bytes: ciphertext_byte_size(&result),
+        output_ciphertexts: result.data.len(),
+    };
+    log::debug!(
+        "{}: {} ciphertexts of {} bytes became {} ciphertexts of {} bytes",
+        stats.operation,
+        stats.input_ciphertexts,
+        stats.input_bytes,
+        stats.output_ciphertexts,
+        stats.output_bytes
+    );
+    Ok((result, stats))
+}
+
+/// Runs two computations, in parallel on the rayon thread pool when the `rayon` feature
+/// is enabled
+///
+/// # Arguments
+/// * `a` - The first computation
+/// * `b` - The second computation
+///
+/// # Returns
+/// * The results of both computations
+fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "rayon")]
+    return rayon::join(a, b);
+    #[cfg(not(feature = "rayon"))]
+    (a(), b())
+}
+
+/// Sets the number of threads of the rayon thread pool used by the computations
+///
+/// Without a call the pool has one thread per core, or as many as the `RAYON_NUM_THREADS`
+/// environment variable asks for. The pool can only be configured before its first use.
+///
+/// # Arguments
+/// * `num_threads` - The number of worker threads
+///
+/// # Returns
+/// * `Result<(), ComputationError>` - An error if the pool is already running, or if the
+///   `rayon` feature is disabled and more than one thread is requested
+#[cfg(feature = "rayon")]
+pub fn set_num_threads(num_threads: usize) -> Result<(), ComputationError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build_global()
+        .map_err(|e| {
+            ComputationError::InvalidInput(format!("Cannot use {} threads: {}", num_threads, e))
+        })
+}
+
+/// Sets the number of threads of the rayon thread pool used by the computations
+///
+/// Without the `rayon` feature the computations run on the calling thread only.
+///
+/// # Arguments
+/// * `num_threads` - The number of worker threads
+///
+/// # Returns
+/// * `Result<(), ComputationError>` - An error if more than one thread is requested
+#[cfg(not(feature = "rayon"))]
+pub fn set_num_threads(num_threads: usize) -> Result<(), ComputationError> {
+    if num_threads > 1 {
+        return Err(ComputationError::Unsupported(format!(
+            "Cannot use {} threads without the rayon feature",
+            num_threads
+        )));
+    }
+    Ok(())
+}
+
+/// Encrypted column sums shared by the analyses of one `run_biosample_analysis` run
+///
+/// Means, variances, covariances, regressions, group means and counts all start from the
+/// sum of a column. The sums are computed once per field before the analyses run, and
+/// every analysis takes the sums it needs from here instead of summing the column again.
+struct FieldSums {
+    sums: HashMap<String, SignedRadixCiphertext>, // Encrypted sum of each field
+}
+
+impl FieldSums {
+    /// Sums the given fields of a dataset, one field per thread
+    ///
+    /// # Arguments
+    /// * `encrypted_data` - A map of feature names to encrypted vectors containing the data
+    /// * `keys` - The fields to sum, all of which must be in `encrypted_data`
+    /// * `server_key` - The server key used for homomorphic operations
+    ///
+    /// # Returns
+    /// * `Result<FieldSums, ComputationError>` - The sum of each field, or an error if a
+    ///   field is empty or cannot be summed
+    fn compute(
+        encrypted_data: &HashMap<String, EncryptedVector>,
+        keys: &[&str],
+        server_key: &ServerKey,
+    ) -> Result<Self, ComputationError> {
+        #[cfg(feature = "rayon")]
+        let keys = keys.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let keys = keys.iter();
+        let sums: HashMap<String, SignedRadixCiphertext> = keys
+            .map(|&key| {
+                let sum = compute_encrypted_sum(&encrypted_data[key], server_key)?;
+                Ok((key.to_string(), sum))
+            })
+            .collect::<Result<_, ComputationError>>()?;
+        Ok(FieldSums { sums })
+    }
+
+    /// Returns the encrypted sum of a field
+    ///
+    /// # Arguments
+    /// * `field` - The name of the field
+    ///
+    /// # Returns
+    /// * `Result<&SignedRadixCiphertext, ComputationError>` - The sum, or an error if the
+    ///   field was not summed
+    fn get(&self, field: &str) -> Result<&SignedRadixCiphertext, ComputationError> {
+        self.sums.get(field).ok_or_else(|| {
+            ComputationError::InvalidInput(format!("The sum of {} was not computed", field))
+        })
+    }
+}
+
+/// Runs analysis on encrypted biosample data
+///
+/// The sums of the age, glucose, cholesterol, marker, blood type and facility fields are
+/// computed once and shared by every analysis that needs them, and the facility sums are
+/// the facility counts. The analyses of the age, glucose, cholesterol and marker fields,
+/// the pairwise analyses and the blood type counts are independent of each other and run
+/// concurrently on the rayon thread pool, which they share with the parallel operations of
+/// the server key. The result is the same however many threads the pool has.
+///
+/// # Arguments
+/// * `encrypted_data` - A map of feature names to encrypted vectors containing the data
+/// * `server_key` - The server key used for homomorphic operations
+///
+/// # Returns
+/// * The analysis results, including average age, glucose, cholesterol, the variance
+///   terms of glucose and cholesterol, their covariance terms, the regression sums of
+///   glucose on age, the mean glucose-age product, the marker-positive and high
+///   cholesterol counts, and blood type and facility counts
+///
+/// # Errors
+/// * Returns an error if the fields were encrypted under different client keys, or any of
+///   the homomorphic operations fail
+pub fn run_biosample_analysis(
+    encrypted_data: &HashMap<String, EncryptedVector>,
+    server_key: &ServerKey,
+) -> Result<AnalysisResult, ComputationError> {
+    analyse(encrypted_data, server_key, None)
+}
+
+/// Runs analysis on encrypted biosample data saved to a directory
+///
+/// The fields are read from the files written by `save_encrypted_fields`, and each one is
+/// checked for corruption before the analysis of `run_biosample_analysis` runs on them.
+///
+/// # Arguments
+/// * `dir` - The directory holding one file per encrypted field
+/// * `server_key` - The server key used for homomorphic operations
+///
+/// # Returns
+/// * `Result<AnalysisResult, ComputationError>` - The analysis results, or an error if a
+///   field file cannot be read or is corrupted, or any of the homomorphic operations fail
+#[cfg(feature = "native")]
+#[allow(dead_code)]
+pub fn run_biosample_analysis_from_dir(
+    dir: &Path,
+    server_key: &ServerKey,
+) -> Result<AnalysisResult, ComputationError> {
+    let encrypted_data = load_encrypted_fields(dir)?;
+    analyse(&encrypted_data, server_key, None)
+}
+
+/// Runs analysis on encrypted biosample data, and measures how long each part takes
+///
+/// The analysis is the same as `run_biosample_analysis`. Next to the results it returns
+/// the wall-clock time of the field sums and of each analysis that had its fields in the
+/// data, under the names `"Field Sums"`, `"Age"`, `"Glucose"`, `"Cholesterol"`,
+/// `"Glucose-Cholesterol Covariance"`, `"Glucose vs Age Regression"`, `"Marker"` and
+/// `"Blood Types"`. The analyses run concurrently, so their times can add up to more than
+/// the time of the whole run.
+///
+/// # Arguments
+/// * `encrypted_data` - A map of feature names to encrypted vectors containing the data
+/// * `server_key` - The server key used for homomorphic operations
+///
+/// # Returns
+/// * `Result<(AnalysisResult, HashMap<String, Duration>), ComputationError>` - The
+///   analysis results and the time of each part, or an error if any of the homomorphic
+///   operations fail
+#[allow(dead_code)]
+pub fn run_biosample_analysis_timed(
+    encrypted_data: &HashMap<String, EncryptedVector>,
+    server_key: &ServerKey,
+) -> Result<(AnalysisResult, HashMap<String, Duration>), ComputationError> {
+    let timings = Mutex::new(HashMap::new());
+    let results = analyse(encrypted_data, server_key, Some(&timings))?;
+    let timings = timings.into_inner().unwrap_or_else(PoisonError::into_inner);
+    Ok((results, timings))
+}
+
+/// Runs a computation, and records how long it took when `timings` is given
+///
+/// # Arguments
+/// * `timings` - The map to record the time in under `name`, if the time is wanted
+/// * `name` - The name of the computation
+/// * `computation` - The computation to run
+///
+/// # Returns
+/// * The result of the computation
+fn timed<R>(
+    timings: Option<&Mutex<HashMap<String, Duration>>>,
+    name: &str,
+    computation: impl FnOnce() -> R,
+) -> R {
+    let Some(timings) = timings else {
+        return computation();
+    };
+    let start = Instant::now();
+    let result = computation();
+    timings
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(name.to_string(), start.elapsed());
+    result
+}
+
+/// Sums the analysed fields once and runs the analyses of `run_biosample_analysis`
+///
+/// # Arguments
+/// * `encrypted_data` - A map of feature names to encrypted vectors containing the data
+/// * `server_key` - The server key used for homomorphic operations
+/// * `timings` - The map to record the time of each part in, if the times are wanted
+///
+/// # Returns
+/// * `Result<AnalysisResult, ComputationError>` - The analysis results, or an error if any
+///   of the homomorphic operations fail
+fn analyse(
+    encrypted_data: &HashMap<String, EncryptedVector>,
+    server_key: &ServerKey,
+    timings: Option<&Mutex<HashMap<String, Duration>>>,
+) -> Result<AnalysisResult, ComputationError> {
+    let summed_fields = [
+        fields::AGE,
+        fields::GLUCOSE,
+        fields::CHOLESTEROL,
+        fields::MARKER,
+    ];
+    let keys: Vec<&str> = encrypted_data
+        .keys()
+        .map(String::as_str)
+        .filter(|key| {
+            summed_fields.contains(key)
+                || key.starts_with(fields::BLOOD_TYPE_PREFIX)
+                || key.starts_with(fields::FACILITY_PREFIX)
+        })
+        .collect();
+    let sums = timed(timings, "Field Sums", || {
+        FieldSums::compute(encrypted_data, &keys, server_key)
+    })?;
+    analyse_with_sums(encrypted_data, &sums, server_key, timings)
+}
+
+/// Runs the analyses of `run_biosample_analysis` on precomputed field sums
+///
+/// # Arguments
+/// * `encrypted_data` - A map of feature names to encrypted vectors containing the data
+/// * `sums` - The sums of the analysed fields
+/// * `server_key` - The server key used for homomorphic operations
+/// * `timings` - The map to record the time of each analysis in, if the times are wanted
+///
+/// # Returns
+/// * `Result<AnalysisResult, ComputationError>` - The analysis results, or an error if any
+///   of the homomorphic operations fail
+fn analyse_with_sums(
+    encrypted_data: &HashMap<String, EncryptedVector>,
+    sums: &FieldSums,
+    server_key: &ServerKey,
+    timings: Option<&Mutex<HashMap<String, Duration>>>,
+) -> Result<AnalysisResult, ComputationError> {
+    let age = encrypted_data.get(fields::AGE);
+    let glucose = encrypted_data.get(fields::GLUCOSE);
+    let cholesterol = encrypted_data.get(fields::CHOLESTEROL);
+    let marker = encrypted_data.get(fields::MARKER);
+    let vectors: Vec<&EncryptedVector> = encrypted_data.values().collect();
+    let key_fingerprint = shared_key_fingerprint(&vectors)?;
+
+    // Compute average age
+    let age_analysis = || {
+        age.map(|age_data| {
+            timed(timings, "Age", || {
+                let sum = sums.get(fields::AGE)?;
+                Ok::<_, ComputationError>(mean_from_sum(sum, age_data, server_key))
+            })
+        })
+    };
+
+    // Compute average and variance of glucose levels
+    let glucose_analysis = || {
+        glucose.map(|glucose_data| {
+            timed(timings, "Glucose", || -> Result<_, ComputationError> {
+                let sum = sums.get(fields::GLUCOSE)?;
+                Ok(join(
+                    || mean_from_sum(sum, glucose_data, server_key),
+                    || variance_from_sum(glucose_data, sum, server_key),
+                ))
+            })
+        })
+    };
+
+    // Compute average and variance of cholesterol levels, and count patients with high
+    // cholesterol
+    let cholesterol_analysis = || {
+        cholesterol.map(|cholesterol_data| {
+            timed(timings, "Cholesterol", || -> Result<_, ComputationError> {
+                let sum = sums.get(fields::CHOLESTEROL)?;
+                let threshold =
+                    (HIGH_CHOLESTEROL_THRESHOLD * cholesterol_data.scale).round() as i64;
+                Ok(join(
+                    || {
+                        join(
+                            || mean_from_sum(sum, cholesterol_data, server_key),
+                            || variance_from_sum(cholesterol_data, sum, server_key),
+                        )
+                    },
+                    || compute_encrypted_threshold_count(cholesterol_data, server_key, threshold),
+                ))
+            })
+        })
+    };
+
+    // Compute the covariance terms of glucose and cholesterol levels
+    let covariance_analysis = || match (glucose, cholesterol) {
+        (Some(glucose_data), Some(cholesterol_data)) => {
+            timed(timings, "Glucose-Cholesterol Covariance", || {
+                covariance_from_sums(
+                    glucose_data,
+                    cholesterol_data,
+                    sums.get(fields::GLUCOSE)?,
+                    sums.get(fields::CHOLESTEROL)?,
+                    server_key,
+                )
+            })
+            .map(Some)
+        }
+        _ => Ok(None),
+    };
+
+    // Compute the regression sums of glucose on age and the mean of the per-record
+    // glucose-age products
+    let regression_analysis = || match (age, glucose) {
+        (Some(age_data), Some(glucose_data)) => {
+            Some(timed(timings, "Glucose vs Age Regression", || {
+                join(
+                    || -> Result<_, ComputationError> {
+                        let terms = correlation_terms_from_sums(
+                            age_data,
+                            glucose_data,
+                            sums.get(fields::AGE)?,
+                            sums.get(fields::GLUCOSE)?,
+                            server_key,
+                        )?;
+                        regression_from_terms(terms, age_data.scale)
+                    },
+                    || compute_encrypted_product_mean(glucose_data, age_data, server_key),
+                )
+            }))
+        }
+        _ => None,
+    };
+
+    // Count marker-positive patients, and compute the glucose sums and counts of
+    // marker-positive and marker-negative patients
+    let marker_analysis = || {
+        marker.map(|marker_data| {
+            timed(timings, "Marker", || -> Result<_, ComputationError> {
+                let positive_count = sums.get(fields::MARKER)?;
+                let groups = glucose
+                    .map(|glucose_data| {
+                        group_means_from_sums(
+                            glucose_data,
+                            marker_data,
+                            sums.get(fields::GLUCOSE)?,
+                            positive_count,
+                            server_key,
+                        )
+                    })
+                    .transpose()?;
+                Ok((
+                    serialize_ciphertexts(std::slice::from_ref(positive_count))
+                        .stamped(key_fingerprint),
+                    groups,
+                ))
+            })
+        })
+    };
+
+    // Count blood types, and compute the cholesterol sum and count of each blood type
+    let blood_type_analysis = || -> Result<_, ComputationError> {
+        let blood_types: HashMap<&str, &EncryptedVector> = encrypted_data
+            .iter()
+            .filter_map(|(key, vector)| {
+                key.strip_prefix(fields::BLOOD_TYPE_PREFIX)
+                    .map(|blood_type| (blood_type, vector))
+            })
+            .collect();
+        if blood_types.is_empty() {
+            return Ok((HashMap::new(), HashMap::new(), None));
+        }
+        let count =
+            |blood_type: &str| sums.get(&format!("{}{}", fields::BLOOD_TYPE_PREFIX, blood_type));
+
+        timed(timings, "Blood Types", || {
+            let blood_type_sums = blood_types
+                .keys()
+                .map(|&blood_type| Ok((blood_type, count(blood_type)?.clone())))
+                .collect::<Result<Vec<_>, ComputationError>>()?;
+            let counts: HashMap<_, _> = blood_type_sums
+                .iter()
+                .map(|(blood_type, sum)| {
+                    let count = std::slice::from_ref(sum);
+                    (
+                        blood_type.to_string(),
+                        serialize_ciphertexts(count).stamped(key_fingerprint),
+                    )
+                })
+                .collect();
+            let by_blood_type = match cholesterol {
+                Some(cholesterol_data) => means_by_category_with_counts(
+                    cholesterol_data,
+                    &blood_types,
+                    server_key,
+                    |blood_type, _| count(blood_type).cloned(),
+                )?,
+                None => HashMap::new(),
+            };
+            // Whether each blood type occurs, summed into the number of distinct types
+            let sums: Vec<_> = blood_type_sums.into_iter().map(|(_, sum)| sum).collect();
+            let distinct = serialize_ciphertexts(&[count_nonempty(&sums, server_key)?])
+                .stamped(key_fingerprint);
+            Ok((counts, by_blood_type, Some(distinct)))
+        })
+    };
+
+    let (
+        ((age_result, glucose_result), (cholesterol_result, covariance_result)),
+        ((regression_result, marker_result), blood_type_result),
+    ) = join(
+        || {
+            join(
+                || join(age_analysis, glucose_analysis),
+                || join(cholesterol_analysis, covariance_analysis),
+            )
+        },
+        || {
+            join(
+                || join(regression_analysis, marker_analysis),
+                blood_type_analysis,
+            )
+        },
+    );
+
+    let mut results = AnalysisResult {
+        avg_age: age_result.transpose()?,
+        cov_glucose_cholesterol: covariance_result?,
+        ..AnalysisResult::default()
+    };
+    if let Some((mean, variance)) = glucose_result.transpose()? {
+        results.avg_glucose = Some(mean);
+        results.var_glucose = Some(variance?);
+    }
+    if let Some(((mean, variance), high_count)) = cholesterol_result.transpose()? {
+        results.avg_cholesterol = Some(mean);
+        results.var_cholesterol = Some(variance?);
+        results.high_cholesterol_count = Some(high_count?);
+    }
+    if let Some((regression, product_mean)) = regression_result {
+        results.glucose_vs_age_regression = Some(regression?);
+        results.mean_glucose_age_product = Some(product_mean?);
+    }
+    if let Some((positive_count, groups)) = marker_result.transpose()? {
+        results.marker_positive_count = Some(positive_count);
+        results.glucose_by_marker = groups;
+    }
+    let (blood_type_counts, cholesterol_by_blood_type, distinct_blood_types) = blood_type_result?;
+    results.blood_type_counts = blood_type_counts;
+    results.cholesterol_by_blood_type = cholesterol_by_blood_type;
+    results.distinct_blood_types = distinct_blood_types;
+
+    // Count the records of each facility, which are the sums of the facility masks
+    results.facility_counts = encrypted_data
+        .keys()
+        .filter_map(|key| {
+            key.strip_prefix(fields::FACILITY_PREFIX)
+                .map(|id| (key, id))
+        })
+        .map(|(key, facility)| {
+            let count = std::slice::from_ref(sums.get(key)?);
+            Ok((
+                facility.to_string(),
+                serialize_ciphertexts(count).stamped(key_fingerprint),
+            ))
+        })
+        .collect::<Result<_, ComputationError>>()?;
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_generator::{collection_day, generate_biosample_data};
+    use crate::encryption::{
+        encrypt_biosample_data, BoolDecoding, InvalidBoolError, ScaleConfig, DEFAULT_SCALE,
+    };
+
+    #[test]
+    fn test_verify_reports_errors() {
+        let report = verify(102.0, 100.0, Tolerance::Relative(0.05));
+        assert!(report.passed);
+        assert!((report.abs_error - 2.0).abs() < 1e-12);
+        assert!((report.rel_error.unwrap() - 0.02).abs() < 1e-12);
+        assert!((report.tolerance_used - 5.0).abs() < 1e-12);
+
+        // Outside the relative tolerance the check fails but still reports the error
+        let report = verify(110.0, 100.0, Tolerance::Relative(0.05));
+        assert!(!report.passed);
+        assert!((report.rel_error.unwrap() - 0.1).abs() < 1e-12);
+        assert!(!verify_computation(110.0, 100.0, 0.05));
+
+        // Combined bounds accept whichever is looser
+        let both = Tolerance::Both {
+            absolute: 0.5,
+            relative: 0.01,
+        };
+        assert_eq!(verify(100.9, 100.0, both).tolerance_used, 1.0);
+        assert_eq!(verify(10.4, 10.0, both).tolerance_used, 0.5);
+        assert!(!verify(10.6, 10.0, both).passed);
+    }
+
+    #[test]
+    fn test_verify_zero_expectation() {
+        // A relative bound would reject anything but an exact zero, so it is applied absolutely
+        let report = verify(0.01, 0.0, Tolerance::Relative(0.05));
+        assert!(report.passed);
+        assert_eq!(report.rel_error, None);
+        assert!((report.abs_error - 0.01).abs() < 1e-12);
+        assert!(verify_computation(-0.04, 0.0, 0.05));
+
+        let report = verify(0.5, 0.0, Tolerance::Relative(0.05));
+        assert!(!report.passed);
+        assert_eq!(report.rel_error, None);
+
+        let both = Tolerance::Both {
+            absolute: 0.1,
+            relative: 0.05,
+        };
+        assert!(verify(0.08, 0.0, both).passed);
+        assert!(!verify(0.2, 0.0, both).passed);
+    }
+
+    #[test]
+    fn test_verify_computation_with_context_uses_field_scale() {
+        let encrypted_data = HashMap::from([
+            (
+                fields::AGE.to_string(),
+                EncryptedVector::new(Vec::new(), VectorKind::Numeric).with_scale(1.0),
+            ),
+            (
+                fields::GLUCOSE.to_string(),
+                EncryptedVector::new(Vec::new(), VectorKind::Numeric).with_scale(100.0),
+            ),
+        ]);
+        let context = EncryptionContext::new(0, &encrypted_data);
+
+        // Whole-year ages may be off by half a year, glucose only by half a hundredth
+        assert!(
+            verify_computation_with_context(41.6, 42.0, fields::AGE, &context)
+                .unwrap()
+                .passed
+        );
+        assert!(
+            !verify_computation_with_context(41.4, 42.0, fields::AGE, &context)
+                .unwrap()
+                .passed
+        );
+        assert!(
+            verify_computation_with_context(99.996, 100.0, fields::GLUCOSE, &context)
+                .unwrap()
+                .passed
+        );
+        assert!(
+            !verify_computation_with_context(99.99, 100.0, fields::GLUCOSE, &context)
+                .unwrap()
+                .passed
+        );
+        assert!(verify_computation_with_context(1.0, 1.0, fields::MARKER, &context).is_err());
+    }
+
+    #[test]
+    fn test_analysis_with_heterogeneous_scales() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(5, 42).unwrap();
+        let scales = ScaleConfig::new(10.0)
+            .with_scale(fields::AGE, 1.0)
+            .with_scale(fields::CHOLESTEROL, 5.0);
+        let (encrypted_data, context) = encrypt_biosample_data(&fhe, &records, &scales).unwrap();
+
+        let results = run_biosample_analysis(&encrypted_data, fhe.server_key()).unwrap();
+
+        // Each mean carries the scale of its field times the precision of the division, so it
+        // decrypts without outside knowledge
+        let n = records.len() as f64;
+        let expected = [
+            (
+                fields::AGE,
+                &results.avg_age,
+                1.0,
+                records.iter().map(|r| r.age as f64).sum::<f64>() / n,
+            ),
+            (
+                fields::GLUCOSE,
+                &results.avg_glucose,
+                10.0,
+                records.iter().map(|r| r.glucose_level).sum::<f64>() / n,
+            ),
+            (
+                fields::CHOLESTEROL,
+                &results.avg_cholesterol,
+                5.0,
+                records.iter().map(|r| r.cholesterol_level).sum::<f64>() / n,
+            ),
+        ];
+        for (key, result, scale, plaintext) in expected {
+            let result = result.as_ref().unwrap();
+            assert_eq!(result.scale, scale * MEAN_PRECISION as f64);
+            let decrypted = fhe.decrypt_f64_vector(result, result.scale)[0];
+            assert!(
+                verify_computation(decrypted, plaintext, 0.01),
+                "{}: {} vs {}",
+                key,
+                decrypted,
+                plaintext
+            );
+        }
+
+        // High cholesterol is counted on the values as they were rounded for encryption
+        let threshold_scaled = (HIGH_CHOLESTEROL_THRESHOLD * 5.0).round() as i64;
+        let expected = records
+            .iter()
+            .filter(|r| (r.cholesterol_level * 5.0).round() as i64 > threshold_scaled)
+            .count() as f64;
+        let count = results.high_cholesterol_count.as_ref().unwrap();
+        assert_eq!(fhe.decrypt_element(count, 0, 1.0).unwrap(), expected);
+
+        // The variances are finished client-side from the sum and sum of squares
+        for (terms, field, values) in [
+            (
+                &results.var_glucose,
+                fields::GLUCOSE,
+                records.iter().map(|r| r.glucose_level).collect::<Vec<_>>(),
+            ),
+            (
+                &results.var_cholesterol,
+                fields::CHOLESTEROL,
+                records.iter().map(|r| r.cholesterol_level).collect(),
+            ),
+        ] {
+            let mean = values.iter().sum::<f64>() / n;
+            let plaintext = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+            let (variance, _) = fhe
+                .decrypt_variance(terms.as_ref().unwrap(), field, &context)
+                .unwrap();
+            assert!(
+                verify_computation(variance, plaintext, 0.05),
+                "{}: {} vs {}",
+                field,
+                variance,
+                plaintext
+            );
+        }
+
+        // The correlation combines the covariance terms with both standard deviations
+        let decrypted = results.decrypt(&fhe, &context).unwrap();
+
+        // The glucose-age product mean carries both scales and the precision of the division
+        let product_mean = results.mean_glucose_age_product.as_ref().unwrap();
+        assert_eq!(product_mean.scale, 10.0 * MEAN_PRECISION as f64);
+        let plaintext = records
+            .iter()
+            .map(|r| r.glucose_level * r.age as f64)
+            .sum::<f64>()
+            / n;
+        let product_mean = decrypted.mean_glucose_age_product.unwrap();
+        assert!(
+            verify(product_mean, plaintext, Tolerance::Relative(0.01)).passed,
+            "{} vs {}",
+            product_mean,
+            plaintext
+        );
+        let correlation = decrypted.glucose_cholesterol_correlation().unwrap();
+
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let cholesterol: Vec<f64> = records.iter().map(|r| r.cholesterol_level).collect();
+        let expected = pearson_from_terms(
+            records.len(),
+            glucose.iter().sum(),
+            cholesterol.iter().sum(),
+            glucose.iter().zip(&cholesterol).map(|(g, c)| g * c).sum(),
+            glucose.iter().map(|g| g * g).sum(),
+            cholesterol.iter().map(|c| c * c).sum(),
+        )
+        .unwrap();
+        assert!(
+            verify_computation(correlation, expected, 0.05),
+            "{} vs {}",
+            correlation,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_analysis_from_shared_sums_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(3, 42).unwrap();
+        let (mut encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        // Leave out age and cholesterol, whose products are slow
+        encrypted_data.retain(|key, _| {
+            key == fields::GLUCOSE
+                || key == fields::MARKER
+                || key.starts_with(fields::BLOOD_TYPE_PREFIX)
+                || key.starts_with(fields::FACILITY_PREFIX)
+        });
+
+        // The glucose and marker sums are shared by the means, counts and group means
+        let results = run_biosample_analysis(&encrypted_data, fhe.server_key()).unwrap();
+        let decrypted = results.decrypt(&fhe, &context).unwrap();
+        let n = records.len() as f64;
+        let avg_glucose = records.iter().map(|r| r.glucose_level).sum::<f64>() / n;
+        assert!(verify_computation(
+            decrypted.avg_glucose.unwrap(),
+            avg_glucose,
+            0.01
+        ));
+        let positives = records.iter().filter(|r| r.marker_alpha).count();
+        assert_eq!(decrypted.marker_positive_count, Some(positives as f64));
+        let (positive_mean, negative_mean) = decrypted.glucose_by_marker.unwrap();
+        let group_mean = |positive: bool| {
+            let group: Vec<f64> = records
+                .iter()
+                .filter(|r| r.marker_alpha == positive)
+                .map(|r| r.glucose_level)
+                .collect();
+            (!group.is_empty()).then(|| group.iter().sum::<f64>() / group.len() as f64)
+        };
+        for (decrypted, expected) in [
+            (positive_mean, group_mean(true)),
+            (negative_mean, group_mean(false)),
+        ] {
+            match (decrypted, expected) {
+                (Some(decrypted), Some(expected)) => {
+                    assert!(verify_computation(decrypted, expected, 0.01))
+                }
+                (decrypted, expected) => assert_eq!(decrypted, expected),
+            }
+        }
+        let blood_type = &records[0].blood_type;
+        let expected = records
+            .iter()
+            .filter(|r| &r.blood_type == blood_type)
+            .count();
+        assert_eq!(decrypted.blood_type_counts[blood_type], expected as f64);
+        let distinct: std::collections::HashSet<&str> =
+            records.iter().map(|r| r.blood_type.as_str()).collect();
+        assert_eq!(decrypted.distinct_blood_types, Some(distinct.len() as f64));
+        for record in &records {
+            let facility = record.facility_id.to_string();
+            let expected = records
+                .iter()
+                .filter(|r| r.facility_id == record.facility_id)
+                .count();
+            assert_eq!(decrypted.facility_counts[&facility], expected as f64);
+        }
+    }
+
+    #[test]
+    fn test_timed_analysis_times_each_computed_part() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(3, 42).unwrap();
+        let (mut encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        // Leave out age and cholesterol, whose products are slow
+        encrypted_data.retain(|key, _| {
+            key == fields::GLUCOSE
+                || key == fields::MARKER
+                || key.starts_with(fields::BLOOD_TYPE_PREFIX)
+        });
+
+        let (results, timings) =
+            run_biosample_analysis_timed(&encrypted_data, fhe.server_key()).unwrap();
+
+        let mut timed: Vec<&str> = timings.keys().map(String::as_str).collect();
+        timed.sort_unstable();
+        assert_eq!(timed, ["Blood Types", "Field Sums", "Glucose", "Marker"]);
+        let decrypted = results.decrypt(&fhe, &context).unwrap();
+        let positives = records.iter().filter(|r| r.marker_alpha).count();
+        assert_eq!(decrypted.marker_positive_count, Some(positives as f64));
+    }
+
+    #[test]
+    fn test_analysis_result_roundtrips_through_a_single_blob() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(6, 42).unwrap();
+        let (mut encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        // Products of glucose and cholesterol are slow, so analyse the cheap fields only
+        encrypted_data.retain(|key, _| {
+            key == fields::AGE
+                || key == fields::MARKER
+                || key.starts_with(fields::BLOOD_TYPE_PREFIX)
+        });
+
+        let results = run_biosample_analysis(&encrypted_data, fhe.server_key()).unwrap();
+        let blob = bincode::serialize(&results).unwrap();
+        let restored: AnalysisResult = bincode::deserialize(&blob).unwrap();
+
+        let decrypted = restored.decrypt(&fhe, &context).unwrap();
+        assert_eq!(decrypted, results.decrypt(&fhe, &context).unwrap());
+
+        let n = records.len() as f64;
+        let avg_age = records.iter().map(|r| r.age as f64).sum::<f64>() / n;
+        assert!(verify_computation(
+            decrypted.avg_age.unwrap(),
+            avg_age,
+            0.01
+        ));
+        let positives = records.iter().filter(|r| r.marker_alpha).count();
+        assert_eq!(decrypted.marker_positive_count, Some(positives as f64));
+        for record in &records {
+            let expected = records
+                .iter()
+                .filter(|r| r.blood_type == record.blood_type)
+                .count();
+            assert_eq!(
+                decrypted.blood_type_counts[&record.blood_type],
+                expected as f64
+            );
+        }
+
+        // Analyses whose fields were not encrypted are absent
+        assert_eq!(decrypted.avg_glucose, None);
+        assert_eq!(decrypted.glucose_vs_age_regression, None);
+        assert!(decrypted.cholesterol_by_blood_type.is_empty());
+        assert_eq!(decrypted.glucose_cholesterol_correlation(), None);
+    }
+
+    #[test]
+    fn test_sum_width_covers_length_times_largest_value() {
+        let fhe = BiosampleFHE::new();
+        let server_key = fhe.server_key();
+
+        // Two bits per block: a single value needs no extra block, 1000 values need 10 bits
+        assert_eq!(sum_width_blocks(FHE_INT_BITS, 1, server_key), FHE_INT_BITS);
+        assert_eq!(
+            sum_width_blocks(FHE_INT_BITS, 2, server_key),
+            FHE_INT_BITS + 1
+        );
+        assert_eq!(
+            sum_width_blocks(FHE_INT_BITS, 1000, server_key),
+            FHE_INT_BITS + 5
+        );
+
+        // Three values of 30000 each sum past the 16-bit range
+        let values = vec![300.0, 300.0, 300.0];
+        let encrypted = fhe.encrypt_f64_vector(&values, 100.0).unwrap();
+        let column = encrypted.decode().unwrap();
+        assert_eq!(column.sum_blocks(server_key), FHE_INT_BITS + 1);
+        let sum = serialize_ciphertexts(&[compute_encrypted_sum(&encrypted, server_key).unwrap()]);
+        assert_eq!(fhe.decrypt_f64_vector(&sum, 100.0), vec![900.0]);
+
+        // Overriding the width with the width of a single value wraps around
+        let narrow =
+            compute_encrypted_sum_with_width(&encrypted, server_key, FHE_INT_BITS).unwrap();
+        let narrow = serialize_ciphertexts(&[narrow]);
+        assert_ne!(fhe.decrypt_f64_vector(&narrow, 100.0), vec![900.0]);
+
+        let encrypted_data = HashMap::from([(fields::AGE.to_string(), encrypted)]);
+        let results = run_biosample_analysis(&encrypted_data, server_key).unwrap();
+        let mean = results.avg_age.as_ref().unwrap();
+        assert_eq!(fhe.decrypt_f64_vector(mean, mean.scale), vec![300.0]);
+    }
+
+    #[test]
+    fn test_mean_of_1000_cholesterol_levels_does_not_overflow() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(1000, 42).unwrap();
+        let cholesterol: Vec<f64> = records.iter().map(|r| r.cholesterol_level).collect();
+        let encrypted = fhe.encrypt_f64_vector(&cholesterol, 100.0).unwrap();
+
+        let mean = compute_encrypted_mean(&encrypted, fhe.server_key()).unwrap();
+        let mean = fhe.decrypt_element(&mean, 0, mean.scale).unwrap();
+        let expected = cholesterol.iter().sum::<f64>() / cholesterol.len() as f64;
+        assert!(
+            verify_computation(mean, expected, 0.01),
+            "{} vs {}",
+            mean,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_reduce_encrypted_matches_dedicated_sum_and_min() {
+        let fhe = BiosampleFHE::new();
+        // Small enough for the sum to fit the width the values were encrypted at
+        let values = [12.5, -3.25, 40.0, 7.75, 0.5];
+        let encrypted = fhe.encrypt_f64_vector(&values, 100.0).unwrap();
+
+        let sum = reduce_encrypted(&encrypted, fhe.server_key(), |sk, a, b| {
+            sk.add_parallelized(a, b)
+        })
+        .unwrap();
+        let expected_sum = compute_encrypted_sum(&encrypted, fhe.server_key()).unwrap();
+        assert_eq!(sum.length, 1);
+        assert_eq!(sum.scale, 100.0);
+        assert_eq!(
+            fhe.decrypt_f64_vector(&sum, 100.0),
+            fhe.decrypt_f64_vector(&serialize_ciphertexts(&[expected_sum]), 100.0)
+        );
+
+        let min = reduce_encrypted(&encrypted, fhe.server_key(), |sk, a, b| {
+            sk.min_parallelized(a, b)
+        })
+        .unwrap();
+        let expected_min = compute_encrypted_min(&encrypted, fhe.server_key()).unwrap();
+        assert_eq!(
+            fhe.decrypt_f64_vector(&min, 100.0),
+            fhe.decrypt_f64_vector(&expected_min, 100.0)
+        );
+        assert_eq!(fhe.decrypt_f64_vector(&min, 100.0), vec![-3.25]);
+
+        let empty = EncryptedVector::new(Vec::new(), VectorKind::Numeric);
+        assert!(matches!(
+            reduce_encrypted(&empty, fhe.server_key(), |sk, a, b| sk
+                .add_parallelized(a, b)),
+            Err(ComputationError::EmptyInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_min_max_match_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(20, 42).unwrap();
+        let scales = ScaleConfig::new(10.0).with_scale(fields::AGE, 1.0);
+        let (encrypted_data, context) = encrypt_biosample_data(&fhe, &records, &scales).unwrap();
+
+        // Ages are whole years and cholesterol is compared after rounding to its scale
+        let ages: Vec<f64> = records.iter().map(|r| r.age as f64).collect();
+        let cholesterol: Vec<f64> = records
+            .iter()
+            .map(|r| (r.cholesterol_level * 10.0).round() / 10.0)
+            .collect();
+        for (field, values) in [(fields::AGE, ages), (fields::CHOLESTEROL, cholesterol)] {
+            let scale = context.scale_for(field).unwrap();
+            let min = compute_encrypted_min(&encrypted_data[field], fhe.server_key()).unwrap();
+            let max = compute_encrypted_max(&encrypted_data[field], fhe.server_key()).unwrap();
+            assert_eq!(min.length, 1);
+            assert_eq!(min.scale, scale);
+
+            let expected_min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let expected_max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            assert_eq!(fhe.decrypt_element(&min, 0, scale).unwrap(), expected_min);
+            assert_eq!(fhe.decrypt_element(&max, 0, scale).unwrap(), expected_max);
+        }
+
+        // Empty vectors and identifier hashes have no extremes
+        let empty = EncryptedVector::new(Vec::new(), VectorKind::Numeric);
+        assert!(compute_encrypted_min(&empty, fhe.server_key()).is_err());
+        assert!(compute_encrypted_max(&empty, fhe.server_key()).is_err());
+        let ids = fhe.encrypt_id_vector(&["P1".to_string()]);
+        assert!(compute_encrypted_max(&ids, fhe.server_key()).is_err());
+    }
+
+    #[test]
+    fn test_top_k_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        // The duplicate maximum must be selected twice
+        let values = vec![120.0, 85.0, 142.0, 97.0, 142.0];
+        let encrypted = fhe.encrypt_f64_vector(&values, 1.0).unwrap();
+
+        let top = compute_encrypted_top_k(&encrypted, 3, fhe.server_key()).unwrap();
+        assert_eq!(top.length, 3);
+        assert_eq!(
+            fhe.decrypt_f64_vector(&top, top.scale),
+            vec![142.0, 142.0, 120.0]
+        );
+
+        assert!(compute_encrypted_top_k(&encrypted, 6, fhe.server_key()).is_err());
+        let none = compute_encrypted_top_k(&encrypted, 0, fhe.server_key()).unwrap();
+        assert_eq!(none.length, 0);
+    }
+
+    #[test]
+    fn test_sorting_network_sorts_all_binary_inputs() {
+        // By the zero-one principle, a network that sorts every 0/1 input sorts any input
+        for n in 0..=10 {
+            let pairs = sorting_network_pairs(n);
+            for bits in 0u32..(1 << n) {
+                let mut values: Vec<u32> = (0..n).map(|i| (bits >> i) & 1).collect();
+                for &(i, j) in &pairs {
+                    if values[i] > values[j] {
+                        values.swap(i, j);
+                    }
+                }
+                assert!(values.windows(2).all(|w| w[0] <= w[1]), "n = {}", n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sort_encrypted_vector() {
+        let fhe = BiosampleFHE::new();
+        let values = vec![142.0, -3.5, 97.25, 97.25, 0.0];
+        let encrypted = fhe.encrypt_f64_vector(&values, 100.0).unwrap();
+
+        let sorted = sort_encrypted_vector(&encrypted, fhe.server_key()).unwrap();
+        let decrypted = fhe.decrypt_f64_vector(&sorted, sorted.scale);
+
+        // Non-decreasing and a permutation of the input
+        assert!(decrypted.windows(2).all(|w| w[0] <= w[1]));
+        let mut expected = values.clone();
+        expected.sort_by(f64::total_cmp);
+        assert_eq!(decrypted, expected);
+    }
+
+    #[test]
+    fn test_threshold_count_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(20, 42).unwrap();
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+        // Compare the values as they were rounded for encryption
+        let scale = context.scale_for(fields::CHOLESTEROL).unwrap();
+        let scaled: Vec<i64> = records
+            .iter()
+            .map(|r| (r.cholesterol_level * scale).round() as i64)
+            .collect();
+        let encrypted_cholesterol = &encrypted_data[fields::CHOLESTEROL];
+        // Thresholds beyond the range of the ciphertexts count none or all of the values
+        for threshold in [150.0, HIGH_CHOLESTEROL_THRESHOLD, 1000.0, -1000.0] {
+            let threshold_scaled = (threshold * scale).round() as i64;
+            let count = compute_encrypted_threshold_count(
+                encrypted_cholesterol,
+                fhe.server_key(),
+                threshold_scaled,
+            )
+            .unwrap();
+            let expected = scaled.iter().filter(|&&c| c > threshold_scaled).count() as f64;
+            assert_eq!(fhe.decrypt_element(&count, 0, 1.0).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_range_count_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(200, 42).unwrap();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let encrypted = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+
+        // Compare the values as they were rounded for encryption
+        let scaled: Vec<i64> = glucose.iter().map(|g| (g * 100.0).round() as i64).collect();
+        for (low, high) in [(100.0, 125.0), (0.0, 90.5), (110.0, 110.0)] {
+            let count =
+                compute_encrypted_range_count(&encrypted, low, high, 100.0, fhe.server_key())
+                    .unwrap();
+            let (low_scaled, high_scaled) = ((low * 100.0) as i64, (high * 100.0) as i64);
+            let expected = scaled
+                .iter()
+                .filter(|&&g| low_scaled <= g && g < high_scaled)
+                .count() as f64;
+            assert_eq!(
+                fhe.decrypt_element(&count, 0, 1.0).unwrap(),
+                expected,
+                "[{}, {})",
+                low,
+                high
+            );
+        }
+
+        let error =
+            compute_encrypted_range_count(&encrypted, 125.0, 100.0, 100.0, fhe.server_key())
+                .err()
+                .unwrap();
+        assert!(error.to_string().contains("Inverted range"));
+    }
+
+    #[test]
+    fn test_collection_date_range_mask_matches_plaintext_filter() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(12, 3).unwrap();
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        assert_eq!(context.scale_for(fields::COLLECTION_DAY).unwrap(), 1.0);
+
+        // Select the samples collected between the dates of two of the records
+        let days: Vec<i64> = records
+            .iter()
+            .map(|r| collection_day(&r.collection_date).unwrap())
+            .collect();
+        let start = days[3].min(days[7]);
+        let end = days[3].max(days[7]);
+
+        let mask = range_mask(
+            &encrypted_data[fields::COLLECTION_DAY],
+            start,
+            end,
+            fhe.server_key(),
+        )
+        .unwrap();
+        let expected: Vec<bool> = days.iter().map(|day| (start..end).contains(day)).collect();
+        assert_eq!(fhe.decrypt_bool_vector(&mask).unwrap(), expected);
+
+        // Malformed dates are rejected
+        let mut malformed = generate_biosample_data(2, 3).unwrap();
+        malformed[1].collection_date = "2023/01/02".to_string();
+        let error = encrypt_biosample_data(&fhe, &malformed, &ScaleConfig::default())
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("2023/01/02"), "{}", error);
+    }
+
+    #[test]
+    fn test_glucose_range_mask_selects_values_in_closed_range() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(10, 5).unwrap();
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        let scale = context.scale_for(fields::GLUCOSE).unwrap();
+
+        // The closed range [lo, hi] between the levels of two of the records
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let scaled: Vec<i64> = glucose.iter().map(|g| (g * scale).round() as i64).collect();
+        let lo = scaled[2].min(scaled[6]);
+        let hi = scaled[2].max(scaled[6]);
+
+        let mask = range_mask(
+            &encrypted_data[fields::GLUCOSE],
+            lo,
+            hi + 1,
+            fhe.server_key(),
+        )
+        .unwrap();
+        let decrypted_mask = fhe.decrypt_bool_vector(&mask).unwrap();
+        let expected: Vec<bool> = scaled.iter().map(|g| (lo..=hi).contains(g)).collect();
+        assert_eq!(decrypted_mask, expected);
+
+        let decrypted = fhe.decrypt_f64_vector(&encrypted_data[fields::GLUCOSE], scale);
+        let selected = apply_mask_after_decrypt(&decrypted, &decrypted_mask).unwrap();
+        let in_range: Vec<f64> = decrypted
+            .iter()
+            .zip(&scaled)
+            .filter(|(_, g)| (lo..=hi).contains(*g))
+            .map(|(&value, _)| value)
+            .collect();
+        assert_eq!(selected, in_range);
+        assert!(selected.len() >= 2);
+
+        assert!(matches!(
+            apply_mask_after_decrypt(&decrypted, &[true]),
+            Err(ComputationError::LengthMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_monthly_means_match_plaintext_buckets() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(9, 11).unwrap();
+        let days: Vec<i64> = records
+            .iter()
+            .map(|r| collection_day(&r.collection_date).unwrap())
+            .collect();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let encrypted_days = fhe.encrypt_i64_vector(&days).unwrap();
+        let encrypted_glucose = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+
+        // Split the span of the collection dates into three buckets
+        let first = *days.iter().min().unwrap();
+        let last = *days.iter().max().unwrap();
+        let bucket_days = (last - first + 3) / 3;
+        let buckets = compute_encrypted_monthly_means(
+            &encrypted_glucose,
+            &encrypted_days,
+            first..last + 1,
+            bucket_days,
+            fhe.server_key(),
+        )
+        .unwrap();
+        assert_eq!(buckets.len(), 3);
+
+        for (start, sum, count) in &buckets {
+            let inside: Vec<f64> = days
+                .iter()
+                .zip(&glucose)
+                .filter(|(day, _)| (*start..start + bucket_days).contains(*day))
+                .map(|(_, g)| (g * 100.0).round() / 100.0)
+                .collect();
+            assert_eq!(sum.scale, 100.0);
+            assert_eq!(
+                fhe.decrypt_element(count, 0, 1.0).unwrap(),
+                inside.len() as f64
+            );
+            let decrypted_sum = fhe.decrypt_element(sum, 0, sum.scale).unwrap();
+            let expected_sum: f64 = inside.iter().sum();
+            assert!(
+                (decrypted_sum - expected_sum).abs() < 1e-6,
+                "bucket {}: {} vs {}",
+                start,
+                decrypted_sum,
+                expected_sum
+            );
+        }
+
+        assert!(matches!(
+            compute_encrypted_monthly_means(
+                &encrypted_glucose,
+                &encrypted_days,
+                first..first,
+                30,
+                fhe.server_key()
+            ),
+            Err(ComputationError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_median_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+
+        // Odd length: the middle element
+        let odd = fhe
+            .encrypt_f64_vector(&[142.0, 85.5, 97.25, 120.0, 101.0], 100.0)
+            .unwrap();
+        let median = compute_encrypted_median(&odd, fhe.server_key()).unwrap();
+        assert_eq!(
+            fhe.decrypt_element(&median, 0, median.scale).unwrap(),
+            101.0
+        );
+
+        // Even length: the client halves the sum of the two middle elements
+        let even = fhe
+            .encrypt_f64_vector(&[300.0, 85.5, 310.0, 97.25], 100.0)
+            .unwrap();
+        let median = compute_encrypted_median(&even, fhe.server_key()).unwrap();
+        let median = fhe.decrypt_element(&median, 0, median.scale).unwrap() / 2.0;
+        assert_eq!(median, (97.25 + 300.0) / 2.0);
+
+        let empty = EncryptedVector::new(Vec::new(), VectorKind::Numeric);
+        assert!(compute_encrypted_median(&empty, fhe.server_key()).is_err());
+    }
+
+    #[test]
+    fn test_approximate_median_within_one_unit() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(25, 42).unwrap();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let encrypted = fhe.encrypt_f64_vector(&glucose, 10.0).unwrap();
+
+        // 12 halvings narrow 4000 scaled units down to one
+        let median = compute_encrypted_median_approx(&encrypted, -100.0, 300.0, 12, &fhe).unwrap();
+        let mut sorted = glucose.clone();
+        sorted.sort_by(f64::total_cmp);
+        let expected = sorted[sorted.len() / 2];
+        assert!(
+            (median - expected).abs() <= 0.1,
+            "{} vs {}",
+            median,
+            expected
+        );
+
+        // An even number of values averages the two middle values
+        let even = fhe
+            .encrypt_f64_vector(&[90.0, 100.0, 110.0, 95.0], 1.0)
+            .unwrap();
+        let median = compute_encrypted_median_approx(&even, 0.0, 127.0, 7, &fhe).unwrap();
+        assert_eq!(median, 97.5);
+
+        assert!(compute_encrypted_median_approx(&even, 10.0, 0.0, 7, &fhe).is_err());
+    }
+
+    #[test]
+    fn test_percentile_rank_counts_values_at_most_threshold() {
+        let fhe = BiosampleFHE::new();
+        let values = [72.5, 95.25, 95.25, 110.0, 140.75, 64.0];
+        let encrypted = fhe.encrypt_f64_vector(&values, 100.0).unwrap();
+
+        // Thresholds below, at, between and above the values
+        for threshold in [50.0, 64.0, 95.25, 100.0, 140.75] {
+            let rank = compute_encrypted_percentile_rank(
+                &encrypted,
+                (threshold * 100.0) as i64,
+                fhe.server_key(),
+            )
+            .unwrap();
+            let expected = values.iter().filter(|&&v| v <= threshold).count();
+            assert_eq!(
+                fhe.decrypt_element(&rank, 0, 1.0).unwrap(),
+                expected as f64,
+                "threshold {}",
+                threshold
+            );
+        }
+    }
+
+    #[test]
+    fn test_dot_product_with_indicator_is_conditional_sum() {
+        let fhe = BiosampleFHE::new();
+        // Products need twice the integer width, so keep the vectors short
+        let records = generate_biosample_data(4, 7).unwrap();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let marker: Vec<bool> = records.iter().map(|r| r.marker_alpha).collect();
+        let encrypted_glucose = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+        let encrypted_marker = fhe.encrypt_bool_vector(&marker);
+
+        let dot =
+            compute_encrypted_dot_product(&encrypted_glucose, &encrypted_marker, fhe.server_key())
+                .unwrap();
+        assert_eq!(dot.length, 1);
+
+        // Plaintext sum of glucose among marker-positive patients, as rounded for encryption
+        let expected: f64 = records
+            .iter()
+            .filter(|r| r.marker_alpha)
+            .map(|r| (r.glucose_level * 100.0).round())
+            .sum::<f64>()
+            / 100.0;
+        let decrypted = fhe.decrypt_element(&dot, 0, dot.scale).unwrap();
+        assert!(
+            (decrypted - expected).abs() < 1e-9,
+            "{} vs {}",
+            decrypted,
+            expected
+        );
+
+        let short = fhe.encrypt_bool_vector(&marker[..3]);
+        let error = compute_encrypted_dot_product(&encrypted_glucose, &short, fhe.server_key())
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Length mismatch"));
+        let empty = EncryptedVector::new(Vec::new(), VectorKind::Numeric);
+        assert!(compute_encrypted_dot_product(&empty, &empty, fhe.server_key()).is_err());
+    }
+
+    #[test]
+    fn test_scalar_sub_centers_encrypted_vector() {
+        let fhe = BiosampleFHE::new();
+        let ages = fhe.encrypt_f64_vector(&[30.0, 45.5, 51.0], 100.0).unwrap();
+
+        // Subtract a mean of 50 in the encoded units of the vector
+        let centered = scalar_sub_encrypted_vector(&ages, 5000, fhe.server_key()).unwrap();
+        assert_eq!(centered.length, 3);
+        assert_eq!(centered.scale, 100.0);
+        assert_eq!(
+            fhe.decrypt_f64_vector(&centered, centered.scale),
+            vec![-20.0, -4.5, 1.0]
+        );
+
+        // A difference past the 16-bit range of the values does not wrap around
+        let large = fhe.encrypt_f64_vector(&[300.0], 100.0).unwrap();
+        let shifted = scalar_sub_encrypted_vector(&large, -10000, fhe.server_key()).unwrap();
+        assert_eq!(fhe.decrypt_f64_vector(&shifted, 100.0), vec![400.0]);
+
+        let marker = fhe.encrypt_bool_vector(&[true, false]);
+        assert!(matches!(
+            scalar_sub_encrypted_vector(&marker, 1, fhe.server_key()),
+            Err(ComputationError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_scalar_add_and_mul_match_plaintext_arithmetic() {
+        let fhe = BiosampleFHE::new();
+        let values = [30.0, -45.5, 127.25];
+        let encrypted = fhe.encrypt_f64_vector(&values, 100.0).unwrap();
+
+        for scalar in [150, -20000] {
+            let sums = scalar_add_encrypted_vector(&encrypted, scalar, fhe.server_key()).unwrap();
+            assert_eq!(sums.scale, 100.0);
+            let expected: Vec<f64> = values.iter().map(|v| v + scalar as f64 / 100.0).collect();
+            assert_eq!(fhe.decrypt_f64_vector(&sums, sums.scale), expected);
+        }
+
+        // Whole factors keep the scale, and the products do not wrap around
+        for factor in [3.0, -1000.0] {
+            let products =
+                scalar_mul_encrypted_vector(&encrypted, factor, 1.0, fhe.server_key()).unwrap();
+            assert_eq!(products.scale, 100.0);
+            let expected: Vec<f64> = values.iter().map(|v| v * factor).collect();
+            assert_eq!(fhe.decrypt_f64_vector(&products, products.scale), expected);
+        }
+
+        // Glucose from mg/dL to mmol/L, with the precision folded into the scale
+        let glucose = [90.0, 126.0, 200.0];
+        let encrypted_glucose = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+        let mmol = scalar_mul_encrypted_vector(
+            &encrypted_glucose,
+            1.0 / 18.016,
+            10000.0,
+            fhe.server_key(),
+        )
+        .unwrap();
+        assert_eq!(mmol.scale, 1_000_000.0);
+        for (converted, mg) in fhe
+            .decrypt_f64_vector(&mmol, mmol.scale)
+            .iter()
+            .zip(glucose)
+        {
+            assert!(
+                (converted - mg / 18.016).abs() < 0.01,
+                "{} vs {}",
+                converted,
+                mg
+            );
+        }
+
+        let marker = fhe.encrypt_bool_vector(&[true, false]);
+        assert!(matches!(
+            scalar_add_encrypted_vector(&marker, 1, fhe.server_key()),
+            Err(ComputationError::Unsupported(_))
+        ));
+        assert!(matches!(
+            scalar_mul_encrypted_vector(&encrypted, f64::NAN, 1.0, fhe.server_key()),
+            Err(ComputationError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn test_deviations_from_encrypted_mean_sum_to_zero() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(6, 12).unwrap();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let encrypted = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+
+        let mean = compute_encrypted_mean(&encrypted, fhe.server_key()).unwrap();
+        let deviations = compute_encrypted_deviations(&encrypted, &mean, fhe.server_key()).unwrap();
+        assert_eq!(deviations.length, glucose.len());
+        assert_eq!(deviations.scale, mean.scale);
+
+        // The server truncates the mean by less than one unit of its scale
+        let decrypted = fhe.decrypt_f64_vector(&deviations, deviations.scale);
+        let sum: f64 = decrypted.iter().sum();
+        assert!(
+            sum.abs() <= glucose.len() as f64 / mean.scale + 1e-9,
+            "deviations sum to {}",
+            sum
+        );
+        let plaintext_mean = glucose.iter().sum::<f64>() / glucose.len() as f64;
+        for (deviation, value) in decrypted.iter().zip(&glucose) {
+            assert!((deviation - (value - plaintext_mean)).abs() < 0.02);
+        }
+
+        // Negation flips the sign of every value, so adding it back cancels out
+        let negated = vector_negate(&encrypted, fhe.server_key()).unwrap();
+        let negated_values = fhe.decrypt_f64_vector(&negated, 100.0);
+        for (negated, value) in negated_values.iter().zip(&glucose) {
+            assert!((negated + value).abs() < 0.01);
+        }
+        let cancelled = vector_add(&encrypted, &negated, fhe.server_key()).unwrap();
+        assert!(fhe
+            .decrypt_f64_vector(&cancelled, 100.0)
+            .iter()
+            .all(|&v| v == 0.0));
+
+        // The mean must be a single value at a whole multiple of the field's scale
+        assert!(matches!(
+            compute_encrypted_deviations(&encrypted, &encrypted, fhe.server_key()),
+            Err(ComputationError::LengthMismatch(_))
+        ));
+        let coarse = fhe.encrypt_f64_vector(&[100.0], 30.0).unwrap();
+        assert!(matches!(
+            compute_encrypted_deviations(&encrypted, &coarse, fhe.server_key()),
+            Err(ComputationError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_exact_mean_rebuilds_the_decrypted_sum() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(7, 13).unwrap();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let encrypted = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+        let sum = compute_encrypted_sum(&encrypted, fhe.server_key()).unwrap();
+        let decrypted_sum = fhe.decrypt_i64_vector(&serialize_ciphertexts(&[sum]))[0];
+
+        let exact_mean =
+            compute_encrypted_mean_exact(&encrypted, glucose.len(), fhe.server_key()).unwrap();
+        assert_eq!(exact_mean.length, 2);
+        assert_eq!(exact_mean.scale, 100.0);
+        let exact = fhe.decrypt_exact_mean(&exact_mean, glucose.len()).unwrap();
+        assert_eq!(exact.scaled_sum(), decrypted_sum as i128);
+        assert!((0..glucose.len() as i64).contains(&exact.remainder));
+        let scaled_mean = decrypted_sum as f64 / glucose.len() as f64 / 100.0;
+        assert!((exact.value() - scaled_mean).abs() < 1e-9);
+
+        // The remainder of a negative sum is negative, like the quotient
+        let encrypted = fhe.encrypt_f64_vector(&[-1.25, 0.5, -3.01], 100.0).unwrap();
+        let exact_mean = compute_encrypted_mean_exact(&encrypted, 3, fhe.server_key()).unwrap();
+        let exact = fhe.decrypt_exact_mean(&exact_mean, 3).unwrap();
+        assert_eq!((exact.quotient, exact.remainder), (-125, -1));
+        assert_eq!(exact.scaled_sum(), -376);
+
+        assert!(matches!(
+            compute_encrypted_mean_exact(&encrypted, 0, fhe.server_key()),
+            Err(ComputationError::InvalidInput(_))
+        ));
+        assert!(fhe.decrypt_exact_mean(&encrypted, 3).is_err());
+    }
+
+    #[test]
+    fn test_vector_add_and_sub_match_plaintext_per_record() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(5, 8).unwrap();
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        let scale = context.scale_for(fields::CHOLESTEROL).unwrap();
+        assert_eq!(context.scale_for(fields::GLUCOSE).unwrap(), scale);
+        let cholesterol = &encrypted_data[fields::CHOLESTEROL];
+        let glucose = &encrypted_data[fields::GLUCOSE];
+
+        let sums = vector_add(cholesterol, glucose, fhe.server_key()).unwrap();
+        let differences = vector_sub(cholesterol, glucose, fhe.server_key()).unwrap();
+        assert_eq!(sums.length, records.len());
+        assert_eq!(differences.scale, scale);
+
+        let round = |value: f64| (value * scale).round();
+        let decrypted_sums = fhe.decrypt_f64_vector(&sums, scale);
+        let decrypted_differences = fhe.decrypt_f64_vector(&differences, scale);
+        for (i, record) in records.iter().enumerate() {
+            let (c, g) = (round(record.cholesterol_level), round(record.glucose_level));
+            assert_eq!(round(decrypted_sums[i]), c + g, "record {}", i);
+            assert_eq!(round(decrypted_differences[i]), c - g, "record {}", i);
+        }
+
+        // A difference past the 16-bit range of the operands does not wrap around
+        let low = fhe.encrypt_f64_vector(&[-300.0], 100.0).unwrap();
+        let high = fhe.encrypt_f64_vector(&[300.0], 100.0).unwrap();
+        let spread = vector_sub(&high, &low, fhe.server_key()).unwrap();
+        assert_eq!(fhe.decrypt_f64_vector(&spread, 100.0), vec![600.0]);
+
+        let other_scale = fhe.encrypt_f64_vector(&[1.0; 5], 10.0).unwrap();
+        assert!(matches!(
+            vector_add(cholesterol, &other_scale, fhe.server_key()),
+            Err(ComputationError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            vector_sub(cholesterol, &high, fhe.server_key()),
+            Err(ComputationError::LengthMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_operation_stats_report_size_of_summed_result() {
+        let fhe = BiosampleFHE::new();
+        let values = fhe
+            .encrypt_f64_vector(&[12.5, 40.0, 7.25, 99.0], 100.0)
+            .unwrap();
+        assert!(ciphertext_byte_size(&values) > 0);
+
+        let (sum, stats) = log_operation_stats("sum", &values, |input| {
+            let sum = compute_encrypted_sum(input, fhe.server_key())?;
+            Ok(serialize_ciphertexts(&[sum]).with_scale(input.scale))
+        })
+        .unwrap();
+        assert_eq!(stats.operation, "sum");
+        assert_eq!(stats.input_bytes, ciphertext_byte_size(&values));
+        assert_eq!(stats.input_ciphertexts, 4);
+        assert_eq!(stats.output_ciphertexts, 1);
+        assert_eq!(stats.output_bytes, ciphertext_byte_size(&sum));
+        assert!(stats.output_bytes > 0);
+        // The sum is wider than each of the values it adds up
+        assert!(stats.growth_per_ciphertext().unwrap() > 1.0);
+        assert_eq!(fhe.decrypt_element(&sum, 0, 100.0).unwrap(), 158.75);
+    }
+
+    #[test]
+    fn test_elementwise_product_keeps_full_width_and_combined_scale() {
+        let fhe = BiosampleFHE::new();
+        // 250.00 × 90.00 at scale 100 is 2.25e8, far past the 16-bit range of either factor
+        let glucose = fhe
+            .encrypt_f64_vector(&[250.0, -1.5, 80.25], 100.0)
+            .unwrap();
+        let age = fhe.encrypt_f64_vector(&[90.0, 40.0, 2.0], 100.0).unwrap();
+
+        let products =
+            compute_encrypted_elementwise_product(&glucose, &age, fhe.server_key()).unwrap();
+        assert_eq!(products.length, 3);
+        assert_eq!(products.scale, 10000.0);
+        assert_eq!(
+            fhe.decrypt_f64_vector(&products, products.scale),
+            vec![22500.0, -60.0, 160.5]
+        );
+
+        let mean = compute_encrypted_product_mean(&glucose, &age, fhe.server_key()).unwrap();
+        assert_eq!(mean.scale, 10000.0 * MEAN_PRECISION as f64);
+        let decrypted = fhe.decrypt_element(&mean, 0, mean.scale).unwrap();
+        assert!((decrypted - 22600.5 / 3.0).abs() < 1e-6, "{}", decrypted);
+
+        let short = fhe.encrypt_f64_vector(&[1.0], 100.0).unwrap();
+        assert!(matches!(
+            compute_encrypted_elementwise_product(&glucose, &short, fhe.server_key()),
+            Err(ComputationError::LengthMismatch(_))
+        ));
+        let empty = EncryptedVector::new(Vec::new(), VectorKind::Numeric);
+        assert!(matches!(
+            compute_encrypted_product_mean(&empty, &empty, fhe.server_key()),
+            Err(ComputationError::EmptyInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_group_means_match_plaintext() {
+        let fhe = BiosampleFHE::new();
+        // Products need twice the integer width, so keep the vectors short
+        let glucose = vec![85.5, 110.2, 104.0, 92.7];
+        let marker = vec![true, false, true, false];
+        let encrypted_data = HashMap::from([(
+            fields::GLUCOSE.to_string(),
+            fhe.encrypt_f64_vector(&glucose, 10.0).unwrap(),
+        )]);
+        let context = EncryptionContext::new(glucose.len(), &encrypted_data);
+        let encrypted_marker = fhe.encrypt_bool_vector(&marker);
+
+        let terms = compute_encrypted_group_means(
+            &encrypted_data[fields::GLUCOSE],
+            &encrypted_marker,
+            fhe.server_key(),
+        )
+        .unwrap();
+        assert_eq!(terms.length, 4);
+        let (positive, negative) = fhe
+            .decrypt_group_means(&terms, fields::GLUCOSE, &context)
+            .unwrap();
+
+        // Plaintext means of both groups
+        for (mean, group) in [(positive, true), (negative, false)] {
+            let values: Vec<f64> = glucose
+                .iter()
+                .zip(&marker)
+                .filter(|(_, &m)| m == group)
+                .map(|(&g, _)| g)
+                .collect();
+            let expected = values.iter().sum::<f64>() / values.len() as f64;
+            let mean = mean.unwrap();
+            assert!((mean - expected).abs() < 1e-9, "{} vs {}", mean, expected);
+        }
+
+        // The indicator must be boolean
+        let numeric = fhe.encrypt_f64_vector(&[1.0, 0.0, 1.0, 0.0], 1.0).unwrap();
+        assert!(compute_encrypted_group_means(
+            &encrypted_data[fields::GLUCOSE],
+            &numeric,
+            fhe.server_key()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_weighted_sum_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let glucose = vec![85.5, 110.25, 104.0];
+        let cholesterol = vec![180.0, 240.5, 199.75];
+        let encrypted_glucose = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+        let encrypted_cholesterol = fhe.encrypt_f64_vector(&cholesterol, 100.0).unwrap();
+        let fields = [(&encrypted_glucose, 0.3), (&encrypted_cholesterol, 0.7)];
+
+        let scores = compute_encrypted_weighted_sum(&fields, fhe.server_key(), 10.0).unwrap();
+        assert_eq!(scores.length, 3);
+        // Values scaled by 100 and weights by 10
+        assert_eq!(scores.scale, 1000.0);
+        let decrypted = fhe.decrypt_f64_vector(&scores, scores.scale);
+        for (i, score) in decrypted.iter().enumerate() {
+            let expected = 0.3 * glucose[i] + 0.7 * cholesterol[i];
+            assert!((score - expected).abs() < 1e-6, "{} vs {}", score, expected);
+        }
+
+        let total = compute_encrypted_weighted_total(&fields, fhe.server_key(), 10.0).unwrap();
+        let expected: f64 = (0..3)
+            .map(|i| 0.3 * glucose[i] + 0.7 * cholesterol[i])
+            .sum();
+        let decrypted = fhe.decrypt_element(&total, 0, total.scale).unwrap();
+        assert!((decrypted - expected).abs() < 1e-6);
+
+        // Fields must share one scale
+        let ages = fhe.encrypt_f64_vector(&[25.0, 40.0, 70.0], 1.0).unwrap();
+        let mixed = [(&encrypted_glucose, 0.3), (&ages, 0.7)];
+        assert!(compute_encrypted_weighted_sum(&mixed, fhe.server_key(), 10.0).is_err());
+        assert!(compute_encrypted_weighted_sum(&[], fhe.server_key(), 10.0).is_err());
+    }
+
+    #[test]
+    fn test_risk_scores_count_matches_plaintext_model() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(8, 5).unwrap();
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+        // Ages are encrypted at scale 1, glucose and cholesterol at scale 100
+        let weights = [
+            (fields::AGE, 0.03),
+            (fields::GLUCOSE, 0.02),
+            (fields::CHOLESTEROL, 0.01),
+        ];
+        let plaintext_scores: Vec<f64> = records
+            .iter()
+            .map(|r| {
+                -5.0 + 0.03 * r.age as f64 + 0.02 * r.glucose_level + 0.01 * r.cholesterol_level
+            })
+            .collect();
+        // Put the threshold between two scores, so rounding cannot move a patient across it
+        let mut sorted = plaintext_scores.clone();
+        sorted.sort_by(f64::total_cmp);
+        let threshold = (sorted[3] + sorted[4]) / 2.0;
+        let model = weights
+            .iter()
+            .fold(RiskModel::new(-5.0, threshold), |model, (field, weight)| {
+                model.with_weight(field, *weight)
+            });
+
+        let risk =
+            compute_encrypted_risk_scores(&model, &encrypted_data, fhe.server_key()).unwrap();
+        let expected = plaintext_scores.iter().filter(|&&s| s > threshold).count();
+        assert_eq!(
+            fhe.decrypt_element(&risk.above_threshold, 0, 1.0).unwrap(),
+            expected as f64
+        );
+
+        // The scores carry the largest field scale times the weight scale
+        assert_eq!(
+            risk.scores.scale,
+            context.scale_for(fields::GLUCOSE).unwrap() * RISK_WEIGHT_SCALE
+        );
+        let decrypted = fhe.decrypt_f64_vector(&risk.scores, risk.scores.scale);
+        for (score, expected) in decrypted.iter().zip(&plaintext_scores) {
+            assert!((score - expected).abs() < 0.01, "{} vs {}", score, expected);
+        }
+
+        // Every weighted field must be encrypted
+        let unknown = RiskModel::new(0.0, 1.0).with_weight("bmi", 1.0);
+        assert!(matches!(
+            compute_encrypted_risk_scores(&unknown, &encrypted_data, fhe.server_key()),
+            Err(ComputationError::Encryption(EncryptionError::MissingField(field))) if field == "bmi"
+        ));
+        assert!(matches!(
+            compute_encrypted_risk_scores(
+                &RiskModel::new(0.0, 1.0),
+                &encrypted_data,
+                fhe.server_key()
+            ),
+            Err(ComputationError::EmptyInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_variance_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        // Squares need twice the integer width, so keep the vector short
+        let glucose = vec![85.5, 110.2, 104.0, 92.7];
+        let encrypted_data = HashMap::from([(
+            fields::GLUCOSE.to_string(),
+            fhe.encrypt_f64_vector(&glucose, 10.0).unwrap(),
+        )]);
+        let context = EncryptionContext::new(glucose.len(), &encrypted_data);
+
+        let terms =
+            compute_encrypted_variance(&encrypted_data[fields::GLUCOSE], fhe.server_key()).unwrap();
+        assert_eq!(terms.length, 2);
+        let (variance, std_dev) = fhe
+            .decrypt_variance(&terms, fields::GLUCOSE, &context)
+            .unwrap();
+
+        // Plaintext population variance
+        let n = glucose.len() as f64;
+        let mean = glucose.iter().sum::<f64>() / n;
+        let expected = glucose.iter().map(|g| (g - mean).powi(2)).sum::<f64>() / n;
+        assert!(
+            verify_computation(variance, expected, 0.05),
+            "{} vs {}",
+            variance,
+            expected
+        );
+        assert!(verify_computation(std_dev, expected.sqrt(), 0.05));
+
+        // Only numeric, non-empty vectors have a variance
+        let empty = EncryptedVector::new(Vec::new(), VectorKind::Numeric);
+        assert!(compute_encrypted_variance(&empty, fhe.server_key()).is_err());
+        let flags = fhe.encrypt_bool_vector(&[true, false]);
+        assert!(compute_encrypted_variance(&flags, fhe.server_key()).is_err());
+    }
+
+    #[test]
+    fn test_sum_accumulator_matches_compute_encrypted_sum() {
+        let fhe = BiosampleFHE::new();
+        let values = vec![120.5, 98.25, 101.0, 87.75, 110.0];
+        let encrypted = fhe.encrypt_f64_vector(&values, 100.0).unwrap();
+
+        let mut accumulator = EncryptedSumAccumulator::new();
+        for bytes in encrypted.ciphertext_bytes().unwrap().iter() {
+            accumulator.add_serialized(bytes, fhe.server_key()).unwrap();
+        }
+        assert_eq!(accumulator.len(), values.len());
+        let streamed = accumulator.finalize().unwrap().with_scale(100.0);
+
+        let expected = compute_encrypted_sum(&encrypted, fhe.server_key()).unwrap();
+        let expected = serialize_ciphertexts(&[expected]);
+        assert_eq!(
+            fhe.decrypt_f64_vector(&streamed, 100.0),
+            fhe.decrypt_f64_vector(&expected, 100.0)
+        );
+        assert_eq!(fhe.decrypt_f64_vector(&streamed, 100.0), vec![517.5]);
+
+        assert!(EncryptedSumAccumulator::new().finalize().is_err());
+        let mut accumulator = EncryptedSumAccumulator::new();
+        assert!(accumulator
+            .add_serialized(&[0u8; 4], fhe.server_key())
+            .is_err());
+    }
+
+    #[test]
+    fn test_incremental_sum_of_three_batches_matches_plaintext_total() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(9, 11).unwrap();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let batches: Vec<EncryptedVector> = glucose
+            .chunks(3)
+            .map(|batch| fhe.encrypt_f64_vector(batch, 100.0).unwrap())
+            .collect();
+
+        let mut incremental = IncrementalSum::new(fhe.server_key_arc());
+        assert!(incremental.finish().is_err());
+        incremental.add_batch(&batches[0]).unwrap();
+        incremental.add_batch(&batches[1]).unwrap();
+
+        // Persist the accumulator between two runs
+        let bytes = bincode::serialize(&incremental).unwrap();
+        let mut incremental: IncrementalSum = bincode::deserialize(&bytes).unwrap();
+        assert!(incremental.add_batch(&batches[2]).is_err());
+        incremental = incremental.with_server_key(fhe.server_key_arc());
+        incremental.add_batch(&batches[2]).unwrap();
+        assert_eq!(incremental.len(), glucose.len());
+
+        let total = incremental.finish().unwrap();
+        let expected: i64 = glucose.iter().map(|v| (v * 100.0).round() as i64).sum();
+        assert_eq!(
+            fhe.decrypt_f64_vector(&total, total.scale),
+            vec![expected as f64 / 100.0]
+        );
+
+        let other_scale = fhe.encrypt_f64_vector(&[1.0], 10.0).unwrap();
+        assert!(matches!(
+            incremental.add_batch(&other_scale),
+            Err(ComputationError::InvalidInput(_))
+        ));
+        // Boolean and identifier batches are not sums of values
+        let markers = fhe.encrypt_bool_vector(&[true]);
+        let ids = fhe.encrypt_id_vector(&["P1".to_string()]);
+        for batch in [markers, ids] {
+            assert!(matches!(
+                incremental.add_batch(&batch),
+                Err(ComputationError::Unsupported(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_nonzero_count_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let values = [0, 3, 0, -2, 1, 0, 0, 120, -128, 0];
+        let encrypted = fhe.encrypt_i64_vector(&values).unwrap();
+
+        let count = compute_encrypted_nonzero_count(&encrypted, fhe.server_key()).unwrap();
+        let expected = values.iter().filter(|&&v| v != 0).count() as f64;
+        assert_eq!(fhe.decrypt_element(&count, 0, 1.0).unwrap(), expected);
+
+        let zeros = fhe.encrypt_i64_vector(&[0, 0, 0]).unwrap();
+        let count = compute_encrypted_nonzero_count(&zeros, fhe.server_key()).unwrap();
+        assert_eq!(fhe.decrypt_element(&count, 0, 1.0).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_count_true_and_proportion_match_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(40, 7).unwrap();
+        let markers: Vec<bool> = records.iter().map(|r| r.marker_alpha).collect();
+        let expected = markers.iter().filter(|&&m| m).count();
+        let encrypted = fhe.encrypt_bool_vector(&markers);
+
+        let count = compute_encrypted_count_true(&encrypted, fhe.server_key()).unwrap();
+        assert_eq!(
+            fhe.decrypt_element(&count, 0, 1.0).unwrap(),
+            expected as f64
+        );
+
+        let proportion = compute_encrypted_proportion(&encrypted, fhe.server_key()).unwrap();
+        assert_eq!(proportion.total, markers.len());
+        assert_eq!(
+            proportion.decrypt_rate(&fhe).unwrap(),
+            expected as f64 / markers.len() as f64
+        );
+
+        // The analysis reports the same count
+        let encrypted_data = HashMap::from([(fields::MARKER.to_string(), encrypted)]);
+        let results = run_biosample_analysis(&encrypted_data, fhe.server_key()).unwrap();
+        let count = results.marker_positive_count.as_ref().unwrap();
+        assert_eq!(fhe.decrypt_element(count, 0, 1.0).unwrap(), expected as f64);
+
+        // Numeric values are not indicators
+        let ages = fhe.encrypt_f64_vector(&[30.0, 45.0], 1.0).unwrap();
+        assert!(compute_encrypted_count_true(&ages, fhe.server_key()).is_err());
+    }
+
+    #[test]
+    fn test_mean_difference_between_genders_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        // Squares need twice the integer width, so keep the cohorts small
+        let records = generate_biosample_data(12, 42).unwrap();
+        let cohort = |gender: &str| -> Vec<f64> {
+            records
+                .iter()
+                .filter(|r| r.gender == gender)
+                .map(|r| r.cholesterol_level)
+                .collect()
+        };
+        let (male, female) = (cohort("Male"), cohort("Female"));
+        assert!(male.len() >= 2 && female.len() >= 2);
+
+        let terms = compute_encrypted_mean_difference(
+            &fhe.encrypt_f64_vector(&male, 100.0).unwrap(),
+            &fhe.encrypt_f64_vector(&female, 100.0).unwrap(),
+            fhe.server_key(),
+        )
+        .unwrap();
+        let (difference, standard_error) = terms.decrypt_difference(&fhe).unwrap();
+
+        // Plaintext difference and Welch standard error
+        let mean_and_squared_error = |values: &[f64]| {
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            (mean, variance / n)
+        };
+        let (mean_male, error_male) = mean_and_squared_error(&male);
+        let (mean_female, error_female) = mean_and_squared_error(&female);
+        let expected_error = (error_male + error_female).sqrt();
+
+        assert!((difference - (mean_male - mean_female)).abs() < 0.01);
+        assert!((standard_error - expected_error).abs() < 0.01 * expected_error);
+
+        // Cohorts encoded with different scales cannot be compared
+        assert!(compute_encrypted_mean_difference(
+            &fhe.encrypt_f64_vector(&male, 100.0).unwrap(),
+            &fhe.encrypt_f64_vector(&female, 10.0).unwrap(),
+            fhe.server_key(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_outlier_flags_match_plaintext_z_scores() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(60, 42).unwrap();
+        let cholesterol: Vec<f64> = records.iter().map(|r| r.cholesterol_level).collect();
+        let encrypted = fhe.encrypt_f64_vector(&cholesterol, 100.0).unwrap();
+
+        // The aggregates the client would decrypt from the mean and variance
+        let n = cholesterol.len() as f64;
+        let mean = cholesterol.iter().sum::<f64>() / n;
+        let std = (cholesterol.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let k = 2.0;
+
+        let outliers =
+            compute_encrypted_outlier_flags(&encrypted, mean, std, k, fhe.server_key()).unwrap();
+
+        let expected: Vec<bool> = cholesterol
+            .iter()
+            .map(|c| (c - mean).abs() > k * std)
+            .collect();
+        let expected_count = expected.iter().filter(|&&outlier| outlier).count();
+        assert!(expected_count > 0);
+        assert_eq!(fhe.decrypt_bool_vector(&outliers.flags).unwrap(), expected);
+        assert_eq!(
+            fhe.decrypt_element(&outliers.count, 0, 1.0).unwrap(),
+            expected_count as f64
+        );
+
+        assert!(
+            compute_encrypted_outlier_flags(&encrypted, mean, -std, k, fhe.server_key()).is_err()
+        );
+    }
+
+    #[test]
+    #[ignore = "600 products of wide ciphertexts take hours on a single core"]
+    fn test_linear_regression_matches_plaintext_least_squares() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(200, 42).unwrap();
+        let ages: Vec<f64> = records.iter().map(|r| r.age as f64).collect();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let encrypted_ages = fhe.encrypt_f64_vector(&ages, 1.0).unwrap();
+        let encrypted_glucose = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+
+        let terms = compute_encrypted_linear_regression(
+            &encrypted_ages,
+            &encrypted_glucose,
+            fhe.server_key(),
+        )
+        .unwrap();
+
+        // Each sum decrypts with the scale of its products
+        let scales = [1.0, 100.0, 100.0, 1.0, 100.0 * 100.0];
+        let mut decrypted = [0.0; 5];
+        for (i, scale) in scales.iter().enumerate() {
+            decrypted[i] = fhe.decrypt_element(&terms, i, *scale).unwrap();
+        }
+        let (slope, intercept, r) = finish_regression(decrypted, records.len()).unwrap();
+
+        // Plaintext least-squares fit
+        let n = ages.len() as f64;
+        let mean_x = ages.iter().sum::<f64>() / n;
+        let mean_y = glucose.iter().sum::<f64>() / n;
+        let covariance: f64 = ages
+            .iter()
+            .zip(glucose.iter())
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        let spread_x: f64 = ages.iter().map(|x| (x - mean_x).powi(2)).sum();
+        let spread_y: f64 = glucose.iter().map(|y| (y - mean_y).powi(2)).sum();
+        let expected_slope = covariance / spread_x;
+        let expected_intercept = mean_y - expected_slope * mean_x;
+
+        assert!(
+            (slope - expected_slope).abs() <= 0.05 * expected_slope.abs(),
+            "slope {} expected {}",
+            slope,
+            expected_slope
+        );
+        assert!((intercept - expected_intercept).abs() < 0.05 * expected_intercept.abs());
+        assert!((r - covariance / (spread_x * spread_y).sqrt()).abs() < 1e-3);
+
+        // A constant column has no least-squares line
+        assert!(finish_regression([3.0, 6.0, 6.0, 3.0, 12.0], 3).is_none());
+    }
+
+    #[test]
+    fn test_correlation_terms_match_plaintext_pearson() {
+        let fhe = BiosampleFHE::new();
+        // Products need twice the integer width, so keep the vectors short
+        let ages = vec![25.0, 40.0, 70.0];
+        let glucose = vec![85.5, 110.25, 104.0];
+
+        let encrypted_ages = fhe.encrypt_f64_vector(&ages, 1.0).unwrap();
+        let encrypted_glucose = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+        let terms = compute_encrypted_correlation_terms(
+            &encrypted_ages,
+            &encrypted_glucose,
+            fhe.server_key(),
+        )
+        .unwrap();
+
+        let decrypt = |term: &EncryptedVector| fhe.decrypt_f64_vector(term, term.scale)[0];
+        let r = pearson_from_terms(
+            terms.length,
+            decrypt(&terms.sum_a),
+            decrypt(&terms.sum_b),
+            decrypt(&terms.sum_ab),
+            decrypt(&terms.sum_a_sq),
+            decrypt(&terms.sum_b_sq),
+        )
+        .unwrap();
+
+        // Plaintext Pearson r
+        let n = ages.len() as f64;
+        let mean_a = ages.iter().sum::<f64>() / n;
+        let mean_b = glucose.iter().sum::<f64>() / n;
+        let covariance: f64 = ages
+            .iter()
+            .zip(glucose.iter())
+            .map(|(a, b)| (a - mean_a) * (b - mean_b))
+            .sum();
+        let spread_a: f64 = ages.iter().map(|a| (a - mean_a).powi(2)).sum();
+        let spread_b: f64 = glucose.iter().map(|b| (b - mean_b).powi(2)).sum();
+        let expected = covariance / (spread_a * spread_b).sqrt();
+
+        assert!((r - expected).abs() < 1e-6, "r {} expected {}", r, expected);
+
+        let shorter = fhe.encrypt_f64_vector(&ages[..2], 1.0).unwrap();
+        assert!(compute_encrypted_correlation_terms(
+            &shorter,
+            &encrypted_glucose,
+            fhe.server_key()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_decoded_column_reductions_match_standalone_functions() {
+        let fhe = BiosampleFHE::new();
+        let values = vec![12.5, -7.25, 30.0, 4.0];
+        let scale = 100.0;
+        let encrypted = fhe.encrypt_f64_vector(&values, scale).unwrap();
+
+        // Deserialize once and run several reductions on the same column
+        let column = encrypted.decode().unwrap();
+        assert_eq!(column.len(), values.len());
+        let sum = serialize_ciphertexts(&[column.sum(fhe.server_key()).unwrap()]);
+        let min = serialize_ciphertexts(&[column.min(fhe.server_key()).unwrap()]);
+
+        let standalone_sum =
+            serialize_ciphertexts(&[compute_encrypted_sum(&encrypted, fhe.server_key()).unwrap()]);
+        assert_eq!(
+            fhe.decrypt_f64_vector(&sum, scale),
+            fhe.decrypt_f64_vector(&standalone_sum, scale)
+        );
+        assert_eq!(fhe.decrypt_f64_vector(&min, scale), vec![-7.25]);
+
+        // Encoding the column gives back an equivalent vector
+        let encoded = column.encode();
+        assert_eq!(encoded.kind, encrypted.kind);
+        assert_eq!(encoded.scale, encrypted.scale);
+        assert_eq!(fhe.decrypt_f64_vector(&encoded, scale), values);
+    }
+
+    #[test]
+    fn test_eq_scalar_mask_counts_matching_facility() {
+        let fhe = BiosampleFHE::new();
+        let facility_ids = vec![1.0, 3.0, 1.0, 2.0, 1.0];
+        let target = 1;
+
+        // Facility ids are exact integers, so a scale of 1 keeps them unchanged
+        let encrypted = fhe.encrypt_f64_vector(&facility_ids, 1.0).unwrap();
+        let mask = eq_scalar_mask(&encrypted, target, fhe.server_key()).unwrap();
+        assert_eq!(mask.length, facility_ids.len());
+
+        // The decrypted mask should flag exactly the matching records
+        let expected_mask: Vec<bool> = facility_ids.iter().map(|&id| id as i64 == target).collect();
+        assert_eq!(fhe.decrypt_bool_vector(&mask).unwrap(), expected_mask);
+
+        // Summing the mask should count the matches
+        let count = compute_encrypted_sum(&mask, fhe.server_key()).unwrap();
+        let decrypted_count = fhe.decrypt_f64_vector(&serialize_ciphertexts(&[count]), 1.0);
+        let expected_count = expected_mask.iter().filter(|&&m| m).count() as f64;
+        assert_eq!(decrypted_count[0], expected_count);
+
+        // Identifier hashes are rejected like in the other comparisons
+        let ids = fhe.encrypt_id_vector(&["P1".to_string()]);
+        assert!(matches!(
+            eq_scalar_mask(&ids, target, fhe.server_key()),
+            Err(ComputationError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_indicator_and_or_counts_match_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(24, 5).unwrap();
+        let (encrypted_data, _) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        let marker = &encrypted_data[fields::MARKER];
+        let o_positive = &encrypted_data[&fields::blood_type_key("O+")];
+
+        let both = indicator_and(marker, o_positive, fhe.server_key()).unwrap();
+        let either = indicator_or(marker, o_positive, fhe.server_key()).unwrap();
+        let expected_both: Vec<bool> = records
+            .iter()
+            .map(|r| r.marker_alpha && r.blood_type == "O+")
+            .collect();
+        let expected_either: Vec<bool> = records
+            .iter()
+            .map(|r| r.marker_alpha || r.blood_type == "O+")
+            .collect();
+        assert_eq!(fhe.decrypt_bool_vector(&both).unwrap(), expected_both);
+        assert_eq!(fhe.decrypt_bool_vector(&either).unwrap(), expected_either);
+
+        // The combined indicators count like any boolean field
+        for (indicator, expected) in [(&both, &expected_both), (&either, &expected_either)] {
+            let count = compute_encrypted_count_true(indicator, fhe.server_key()).unwrap();
+            let expected = expected.iter().filter(|&&selected| selected).count() as f64;
+            assert_eq!(fhe.decrypt_f64_vector(&count, 1.0)[0], expected);
+        }
+
+        let numeric = &encrypted_data[fields::GLUCOSE];
+        assert!(matches!(
+            indicator_and(marker, numeric, fhe.server_key()),
+            Err(ComputationError::Unsupported(_))
+        ));
+        let shorter = fhe.encrypt_bool_vector(&[true, false]);
+        assert!(matches!(
+            indicator_or(marker, &shorter, fhe.server_key()),
+            Err(ComputationError::LengthMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_blood_type_counts_exact_for_1000_records() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(1000, 42).unwrap();
+        let (encrypted_data, _) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+        // The two most common blood types both occur more than 255 times
+        let blood_type_data: HashMap<String, EncryptedVector> = ["O+", "A+"]
+            .iter()
+            .map(|blood_type| {
+                let key = fields::blood_type_key(blood_type);
+                (key.clone(), encrypted_data[&key].clone())
+            })
+            .collect();
+        let counts = compute_encrypted_category_counts(&blood_type_data, fhe.server_key()).unwrap();
+        assert_eq!(counts.len(), 2);
+
+        for (key, encrypted_count) in &counts {
+            let blood_type = key.trim_start_matches(fields::BLOOD_TYPE_PREFIX);
+            let expected = records
+                .iter()
+                .filter(|r| r.blood_type == blood_type)
+                .count();
+            assert!(expected > 255);
+
+            let decrypted = fhe.decrypt_f64_vector(encrypted_count, 1.0);
+            assert_eq!(decrypted[0], expected as f64, "Wrong count for {}", key);
+        }
+    }
+
+    #[test]
+    fn test_masked_sum_matches_plaintext_subset() {
+        let fhe = BiosampleFHE::new();
+        let glucose = vec![95.5, 120.25, 88.0, 140.75];
+        let facility_ids = vec![2.0, 1.0, 2.0, 3.0];
+        let scale = 100.0;
+
+        // Select the glucose readings from facility 2
+        let encrypted_glucose = fhe.encrypt_f64_vector(&glucose, scale).unwrap();
+        let encrypted_facilities = fhe.encrypt_f64_vector(&facility_ids, 1.0).unwrap();
+        let mask = eq_scalar_mask(&encrypted_facilities, 2, fhe.server_key()).unwrap();
+
+        let sum = masked_sum(&encrypted_glucose, &mask, fhe.server_key()).unwrap();
+        let decrypted = fhe.decrypt_f64_vector(&sum, scale);
+
+        let expected: f64 = glucose
+            .iter()
+            .zip(facility_ids.iter())
+            .filter(|(_, &id)| id == 2.0)
+            .map(|(g, _)| g)
+            .sum();
+        assert!((decrypted[0] - expected).abs() < 0.01);
+
+        // Selected totals beyond the 16-bit range of a single value must not wrap around
+        let high_glucose = vec![180.5, 210.25, 195.0, 240.75, 99.0];
+        let selected = [true, true, true, true, false];
+        let encrypted_glucose = fhe.encrypt_f64_vector(&high_glucose, scale).unwrap();
+        let mask = fhe.encrypt_bool_vector(&selected);
+        let sum = masked_sum(&encrypted_glucose, &mask, fhe.server_key()).unwrap();
+        let expected: f64 = high_glucose.iter().take(4).sum();
+        assert!(expected * scale > i16::MAX as f64);
+        assert!((fhe.decrypt_f64_vector(&sum, scale)[0] - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_group_by_facility_mean_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(24, 11).unwrap();
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        let scale = context.scale_for(fields::GLUCOSE).unwrap();
+        assert_eq!(scale, DEFAULT_SCALE);
+        let encrypted_glucose = &encrypted_data[fields::GLUCOSE];
+
+        // Other fields in the map are ignored
+        let groups =
+            group_by_facility_mean(encrypted_glucose, &encrypted_data, fhe.server_key()).unwrap();
+        let facilities: std::collections::HashSet<u32> =
+            records.iter().map(|r| r.facility_id).collect();
+        assert_eq!(groups.len(), facilities.len());
+
+        let mut largest_total: f64 = 0.0;
+        for (facility_id, (sum, count)) in &groups {
+            let selected: Vec<f64> = records
+                .iter()
+                .filter(|r| r.facility_id == *facility_id)
+                .map(|r| r.glucose_level)
+                .collect();
+            let expected_total: f64 = selected.iter().sum();
+            largest_total = largest_total.max(expected_total);
+
+            let decrypted_count = fhe.decrypt_f64_vector(count, 1.0)[0];
+            let decrypted_total = fhe.decrypt_f64_vector(sum, sum.scale)[0];
+            assert_eq!(decrypted_count, selected.len() as f64);
+            assert!((decrypted_total - expected_total).abs() < 0.01 * selected.len() as f64);
+        }
+
+        // The facility totals exceed the range of a single scaled value
+        assert!(largest_total * scale > i16::MAX as f64);
+    }
+
+    #[test]
+    fn test_distinct_blood_type_count_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(10, 42).unwrap();
+        let (mut encrypted_data, _) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+        // Masks for the blood types without patients must not be counted
+        for blood_type in ["A+", "A-", "B+", "B-", "AB+", "AB-", "O+", "O-"] {
+            encrypted_data
+                .entry(fields::blood_type_key(blood_type))
+                .or_insert_with(|| fhe.encrypt_bool_vector(&vec![false; records.len()]));
+        }
+
+        let present: std::collections::HashSet<&str> =
+            records.iter().map(|r| r.blood_type.as_str()).collect();
+        assert!(present.len() < 8);
+
+        let distinct = compute_encrypted_distinct_count(&encrypted_data, fhe.server_key()).unwrap();
+        assert_eq!(
+            fhe.decrypt_element(&distinct, 0, 1.0).unwrap(),
+            present.len() as f64
+        );
+
+        assert!(compute_encrypted_distinct_count(&HashMap::new(), fhe.server_key()).is_err());
+    }
+
+    #[test]
+    fn test_nonempty_category_count_of_1_3_and_8_blood_types() {
+        let fhe = BiosampleFHE::new();
+        let all_types = ["A+", "A-", "B+", "B-", "AB+", "AB-", "O+", "O-"];
+
+        for present in [&all_types[6..7], &all_types[2..5], &all_types[..]] {
+            // Every blood type is a category, but only the present ones have patients
+            let values: Vec<&str> = present.iter().cycle().take(8).copied().collect();
+            let categorical = EncryptedCategorical {
+                categories: all_types.iter().map(|t| t.to_string()).collect(),
+                vectors: all_types
+                    .iter()
+                    .map(|blood_type| {
+                        let one_hot: Vec<bool> = values.iter().map(|v| v == blood_type).collect();
+                        fhe.encrypt_bool_vector(&one_hot)
+                    })
+                    .collect(),
+            };
+
+            let count =
+                compute_encrypted_nonempty_category_count(&categorical, fhe.server_key()).unwrap();
+            assert_eq!(
+                fhe.decrypt_element(&count, 0, 1.0).unwrap(),
+                present.len() as f64
+            );
+        }
+
+        let empty = EncryptedCategorical {
+            categories: Vec::new(),
+            vectors: Vec::new(),
+        };
+        assert!(matches!(
+            compute_encrypted_nonempty_category_count(&empty, fhe.server_key()),
+            Err(ComputationError::EmptyInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_facility_counts_match_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(20, 7).unwrap();
+        let (encrypted_data, _) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+        let counts = compute_encrypted_facility_counts(&encrypted_data, fhe.server_key()).unwrap();
+        let mut expected: HashMap<String, f64> = HashMap::new();
+        for record in &records {
+            assert!((1..=5).contains(&record.facility_id));
+            *expected
+                .entry(fields::facility_key(&record.facility_id.to_string()))
+                .or_default() += 1.0;
+        }
+
+        assert_eq!(counts.len(), expected.len());
+        for (facility, count) in &expected {
+            assert_eq!(
+                fhe.decrypt_element(&counts[facility], 0, 1.0).unwrap(),
+                *count
+            );
+        }
+    }
+
+    #[test]
+    fn test_equality_count_matches_plaintext_facility_counts() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(20, 7).unwrap();
+        let facility_ids: Vec<i64> = records.iter().map(|r| r.facility_id as i64).collect();
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        // Facility ids are encrypted exactly, without scaling
+        let encrypted = &encrypted_data[fields::FACILITY];
+        assert_eq!(context.scale_for(fields::FACILITY).unwrap(), 1.0);
+        assert_eq!(fhe.decrypt_i64_vector(encrypted), facility_ids);
+
+        // Facility 6 has no records
+        for facility in 1..=6 {
+            let count =
+                compute_encrypted_equality_count(encrypted, facility as f64, 1.0, fhe.server_key())
+                    .unwrap();
+            let expected = facility_ids.iter().filter(|&&id| id == facility).count();
+            assert_eq!(
+                fhe.decrypt_element(&count, 0, 1.0).unwrap(),
+                expected as f64,
+                "facility {}",
+                facility
+            );
+        }
+
+        // Scaled values are compared in their encoded units
+        let glucose = fhe.encrypt_f64_vector(&[95.5, 120.0, 95.5], 100.0).unwrap();
+        let count =
+            compute_encrypted_equality_count(&glucose, 95.5, 100.0, fhe.server_key()).unwrap();
+        assert_eq!(fhe.decrypt_element(&count, 0, 1.0).unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_means_by_category_match_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let cholesterol = vec![180.5, 221.0, 195.25, 240.0, 172.5, 205.0];
+        let blood_types = ["A+", "O-", "A+", "B+", "O-", "A+"];
+        let encrypted_data = HashMap::from([(
+            fields::CHOLESTEROL.to_string(),
+            fhe.encrypt_f64_vector(&cholesterol, 10.0).unwrap(),
+        )]);
+        let context = EncryptionContext::new(cholesterol.len(), &encrypted_data);
+
+        let names: Vec<String> = blood_types.iter().map(|b| b.to_string()).collect();
+        let encrypted_blood_types = fhe.encrypt_categorical(&names);
+        let mut categories: HashMap<&str, &EncryptedVector> = encrypted_blood_types
+            .categories
+            .iter()
+            .map(String::as_str)
+            .zip(&encrypted_blood_types.vectors)
+            .collect();
+        // A blood type without patients
+        let no_patients = fhe.encrypt_bool_vector(&[false; 6]);
+        categories.insert("AB-", &no_patients);
+
+        let groups = compute_encrypted_means_by_category(
+            &encrypted_data[fields::CHOLESTEROL],
+            &categories,
+            fhe.server_key(),
+        )
+        .unwrap();
+        assert_eq!(groups.len(), 4);
+
+        for (blood_type, terms) in &groups {
+            let selected: Vec<f64> = cholesterol
+                .iter()
+                .zip(blood_types.iter())
+                .filter(|(_, b)| *b == blood_type)
+                .map(|(&c, _)| c)
+                .collect();
+            let mean = fhe
+                .decrypt_conditional_mean(terms, fields::CHOLESTEROL, &context)
+                .unwrap();
+            if selected.is_empty() {
+                assert_eq!(mean, None, "{}", blood_type);
+            } else {
+                let expected = selected.iter().sum::<f64>() / selected.len() as f64;
+                let mean = mean.unwrap();
+                assert!(
+                    (mean - expected).abs() < 0.05,
+                    "{}: {} vs {}",
+                    blood_type,
+                    mean,
+                    expected
+                );
+            }
+        }
+
+        // Indicators must be boolean and aligned with the values
+        let short_indicator = fhe.encrypt_bool_vector(&[true]);
+        let short = HashMap::from([("A+", &short_indicator)]);
+        assert!(compute_encrypted_means_by_category(
+            &encrypted_data[fields::CHOLESTEROL],
+            &short,
+            fhe.server_key()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_encrypted_histogram_matches_plaintext() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(12, 3).unwrap();
+        let mut glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        // Values exactly on an edge belong to the bin the edge opens
+        glucose.extend([80.0, 120.0]);
+        let bin_edges = [60.0, 80.0, 100.0, 120.0, 140.0, 160.0, 180.0];
+        let encrypted = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+
+        let counts = compute_encrypted_histogram_with_overflow(
+            &encrypted,
+            &bin_edges,
+            100.0,
+            fhe.server_key(),
+        )
+        .unwrap();
+        let counts = fhe.decrypt_f64_vector(&counts, 1.0);
+        assert_eq!(counts.len(), bin_edges.len() + 1);
+
+        let mut expected = vec![0.0; bin_edges.len() + 1];
+        for &value in &glucose {
+            let bin = bin_edges.iter().filter(|&&edge| value >= edge).count();
+            expected[bin] += 1.0;
+        }
+        assert_eq!(counts, expected);
+        assert_eq!(counts.iter().sum::<f64>(), glucose.len() as f64);
+
+        // Without overflow bins only the six inner bins are returned
+        let inner =
+            compute_encrypted_histogram(&encrypted, &bin_edges, 100.0, fhe.server_key()).unwrap();
+        assert_eq!(
+            fhe.decrypt_f64_vector(&inner, 1.0),
+            expected[1..bin_edges.len()].to_vec()
+        );
+    }
+
+    #[test]
+    fn test_encrypted_id_match_count_with_known_overlap() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(6, 1).unwrap();
+        let ids_a: Vec<String> = records.iter().map(|r| r.patient_id.clone()).collect();
+        // Two records of the second dataset belong to other patients
+        let mut ids_b = ids_a.clone();
+        ids_b[1] = "P900001".to_string();
+        ids_b[4] = "P900002".to_string();
+
+        let encrypted_a = fhe.encrypt_id_vector(&ids_a);
+        let encrypted_b = fhe.encrypt_id_vector(&ids_b);
+
+        let count =
+            compute_encrypted_id_match_count(&encrypted_a, &encrypted_b, fhe.server_key()).unwrap();
+        assert_eq!(fhe.decrypt_f64_vector(&count, 1.0), vec![4.0]);
+
+        // Identifiers can only be compared with identifiers of the same length
+        let numeric = fhe.encrypt_f64_vector(&[1.0; 6], 1.0).unwrap();
+        assert!(
+            compute_encrypted_id_match_count(&encrypted_a, &numeric, fhe.server_key()).is_err()
+        );
+        let shorter = fhe.encrypt_id_vector(&ids_b[..3]);
+        assert!(
+            compute_encrypted_id_match_count(&encrypted_a, &shorter, fhe.server_key()).is_err()
+        );
+        assert!(compute_encrypted_sum(&encrypted_a, fhe.server_key()).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_histogram_rejects_invalid_edges() {
+        let fhe = BiosampleFHE::new();
+        let encrypted = fhe.encrypt_f64_vector(&[20.0], 1.0).unwrap();
+
+        assert!(compute_encrypted_histogram(&encrypted, &[18.0], 1.0, fhe.server_key()).is_err());
+        assert!(
+            compute_encrypted_histogram(&encrypted, &[30.0, 18.0], 1.0, fhe.server_key()).is_err()
+        );
+        // Edges that round to the same scaled integer do not delimit a bin
+        assert!(
+            compute_encrypted_histogram(&encrypted, &[18.0, 18.2], 1.0, fhe.server_key()).is_err()
+        );
+        assert!(
+            compute_encrypted_histogram(&encrypted, &[18.0, f64::NAN], 1.0, fhe.server_key())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_masked_sum_length_mismatch() {
+        let fhe = BiosampleFHE::new();
+        let values = fhe.encrypt_f64_vector(&[1.0, 2.0], 1.0).unwrap();
+        let mask = fhe.encrypt_bool_vector(&[true]);
+
+        assert!(matches!(
+            masked_sum(&values, &mask, fhe.server_key()),
+            Err(ComputationError::LengthMismatch(_))
+        ));
+
+        // Only boolean masks select values, and only numeric values can be summed
+        let numeric_mask = fhe.encrypt_f64_vector(&[1.0, 0.0], 1.0).unwrap();
+        assert!(matches!(
+            masked_sum(&values, &numeric_mask, fhe.server_key()),
+            Err(ComputationError::Unsupported(_))
+        ));
+        let ids = fhe.encrypt_id_vector(&["P1".to_string(), "P2".to_string()]);
+        let mask = fhe.encrypt_bool_vector(&[true, false]);
+        assert!(masked_sum(&ids, &mask, fhe.server_key()).is_err());
+    }
+
+    #[test]
+    fn test_errors_distinguish_empty_input_from_length_mismatch() {
+        let fhe = BiosampleFHE::new();
+        let empty = fhe.encrypt_f64_vector(&[], 1.0).unwrap();
+        let pair = fhe.encrypt_f64_vector(&[1.0, 2.0], 1.0).unwrap();
+        let single = fhe.encrypt_f64_vector(&[3.0], 1.0).unwrap();
+
+        assert!(matches!(
+            compute_encrypted_sum(&empty, fhe.server_key()),
+            Err(ComputationError::EmptyInput(_))
+        ));
+        assert!(matches!(
+            compute_encrypted_mean(&empty, fhe.server_key()),
+            Err(ComputationError::EmptyInput(_))
+        ));
+        assert!(matches!(
+            compute_encrypted_dot_product(&pair, &single, fhe.server_key()),
+            Err(ComputationError::LengthMismatch(_))
+        ));
+        assert!(matches!(
+            compute_encrypted_covariance(&pair, &single, fhe.server_key()),
+            Err(ComputationError::LengthMismatch(_))
+        ));
+        assert!(matches!(
+            compute_encrypted_weighted_sum(&[(&pair, f64::INFINITY)], fhe.server_key(), 10.0),
+            Err(ComputationError::Overflow(_))
+        ));
+
+        // Both still propagate as boxed errors
+        let boxed: Box<dyn std::error::Error> = compute_encrypted_sum(&empty, fhe.server_key())
+            .unwrap_err()
+            .into();
+        assert_eq!(boxed.to_string(), "Cannot compute sum of empty vector");
+    }
+
+    #[test]
+    fn test_undecodable_ciphertexts_return_errors() {
+        let fhe = BiosampleFHE::new();
+        // The checksum covers the garbage bytes, so only decoding them fails
+        let garbage = EncryptedVector::new(vec![vec![1, 2, 3]], VectorKind::Numeric);
+        let values = fhe.encrypt_f64_vector(&[1.0], 1.0).unwrap();
+
+        assert!(matches!(
+            garbage.decode(),
+            Err(ComputationError::Deserialization(_))
+        ));
+        assert!(matches!(
+            compute_encrypted_sum(&garbage, fhe.server_key()),
+            Err(ComputationError::Deserialization(_))
+        ));
+        assert!(matches!(
+            compute_encrypted_dot_product(&values, &garbage, fhe.server_key()),
+            Err(ComputationError::Deserialization(_))
+        ));
+        assert!(matches!(
+            compute_encrypted_threshold_count(&garbage, fhe.server_key(), 0),
+            Err(ComputationError::Deserialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_mixing_client_keys_is_a_key_mismatch() {
+        let fhe = BiosampleFHE::new();
+        let other = BiosampleFHE::new();
+        let server_key = fhe.server_key();
+        let values = fhe.encrypt_f64_vector(&[1.0, 2.0], 100.0).unwrap();
+        let foreign = other.encrypt_f64_vector(&[3.0, 4.0], 100.0).unwrap();
+        let mask = fhe.encrypt_bool_vector(&[true, false]);
+        let foreign_mask = other.encrypt_bool_vector(&[false, true]);
+
+        let mismatches = [
+            vector_add(&values, &foreign, server_key).err(),
+            masked_sum(&values, &foreign_mask, server_key).err(),
+            indicator_and(&mask, &foreign_mask, server_key).err(),
+            compute_encrypted_dot_product(&values, &foreign, server_key).err(),
+            compute_encrypted_covariance(&values, &foreign, server_key).err(),
+        ];
+        for error in mismatches {
+            assert!(matches!(error, Some(ComputationError::KeyMismatch(_))));
+        }
+
+        let mut incremental = IncrementalSum::new(Arc::new(server_key.clone()));
+        incremental.add_batch(&values).unwrap();
+        assert!(matches!(
+            incremental.add_batch(&foreign),
+            Err(ComputationError::KeyMismatch(_))
+        ));
+
+        // Results carry the fingerprint, so decrypting them with the other key fails
+        let sum = masked_sum(&values, &mask, server_key).unwrap();
+        assert_eq!(sum.key_fingerprint, Some(fhe.key_fingerprint()));
+        assert_eq!(fhe.try_decrypt_f64_vector(&sum, 100.0).unwrap(), vec![1.0]);
+        assert!(matches!(
+            other.try_decrypt_f64_vector(&sum, 100.0),
+            Err(EncryptionError::KeyMismatch(_))
+        ));
+        let count = compute_encrypted_count_true(&mask, server_key).unwrap();
+        assert!(matches!(
+            other.try_decrypt_i64_vector(&count),
+            Err(EncryptionError::KeyMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_strict_bool_decryption_rejects_summed_indicators() {
+        let fhe = BiosampleFHE::new();
+        let indicators = fhe.encrypt_bool_vector(&[true, true]);
+
+        // Adding two indicators yields a 2, which is not a valid boolean
+        let two = compute_encrypted_sum(&indicators, fhe.server_key()).unwrap();
+        let mut vector = serialize_ciphertexts(&[
+            deserialize_ciphertexts(&indicators).unwrap()[0].clone(),
+            two,
+        ]);
+        vector.kind = VectorKind::Boolean;
+
+        let error = fhe.decrypt_bool_vector(&vector).unwrap_err();
+        assert!(matches!(
+            error,
+            EncryptionError::InvalidBool(InvalidBoolError { index: 1, value: 2 })
+        ));
+
+        // Lenient decoding keeps treating any non-zero value as true
+        let lenient = fhe
+            .decrypt_bool_vector_with_mode(&vector, BoolDecoding::Lenient)
+            .unwrap();
+        assert_eq!(lenient, vec![true, true]);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compute_encrypted_sum_on_compressed_vector() {
+        let fhe = BiosampleFHE::new();
+        let values = vec![12.5, 7.25, 30.0];
+        let scale = 100.0;
+
+        let encrypted = fhe.encrypt_f64_vector(&values, scale).unwrap();
+        let compressed = encrypted.compress(3).unwrap();
+
+        // Computations transparently decompress their inputs
+        let mean = compute_encrypted_mean(&encrypted, fhe.server_key()).unwrap();
+        let compressed_mean = compute_encrypted_mean(&compressed, fhe.server_key()).unwrap();
+        assert_eq!(
+            fhe.decrypt_f64_vector(&mean, mean.scale),
+            fhe.decrypt_f64_vector(&compressed_mean, compressed_mean.scale)
+        );
+    }
+
+    #[test]
+    fn test_sum_strategies_decrypt_to_identical_values() {
+        let fhe = BiosampleFHE::new();
+        let ages: Vec<f64> = (0..23).map(|i| (20 + i * 3) as f64).collect();
+        let flags: Vec<bool> = (0..23).map(|i| i % 3 == 0).collect();
+
+        for (vector, expected) in [
+            (
+                fhe.encrypt_f64_vector(&ages, 1.0).unwrap(),
+                ages.iter().sum::<f64>(),
+            ),
+            (
+                fhe.encrypt_bool_vector(&flags),
+                flags.iter().filter(|&&f| f).count() as f64,
+            ),
+        ] {
+            for strategy in [
+                SumStrategy::MultiOperand,
+                SumStrategy::Sequential,
+                SumStrategy::Tree,
+            ] {
+                let sum = compute_encrypted_sum_with_strategy(&vector, fhe.server_key(), strategy)
+                    .unwrap();
+                let decrypted = fhe.decrypt_element(&serialize_ciphertexts(&[sum]), 0, 1.0);
+                assert_eq!(decrypted.unwrap(), expected, "{:?}", strategy);
+            }
+        }
+
+        let empty = EncryptedVector::new(Vec::new(), VectorKind::Numeric);
+        assert!(
+            compute_encrypted_sum_with_strategy(&empty, fhe.server_key(), SumStrategy::Tree)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_compute_encrypted_sum_chunked_matches_monolithic() {
+        let fhe = BiosampleFHE::new();
+        let values = vec![12.5, 7.25, 30.0, 4.0];
+        let scale = 100.0;
+
+        // Monolithic path over the whole in-memory vector
+        let encrypted = fhe.encrypt_f64_vector(&values, scale).unwrap();
+        let monolithic = compute_encrypted_sum(&encrypted, fhe.server_key()).unwrap();
+
+        // Chunked path over a stream encrypted and summed with a small chunk size
+        let mut stream: Vec<u8> = Vec::new();
+        fhe.encrypt_f64_vector_chunked(&values, scale, 3, &mut stream)
+            .unwrap();
+        let chunked =
+            compute_encrypted_sum_chunked(&mut stream.as_slice(), 3, fhe.server_key()).unwrap();
+
+        assert_eq!(
+            fhe.decrypt_f64_vector(&serialize_ciphertexts(&[chunked]), scale),
+            fhe.decrypt_f64_vector(&serialize_ciphertexts(&[monolithic]), scale)
+        );
+
+        // The stream records the scale and key fingerprint, which the mean carries on
+        let mean =
+            compute_encrypted_mean_chunked(&mut stream.as_slice(), 3, fhe.server_key()).unwrap();
+        let expected = compute_encrypted_mean(&encrypted, fhe.server_key()).unwrap();
+        assert_eq!(mean.scale, expected.scale);
+        assert_eq!(mean.key_fingerprint, Some(fhe.key_fingerprint()));
+        assert_eq!(
+            fhe.decrypt_f64_vector(&mean, mean.scale),
+            fhe.decrypt_f64_vector(&expected, expected.scale)
+        );
+    }
+
+    #[cfg(feature = "high-level-api")]
+    #[test]
+    fn test_high_level_mean_matches_radix_mean() {
+        let fhe = BiosampleFHE::new();
+        let values = vec![45.5, 30.25, 62.0];
+        let scale = 100.0;
+        let expected = values.iter().sum::<f64>() / values.len() as f64;
+
+        // Integer radix backend
+        let encrypted = fhe.encrypt_f64_vector(&values, scale).unwrap();
+        let mean = compute_encrypted_mean(&encrypted, fhe.server_key()).unwrap();
+        let radix_mean = fhe.decrypt_f64_vector(&mean, mean.scale)[0];
+
+        // High-level FheInt64 backend
+        let server_key = fhe.high_level_server_key();
+        let encrypted_hl = fhe.encrypt_f64_vector_hl(&values, scale).unwrap();
+        assert_eq!(encrypted_hl.kind, VectorKind::HighLevelInt);
+        let mean_hl = compute_encrypted_mean_hl(&encrypted_hl, &server_key).unwrap();
+        let hl_mean = fhe.decrypt_f64_vector_hl(&mean_hl, mean_hl.scale)[0];
+
+        assert!((radix_mean - expected).abs() < 0.01);
+        assert!((hl_mean - radix_mean).abs() < 0.01);
+
+        // Each backend rejects the other's vectors
+        assert!(matches!(
+            compute_encrypted_sum(&encrypted_hl, fhe.server_key()),
+            Err(ComputationError::Unsupported(_))
+        ));
+        assert!(compute_encrypted_sum_hl(&encrypted, &server_key).is_err());
+
+        // Values without a 64-bit encoding are rejected like on the radix path
+        for value in [f64::NAN, f64::INFINITY, 2f64.powi(63)] {
+            let error = fhe.encrypt_f64_vector_hl(&[1.0, value], 1.0).err().unwrap();
+            assert!(error.to_string().contains("index 1"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_compute_encrypted_sum_chunked_empty_stream() {
+        let fhe = BiosampleFHE::new();
+        let empty: Vec<u8> = Vec::new();
+
+        let result = compute_encrypted_sum_chunked(&mut empty.as_slice(), 2, fhe.server_key());
+        assert!(matches!(result, Err(ComputationError::EmptyInput(_))));
+    }
+
+    /// Reader that records the largest number of bytes asked of it at once
+    struct LargestReadTracker<R: Read> {
+        inner: R,
+        largest_read: usize,
+    }
+
+    impl<R: Read> Read for LargestReadTracker<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.largest_read = self.largest_read.max(buf.len());
+            self.inner.read(buf)
+        }
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_streaming_sum_of_file_matches_in_memory_sum() {
+        use crate::encryption::EncryptedVectorWriter;
+        use std::io::BufReader;
+
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(200, 5).unwrap();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let encrypted = fhe.encrypt_f64_vector(&glucose, 100.0).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("glucose_level.bin");
+        let mut writer = EncryptedVectorWriter::new(std::fs::File::create(&path).unwrap());
+        writer.write_vector(&encrypted).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.written(), 200);
+
+        // A small buffer means the file is only ever read one ciphertext at a time
+        let buffer_capacity = 64;
+        let file = BufReader::with_capacity(buffer_capacity, std::fs::File::open(&path).unwrap());
+        let mut tracker = LargestReadTracker {
+            inner: file,
+            largest_read: 0,
+        };
+        let streamed = compute_encrypted_sum_streaming(
+            &mut EncryptedVectorReader::new(&mut tracker),
+            fhe.server_key(),
+        )
+        .unwrap();
+        let ciphertext_size = encrypted.data.iter().map(Vec::len).max().unwrap();
+        assert!(
+            tracker.largest_read <= ciphertext_size.max(buffer_capacity),
+            "read {} bytes at once",
+            tracker.largest_read
+        );
+
+        let in_memory = compute_encrypted_sum(&encrypted, fhe.server_key()).unwrap();
+        assert_eq!(
+            fhe.decrypt_f64_vector(&serialize_ciphertexts(&[streamed]), 100.0),
+            fhe.decrypt_f64_vector(&serialize_ciphertexts(&[in_memory]), 100.0)
+        );
+
+        let empty: Vec<u8> = Vec::new();
+        let result = compute_encrypted_sum_streaming(
+            &mut EncryptedVectorReader::new(empty.as_slice()),
+            fhe.server_key(),
+        );
+        assert!(matches!(result, Err(ComputationError::EmptyInput(_))));
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_analysis_from_dir_matches_in_memory_analysis() {
+        use crate::encryption::save_encrypted_fields;
+
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(6, 9).unwrap();
+        let (mut encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        // The counts alone keep the two analyses quick
+        encrypted_data.retain(|key, _| {
+            key == fields::MARKER
+                || key.starts_with(fields::BLOOD_TYPE_PREFIX)
+                || key.starts_with(fields::FACILITY_PREFIX)
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        save_encrypted_fields(&encrypted_data, dir.path()).unwrap();
+        let from_dir = run_biosample_analysis_from_dir(dir.path(), fhe.server_key())
+            .unwrap()
+            .decrypt(&fhe, &context)
+            .unwrap();
+        let in_memory = run_biosample_analysis(&encrypted_data, fhe.server_key())
+            .unwrap()
+            .decrypt(&fhe, &context)
+            .unwrap();
+        assert_eq!(from_dir.blood_type_counts, in_memory.blood_type_counts);
+        assert_eq!(from_dir.facility_counts, in_memory.facility_counts);
+        assert_eq!(
+            from_dir.marker_positive_count,
+            in_memory.marker_positive_count
+        );
+
+        // A corrupted field file is reported rather than analysed
+        let marker_file = dir.path().join(format!("{}.bin", fields::MARKER));
+        let mut bytes = std::fs::read(&marker_file).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        std::fs::write(&marker_file, bytes).unwrap();
+        assert!(run_biosample_analysis_from_dir(dir.path(), fhe.server_key()).is_err());
+    }
 }
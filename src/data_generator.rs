@@ -2,13 +2,16 @@
 /// This module generates synthetic biosample data for testing and development purposes.
 /// It includes functions to generate random values for various biosample attributes.
 // Required libraries
-use chrono::{Duration, Utc}; // For generating random dates
+use chrono::{DateTime, Duration, NaiveDate, Utc}; // For generating and parsing dates
+#[cfg(feature = "native")]
 use csv::Writer; // For writing CSV files
 use rand::prelude::*; // For generating random numbers
 use rand_distr::{Distribution, Normal}; // For generating normally distributed random numbers
 use serde::{Deserialize, Serialize}; // For serializing and deserializing data (e.g., to/from CSV)
 use std::error::Error; // For error handling
+#[cfg(feature = "native")]
 use std::fs::File; // For file operations
+#[cfg(feature = "native")]
 use std::path::Path; // For path operations
 
 /// Represents a biosample record with patient and medical information
@@ -16,7 +19,7 @@ use std::path::Path; // For path operations
 /// This struct contains various attributes of a biosample including patient identifiers,
 /// demographic information, medical measurements, and collection metadata.
 /// It is used for generating and storing synthetic biosample data.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct BiosampleRecord {
     pub patient_id: String,
     pub age: u32,
@@ -29,11 +32,59 @@ pub struct BiosampleRecord {
     pub facility_id: u32,
 }
 
+/// Configures the distributions used by the synthetic data generator
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorConfig {
+    pub male_probability: f64, // Probability that a generated patient is male
+    pub base_date: Option<NaiveDate>, // First possible collection date, `None` for a year before today
+    pub num_facilities: u32,          // Number of collection sites, with ids 1..=num_facilities
+}
+
+/// First possible collection date of reproducible datasets, see `GeneratorConfig::reproducible`
+pub const REPRODUCIBLE_BASE_DATE: &str = "2024-01-01";
+
+/// Implements the Default trait for GeneratorConfig
+///
+/// The default configuration models a balanced cohort whose samples were collected over
+/// the past year at five facilities.
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            male_probability: 0.5,
+            base_date: None,
+            num_facilities: 5,
+        }
+    }
+}
+
+impl GeneratorConfig {
+    /// Creates the default configuration with collection dates anchored at
+    /// `REPRODUCIBLE_BASE_DATE` instead of today
+    ///
+    /// With the default configuration the collection dates move with the wall clock, so
+    /// the same seed yields different records on different days. With this one it yields
+    /// the same records whenever it runs.
+    ///
+    /// # Returns
+    /// * A `GeneratorConfig` with a fixed base date
+    #[allow(dead_code)]
+    pub fn reproducible() -> Self {
+        GeneratorConfig {
+            base_date: NaiveDate::parse_from_str(REPRODUCIBLE_BASE_DATE, COLLECTION_DATE_FORMAT)
+                .ok(),
+            ..GeneratorConfig::default()
+        }
+    }
+}
+
 /// Generates a vector of synthetic biosample records for testing and development
 ///
 /// This function creates a specified number of biosample records with randomized but realistic
 /// values for patient attributes such as age, gender, blood type, glucose levels, etc.
-/// The random number generator is seeded to ensure reproducible results.
+/// The random number generator is seeded to ensure reproducible results, though the
+/// collection dates fall within the year before today; use
+/// `generate_biosample_data_with_config` with `GeneratorConfig::reproducible()` for
+/// records that do not depend on the current date.
 ///
 /// # Arguments
 /// * `num_samples` - The number of biosample records to generate
@@ -41,10 +92,71 @@ pub struct BiosampleRecord {
 ///
 /// # Returns
 /// * `Result<Vec<BiosampleRecord>, Box<dyn Error>>` - A vector of generated biosample records or an error
+#[allow(dead_code)]
 pub fn generate_biosample_data(
     num_samples: usize,
     seed: u64,
 ) -> Result<Vec<BiosampleRecord>, Box<dyn Error>> {
+    generate_biosample_data_with_config(num_samples, seed, &GeneratorConfig::default())
+}
+
+/// Generates synthetic biosample records using the distributions of a `GeneratorConfig`
+///
+/// Behaves like `generate_biosample_data`. Every record draws its values from the seeded
+/// generator in a fixed order (age, gender, blood type, glucose, cholesterol, marker,
+/// collection date, facility), and each draw consumes the same amount of randomness
+/// whatever the gender probability or base date. The number of facilities changes the
+/// facility draws, and may shift the records that follow them. The same seed therefore
+/// yields the same records on every run of the same build, as long as the configuration
+/// has a `base_date`; without one the collection dates are drawn from the year before
+/// today. `StdRng` may change its algorithm between rand versions and platforms, so
+/// records are not guaranteed to match across builds. Reordering, adding or removing draws
+/// changes all generated data.
+///
+/// # Arguments
+/// * `num_samples` - The number of biosample records to generate
+/// * `seed` - A seed value for the random number generator to ensure reproducibility
+/// * `config` - The distributions to draw from
+///
+/// # Returns
+/// * `Result<Vec<BiosampleRecord>, Box<dyn Error>>` - A vector of generated biosample records,
+///   or an error if the configuration is invalid
+pub fn generate_biosample_data_with_config(
+    num_samples: usize,
+    seed: u64,
+    config: &GeneratorConfig,
+) -> Result<Vec<BiosampleRecord>, Box<dyn Error>> {
+    generate_records(num_samples, seed, config, Utc::now().date_naive())
+}
+
+/// Generates the records of `generate_biosample_data_with_config` as if run on `today`
+///
+/// # Arguments
+/// * `num_samples` - The number of biosample records to generate
+/// * `seed` - A seed value for the random number generator to ensure reproducibility
+/// * `config` - The distributions to draw from
+/// * `today` - The current date, which anchors the collection dates without a `base_date`
+///
+/// # Returns
+/// * `Result<Vec<BiosampleRecord>, Box<dyn Error>>` - A vector of generated biosample records,
+///   or an error if the configuration is invalid
+fn generate_records(
+    num_samples: usize,
+    seed: u64,
+    config: &GeneratorConfig,
+    today: NaiveDate,
+) -> Result<Vec<BiosampleRecord>, Box<dyn Error>> {
+    if !(0.0..=1.0).contains(&config.male_probability) {
+        return Err(format!(
+            "Male probability must be between 0 and 1, got {}",
+            config.male_probability
+        )
+        .into());
+    }
+    if config.num_facilities == 0 {
+        return Err("Number of facilities must be at least 1".into());
+    }
+
     // Initialize a random number generator with a seed
     let mut random_num_gen = StdRng::seed_from_u64(seed);
 
@@ -57,7 +169,12 @@ pub fn generate_biosample_data(
     let blood_types = ["A+", "A-", "B+", "B-", "AB+", "AB-", "O+", "O-"];
     let blood_type_weights = [0.34, 0.06, 0.09, 0.02, 0.03, 0.01, 0.38, 0.07]; // Approximate frequencies
 
-    let base_date = Utc::now() - Duration::days(365); // Base date for collection
+    // Genders are drawn the same weighted way
+    let genders = ["Male", "Female"];
+    let gender_weights = [config.male_probability, 1.0 - config.male_probability];
+
+    // Collection dates fall within a year of the base date
+    let base_date = config.base_date.unwrap_or(today - Duration::days(365));
 
     // Generate the biosample records
     let mut biosample_records = Vec::with_capacity(num_samples);
@@ -67,45 +184,29 @@ pub fn generate_biosample_data(
         let age_f64 = f64::round(age_dist.sample(&mut random_num_gen));
         let age = age_f64.clamp(18.0, 90.0) as u32;
 
-        // Generate patient gender
-        let gender = if random_num_gen.gen_bool(0.5) {
-            "Male"
-        } else {
-            "Female"
-        };
-        // Generate blood type based on weighted random selection
-        let blood_type_index = {
-            let mut cumulative = 0.0;
-            let r: f64 = random_num_gen.gen();
-            let mut selected = 0;
-
-            for (i, &weight) in blood_type_weights.iter().enumerate() {
-                cumulative += weight;
-                if r < cumulative {
-                    selected = i;
-                    break;
-                }
-            }
-            selected
-        };
+        // Generate patient gender based on weighted random selection
+        let gender = genders[weighted_index(&mut random_num_gen, &gender_weights)];
 
-        let blood_type = blood_types[blood_type_index];
+        // Generate blood type based on weighted random selection
+        let blood_type = blood_types[weighted_index(&mut random_num_gen, &blood_type_weights)];
 
-        // Generate glucose and cholesterol levels
-        let glucose_level = glucose_dist.sample(&mut random_num_gen);
-        let cholesterol_level = cholesterol_dist.sample(&mut random_num_gen);
+        // Generate glucose and cholesterol levels, kept non-negative and below 320 mg/dL so
+        // they fit a 16-bit encrypted integer at the default scale of 100
+        let glucose_level = f64::clamp(glucose_dist.sample(&mut random_num_gen), 0.0, 320.0);
+        let cholesterol_level =
+            f64::clamp(cholesterol_dist.sample(&mut random_num_gen), 0.0, 320.0);
 
         // Generate marker alpha (boolean)
         let marker_alpha = random_num_gen.gen_bool(0.3); // 30% chance of being true
 
-        // Generate collection date within the last year
+        // Generate collection date within a year of the base date
         let days_offset = random_num_gen.gen_range(0..365);
         let collection_date = (base_date + Duration::days(days_offset))
-            .format("%Y-%m-%d")
+            .format(COLLECTION_DATE_FORMAT)
             .to_string();
 
-        // Generate facility ID
-        let facility_id = random_num_gen.gen_range(1..6);
+        // Generate facility ID, uniformly over the configured sites
+        let facility_id = random_num_gen.gen_range(1..=config.num_facilities);
 
         // Create a new biosample record
         let biosample_record = BiosampleRecord {
@@ -128,6 +229,64 @@ pub fn generate_biosample_data(
     Ok(biosample_records)
 }
 
+/// Selects an index with probability proportional to its weight
+///
+/// Exactly one value is drawn from the generator, whatever the weights, so the draws that
+/// follow are not shifted by the configuration.
+///
+/// # Arguments
+/// * `rng` - The random number generator to draw from
+/// * `weights` - The probability of each index; they should sum to 1
+///
+/// # Returns
+/// * The selected index, or the first index if rounding leaves the draw above the total
+fn weighted_index(rng: &mut StdRng, weights: &[f64]) -> usize {
+    let r: f64 = rng.gen();
+    let mut cumulative = 0.0;
+    for (i, &weight) in weights.iter().enumerate() {
+        cumulative += weight;
+        if r < cumulative {
+            return i;
+        }
+    }
+    0
+}
+
+/// Format of the `collection_date` of a biosample record
+pub const COLLECTION_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Converts a collection date into a day ordinal that can be encrypted as an integer
+///
+/// The ordinal counts the days since 1970-01-01, so later dates have larger ordinals and
+/// date ranges become integer ranges.
+///
+/// # Arguments
+/// * `date` - The collection date, formatted as YYYY-MM-DD
+///
+/// # Returns
+/// * `Result<i64, Box<dyn Error>>` - The number of days since 1970-01-01, or an error if
+///   the date is malformed
+pub fn collection_day(date: &str) -> Result<i64, Box<dyn Error>> {
+    let parsed = NaiveDate::parse_from_str(date, COLLECTION_DATE_FORMAT)
+        .map_err(|e| format!("Invalid collection date {:?}: {}", date, e))?;
+    Ok((parsed - DateTime::UNIX_EPOCH.date_naive()).num_days())
+}
+
+/// Converts a day ordinal from `collection_day` back into a collection date
+///
+/// # Arguments
+/// * `day` - The number of days since 1970-01-01
+///
+/// # Returns
+/// * `Result<String, Box<dyn Error>>` - The date formatted as YYYY-MM-DD, or an error if
+///   the ordinal is outside the range of dates
+pub fn collection_date_from_day(day: i64) -> Result<String, Box<dyn Error>> {
+    let date = Duration::try_days(day)
+        .and_then(|days| DateTime::UNIX_EPOCH.date_naive().checked_add_signed(days))
+        .ok_or_else(|| format!("Day {} is outside the range of dates", day))?;
+    Ok(date.format(COLLECTION_DATE_FORMAT).to_string())
+}
+
 /// Saves a collection of biosample records to a CSV file.
 ///
 /// # Arguments
@@ -138,6 +297,7 @@ pub fn generate_biosample_data(
 /// # Returns
 ///
 /// * `Result<(), Box<dyn Error>>` - Ok(()) on success, or an error if the operation fails
+#[cfg(feature = "native")]
 pub fn save_biosample_data(
     biosample_records: &[BiosampleRecord],
     path: &Path,
@@ -165,6 +325,7 @@ pub fn save_biosample_data(
 /// # Returns
 ///
 /// * `Result<Vec<BiosampleRecord>, Box<dyn Error>>` - A vector of BiosampleRecord on success, or an error if the operation fails
+#[cfg(feature = "native")]
 pub fn load_biosample_data(path: &Path) -> Result<Vec<BiosampleRecord>, Box<dyn Error>> {
     // Open the CSV file
     let file = File::open(path)?;
@@ -180,3 +341,168 @@ pub fn load_biosample_data(path: &Path) -> Result<Vec<BiosampleRecord>, Box<dyn
     // Return the loaded biosample records
     Ok(biosample_records)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gender_probability_is_configurable() {
+        let all_female = GeneratorConfig {
+            male_probability: 0.0,
+            ..GeneratorConfig::default()
+        };
+        let records = generate_biosample_data_with_config(200, 42, &all_female).unwrap();
+        assert!(records.iter().all(|r| r.gender == "Female"));
+
+        let skewed = GeneratorConfig {
+            male_probability: 0.9,
+            ..GeneratorConfig::default()
+        };
+        let records = generate_biosample_data_with_config(1000, 42, &skewed).unwrap();
+        let males = records.iter().filter(|r| r.gender == "Male").count();
+        assert!((850..=950).contains(&males), "{} males", males);
+
+        let invalid = GeneratorConfig {
+            male_probability: 1.5,
+            ..GeneratorConfig::default()
+        };
+        assert!(generate_biosample_data_with_config(10, 42, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_facility_ids_cover_configured_sites() {
+        let config = GeneratorConfig {
+            num_facilities: 10,
+            ..GeneratorConfig::default()
+        };
+        let records = generate_biosample_data_with_config(2000, 42, &config).unwrap();
+        assert!(records.iter().all(|r| (1..=10).contains(&r.facility_id)));
+
+        let mut counts = [0usize; 10];
+        for record in &records {
+            counts[record.facility_id as usize - 1] += 1;
+        }
+        // Each site expects 200 records
+        assert!(
+            counts.iter().all(|&count| (140..=260).contains(&count)),
+            "{:?}",
+            counts
+        );
+
+        let invalid = GeneratorConfig {
+            num_facilities: 0,
+            ..GeneratorConfig::default()
+        };
+        assert!(generate_biosample_data_with_config(10, 42, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_levels_stay_within_encodable_range() {
+        // Seed 42 draws a cholesterol level above 320 within its first 200 records
+        let records = generate_biosample_data(1000, 42).unwrap();
+        for record in &records {
+            assert!((0.0..=320.0).contains(&record.glucose_level));
+            assert!((0.0..=320.0).contains(&record.cholesterol_level));
+        }
+        assert!(records.iter().any(|r| r.cholesterol_level == 320.0));
+    }
+
+    #[test]
+    fn test_gender_does_not_shift_other_fields() {
+        // Changing the gender probability must not change any other drawn value
+        let balanced = generate_biosample_data(50, 7).unwrap();
+        let skewed = generate_biosample_data_with_config(
+            50,
+            7,
+            &GeneratorConfig {
+                male_probability: 0.8,
+                ..GeneratorConfig::default()
+            },
+        )
+        .unwrap();
+
+        for (a, b) in balanced.iter().zip(skewed.iter()) {
+            assert_eq!(a.blood_type, b.blood_type);
+            assert_eq!(a.glucose_level, b.glucose_level);
+            assert_eq!(a.facility_id, b.facility_id);
+        }
+    }
+
+    #[test]
+    fn test_collection_day_counts_days_since_epoch() {
+        assert_eq!(collection_day("1970-01-01").unwrap(), 0);
+        assert_eq!(collection_day("2023-01-01").unwrap(), 19358);
+        assert_eq!(
+            collection_day("2024-03-01").unwrap() - collection_day("2024-02-28").unwrap(),
+            2
+        );
+        for date in ["1970-01-01", "2023-01-01", "2024-02-29"] {
+            let day = collection_day(date).unwrap();
+            assert_eq!(collection_date_from_day(day).unwrap(), date);
+        }
+        assert!(collection_date_from_day(i64::MAX).is_err());
+
+        for malformed in [
+            "",
+            "2023-13-01",
+            "2023-02-30",
+            "01/02/2023",
+            "2023-01-01T00:00",
+        ] {
+            let error = collection_day(malformed).err().unwrap();
+            assert!(
+                error.to_string().contains("Invalid collection date"),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_same_seed_produces_identical_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = GeneratorConfig {
+            male_probability: 0.3,
+            ..GeneratorConfig::reproducible()
+        };
+
+        let mut csvs = Vec::new();
+        for run in 0..2 {
+            let path = dir.path().join(format!("run_{}.csv", run));
+            let records = generate_biosample_data_with_config(100, 42, &config).unwrap();
+            save_biosample_data(&records, &path).unwrap();
+            csvs.push(std::fs::read(&path).unwrap());
+        }
+
+        assert_eq!(csvs[0], csvs[1]);
+        let csv = String::from_utf8(csvs[0].clone()).unwrap();
+        assert!(csv.lines().next().unwrap().contains("gender"));
+        assert!(csv.contains("Male") && csv.contains("Female"));
+    }
+
+    #[test]
+    fn test_fixed_base_date_ignores_today() {
+        let config = GeneratorConfig::reproducible();
+        let base_date = config.base_date.unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 3, 14).unwrap();
+        let years_later = NaiveDate::from_ymd_opt(2031, 11, 2).unwrap();
+
+        let first = generate_records(200, 42, &config, today).unwrap();
+        let second = generate_records(200, 42, &config, years_later).unwrap();
+        assert_eq!(first, second);
+
+        for record in &first {
+            let date =
+                NaiveDate::parse_from_str(&record.collection_date, COLLECTION_DATE_FORMAT).unwrap();
+            assert!(date >= base_date && date < base_date + Duration::days(365));
+        }
+
+        // Without a base date the collection dates follow today
+        let default_config = GeneratorConfig::default();
+        let moving_first = generate_records(200, 42, &default_config, today).unwrap();
+        let moving_second = generate_records(200, 42, &default_config, years_later).unwrap();
+        assert_ne!(moving_first, moving_second);
+    }
+}
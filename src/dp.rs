@@ -0,0 +1,146 @@
+/// Differential privacy for decrypted aggregates
+/// This module adds calibrated Laplace noise to results after decryption, so that
+/// released aggregates do not reveal whether any single record was part of the data.
+// Required libraries
+use rand::Rng; // For drawing the noise
+use std::collections::HashMap; // For maps of named results
+use std::error::Error; // For error handling
+
+/// Adds Laplace noise calibrated to a sensitivity and privacy budget to a value
+///
+/// The noise is drawn from a Laplace distribution centred on zero with scale
+/// `sensitivity / epsilon`, which makes the released value epsilon-differentially
+/// private when `sensitivity` bounds how much one record can change it. For example,
+/// the mean of `n` ages between 0 and 120 has a sensitivity of `120 / n`. Every release
+/// spends `epsilon` of the privacy budget, so releasing `k` noisy values costs `k * epsilon`.
+///
+/// # Arguments
+/// * `value` - The exact decrypted value
+/// * `sensitivity` - The largest change a single record can cause in `value`
+/// * `epsilon` - The privacy budget; smaller values add more noise
+/// * `rng` - The random number generator to draw the noise from
+///
+/// # Returns
+/// * The value with noise added
+///
+/// # Panics
+/// * If `sensitivity` is negative or `epsilon` is not positive
+pub fn add_laplace_noise(value: f64, sensitivity: f64, epsilon: f64, rng: &mut impl Rng) -> f64 {
+    assert!(sensitivity >= 0.0, "Sensitivity must not be negative");
+    assert!(epsilon > 0.0, "Epsilon must be positive");
+
+    // Inverse transform sampling: for u uniform in (-1/2, 1/2), the value
+    // -b * sign(u) * ln(1 - 2|u|) follows a Laplace distribution with scale b
+    let scale = sensitivity / epsilon;
+    // `gen_range` can return -1/2, for which the logarithm is infinite, so it is redrawn
+    let u: f64 = loop {
+        let u = rng.gen_range(-0.5..0.5);
+        if u != -0.5 {
+            break u;
+        }
+    };
+    value - scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+/// Adds Laplace noise to every result in a map of decrypted aggregates
+///
+/// Each result gets its own noise, calibrated with its own sensitivity and the shared
+/// `epsilon`, so releasing the whole map spends `epsilon` once per result.
+///
+/// # Arguments
+/// * `results` - The exact decrypted results, by name
+/// * `sensitivities` - The sensitivity of each result, by the same names
+/// * `epsilon` - The privacy budget spent on each result
+/// * `rng` - The random number generator to draw the noise from
+///
+/// # Returns
+/// * `Result<HashMap<String, f64>, Box<dyn Error>>` - The noisy results, or an error if a
+///   result has no sensitivity or a parameter is invalid
+pub fn add_laplace_noise_to_results(
+    results: &HashMap<String, f64>,
+    sensitivities: &HashMap<String, f64>,
+    epsilon: f64,
+    rng: &mut impl Rng,
+) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+    if epsilon.is_nan() || epsilon <= 0.0 {
+        return Err(format!("Epsilon must be positive, got {}", epsilon).into());
+    }
+
+    results
+        .iter()
+        .map(|(name, &value)| {
+            let sensitivity = *sensitivities
+                .get(name)
+                .ok_or_else(|| format!("No sensitivity given for result {}", name))?;
+            if sensitivity.is_nan() || sensitivity < 0.0 {
+                return Err(format!(
+                    "Sensitivity of result {} must be a non-negative number",
+                    name
+                )
+                .into());
+            }
+            Ok((
+                name.clone(),
+                add_laplace_noise(value, sensitivity, epsilon, rng),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Draws noisy copies of a value and returns their mean and mean absolute deviation
+    fn sample_noise(value: f64, sensitivity: f64, epsilon: f64, samples: usize) -> (f64, f64) {
+        let mut rng = StdRng::seed_from_u64(42);
+        let noisy: Vec<f64> = (0..samples)
+            .map(|_| add_laplace_noise(value, sensitivity, epsilon, &mut rng))
+            .collect();
+        let mean = noisy.iter().sum::<f64>() / samples as f64;
+        let deviation = noisy.iter().map(|v| (v - value).abs()).sum::<f64>() / samples as f64;
+        (mean, deviation)
+    }
+
+    #[test]
+    fn test_laplace_noise_is_centred_on_value() {
+        let (mean, deviation) = sample_noise(50.0, 1.0, 1.0, 20_000);
+
+        assert!((mean - 50.0).abs() < 0.05, "mean {}", mean);
+        // The mean absolute deviation of a Laplace distribution equals its scale
+        assert!((deviation - 1.0).abs() < 0.05, "deviation {}", deviation);
+    }
+
+    #[test]
+    fn test_smaller_epsilon_spreads_noise() {
+        let (_, strict) = sample_noise(50.0, 1.0, 0.5, 20_000);
+        let (_, loose) = sample_noise(50.0, 1.0, 2.0, 20_000);
+
+        // Quartering epsilon quadruples the scale of the noise
+        let ratio = strict / loose;
+        assert!((ratio - 4.0).abs() < 0.2, "ratio {}", ratio);
+    }
+
+    #[test]
+    fn test_add_laplace_noise_to_results() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let results = HashMap::from([("avg_age".to_string(), 45.0)]);
+        let sensitivities = HashMap::from([("avg_age".to_string(), 0.12)]);
+
+        let noisy = add_laplace_noise_to_results(&results, &sensitivities, 1.0, &mut rng).unwrap();
+        assert_eq!(noisy.len(), 1);
+        assert_ne!(noisy["avg_age"], 45.0);
+
+        // Every result needs a sensitivity and the budget must be positive
+        assert!(add_laplace_noise_to_results(&results, &HashMap::new(), 1.0, &mut rng).is_err());
+        assert!(add_laplace_noise_to_results(&results, &sensitivities, 0.0, &mut rng).is_err());
+
+        // NaN parameters are errors rather than panics
+        let nan = f64::NAN;
+        assert!(add_laplace_noise_to_results(&results, &sensitivities, nan, &mut rng).is_err());
+        let nan_sensitivities = HashMap::from([("avg_age".to_string(), nan)]);
+        assert!(add_laplace_noise_to_results(&results, &nan_sensitivities, 1.0, &mut rng).is_err());
+    }
+}
@@ -3,29 +3,70 @@ use serde::{Deserialize, Serialize};
 /// This module provides functions to encrypt and decrypt biosample data
 /// using a tfhe fully homomorphic encryption scheme.
 // Required libraries
+use std::borrow::Cow; // For borrowing or decompressing ciphertext payloads
 use std::collections::HashMap; // For HashMap
 use std::error::Error; // For error handling
+use std::fmt; // For formatting errors
+#[cfg(feature = "native")]
 use std::fs::File; // For file handling
 use std::io::{Read, Write}; // For reading and writing files
+use std::ops::Range; // For ranges of elements to decrypt
+#[cfg(feature = "native")]
 use std::path::Path; // For path handling
                      // For serialization and deserialization
-use tfhe::integer::{ServerKey, SignedRadixCiphertext}; // For integer encryption
-use tfhe::shortint::parameters::PARAM_MESSAGE_2_CARRY_2;
+use sha3::{Digest, Sha3_256}; // For hashing patient identifiers
+use std::sync::Arc; // For sharing keys between clones
+use tfhe::integer::{RadixCiphertext, ServerKey, SignedRadixCiphertext}; // For integer encryption
+
+/// Shortint parameter sets accepted by `BiosampleFHE::with_params`
+#[allow(unused_imports)]
+pub use tfhe::shortint::parameters::{
+    ClassicPBSParameters, PARAM_MESSAGE_2_CARRY_2, PARAM_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64,
+};
 
 // use the BiosampleRecord struct from the data_generator module
-use crate::data_generator::BiosampleRecord;
+use crate::data_generator::{collection_date_from_day, collection_day, BiosampleRecord};
+use crate::fields;
 
 /// Number of bits to use for integer encodings
-const FHE_INT_BITS: usize = 8;
+pub(crate) const FHE_INT_BITS: usize = 8;
+
+/// Number of blocks to use for boolean and one-hot encodings
+pub(crate) const FHE_BOOL_BLOCKS: usize = 1;
+
+/// Number of blocks to use for hashed patient identifiers (32 bits)
+const FHE_ID_BLOCKS: usize = 16;
+
+/// Domain separator for deriving the identifier hash key from the client key
+const ID_HASH_DOMAIN: &[u8] = b"fhe_mini_project/patient-id-hash/v1";
+
+/// Domain separator for fingerprinting the client key
+const KEY_FINGERPRINT_DOMAIN: &[u8] = b"fhe_mini_project/key-fingerprint/v1";
+
+/// Version of the on-disk format written by `EncryptedCategorical::save`
+#[cfg(feature = "native")]
+const CATEGORICAL_FORMAT_VERSION: u32 = 1;
+
+/// Name of the manifest file in a directory written by `EncryptedCategorical::save`
+#[cfg(feature = "native")]
+const CATEGORICAL_MANIFEST_FILE: &str = "manifest.json";
+
+/// Number of values encrypted between two invocations of a progress callback
+const PROGRESS_INTERVAL: usize = 100;
+
+/// Scale applied to fields without an explicit entry in a `ScaleConfig`
+pub const DEFAULT_SCALE: f64 = 100.0;
 
 /// Represents a structure for handling Fully Homomorphic Encryption operations on biosample data
 ///
 /// This structure contains the client key for encryption/decryption and the server key
-/// for performing homomorphic operations on encrypted data without decryption.
+/// for performing homomorphic operations on encrypted data without decryption. Both keys
+/// are shared behind `Arc`, so cloning an instance is cheap and clones use the same keys.
 #[derive(Clone)]
 pub struct BiosampleFHE {
-    client_key: tfhe::integer::ClientKey,
-    server_key: ServerKey,
+    client_key: Arc<tfhe::integer::ClientKey>,
+    server_key: Arc<ServerKey>,
+    key_fingerprint: [u8; 32], // Fingerprint of the client key, see `key_fingerprint`
 }
 
 /// Represents an encrypted vector of data
@@ -36,6 +77,228 @@ pub struct BiosampleFHE {
 pub struct EncryptedVector {
     pub data: Vec<Vec<u8>>, // Serialized ciphertexts
     pub length: usize,      // Length of the vector
+    #[serde(default)]
+    pub compressed: bool, // Whether each serialized ciphertext is zstd-compressed
+    #[serde(default)]
+    pub kind: VectorKind, // What the encrypted values represent
+    #[serde(default)]
+    pub checksum: u64, // Checksum of the serialized ciphertexts, see `verify_integrity`
+    #[serde(default = "unit_scale")]
+    pub scale: f64, // Factor the values were multiplied by before encryption
+    #[serde(default)]
+    pub key_fingerprint: Option<[u8; 32]>, // Fingerprint of the encrypting client key, if known
+}
+
+/// Scale of vectors whose values were encrypted as-is, used as the serde default
+fn unit_scale() -> f64 {
+    1.0
+}
+
+/// Describes what the values of an `EncryptedVector` represent
+///
+/// Boolean vectors hold 0/1 indicators encrypted with a minimal number of blocks, so
+/// computations must widen them to a larger accumulator before summing. High-level vectors
+/// hold `FheInt64` ciphertexts from the `high-level-api` backend and can only be used by
+/// its `_hl` functions. Identifier vectors hold unsigned 32-bit hashes of patient
+/// identifiers and can only be compared for equality.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VectorKind {
+    #[default]
+    Numeric, // Scaled integer values
+    Boolean, // 0/1 indicators
+    #[allow(dead_code)]
+    HighLevelInt, // Scaled integer values as high-level FheInt64 ciphertexts
+    Identifier, // Keyed 32-bit hashes of patient identifiers
+}
+
+/// Maps field names to the scale used to encode their values as integers
+///
+/// Each field is multiplied by its scale and rounded before encryption, so the scale sets
+/// the precision that survives and how quickly sums exhaust the integer range. Fields
+/// without an explicit entry use the default scale.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScaleConfig {
+    pub default: f64,                 // Scale of fields without an explicit entry
+    pub scales: HashMap<String, f64>, // Per-field scales
+}
+
+/// Length and scale of one encrypted field, as recorded in an `EncryptionContext`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct FieldInfo {
+    pub length: usize, // Number of encrypted values
+    pub scale: f64,    // Factor the values were multiplied by before encryption
+}
+
+/// Remembers how each field of a dataset was encrypted
+///
+/// Produced by `encrypt_biosample_data`, so that decryption and verification can recover
+/// each field's scale and length instead of repeating them. See
+/// `BiosampleFHE::decrypt_mean` and `verify_computation_with_context`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EncryptionContext {
+    pub record_count: usize, // Number of records that were encrypted
+    pub fields: HashMap<String, FieldInfo>, // Length and scale of each encrypted field
+}
+
+/// Selects how decrypted values that are neither 0 nor 1 are decoded as booleans
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum BoolDecoding {
+    #[default]
+    Strict, // Reject such values with an `InvalidBoolError`
+    Lenient, // Decode any non-zero value as `true`
+}
+
+/// Error returned when strict boolean decryption finds a value other than 0 or 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidBoolError {
+    pub index: usize, // Position of the offending value in the vector
+    pub value: i64,   // The decrypted value
+}
+
+impl fmt::Display for InvalidBoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Decrypted value {} at index {} is not a boolean (expected 0 or 1)",
+            self.value, self.index
+        )
+    }
+}
+
+impl Error for InvalidBoolError {}
+
+/// Error returned when biosample data cannot be encrypted, stored, loaded or decrypted
+///
+/// Converts into `Box<dyn Error>` with `?`, so callers that do not match on the variants
+/// can keep propagating it like any other error.
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("Field {0} is not part of the encryption context")]
+    MissingField(String), // A field that was never encrypted was looked up
+    #[error("Index {index} is out of bounds for a vector of length {length}")]
+    IndexOutOfBounds { index: usize, length: usize }, // An element past the end was requested
+    #[error("{0}")]
+    InvalidInput(String), // The input cannot be encrypted or decrypted as asked
+    #[error("{0}")]
+    Integrity(String), // Stored or streamed data is corrupted or inconsistent
+    #[error("{0}")]
+    KeyMismatch(String), // The data was encrypted under a different client key
+    #[error("{0}")]
+    Unsupported(String), // The data needs a format, parameter set or feature that is unavailable
+    #[error(transparent)]
+    InvalidBool(#[from] InvalidBoolError), // Strict boolean decoding found a value other than 0 or 1
+    #[error(transparent)]
+    Serialization(#[from] bincode::Error), // A ciphertext, key or vector cannot be (de)serialized
+    #[error(transparent)]
+    Json(#[from] serde_json::Error), // A manifest cannot be read or written
+    #[error(transparent)]
+    Io(#[from] std::io::Error), // A file or stream cannot be read or written
+}
+
+/// Summarizes the encryption parameters and the integer range they give
+///
+/// Values are encrypted as signed radix integers of `num_blocks` blocks, each holding
+/// `log2(message_modulus)` bits, so sums of more than `max_additions` full-range values may
+/// wrap around unless the ciphertexts are widened first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamsInfo {
+    pub message_modulus: u64,         // Number of message values per block
+    pub carry_modulus: u64,           // Number of carry values per block
+    pub num_blocks: usize,            // Blocks per integer, see `FHE_INT_BITS`
+    pub max_value: i64,               // Largest value an integer of `num_blocks` blocks can hold
+    pub security_level: &'static str, // Security and failure probability of the parameters
+    pub max_additions: usize, // Number of full-range values that can be summed without overflow
+}
+
+impl ParamsInfo {
+    /// Describes integers of `FHE_INT_BITS` blocks under the given moduli
+    ///
+    /// # Arguments
+    ///
+    /// * `message_modulus` - The number of message values per block
+    /// * `carry_modulus` - The number of carry values per block
+    ///
+    /// # Returns
+    ///
+    /// A `ParamsInfo` with an unknown security level
+    pub(crate) fn from_moduli(message_modulus: u64, carry_modulus: u64) -> Self {
+        let bits = FHE_INT_BITS as u32 * message_modulus.ilog2();
+        let max_value = (1i64 << (bits - 1)) - 1;
+        let mut info = ParamsInfo {
+            message_modulus,
+            carry_modulus,
+            num_blocks: FHE_INT_BITS,
+            max_value,
+            security_level: "unknown",
+            max_additions: 0,
+        };
+        info.max_additions = info.max_additions_for(max_value);
+        info
+    }
+
+    /// Returns how many values of at most `max_operand` in magnitude can be summed safely
+    ///
+    /// # Arguments
+    ///
+    /// * `max_operand` - The largest magnitude of the encrypted (scaled) values
+    ///
+    /// # Returns
+    ///
+    /// The number of such values whose sum is guaranteed to fit in `max_value`
+    pub fn max_additions_for(&self, max_operand: i64) -> usize {
+        match max_operand.unsigned_abs() {
+            0 => usize::MAX,
+            operand => (self.max_value as u64 / operand) as usize,
+        }
+    }
+}
+
+impl fmt::Display for ParamsInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Message modulus: {}, carry modulus: {}",
+            self.message_modulus, self.carry_modulus
+        )?;
+        writeln!(
+            f,
+            "Integer width: {} blocks (values up to {})",
+            self.num_blocks, self.max_value
+        )?;
+        writeln!(f, "Security level: {}", self.security_level)?;
+        write!(
+            f,
+            "Max safe additions of full-range values: {}",
+            self.max_additions
+        )
+    }
+}
+
+/// The mean of a field as an exact fraction of its scaled sum
+///
+/// Produced by `BiosampleFHE::decrypt_exact_mean` from the quotient and remainder of
+/// `compute_encrypted_mean_exact`. The scaled sum is `quotient * count + remainder`, so
+/// the mean loses no precision until it is converted to a float.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExactMean {
+    pub quotient: i64,  // Scaled sum divided by the count, truncated towards zero
+    pub remainder: i64, // Rest of the division, with the sign of the sum
+    pub count: usize,   // Number the sum was divided by
+    pub scale: f64,     // Scale of the summed values
+}
+
+impl ExactMean {
+    /// Returns the scaled sum the mean was divided from
+    #[allow(dead_code)]
+    pub fn scaled_sum(&self) -> i128 {
+        self.quotient as i128 * self.count as i128 + self.remainder as i128
+    }
+
+    /// Returns the mean in the units of the field
+    pub fn value(&self) -> f64 {
+        (self.quotient as f64 + self.remainder as f64 / self.count as f64) / self.scale
+    }
 }
 
 /// Represents an encrypted categorical variable
@@ -49,6 +312,62 @@ pub struct EncryptedCategorical {
     pub vectors: Vec<EncryptedVector>, // Encrypted vectors for each category
 }
 
+/// All encrypted fields of a single biosample record, for record-level transmission
+///
+/// Each numeric field is a single-element vector carrying its own scale, and each text
+/// field is a vector with one encrypted byte per UTF-8 byte, so the record decrypts back
+/// exactly with `BiosampleFHE::decrypt_record`. The collection date is encrypted as its
+/// day ordinal, like the `fields::COLLECTION_DAY` column.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EncryptedRecord {
+    pub patient_id: EncryptedVector, // UTF-8 bytes of the patient identifier
+    pub age: EncryptedVector,        // Age in years, at a scale of 1
+    pub gender: EncryptedVector,     // UTF-8 bytes of the gender
+    pub blood_type: EncryptedVector, // UTF-8 bytes of the blood type
+    pub glucose_level: EncryptedVector, // Glucose level, at its scale
+    pub cholesterol_level: EncryptedVector, // Cholesterol level, at its scale
+    pub marker_alpha: EncryptedVector, // Marker alpha indicator
+    pub collection_day: EncryptedVector, // Collection date as days since 1970-01-01
+    pub facility_id: EncryptedVector, // Facility id, at a scale of 1
+}
+
+/// Describes the contents of a directory written by `EncryptedCategorical::save`
+///
+/// The manifest lists the categories in order together with the length of each one-hot
+/// vector, so that a load can detect missing, extra or truncated vectors.
+#[cfg(feature = "native")]
+#[derive(Serialize, Deserialize, Debug)]
+struct CategoricalManifest {
+    version: u32,               // Format version, see `CATEGORICAL_FORMAT_VERSION`
+    categories: Vec<String>,    // Category names, in the order of the vector files
+    vector_lengths: Vec<usize>, // Length of each category's one-hot vector
+}
+
+/// Writes serialized ciphertexts to a byte stream one at a time
+///
+/// Each ciphertext is framed as a little-endian `u64` length prefix followed by its
+/// serialized bytes, so a stream can be produced and consumed incrementally without
+/// holding a whole `EncryptedVector` in memory. Each written vector is preceded by a
+/// metadata frame, marked by the length `METADATA_FRAME`, holding its scale and key
+/// fingerprint, so the chunks read back decrypt like the vector that was written.
+pub struct EncryptedVectorWriter<W: Write> {
+    inner: W,       // Underlying byte sink
+    written: usize, // Number of ciphertexts written so far
+}
+
+/// Reads ciphertexts framed by an `EncryptedVectorWriter` back from a byte stream
+pub struct EncryptedVectorReader<R: Read> {
+    inner: R,                          // Underlying byte source
+    scale: Option<f64>,                // Scale of the ciphertexts, once a metadata frame is read
+    key_fingerprint: Option<[u8; 32]>, // Fingerprint of the encrypting client key, if recorded
+}
+
+/// Length prefix marking a metadata frame rather than a ciphertext in an encrypted stream
+const METADATA_FRAME: u64 = u64::MAX;
+
+/// Size of a metadata frame: the scale, a fingerprint flag and the fingerprint
+const METADATA_FRAME_LEN: usize = 8 + 1 + 32;
+
 /// Implements the Default trait for BiosampleFHE
 ///
 /// This implementation allows creating a BiosampleFHE instance using the default() method,
@@ -63,289 +382,2654 @@ impl Default for BiosampleFHE {
     }
 }
 
-impl BiosampleFHE {
-    /// Creates a new instance of BiosampleFHE with freshly generated keys
+impl Default for ScaleConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_SCALE)
+    }
+}
+
+impl ScaleConfig {
+    /// Creates a configuration that applies the same scale to every field
     ///
-    /// This function initializes a new BiosampleFHE structure by generating a new client key
-    /// for encryption/decryption and a server key for performing homomorphic operations.
+    /// # Arguments
+    ///
+    /// * `default` - The scale of fields without an explicit entry
     ///
     /// # Returns
     ///
-    /// A new BiosampleFHE instance with initialized keys
-    pub fn new() -> Self {
-        // Generate client key
-        let client_key = tfhe::integer::ClientKey::new(PARAM_MESSAGE_2_CARRY_2);
-        // Generate server key for homomorphic operations
-        let server_key = ServerKey::new_radix_server_key(&client_key);
-
+    /// A new `ScaleConfig` without per-field entries
+    pub fn new(default: f64) -> Self {
         Self {
-            client_key,
-            server_key,
+            default,
+            scales: HashMap::new(),
         }
     }
 
-    /// Encrypts a vector of floating-point values using FHE
+    /// Sets the scale of a single field
     ///
-    /// This function takes a slice of f64 values, scales them by the provided factor,
-    /// converts them to integers, and encrypts each value using the client key.
     /// # Arguments
     ///
-    /// * `values` - A slice of f64 values to encrypt
-    /// * `scale` - A scaling factor to convert floating-point values to integers
+    /// * `field` - The name of the field, e.g. `fields::AGE`
+    /// * `scale` - The scale to apply to the field's values
     ///
     /// # Returns
     ///
-    /// An `EncryptedVector` containing the encrypted values
-    pub fn encrypt_f64_vector(&self, values: &[f64], scale: f64) -> EncryptedVector {
-        // Scale and convert to integers
-        let scaled_values: Vec<i64> = values.iter().map(|&v| (v * scale).round() as i64).collect();
+    /// The updated `ScaleConfig`
+    #[allow(dead_code)]
+    pub fn with_scale(mut self, field: &str, scale: f64) -> Self {
+        self.scales.insert(field.to_string(), scale);
+        self
+    }
 
-        // Encrypt each value
-        let encrypted_data: Vec<Vec<u8>> = scaled_values
+    /// Returns the scale of a field, falling back to the default scale
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The name of the field
+    ///
+    /// # Returns
+    ///
+    /// The scale to apply to the field's values
+    pub fn scale_for(&self, field: &str) -> f64 {
+        self.scales.get(field).copied().unwrap_or(self.default)
+    }
+}
+
+impl EncryptionContext {
+    /// Records the length and scale of every vector in an encrypted dataset
+    ///
+    /// # Arguments
+    ///
+    /// * `record_count` - The number of records that were encrypted
+    /// * `encrypted_data` - The encrypted vectors, by field name
+    ///
+    /// # Returns
+    ///
+    /// A context describing each field of `encrypted_data`
+    pub fn new(record_count: usize, encrypted_data: &HashMap<String, EncryptedVector>) -> Self {
+        let fields = encrypted_data
             .iter()
-            .map(|&v| {
-                let ciphertext = self.client_key.encrypt_signed_radix(v, FHE_INT_BITS);
-                bincode::serialize(&ciphertext).unwrap()
+            .map(|(field, vector)| {
+                let info = FieldInfo {
+                    length: vector.length,
+                    scale: vector.scale,
+                };
+                (field.clone(), info)
             })
             .collect();
 
-        EncryptedVector {
-            data: encrypted_data,
-            length: values.len(),
+        EncryptionContext {
+            record_count,
+            fields,
         }
     }
 
-    /// Encrypts a vector of boolean values using FHE
+    /// Looks up the length and scale of a field
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The name of the field
     ///
-    /// This function takes a slice of boolean values, converts them to integers (1 for true, 0 for false),
-    /// and encrypts each value using the client key.
+    /// # Returns
+    ///
+    /// A Result containing the field's `FieldInfo`, or an error if the field was not encrypted
+    pub fn field(&self, field: &str) -> Result<FieldInfo, EncryptionError> {
+        self.fields
+            .get(field)
+            .copied()
+            .ok_or_else(|| EncryptionError::MissingField(field.to_string()))
+    }
+
+    /// Returns the scale a field was encrypted with
     ///
     /// # Arguments
     ///
-    /// * `values` - A slice of boolean values to encrypt
+    /// * `field` - The name of the field
     ///
     /// # Returns
     ///
-    /// An `EncryptedVector` containing the encrypted values
-    pub fn encrypt_bool_vector(&self, values: &[bool]) -> EncryptedVector {
-        // Convert bools to integers
-        let int_values: Vec<i64> = values.iter().map(|&v| if v { 1 } else { 0 }).collect();
+    /// A Result containing the field's scale, or an error if the field was not encrypted
+    #[allow(dead_code)]
+    pub fn scale_for(&self, field: &str) -> Result<f64, EncryptionError> {
+        Ok(self.field(field)?.scale)
+    }
+}
 
-        // Encrypt each value
-        let encrypted_data: Vec<Vec<u8>> = int_values
-            .iter()
-            .map(|&v| {
-                let ciphertext = self.client_key.encrypt_signed_radix(v, FHE_INT_BITS);
-                bincode::serialize(&ciphertext).unwrap()
-            })
-            .collect();
-        EncryptedVector {
-            data: encrypted_data,
-            length: values.len(),
-        }
+impl EncryptedVector {
+    /// Creates an uncompressed vector from serialized ciphertexts and computes its checksum
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The serialized ciphertexts
+    /// * `kind` - What the encrypted values represent
+    ///
+    /// # Returns
+    ///
+    /// A new `EncryptedVector` whose checksum covers `data`, with a scale of 1
+    pub fn new(data: Vec<Vec<u8>>, kind: VectorKind) -> Self {
+        let mut vector = EncryptedVector {
+            length: data.len(),
+            data,
+            compressed: false,
+            kind,
+            checksum: 0,
+            scale: unit_scale(),
+            key_fingerprint: None,
+        };
+        vector.checksum = vector.compute_checksum();
+        vector
     }
 
-    /// Encrypts a vector of categorical values using FHE
+    /// Records the scale the encrypted values were multiplied by
     ///
-    /// This function takes a slice of string values representing categories,
-    /// converts them to one-hot encoded vectors, and encrypts each vector.
+    /// Decryption divides by this scale to recover the original values.
     ///
     /// # Arguments
     ///
-    /// * `values` - A slice of String values to encrypt
+    /// * `scale` - The scaling factor used during encryption
     ///
     /// # Returns
     ///
-    /// An `EncryptedCategorical` containing the encrypted one-hot vectors and category names
-    pub fn encrypt_categorical(&self, values: &[String]) -> EncryptedCategorical {
-        // Find unique categories
-        let mut categories: Vec<String> = values
-            .iter()
-            .cloned()
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect();
-        categories.sort(); // Sort categories for consistency
+    /// The vector with its scale set
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
 
-        //Create a one-hot encoded vectors
-        let mut one_hot_vectors: Vec<Vec<bool>> = Vec::with_capacity(categories.len());
+    /// Records the fingerprint of the client key that encrypted the values
+    ///
+    /// Decryption with a client key of a different fingerprint is then rejected instead of
+    /// returning garbage.
+    ///
+    /// # Arguments
+    ///
+    /// * `fingerprint` - The fingerprint from `BiosampleFHE::key_fingerprint`
+    ///
+    /// # Returns
+    ///
+    /// The vector with its key fingerprint set
+    pub fn with_key_fingerprint(mut self, fingerprint: [u8; 32]) -> Self {
+        self.key_fingerprint = Some(fingerprint);
+        self
+    }
 
-        for category in &categories {
-            let one_hot: Vec<bool> = values.iter().map(|v| v == category).collect();
-            one_hot_vectors.push(one_hot);
-        }
+    /// Computes a checksum over the length and the serialized ciphertexts as stored
+    ///
+    /// This is a 64-bit FNV-1a hash meant to detect accidental corruption in storage or
+    /// transit, not tampering. Each ciphertext is prefixed with its size so that moving
+    /// bytes between ciphertexts changes the checksum.
+    ///
+    /// # Returns
+    ///
+    /// The checksum of the vector's current contents
+    pub fn compute_checksum(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
 
-        // Encrypt each one-hot vector
-        let encrypted_vectors: Vec<EncryptedVector> = one_hot_vectors
-            .iter()
-            .map(|v| self.encrypt_bool_vector(v))
-            .collect();
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut update = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        };
 
-        EncryptedCategorical {
-            categories,
-            vectors: encrypted_vectors,
+        update(&(self.length as u64).to_le_bytes());
+        for ciphertext in &self.data {
+            update(&(ciphertext.len() as u64).to_le_bytes());
+            update(ciphertext);
         }
+        hash
     }
 
-    /// Decrypts a vector of encrypted floating-point values
+    /// Checks that the stored checksum matches the vector's contents
+    ///
+    /// # Returns
+    ///
+    /// `true` if the vector is intact, `false` if its length or ciphertexts were corrupted
+    pub fn verify_integrity(&self) -> bool {
+        self.length == self.data.len() && self.checksum == self.compute_checksum()
+    }
+
+    /// Serializes the vector with bincode for storage or transmission
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the serialized bytes, or an error if serialization fails
+    #[allow(dead_code)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncryptionError> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Deserializes a vector produced by `to_bytes` and verifies its integrity
     ///
     /// # Arguments
     ///
-    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
-    /// * `scale` - The scaling factor used during encryption
+    /// * `bytes` - The serialized vector
     ///
     /// # Returns
     ///
-    /// A vector of decrypted f64 values
-    pub fn decrypt_f64_vector(&self, encrypted: &EncryptedVector, scale: f64) -> Vec<f64> {
-        encrypted
-            .data
-            .iter()
-            .map(|data| {
-                // Use RadixCiphertext instead of BaseSignedRadixCiphertext
-                let ciphertext: SignedRadixCiphertext = bincode::deserialize(data).unwrap();
-                let decrypted_value: i64 = self.client_key.decrypt_signed_radix(&ciphertext);
-                decrypted_value as f64 / scale
-            })
-            .collect()
+    /// A Result containing the `EncryptedVector`, or an error if the bytes cannot be
+    /// deserialized or the checksum does not match
+    #[allow(dead_code)]
+    pub fn from_bytes_checked(bytes: &[u8]) -> Result<EncryptedVector, EncryptionError> {
+        let vector: EncryptedVector = bincode::deserialize(bytes)?;
+        if !vector.verify_integrity() {
+            return Err(EncryptionError::Integrity(
+                "EncryptedVector failed its integrity check".to_string(),
+            ));
+        }
+        Ok(vector)
     }
 
-    /// Decrypts a vector of encrypted boolean values
+    /// Compresses each serialized ciphertext with zstd
+    ///
+    /// The compression flag is recorded in the returned vector so that decryption and
+    /// computations detect it and decompress transparently. Compressing an already
+    /// compressed vector returns an unchanged copy.
     ///
     /// # Arguments
     ///
-    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    /// * `level` - The zstd compression level (1-22, where 0 selects the zstd default)
     ///
     /// # Returns
     ///
-    /// A vector of decrypted boolean values
+    /// A Result containing the compressed `EncryptedVector`, or an error if compression fails
+    #[cfg(feature = "compression")]
     #[allow(dead_code)]
-    pub fn decrypt_bool_vector(&self, encrypted: &EncryptedVector) -> Vec<bool> {
-        encrypted
+    pub fn compress(&self, level: i32) -> Result<EncryptedVector, EncryptionError> {
+        if self.compressed {
+            return Ok(self.clone());
+        }
+
+        let data = self
             .data
             .iter()
-            .map(|data| {
-                let ciphertext: SignedRadixCiphertext = bincode::deserialize(data).unwrap();
-                let decrypted_value: i64 = self.client_key.decrypt_signed_radix(&ciphertext);
-                decrypted_value != 0
-            })
-            .collect()
+            .map(|bytes| zstd::encode_all(bytes.as_slice(), level))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut compressed = EncryptedVector {
+            data,
+            length: self.length,
+            compressed: true,
+            kind: self.kind,
+            checksum: 0,
+            scale: self.scale,
+            key_fingerprint: self.key_fingerprint,
+        };
+        compressed.checksum = compressed.compute_checksum();
+        Ok(compressed)
     }
 
-    /// Returns a reference to the server key
+    /// Decompresses a vector produced by `compress`
     ///
     /// # Returns
     ///
-    /// A reference to the `ServerKey` used for homomorphic operations
-    pub fn server_key(&self) -> &ServerKey {
-        &self.server_key
+    /// A Result containing the uncompressed `EncryptedVector`, or an error if the payloads
+    /// are not valid zstd frames
+    #[cfg(feature = "compression")]
+    #[allow(dead_code)]
+    pub fn decompress(&self) -> Result<EncryptedVector, EncryptionError> {
+        let mut decompressed =
+            EncryptedVector::new(self.ciphertext_bytes()?.into_owned(), self.kind)
+                .with_scale(self.scale);
+        decompressed.key_fingerprint = self.key_fingerprint;
+        Ok(decompressed)
     }
 
-    /// Saves the encryption keys to disk
-    ///
-    /// # Arguments
+    /// Returns the serialized ciphertexts, decompressing them first if needed
     ///
-    /// * `client_key_path` - The path where the client key will be saved
-    /// * `server_key_path` - The path where the server key will be saved
+    /// Uncompressed vectors are borrowed without copying.
     ///
     /// # Returns
     ///
-    /// A Result containing () if successful, or an error if the keys could not be saved
-    #[allow(dead_code)]
-    pub fn save_keys(
-        &self,
-        client_key_path: &Path,
-        server_key_path: &Path,
-    ) -> Result<(), Box<dyn Error>> {
-        // Save the client key
-        let mut client_key_file = File::create(client_key_path)?;
-        let client_key_bytes = bincode::serialize(&self.client_key)?;
-        client_key_file.write_all(&client_key_bytes)?;
-
-        // Save the server key
-        let mut server_key_file = File::create(server_key_path)?;
-        let server_key_bytes = bincode::serialize(&self.server_key)?;
-        server_key_file.write_all(&server_key_bytes)?;
-
-        Ok(())
+    /// A Result containing the serialized ciphertexts, or an error if they cannot be
+    /// decompressed
+    pub(crate) fn ciphertext_bytes(&self) -> Result<Cow<'_, [Vec<u8>]>, EncryptionError> {
+        if self.compressed {
+            Ok(Cow::Owned(self.decompressed_data()?))
+        } else {
+            Ok(Cow::Borrowed(&self.data))
+        }
     }
 
-    /// Loads encryption keys from disk
+    /// Returns the serialized ciphertext at an index, decompressing only that element
     ///
     /// # Arguments
     ///
-    /// * `client_key_path` - The path from which the client key will be loaded
-    /// * `server_key_path` - The path from which the server key will be loaded
+    /// * `index` - The position of the ciphertext in the vector
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the serialized ciphertext, or an error if the index is out of
+    /// bounds or the ciphertext cannot be decompressed
+    pub(crate) fn ciphertext_bytes_at(
+        &self,
+        index: usize,
+    ) -> Result<Cow<'_, [u8]>, EncryptionError> {
+        let bytes = self
+            .data
+            .get(index)
+            .ok_or(EncryptionError::IndexOutOfBounds {
+                index,
+                length: self.data.len(),
+            })?;
+        if self.compressed {
+            Ok(Cow::Owned(decompress_ciphertext(bytes)?))
+        } else {
+            Ok(Cow::Borrowed(bytes))
+        }
+    }
+
+    fn decompressed_data(&self) -> Result<Vec<Vec<u8>>, EncryptionError> {
+        self.data
+            .iter()
+            .map(|bytes| decompress_ciphertext(bytes))
+            .collect()
+    }
+}
+
+/// Decompresses one zstd-compressed serialized ciphertext
+#[cfg(feature = "compression")]
+fn decompress_ciphertext(bytes: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    Ok(zstd::decode_all(bytes)?)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress_ciphertext(_bytes: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    Err(EncryptionError::Unsupported(
+        "EncryptedVector is compressed but the `compression` feature is disabled".to_string(),
+    ))
+}
+
+impl<W: Write> EncryptedVectorWriter<W> {
+    /// Creates a writer that frames ciphertexts onto the given sink
+    pub fn new(inner: W) -> Self {
+        Self { inner, written: 0 }
+    }
+
+    /// Writes a single serialized ciphertext as a length-prefixed frame
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The serialized ciphertext to write
+    ///
+    /// # Returns
+    ///
+    /// A Result containing () if successful, or an error if the sink cannot be written
+    pub fn write_ciphertext(&mut self, bytes: &[u8]) -> Result<(), EncryptionError> {
+        self.inner.write_all(&(bytes.len() as u64).to_le_bytes())?;
+        self.inner.write_all(bytes)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Writes every ciphertext of an `EncryptedVector`, decompressing them if needed
+    ///
+    /// The ciphertexts are preceded by a metadata frame recording the vector's scale and key
+    /// fingerprint.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - The `EncryptedVector` whose ciphertexts should be written
+    ///
+    /// # Returns
+    ///
+    /// A Result containing () if successful, or an error if the sink cannot be written
+    pub fn write_vector(&mut self, encrypted: &EncryptedVector) -> Result<(), EncryptionError> {
+        let mut metadata = [0u8; METADATA_FRAME_LEN];
+        metadata[..8].copy_from_slice(&encrypted.scale.to_le_bytes());
+        if let Some(fingerprint) = encrypted.key_fingerprint {
+            metadata[8] = 1;
+            metadata[9..].copy_from_slice(&fingerprint);
+        }
+        self.inner.write_all(&METADATA_FRAME.to_le_bytes())?;
+        self.inner.write_all(&metadata)?;
+
+        for bytes in encrypted.ciphertext_bytes()?.iter() {
+            self.write_ciphertext(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the underlying sink
+    pub fn flush(&mut self) -> Result<(), EncryptionError> {
+        self.inner.flush()?;
+        Ok(())
+    }
+
+    /// Returns the number of ciphertexts written so far
+    #[allow(dead_code)]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl<R: Read> EncryptedVectorReader<R> {
+    /// Creates a reader over a stream produced by an `EncryptedVectorWriter`
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            scale: None,
+            key_fingerprint: None,
+        }
+    }
+
+    /// Reads the next serialized ciphertext from the stream
+    ///
+    /// Metadata frames before the ciphertext are read into the scale and key fingerprint
+    /// given to the chunks of `read_chunk`.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the next ciphertext's bytes, `None` at the end of the stream,
+    /// or an error if the stream is truncated mid-frame or mixes scales or client keys
+    pub fn read_ciphertext(&mut self) -> Result<Option<Vec<u8>>, EncryptionError> {
+        loop {
+            let Some(length) = self.read_length()? else {
+                return Ok(None);
+            };
+            if length != METADATA_FRAME {
+                return self.read_frame(length).map(Some);
+            }
+            let metadata = self.read_frame(METADATA_FRAME_LEN as u64)?;
+            self.record_metadata(&metadata)?;
+        }
+    }
+
+    /// Reads a frame's length prefix
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the length, `None` at the end of the stream, or an error if the
+    /// stream ends inside the prefix
+    fn read_length(&mut self) -> Result<Option<u64>, EncryptionError> {
+        // Distinguish a clean end of stream from truncation
+        let mut length_bytes = [0u8; 8];
+        let mut filled = 0;
+        while filled < length_bytes.len() {
+            let read = self.inner.read(&mut length_bytes[filled..])?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        if filled == 0 {
+            return Ok(None);
+        }
+        if filled < length_bytes.len() {
+            return Err(EncryptionError::Integrity(
+                "Truncated ciphertext length prefix in encrypted stream".to_string(),
+            ));
+        }
+
+        Ok(Some(u64::from_le_bytes(length_bytes)))
+    }
+
+    /// Reads the body of a frame
+    ///
+    /// The length comes from the stream, so the buffer only grows as bytes arrive rather
+    /// than being allocated up front.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The length of the frame, as read from its prefix
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the frame's bytes, or an error if the stream ends first
+    fn read_frame(&mut self, length: u64) -> Result<Vec<u8>, EncryptionError> {
+        let mut bytes = Vec::new();
+        self.inner.by_ref().take(length).read_to_end(&mut bytes)?;
+        if (bytes.len() as u64) < length {
+            return Err(EncryptionError::Integrity(format!(
+                "Truncated frame in encrypted stream: expected {} bytes but found {}",
+                length,
+                bytes.len()
+            )));
+        }
+        Ok(bytes)
+    }
+
+    /// Records the scale and key fingerprint of a metadata frame
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - The body of the metadata frame
+    ///
+    /// # Returns
+    ///
+    /// A Result containing () if successful, or an error if the frame is malformed or
+    /// disagrees with an earlier one
+    fn record_metadata(&mut self, metadata: &[u8]) -> Result<(), EncryptionError> {
+        let mut scale_bytes = [0u8; 8];
+        scale_bytes.copy_from_slice(&metadata[..8]);
+        let scale = f64::from_le_bytes(scale_bytes);
+        let key_fingerprint = match metadata[8] {
+            0 => None,
+            1 => {
+                let mut fingerprint = [0u8; 32];
+                fingerprint.copy_from_slice(&metadata[9..]);
+                Some(fingerprint)
+            }
+            flag => {
+                return Err(EncryptionError::Integrity(format!(
+                    "Invalid key fingerprint flag {} in encrypted stream",
+                    flag
+                )))
+            }
+        };
+
+        if let Some(expected) = self.scale {
+            if expected != scale {
+                return Err(EncryptionError::Integrity(format!(
+                    "Encrypted stream mixes scales {} and {}",
+                    expected, scale
+                )));
+            }
+            if self.key_fingerprint != key_fingerprint {
+                return Err(EncryptionError::KeyMismatch(
+                    "Encrypted stream mixes ciphertexts of different client keys".to_string(),
+                ));
+            }
+        }
+        self.scale = Some(scale);
+        self.key_fingerprint = key_fingerprint;
+        Ok(())
+    }
+
+    /// Reads up to `chunk_size` ciphertexts from the stream as an `EncryptedVector`
+    ///
+    /// The chunk carries the scale and key fingerprint recorded in the stream, or a scale
+    /// of 1 and no fingerprint if the stream has no metadata frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk_size` - The maximum number of ciphertexts to read
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the next chunk, `None` once the stream is exhausted, or an
+    /// error if the stream is malformed
+    pub fn read_chunk(
+        &mut self,
+        chunk_size: usize,
+    ) -> Result<Option<EncryptedVector>, EncryptionError> {
+        if chunk_size == 0 {
+            return Err(EncryptionError::InvalidInput(
+                "Chunk size must be at least 1".to_string(),
+            ));
+        }
+
+        let mut data = Vec::with_capacity(chunk_size);
+        while data.len() < chunk_size {
+            match self.read_ciphertext()? {
+                Some(bytes) => data.push(bytes),
+                None => break,
+            }
+        }
+
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        let mut chunk =
+            EncryptedVector::new(data, VectorKind::Numeric).with_scale(self.scale.unwrap_or(1.0));
+        chunk.key_fingerprint = self.key_fingerprint;
+        Ok(Some(chunk))
+    }
+}
+
+#[cfg(feature = "native")]
+impl EncryptedCategorical {
+    /// Saves the categorical variable to a directory
+    ///
+    /// The directory receives a `manifest.json` listing the categories, the length of each
+    /// vector and the format version, and one `vector_<i>.bin` file per category holding the
+    /// checksummed vector of the i-th category.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to write to; it is created if it does not exist
+    ///
+    /// # Returns
+    ///
+    /// A Result containing () if successful, or an error if the categorical variable is
+    /// inconsistent or cannot be written
+    #[allow(dead_code)]
+    pub fn save(&self, dir: &Path) -> Result<(), EncryptionError> {
+        if self.categories.len() != self.vectors.len() {
+            return Err(EncryptionError::Integrity(format!(
+                "Cannot save categorical variable with {} categories but {} vectors",
+                self.categories.len(),
+                self.vectors.len()
+            )));
+        }
+
+        std::fs::create_dir_all(dir)?;
+
+        let manifest = CategoricalManifest {
+            version: CATEGORICAL_FORMAT_VERSION,
+            categories: self.categories.clone(),
+            vector_lengths: self.vectors.iter().map(|v| v.length).collect(),
+        };
+        let manifest_file = File::create(dir.join(CATEGORICAL_MANIFEST_FILE))?;
+        serde_json::to_writer_pretty(manifest_file, &manifest)?;
+
+        for (i, vector) in self.vectors.iter().enumerate() {
+            std::fs::write(categorical_vector_path(dir, i), vector.to_bytes()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads a categorical variable saved with `save`
+    ///
+    /// The manifest must have a supported version, list one vector length per category
+    /// with all lengths equal, and the directory must contain exactly one intact vector of
+    /// the listed length per category.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to read from
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the `EncryptedCategorical`, or an error describing the first
+    /// inconsistency found
+    #[allow(dead_code)]
+    pub fn load(dir: &Path) -> Result<Self, EncryptionError> {
+        let manifest_file = File::open(dir.join(CATEGORICAL_MANIFEST_FILE))?;
+        let manifest: CategoricalManifest = serde_json::from_reader(manifest_file)?;
+
+        if manifest.version != CATEGORICAL_FORMAT_VERSION {
+            return Err(EncryptionError::Unsupported(format!(
+                "Unsupported categorical format version {} (expected {})",
+                manifest.version, CATEGORICAL_FORMAT_VERSION
+            )));
+        }
+
+        if manifest.categories.len() != manifest.vector_lengths.len() {
+            return Err(EncryptionError::Integrity(format!(
+                "Manifest lists {} categories but {} vector lengths",
+                manifest.categories.len(),
+                manifest.vector_lengths.len()
+            )));
+        }
+
+        if let Some(&first) = manifest.vector_lengths.first() {
+            if let Some(&other) = manifest.vector_lengths.iter().find(|&&l| l != first) {
+                return Err(EncryptionError::Integrity(format!(
+                    "Manifest lists vectors of different lengths ({} and {})",
+                    first, other
+                )));
+            }
+        }
+
+        // Every category must have exactly one vector file
+        let vector_files = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("vector_") && name.ends_with(".bin")
+            })
+            .count();
+        if vector_files != manifest.categories.len() {
+            return Err(EncryptionError::Integrity(format!(
+                "Manifest lists {} categories but the directory contains {} vectors",
+                manifest.categories.len(),
+                vector_files
+            )));
+        }
+
+        let mut vectors = Vec::with_capacity(manifest.categories.len());
+        for (i, (category, &length)) in manifest
+            .categories
+            .iter()
+            .zip(manifest.vector_lengths.iter())
+            .enumerate()
+        {
+            let bytes = std::fs::read(categorical_vector_path(dir, i))?;
+            let vector = EncryptedVector::from_bytes_checked(&bytes).map_err(|e| {
+                EncryptionError::Integrity(format!("Vector of category {}: {}", category, e))
+            })?;
+            if vector.length != length {
+                return Err(EncryptionError::Integrity(format!(
+                    "Vector of category {} has length {} but the manifest lists {}",
+                    category, vector.length, length
+                )));
+            }
+            vectors.push(vector);
+        }
+
+        Ok(EncryptedCategorical {
+            categories: manifest.categories,
+            vectors,
+        })
+    }
+}
+
+/// Returns the path of the i-th vector file in a directory written by `EncryptedCategorical::save`
+///
+/// # Arguments
+///
+/// * `dir` - The directory of the categorical variable
+/// * `index` - The index of the category
+///
+/// # Returns
+///
+/// The path of the category's vector file
+#[cfg(feature = "native")]
+fn categorical_vector_path(dir: &Path, index: usize) -> std::path::PathBuf {
+    dir.join(format!("vector_{}.bin", index))
+}
+
+/// Returns the entries of a map of fields sorted by field name
+///
+/// `HashMap` iteration order differs between maps, so serializing the output of
+/// `encrypt_biosample_data` directly gives different bytes for the same dataset. Serializing
+/// the sorted entries instead keeps the layout of stored artifacts reproducible, e.g. for
+/// content-addressed storage. The same applies to the `fields` of an `EncryptionContext`.
+///
+/// # Arguments
+///
+/// * `fields` - The map to sort, e.g. the encrypted vectors by field name
+///
+/// # Returns
+///
+/// The field names and their values, in ascending order of field name
+#[allow(dead_code)]
+pub fn to_sorted_vec<V>(fields: &HashMap<String, V>) -> Vec<(&str, &V)> {
+    let mut entries: Vec<(&str, &V)> = fields
+        .iter()
+        .map(|(field, value)| (field.as_str(), value))
+        .collect();
+    entries.sort_by_key(|&(field, _)| field);
+    entries
+}
+
+/// Saves the fields of an encrypted dataset to a directory, one file per field
+///
+/// Each vector is written with `EncryptedVector::to_bytes` to `<field>.bin`, e.g.
+/// `glucose_level.bin` or `blood_type_A+.bin`, so a field can be read without the others.
+///
+/// # Arguments
+///
+/// * `encrypted_data` - The encrypted vectors, by field name
+/// * `dir` - The directory to write to; it is created if it does not exist
+///
+/// # Returns
+///
+/// A Result containing () if successful, or an error if a file cannot be written
+#[cfg(feature = "native")]
+#[allow(dead_code)]
+pub fn save_encrypted_fields(
+    encrypted_data: &HashMap<String, EncryptedVector>,
+    dir: &Path,
+) -> Result<(), EncryptionError> {
+    std::fs::create_dir_all(dir)?;
+    for (field, vector) in encrypted_data {
+        std::fs::write(dir.join(format!("{}.bin", field)), vector.to_bytes()?)?;
+    }
+    Ok(())
+}
+
+/// Loads the fields saved with `save_encrypted_fields`
+///
+/// Every `.bin` file of the directory is read as the field named by its file stem, and
+/// must pass the integrity check of `EncryptedVector::from_bytes_checked`.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to read from
+///
+/// # Returns
+///
+/// A Result containing the encrypted vectors by field name, or an error if the directory
+/// or a file cannot be read, or a file is corrupted
+#[cfg(feature = "native")]
+#[allow(dead_code)]
+pub fn load_encrypted_fields(
+    dir: &Path,
+) -> Result<HashMap<String, EncryptedVector>, EncryptionError> {
+    let mut encrypted_data = HashMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "bin") {
+            continue;
+        }
+        let Some(field) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+
+        let vector = EncryptedVector::from_bytes_checked(&std::fs::read(&path)?).map_err(|e| {
+            EncryptionError::Integrity(format!("Field file {} is corrupted: {}", path.display(), e))
+        })?;
+        encrypted_data.insert(field.to_string(), vector);
+    }
+    Ok(encrypted_data)
+}
+
+/// Saves a categorical variable to a single bincode file
+///
+/// Unlike `EncryptedCategorical::save`, which writes a directory with one file per
+/// category, this keeps the categories and their vectors together in one file.
+///
+/// # Arguments
+///
+/// * `categorical` - The categorical variable to save
+/// * `path` - The path of the file to write
+///
+/// # Returns
+///
+/// A Result containing () if successful, or an error if the file cannot be written
+#[cfg(feature = "native")]
+#[allow(dead_code)]
+pub fn save_categorical(
+    categorical: &EncryptedCategorical,
+    path: &Path,
+) -> Result<(), EncryptionError> {
+    std::fs::write(path, bincode::serialize(categorical)?)?;
+    Ok(())
+}
+
+/// Loads a categorical variable saved with `save_categorical`
+///
+/// There must be one intact vector per category, and all vectors must have the same length.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read
+///
+/// # Returns
+///
+/// A Result containing the `EncryptedCategorical`, or an error if the file cannot be read
+/// or deserialized or its vectors are inconsistent
+#[cfg(feature = "native")]
+#[allow(dead_code)]
+pub fn load_categorical(path: &Path) -> Result<EncryptedCategorical, EncryptionError> {
+    let categorical: EncryptedCategorical = bincode::deserialize(&std::fs::read(path)?)?;
+
+    if categorical.categories.len() != categorical.vectors.len() {
+        return Err(EncryptionError::Integrity(format!(
+            "File holds {} categories but {} vectors",
+            categorical.categories.len(),
+            categorical.vectors.len()
+        )));
+    }
+    for (category, vector) in categorical.categories.iter().zip(&categorical.vectors) {
+        if !vector.verify_integrity() {
+            return Err(EncryptionError::Integrity(format!(
+                "Vector of category {} is corrupted",
+                category
+            )));
+        }
+        if vector.length != categorical.vectors[0].length {
+            return Err(EncryptionError::Integrity(format!(
+                "Vector of category {} has length {} but the first has {}",
+                category, vector.length, categorical.vectors[0].length
+            )));
+        }
+    }
+
+    Ok(categorical)
+}
+
+/// Scales floating-point values and rounds them to integers within a range
+///
+/// `as` would turn NaN into 0 and saturate infinities, so values that are not finite or
+/// fall outside the range once scaled are rejected instead.
+///
+/// # Arguments
+///
+/// * `values` - The values to scale
+/// * `scale` - The factor to multiply each value by
+/// * `min` - The smallest integer the encoding can hold
+/// * `max` - The largest integer the encoding can hold
+///
+/// # Returns
+///
+/// A Result containing the scaled integers, or an error naming the index of the first
+/// value that cannot be encoded
+fn scale_values(
+    values: &[f64],
+    scale: f64,
+    min: i64,
+    max: i64,
+) -> Result<Vec<i64>, EncryptionError> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, &v)| {
+            let scaled = (v * scale).round();
+            // `max + 1` keeps the comparison exact when `max` rounds up as an f64
+            if !scaled.is_finite() || scaled < min as f64 || scaled >= max as f64 + 1.0 {
+                return Err(EncryptionError::InvalidInput(format!(
+                    "Value {} at index {} cannot be encrypted at scale {}: it must lie between {} and {} once scaled",
+                    v, index, scale, min, max
+                )));
+            }
+            Ok(scaled as i64)
+        })
+        .collect()
+}
+
+/// Computes the fingerprint returned by `BiosampleFHE::key_fingerprint`
+///
+/// # Arguments
+///
+/// * `client_key` - The client key to fingerprint
+///
+/// # Returns
+///
+/// A 32-byte SHA3-256 digest of the serialized client key
+fn fingerprint_client_key(client_key: &tfhe::integer::ClientKey) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(KEY_FINGERPRINT_DOMAIN);
+    hasher.update(bincode::serialize(client_key).unwrap());
+    hasher.finalize().into()
+}
+
+/// Hashes a patient identifier under a key and truncates the digest to 32 bits
+///
+/// # Arguments
+///
+/// * `key` - The secret hash key, see `BiosampleFHE::id_hash_key`
+/// * `id` - The patient identifier to hash
+///
+/// # Returns
+///
+/// The first 32 bits of SHA3-256(key || id)
+fn hash_patient_id(key: &[u8; 32], id: &str) -> u32 {
+    let mut hasher = Sha3_256::new();
+    hasher.update(key);
+    hasher.update(id.as_bytes());
+    let digest = hasher.finalize();
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+impl BiosampleFHE {
+    /// Creates a new instance of BiosampleFHE with freshly generated keys
+    ///
+    /// This function initializes a new BiosampleFHE structure by generating a new client key
+    /// for encryption/decryption and a server key for performing homomorphic operations.
+    ///
+    /// # Returns
+    ///
+    /// A new BiosampleFHE instance with initialized keys
+    pub fn new() -> Self {
+        Self::generate_keys(PARAM_MESSAGE_2_CARRY_2)
+    }
+
+    /// Creates a new instance of BiosampleFHE with keys generated for a given parameter set
+    ///
+    /// All parameter sets target 128-bit security; they differ in the probability that a
+    /// programmable bootstrapping (the operation that dominates computation time) returns
+    /// a wrong result, and in speed:
+    ///
+    /// * `PARAM_MESSAGE_2_CARRY_2` - Failure probability of 2^-128 per bootstrapping. This is
+    ///   the default used by `new` and the one to use for real data.
+    /// * `PARAM_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64` - Failure probability of 2^-64, which
+    ///   allows a smaller LWE dimension and slightly faster operations. Suitable for demos,
+    ///   where an occasional wrong result is acceptable.
+    ///
+    /// The integer encodings assume 2 message bits and 2 carry bits per block, so other
+    /// message or carry sizes are rejected. The parameters are stored in the client key, so
+    /// keys restored with `from_key_bytes` or `load_keys` keep them.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The shortint parameter set to generate the keys with
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a new BiosampleFHE instance, or an error if the parameter set
+    /// uses an unsupported block size
+    #[allow(dead_code)]
+    pub fn with_params(params: ClassicPBSParameters) -> Result<Self, EncryptionError> {
+        let default = PARAM_MESSAGE_2_CARRY_2;
+        if params.message_modulus != default.message_modulus
+            || params.carry_modulus != default.carry_modulus
+        {
+            return Err(EncryptionError::Unsupported(format!(
+                "Unsupported parameter set: expected message modulus {} and carry modulus {}, got {} and {}",
+                default.message_modulus.0,
+                default.carry_modulus.0,
+                params.message_modulus.0,
+                params.carry_modulus.0
+            )));
+        }
+
+        Ok(Self::generate_keys(params))
+    }
+
+    /// Generates a client key and the matching server key for a parameter set
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The shortint parameter set to generate the keys with
+    ///
+    /// # Returns
+    ///
+    /// A new BiosampleFHE instance with initialized keys
+    fn generate_keys(params: ClassicPBSParameters) -> Self {
+        // Generate client key
+        let client_key = tfhe::integer::ClientKey::new(params);
+        // Generate server key for homomorphic operations
+        let server_key = ServerKey::new_radix_server_key(&client_key);
+
+        Self {
+            key_fingerprint: fingerprint_client_key(&client_key),
+            client_key: Arc::new(client_key),
+            server_key: Arc::new(server_key),
+        }
+    }
+
+    /// Returns the parameter set the keys were generated with
+    ///
+    /// # Returns
+    ///
+    /// The shortint parameters stored in the client key
+    #[allow(dead_code)]
+    pub fn parameters(&self) -> tfhe::shortint::AtomicPatternParameters {
+        self.client_key.parameters()
+    }
+
+    /// Summarizes the parameters the keys were generated with
+    ///
+    /// # Returns
+    ///
+    /// A `ParamsInfo` with the moduli, integer width, security level and the number of
+    /// full-range values that can be summed before overflowing
+    pub fn parameters_info(&self) -> ParamsInfo {
+        let params = self.parameters();
+        let security_level = if params == PARAM_MESSAGE_2_CARRY_2.into() {
+            "128-bit, failure probability 2^-128"
+        } else if params == PARAM_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64.into() {
+            "128-bit, failure probability 2^-64"
+        } else {
+            "unknown (custom parameters)"
+        };
+
+        ParamsInfo {
+            security_level,
+            ..ParamsInfo::from_moduli(params.message_modulus().0, params.carry_modulus().0)
+        }
+    }
+
+    /// Creates a new instance of BiosampleFHE with keys derived deterministically from a seed
+    ///
+    /// **Test-only.** This reseeds the calling thread's tfhe engine, so the same seed yields the
+    /// same client/server key pair and, as long as the same operations follow on the same thread,
+    /// the same ciphertext bytes. Keys produced this way are only as secret as the seed, which is
+    /// why this constructor is gated behind the `deterministic-keys` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The 32-byte seed; its two halves are mixed into tfhe's 128-bit seed
+    ///
+    /// # Returns
+    ///
+    /// A new BiosampleFHE instance with deterministic keys
+    #[cfg(feature = "deterministic-keys")]
+    #[allow(dead_code)]
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        use tfhe::core_crypto::commons::generators::DeterministicSeeder;
+        use tfhe::core_crypto::commons::math::random::{Seed, Seeder};
+        use tfhe::core_crypto::prelude::DefaultRandomGenerator;
+        use tfhe::shortint::engine::ShortintEngine;
+
+        let (low, high) = seed.split_at(16);
+        let low = u128::from_le_bytes(low.try_into().unwrap());
+        let high = u128::from_le_bytes(high.try_into().unwrap());
+
+        // Mix both halves into a single 128-bit seed, hashing the first half so that
+        // seeds with identical halves do not cancel out
+        let mut root_seeder = DeterministicSeeder::<DefaultRandomGenerator>::new(Seed(low));
+        let mixed = Seed(root_seeder.seed().0 ^ high);
+        let mut seeder = DeterministicSeeder::<DefaultRandomGenerator>::new(mixed);
+
+        // Key generation and encryption draw their randomness from the thread-local engine
+        ShortintEngine::with_thread_local_mut(|engine| {
+            *engine = ShortintEngine::new_from_seeder(&mut seeder)
+        });
+
+        Self::new()
+    }
+
+    /// Encrypts a vector of floating-point values using FHE
+    ///
+    /// This function takes a slice of f64 values, scales them by the provided factor,
+    /// converts them to integers, and encrypts each value using the client key.
+    /// Values that are NaN or infinite, or outside the range of an integer of
+    /// `FHE_INT_BITS` blocks once scaled (see `ParamsInfo::max_value`), would wrap around
+    /// when encrypted and are rejected before anything is encrypted.
+    /// # Arguments
+    ///
+    /// * `values` - A slice of f64 values to encrypt
+    /// * `scale` - A scaling factor to convert floating-point values to integers
+    ///
+    /// # Returns
+    ///
+    /// A Result containing an `EncryptedVector` with the encrypted values, or an error
+    /// naming the index of the first value that cannot be scaled to an integer
+    pub fn encrypt_f64_vector(
+        &self,
+        values: &[f64],
+        scale: f64,
+    ) -> Result<EncryptedVector, EncryptionError> {
+        self.encrypt_f64_vector_with_progress(values, scale, &mut |_, _| {})
+    }
+
+    /// Encrypts a vector of floating-point values using FHE, reporting progress as it goes
+    ///
+    /// Behaves like `encrypt_f64_vector`, but invokes `progress` with `(done, total)` every
+    /// `PROGRESS_INTERVAL` values and once more when the last value has been encrypted.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of f64 values to encrypt
+    /// * `scale` - A scaling factor to convert floating-point values to integers
+    /// * `progress` - A callback receiving the number of values encrypted so far and the total
+    ///
+    /// # Returns
+    ///
+    /// A Result containing an `EncryptedVector` with the encrypted values, or an error
+    /// naming the index of the first value that cannot be scaled to an integer
+    pub fn encrypt_f64_vector_with_progress(
+        &self,
+        values: &[f64],
+        scale: f64,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<EncryptedVector, EncryptionError> {
+        let max_value = self.parameters_info().max_value;
+        let scaled_values = scale_values(values, scale, -max_value - 1, max_value)?;
+
+        Ok(self
+            .encrypt_i64_vector_with_progress(&scaled_values, progress)?
+            .with_scale(scale))
+    }
+
+    /// Encrypts a vector of integers exactly, without scaling
+    ///
+    /// Inherently integer fields such as ages need no fractional precision, and scaling them
+    /// would only use up the range of the ciphertexts. The vector records a scale of 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of i64 values to encrypt
+    ///
+    /// # Returns
+    ///
+    /// A Result containing an `EncryptedVector` with the encrypted values, or an error if a
+    /// value is outside the range of the encrypted integers
+    #[allow(dead_code)]
+    pub fn encrypt_i64_vector(&self, values: &[i64]) -> Result<EncryptedVector, EncryptionError> {
+        self.encrypt_i64_vector_with_progress(values, &mut |_, _| {})
+    }
+
+    /// Encrypts a vector of integers exactly, reporting progress as it goes
+    ///
+    /// Behaves like `encrypt_i64_vector`, but invokes `progress` with `(done, total)` every
+    /// `PROGRESS_INTERVAL` values and once more when the last value has been encrypted.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of i64 values to encrypt
+    /// * `progress` - A callback receiving the number of values encrypted so far and the total
+    ///
+    /// # Returns
+    ///
+    /// A Result containing an `EncryptedVector` with the encrypted values, or an error if a
+    /// value is outside the range of the encrypted integers
+    pub fn encrypt_i64_vector_with_progress(
+        &self,
+        values: &[i64],
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<EncryptedVector, EncryptionError> {
+        // Values outside the range would wrap around silently when encrypted
+        let max_value = self.parameters_info().max_value;
+        if let Some((index, value)) = values
+            .iter()
+            .enumerate()
+            .find(|(_, &v)| v < -max_value - 1 || v > max_value)
+        {
+            return Err(EncryptionError::InvalidInput(format!(
+                "Value {} at index {} cannot be encrypted: it must lie between {} and {}",
+                value,
+                index,
+                -max_value - 1,
+                max_value
+            )));
+        }
+
+        let encrypted_data: Vec<Vec<u8>> = values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let ciphertext = self.client_key.encrypt_signed_radix(v, FHE_INT_BITS);
+                report_progress(i + 1, values.len(), progress);
+                bincode::serialize(&ciphertext).unwrap()
+            })
+            .collect();
+
+        Ok(EncryptedVector::new(encrypted_data, VectorKind::Numeric)
+            .with_scale(1.0)
+            .with_key_fingerprint(self.key_fingerprint()))
+    }
+
+    /// Encrypts a vector of floating-point values in chunks, streaming them to a sink
+    ///
+    /// Values are encrypted `chunk_size` at a time and each chunk is written through an
+    /// `EncryptedVectorWriter` and flushed before the next one is encrypted, so peak memory
+    /// is bounded by the chunk size rather than the length of the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of f64 values to encrypt
+    /// * `scale` - A scaling factor to convert floating-point values to integers
+    /// * `chunk_size` - The number of values to encrypt before flushing to the sink
+    /// * `sink` - The destination for the framed ciphertexts
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the number of ciphertexts written, or an error if the chunk size
+    /// is zero, a value cannot be scaled to an integer or the sink cannot be written
+    #[allow(dead_code)]
+    pub fn encrypt_f64_vector_chunked(
+        &self,
+        values: &[f64],
+        scale: f64,
+        chunk_size: usize,
+        sink: &mut dyn Write,
+    ) -> Result<usize, EncryptionError> {
+        if chunk_size == 0 {
+            return Err(EncryptionError::InvalidInput(
+                "Chunk size must be at least 1".to_string(),
+            ));
+        }
+
+        let mut writer = EncryptedVectorWriter::new(sink);
+        for chunk in values.chunks(chunk_size) {
+            let encrypted_chunk = self.encrypt_f64_vector(chunk, scale)?;
+            writer.write_vector(&encrypted_chunk)?;
+            writer.flush()?;
+        }
+
+        Ok(writer.written())
+    }
+
+    /// Encrypts a vector of boolean values using FHE
+    ///
+    /// This function takes a slice of boolean values and encrypts each value (1 for true, 0 for false)
+    /// using the client key. Since the values are only 0 or 1, each one is encrypted with a single
+    /// block rather than the full integer width.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of boolean values to encrypt
+    ///
+    /// # Returns
+    ///
+    /// An `EncryptedVector` containing the encrypted values
+    #[allow(dead_code)]
+    pub fn encrypt_bool_vector(&self, values: &[bool]) -> EncryptedVector {
+        self.encrypt_bool_vector_with_progress(values, &mut |_, _| {})
+    }
+
+    /// Encrypts a vector of boolean values using FHE, reporting progress as it goes
+    ///
+    /// Behaves like `encrypt_bool_vector`, but invokes `progress` with `(done, total)` every
+    /// `PROGRESS_INTERVAL` values and once more when the last value has been encrypted.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of boolean values to encrypt
+    /// * `progress` - A callback receiving the number of values encrypted so far and the total
+    ///
+    /// # Returns
+    ///
+    /// An `EncryptedVector` containing the encrypted values
+    pub fn encrypt_bool_vector_with_progress(
+        &self,
+        values: &[bool],
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> EncryptedVector {
+        // Encrypt each value as a boolean block, whose degree records that it only holds 0 or 1
+        // so that computations can pack many of them together before propagating carries
+        let encrypted_data: Vec<Vec<u8>> = values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let ciphertext: SignedRadixCiphertext = self
+                    .client_key
+                    .encrypt_bool(v)
+                    .into_radix(FHE_BOOL_BLOCKS, &self.server_key);
+                report_progress(i + 1, values.len(), progress);
+                bincode::serialize(&ciphertext).unwrap()
+            })
+            .collect();
+        EncryptedVector::new(encrypted_data, VectorKind::Boolean)
+            .with_key_fingerprint(self.key_fingerprint())
+    }
+
+    /// Encrypts a vector of categorical values using FHE
+    ///
+    /// This function takes a slice of string values representing categories,
+    /// converts them to one-hot encoded vectors, and encrypts each vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of String values to encrypt
+    ///
+    /// # Returns
+    ///
+    /// An `EncryptedCategorical` containing the encrypted one-hot vectors and category names
+    #[allow(dead_code)]
+    pub fn encrypt_categorical(&self, values: &[String]) -> EncryptedCategorical {
+        self.encrypt_categorical_with_progress(values, &mut |_, _| {})
+    }
+
+    /// Encrypts a vector of categorical values using FHE, reporting progress as it goes
+    ///
+    /// Behaves like `encrypt_categorical`. Progress is counted over every encrypted one-hot
+    /// indicator, so the total is the number of values times the number of categories.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of String values to encrypt
+    /// * `progress` - A callback receiving the number of indicators encrypted so far and the total
+    ///
+    /// # Returns
+    ///
+    /// An `EncryptedCategorical` containing the encrypted one-hot vectors and category names
+    pub fn encrypt_categorical_with_progress(
+        &self,
+        values: &[String],
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> EncryptedCategorical {
+        // Find unique categories
+        let mut categories: Vec<String> = values
+            .iter()
+            .cloned()
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .collect();
+        categories.sort(); // Sort categories for consistency
+
+        //Create a one-hot encoded vectors
+        let mut one_hot_vectors: Vec<Vec<bool>> = Vec::with_capacity(categories.len());
+
+        for category in &categories {
+            let one_hot: Vec<bool> = values.iter().map(|v| v == category).collect();
+            one_hot_vectors.push(one_hot);
+        }
+
+        // Encrypt each one-hot vector, offsetting its progress by the vectors already done
+        let total = values.len() * categories.len();
+        let encrypted_vectors: Vec<EncryptedVector> = one_hot_vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let offset = i * values.len();
+                self.encrypt_bool_vector_with_progress(v, &mut |done, _| {
+                    progress(offset + done, total)
+                })
+            })
+            .collect();
+
+        EncryptedCategorical {
+            categories,
+            vectors: encrypted_vectors,
+        }
+    }
+
+    /// Encrypts a vector of patient identifiers as keyed 32-bit hashes
+    ///
+    /// Each identifier is hashed with SHA3-256 under a key derived from the client key and
+    /// truncated to 32 bits before encryption, so that identifiers of any length can be
+    /// compared homomorphically with `compute_encrypted_id_match_count`. Only datasets
+    /// encrypted with the same keys produce matching hashes.
+    ///
+    /// Truncation means two different identifiers collide with a probability of about
+    /// 2^-32 per compared pair, so match counts over large datasets may be overstated by
+    /// a handful of false matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - A slice of patient identifiers to encrypt
+    ///
+    /// # Returns
+    ///
+    /// An `EncryptedVector` of kind `VectorKind::Identifier` containing the encrypted hashes
+    #[allow(dead_code)]
+    pub fn encrypt_id_vector(&self, ids: &[String]) -> EncryptedVector {
+        let hash_key = self.id_hash_key();
+        let encrypted_data: Vec<Vec<u8>> = ids
+            .iter()
+            .map(|id| {
+                let hash = hash_patient_id(&hash_key, id);
+                let ciphertext: RadixCiphertext =
+                    self.client_key.encrypt_radix(hash as u64, FHE_ID_BLOCKS);
+                bincode::serialize(&ciphertext).unwrap()
+            })
+            .collect();
+
+        EncryptedVector::new(encrypted_data, VectorKind::Identifier)
+            .with_key_fingerprint(self.key_fingerprint())
+    }
+
+    /// Encrypts all fields of a single biosample record into one bundle
+    ///
+    /// Glucose and cholesterol levels are scaled by `DEFAULT_SCALE`, ages, facility ids and
+    /// collection days are encrypted exactly, and text fields byte by byte.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The biosample record to encrypt
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the `EncryptedRecord`, or an error if the collection date is
+    /// malformed or outside the range the integers can hold, or a level is not finite
+    #[allow(dead_code)]
+    pub fn encrypt_record(
+        &self,
+        record: &BiosampleRecord,
+    ) -> Result<EncryptedRecord, EncryptionError> {
+        let day = collection_day(&record.collection_date)
+            .map_err(|e| EncryptionError::InvalidInput(e.to_string()))?;
+        if day < 0 || day > self.parameters_info().max_value {
+            return Err(EncryptionError::InvalidInput(format!(
+                "Collection date {} is outside the encryptable range",
+                record.collection_date
+            )));
+        }
+
+        let text = |value: &str| {
+            let bytes: Vec<i64> = value.bytes().map(i64::from).collect();
+            self.encrypt_i64_vector(&bytes)
+        };
+        Ok(EncryptedRecord {
+            patient_id: text(&record.patient_id)?,
+            age: self.encrypt_i64_vector(&[record.age as i64])?,
+            gender: text(&record.gender)?,
+            blood_type: text(&record.blood_type)?,
+            glucose_level: self.encrypt_f64_vector(&[record.glucose_level], DEFAULT_SCALE)?,
+            cholesterol_level: self
+                .encrypt_f64_vector(&[record.cholesterol_level], DEFAULT_SCALE)?,
+            marker_alpha: self.encrypt_bool_vector(&[record.marker_alpha]),
+            collection_day: self.encrypt_i64_vector(&[day])?,
+            facility_id: self.encrypt_i64_vector(&[record.facility_id as i64])?,
+        })
+    }
+
+    /// Decrypts a bundle from `encrypt_record` back into a biosample record
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - The `EncryptedRecord` to decrypt
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the decrypted `BiosampleRecord`, or an error if a field is empty,
+    /// out of range, not valid UTF-8 or encrypted under a different client key
+    #[allow(dead_code)]
+    pub fn decrypt_record(
+        &self,
+        encrypted: &EncryptedRecord,
+    ) -> Result<BiosampleRecord, EncryptionError> {
+        let text = |vector: &EncryptedVector| {
+            let bytes = self
+                .try_decrypt_i64_vector(vector)?
+                .into_iter()
+                .map(|byte| {
+                    u8::try_from(byte).map_err(|_| {
+                        EncryptionError::InvalidInput(format!("{} is not a text byte", byte))
+                    })
+                })
+                .collect::<Result<Vec<u8>, EncryptionError>>()?;
+            String::from_utf8(bytes).map_err(|e| EncryptionError::InvalidInput(e.to_string()))
+        };
+        let integer = |vector: &EncryptedVector| {
+            let value = self.decrypt_element(vector, 0, 1.0)? as i64;
+            u32::try_from(value).map_err(|_| {
+                EncryptionError::InvalidInput(format!("{} is not a valid record value", value))
+            })
+        };
+        let day = self.decrypt_element(&encrypted.collection_day, 0, 1.0)? as i64;
+
+        Ok(BiosampleRecord {
+            patient_id: text(&encrypted.patient_id)?,
+            age: integer(&encrypted.age)?,
+            gender: text(&encrypted.gender)?,
+            blood_type: text(&encrypted.blood_type)?,
+            glucose_level: self.decrypt_element(
+                &encrypted.glucose_level,
+                0,
+                encrypted.glucose_level.scale,
+            )?,
+            cholesterol_level: self.decrypt_element(
+                &encrypted.cholesterol_level,
+                0,
+                encrypted.cholesterol_level.scale,
+            )?,
+            marker_alpha: self.decrypt_bool_element(&encrypted.marker_alpha, 0)?,
+            collection_date: collection_date_from_day(day)
+                .map_err(|e| EncryptionError::InvalidInput(e.to_string()))?,
+            facility_id: integer(&encrypted.facility_id)?,
+        })
+    }
+
+    /// Derives the key used to hash patient identifiers from the client key
+    ///
+    /// Keying the hash with secret material stops anyone without the client key from
+    /// recovering identifiers by hashing guesses and comparing the results.
+    ///
+    /// # Returns
+    ///
+    /// A 32-byte key that is the same for every instance sharing this client key
+    fn id_hash_key(&self) -> [u8; 32] {
+        let mut hasher = Sha3_256::new();
+        hasher.update(ID_HASH_DOMAIN);
+        hasher.update(bincode::serialize(self.client_key.as_ref()).unwrap());
+        hasher.finalize().into()
+    }
+
+    /// Returns a fingerprint of the client key
+    ///
+    /// Vectors encrypted by this instance are stamped with the fingerprint, and server-side
+    /// computations pass it on to their results, so that decrypting them with keys loaded
+    /// from the wrong files fails with `EncryptionError::KeyMismatch`. The fingerprint is a
+    /// one-way hash, reveals nothing about the key, and is computed once when the keys are
+    /// generated or loaded.
+    ///
+    /// # Returns
+    ///
+    /// A 32-byte fingerprint that is the same for every instance sharing this client key
+    pub fn key_fingerprint(&self) -> [u8; 32] {
+        self.key_fingerprint
+    }
+
+    /// Checks that a vector was encrypted under this instance's client key
+    ///
+    /// Vectors without a fingerprint, such as ones saved before fingerprints were recorded,
+    /// cannot be checked and are accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - The vector about to be decrypted
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the fingerprints match or the vector has none, or a `KeyMismatch` error
+    fn check_key_fingerprint(&self, encrypted: &EncryptedVector) -> Result<(), EncryptionError> {
+        match encrypted.key_fingerprint {
+            Some(fingerprint) if fingerprint != self.key_fingerprint => {
+                Err(EncryptionError::KeyMismatch(
+                    "EncryptedVector was encrypted under a different client key".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Encrypts a vector of floating-point values as high-level `FheInt64` ciphertexts
+    ///
+    /// This is the `high-level-api` counterpart of `encrypt_f64_vector`. The values are
+    /// scaled the same way but encrypted as 64-bit high-level integers, which support
+    /// operators such as `+`, `gt` and `lt` directly, at the cost of larger and slower
+    /// ciphertexts. The returned vector is tagged `VectorKind::HighLevelInt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - A slice of f64 values to encrypt
+    /// * `scale` - A scaling factor to convert floating-point values to integers
+    ///
+    /// # Returns
+    ///
+    /// A Result containing an `EncryptedVector` with the serialized high-level ciphertexts,
+    /// or an error naming the index of the first value that is not finite or does not fit
+    /// a 64-bit integer once scaled
+    #[cfg(feature = "high-level-api")]
+    #[allow(dead_code)]
+    pub fn encrypt_f64_vector_hl(
+        &self,
+        values: &[f64],
+        scale: f64,
+    ) -> Result<EncryptedVector, EncryptionError> {
+        use tfhe::prelude::FheEncrypt;
+
+        let scaled_values = scale_values(values, scale, i64::MIN, i64::MAX)?;
+        let client_key = self.high_level_client_key();
+        let encrypted_data: Vec<Vec<u8>> = scaled_values
+            .iter()
+            .map(|&v| {
+                let ciphertext = tfhe::FheInt64::encrypt(v, &client_key);
+                bincode::serialize(&ciphertext).unwrap()
+            })
+            .collect();
+
+        Ok(
+            EncryptedVector::new(encrypted_data, VectorKind::HighLevelInt)
+                .with_scale(scale)
+                .with_key_fingerprint(self.key_fingerprint()),
+        )
+    }
+
+    /// Decrypts a vector of high-level `FheInt64` ciphertexts
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` produced by the `high-level-api` backend
+    /// * `scale` - The scaling factor used during encryption
+    ///
+    /// # Returns
+    ///
+    /// A vector of decrypted f64 values
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector was encrypted under a different client key, or a ciphertext
+    /// cannot be decompressed or deserialized.
+    #[cfg(feature = "high-level-api")]
+    #[allow(dead_code)]
+    pub fn decrypt_f64_vector_hl(&self, encrypted: &EncryptedVector, scale: f64) -> Vec<f64> {
+        use tfhe::prelude::FheDecrypt;
+
+        self.check_key_fingerprint(encrypted)
+            .expect("encrypted vector should match the client key");
+        let client_key = self.high_level_client_key();
+        encrypted
+            .ciphertext_bytes()
+            .unwrap()
+            .iter()
+            .map(|data| {
+                let ciphertext: tfhe::FheInt64 = bincode::deserialize(data).unwrap();
+                let decrypted_value: i64 = ciphertext.decrypt(&client_key);
+                decrypted_value as f64 / scale
+            })
+            .collect()
+    }
+
+    /// Builds a high-level server key from this instance's server key
+    ///
+    /// The high-level key wraps a copy of the integer server key, so build it once and
+    /// clone it (cheaply) as needed.
+    ///
+    /// # Returns
+    ///
+    /// A `tfhe::ServerKey` for use with the `high-level-api` backend
+    #[cfg(feature = "high-level-api")]
+    #[allow(dead_code)]
+    pub fn high_level_server_key(&self) -> tfhe::ServerKey {
+        tfhe::ServerKey::from_raw_parts(
+            self.server_key.as_ref().clone(),
+            None,
+            None,
+            None,
+            None,
+            tfhe::Tag::default(),
+        )
+    }
+
+    /// Wraps a copy of the integer client key into a high-level client key
+    ///
+    /// # Returns
+    ///
+    /// A `tfhe::ClientKey` using the same secret key as this instance
+    #[cfg(feature = "high-level-api")]
+    fn high_level_client_key(&self) -> tfhe::ClientKey {
+        tfhe::ClientKey::from_raw_parts(
+            self.client_key.as_ref().clone(),
+            None,
+            None,
+            None,
+            tfhe::Tag::default(),
+        )
+    }
+
+    /// Decrypts a vector of encrypted floating-point values
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    /// * `scale` - The scaling factor used during encryption
+    ///
+    /// # Returns
+    ///
+    /// A vector of decrypted f64 values
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector was encrypted under a different client key, or a ciphertext
+    /// cannot be decompressed or deserialized, which only happens when a corrupted vector
+    /// is passed in. Use `try_decrypt_f64_vector` for vectors read from untrusted storage.
+    #[allow(dead_code)]
+    pub fn decrypt_f64_vector(&self, encrypted: &EncryptedVector, scale: f64) -> Vec<f64> {
+        self.try_decrypt_f64_vector(encrypted, scale)
+            .expect("encrypted vector should hold valid ciphertexts")
+    }
+
+    /// Decrypts a vector of encrypted integers
+    ///
+    /// The integers are returned as they were encrypted, without dividing by the scale of
+    /// the vector, so vectors from `encrypt_i64_vector` decrypt to their exact values.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    ///
+    /// # Returns
+    ///
+    /// A vector of decrypted i64 values
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector was encrypted under a different client key, or a ciphertext
+    /// cannot be decompressed or deserialized, like `decrypt_f64_vector`. Use
+    /// `try_decrypt_i64_vector` for vectors read from untrusted storage.
+    #[allow(dead_code)]
+    pub fn decrypt_i64_vector(&self, encrypted: &EncryptedVector) -> Vec<i64> {
+        self.try_decrypt_i64_vector(encrypted)
+            .expect("encrypted vector should hold valid ciphertexts")
+    }
+
+    /// Decrypts a vector of encrypted integers, reporting corrupted ciphertexts
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the decrypted i64 values, or an error if the vector was
+    /// encrypted under a different client key or a ciphertext cannot be decompressed or
+    /// deserialized
+    #[allow(dead_code)]
+    pub fn try_decrypt_i64_vector(
+        &self,
+        encrypted: &EncryptedVector,
+    ) -> Result<Vec<i64>, EncryptionError> {
+        self.check_key_fingerprint(encrypted)?;
+        encrypted
+            .ciphertext_bytes()?
+            .iter()
+            .map(|data| {
+                let ciphertext: SignedRadixCiphertext = bincode::deserialize(data)?;
+                Ok(self.client_key.decrypt_signed_radix(&ciphertext))
+            })
+            .collect()
+    }
+
+    /// Decrypts a vector of encrypted floating-point values, reporting corrupted ciphertexts
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    /// * `scale` - The scaling factor used during encryption
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the decrypted f64 values, or an error if the vector was
+    /// encrypted under a different client key or a ciphertext cannot be decompressed or
+    /// deserialized
+    #[allow(dead_code)]
+    pub fn try_decrypt_f64_vector(
+        &self,
+        encrypted: &EncryptedVector,
+        scale: f64,
+    ) -> Result<Vec<f64>, EncryptionError> {
+        self.check_key_fingerprint(encrypted)?;
+        encrypted
+            .ciphertext_bytes()?
+            .iter()
+            .map(|data| {
+                // Use RadixCiphertext instead of BaseSignedRadixCiphertext
+                let ciphertext: SignedRadixCiphertext = bincode::deserialize(data)?;
+                let decrypted_value: i64 = self.client_key.decrypt_signed_radix(&ciphertext);
+                Ok(decrypted_value as f64 / scale)
+            })
+            .collect()
+    }
+
+    /// Decrypts the encrypted mean of a field
+    ///
+    /// The mean is divided on the server and carries its own scale, which includes the extra
+    /// precision kept by the division.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_mean` - The encrypted mean of the field, e.g. from `compute_encrypted_mean`
+    /// * `field` - The name of the averaged field
+    /// * `context` - The context produced when the field was encrypted
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the mean of the field, or an error if the field is not part of
+    /// the context or has no values
+    #[allow(dead_code)]
+    pub fn decrypt_mean(
+        &self,
+        encrypted_mean: &EncryptedVector,
+        field: &str,
+        context: &EncryptionContext,
+    ) -> Result<f64, EncryptionError> {
+        let info = context.field(field)?;
+        if info.length == 0 {
+            return Err(EncryptionError::InvalidInput(format!(
+                "Cannot compute the mean of empty field {}",
+                field
+            )));
+        }
+
+        self.decrypt_element(encrypted_mean, 0, encrypted_mean.scale)
+    }
+
+    /// Decrypts the quotient and remainder of an exact mean
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_mean` - The encrypted quotient and remainder, e.g. from
+    ///   `compute_encrypted_mean_exact`
+    /// * `count` - The number the sum was divided by on the server
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the exact mean, or an error if `count` is zero, the vector does
+    /// not hold exactly a quotient and a remainder, was encrypted under a different client
+    /// key, or a ciphertext is corrupted
+    pub fn decrypt_exact_mean(
+        &self,
+        encrypted_mean: &EncryptedVector,
+        count: usize,
+    ) -> Result<ExactMean, EncryptionError> {
+        if count == 0 {
+            return Err(EncryptionError::InvalidInput(
+                "An exact mean needs a non-zero count".to_string(),
+            ));
+        }
+        if encrypted_mean.length != 2 {
+            return Err(EncryptionError::InvalidInput(format!(
+                "An exact mean holds a quotient and a remainder, found {} values",
+                encrypted_mean.length
+            )));
+        }
+
+        let parts = self.try_decrypt_i64_vector(encrypted_mean)?;
+        Ok(ExactMean {
+            quotient: parts[0],
+            remainder: parts[1],
+            count,
+            scale: encrypted_mean.scale,
+        })
+    }
+
+    /// Decrypts the variance terms of a field and finishes its variance and standard deviation
+    ///
+    /// The population variance is computed as `E[x²] − E[x]²`. Rounding can make it come out
+    /// slightly below zero for nearly constant fields, so it is clamped at zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_terms` - The encrypted sum and sum of squares of the field, e.g. from
+    ///   `compute_encrypted_variance`
+    /// * `field` - The name of the field
+    /// * `context` - The context produced when the field was encrypted
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the variance and the standard deviation of the field, or an error
+    /// if the field is not part of the context or has no values
+    #[allow(dead_code)]
+    pub fn decrypt_variance(
+        &self,
+        encrypted_terms: &EncryptedVector,
+        field: &str,
+        context: &EncryptionContext,
+    ) -> Result<(f64, f64), EncryptionError> {
+        let info = context.field(field)?;
+        if info.length == 0 {
+            return Err(EncryptionError::InvalidInput(format!(
+                "Cannot compute the variance of empty field {}",
+                field
+            )));
+        }
+
+        // The sum of squares carries the square of the field's scale
+        let n = info.length as f64;
+        let mean = self.decrypt_element(encrypted_terms, 0, info.scale)? / n;
+        let mean_of_squares =
+            self.decrypt_element(encrypted_terms, 1, info.scale * info.scale)? / n;
+        let variance = (mean_of_squares - mean * mean).max(0.0);
+        Ok((variance, variance.sqrt()))
+    }
+
+    /// Decrypts the covariance terms of two fields and finishes their covariance
+    ///
+    /// The population covariance is computed as `E[xy] − E[x]E[y]`. Divided by the
+    /// standard deviations from `decrypt_variance`, it gives the Pearson correlation.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_terms` - The encrypted `Σxy`, `Σx` and `Σy`, e.g. from
+    ///   `compute_encrypted_covariance`
+    /// * `x_field` - The name of the first field
+    /// * `y_field` - The name of the second field
+    /// * `context` - The context produced when the fields were encrypted
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the covariance of the two fields, or an error if a field is not
+    /// part of the context, the fields differ in length or have no values
+    #[allow(dead_code)]
+    pub fn decrypt_covariance(
+        &self,
+        encrypted_terms: &EncryptedVector,
+        x_field: &str,
+        y_field: &str,
+        context: &EncryptionContext,
+    ) -> Result<f64, EncryptionError> {
+        let x = context.field(x_field)?;
+        let y = context.field(y_field)?;
+        if x.length != y.length {
+            return Err(EncryptionError::InvalidInput(format!(
+                "Fields {} and {} have different lengths: {} and {}",
+                x_field, y_field, x.length, y.length
+            )));
+        }
+        if x.length == 0 {
+            return Err(EncryptionError::InvalidInput(format!(
+                "Cannot compute the covariance of empty field {}",
+                x_field
+            )));
+        }
+
+        // Each sum carries the scale of the values it adds up
+        let n = x.length as f64;
+        let mean_xy = self.decrypt_element(encrypted_terms, 0, x.scale * y.scale)? / n;
+        let mean_x = self.decrypt_element(encrypted_terms, 1, x.scale)? / n;
+        let mean_y = self.decrypt_element(encrypted_terms, 2, y.scale)? / n;
+        Ok(mean_xy - mean_x * mean_y)
+    }
+
+    /// Decrypts the sum and count of a field's values in one group and divides them
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_terms` - The encrypted sum and count of the group, e.g. from
+    ///   `compute_encrypted_means_by_category`
+    /// * `field` - The name of the averaged field
+    /// * `context` - The context produced when the field was encrypted
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the mean of the group, `None` if the group has no records, or an
+    /// error if the field is not part of the context
+    #[allow(dead_code)]
+    pub fn decrypt_conditional_mean(
+        &self,
+        encrypted_terms: &EncryptedVector,
+        field: &str,
+        context: &EncryptionContext,
+    ) -> Result<Option<f64>, EncryptionError> {
+        let info = context.field(field)?;
+
+        // The sum carries the scale of the field and the count is a plain integer
+        let count = self.decrypt_element(encrypted_terms, 1, 1.0)?;
+        if count == 0.0 {
+            return Ok(None);
+        }
+        let sum = self.decrypt_element(encrypted_terms, 0, info.scale)?;
+        Ok(Some(sum / count))
+    }
+
+    /// Decrypts the group terms of a field and finishes the mean of each group
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_terms` - The encrypted sums and counts of both groups, e.g. from
+    ///   `compute_encrypted_group_means`
+    /// * `field` - The name of the averaged field
+    /// * `context` - The context produced when the field was encrypted
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the means of the records whose indicator is 1 and of those whose
+    /// indicator is 0, each `None` if its group is empty, or an error if the field is not
+    /// part of the context
+    #[allow(dead_code)]
+    pub fn decrypt_group_means(
+        &self,
+        encrypted_terms: &EncryptedVector,
+        field: &str,
+        context: &EncryptionContext,
+    ) -> Result<(Option<f64>, Option<f64>), EncryptionError> {
+        let info = context.field(field)?;
+
+        // The sums carry the scale of the field and the counts are plain integers
+        let mut means = [None, None];
+        for (group, mean) in means.iter_mut().enumerate() {
+            let sum = self.decrypt_element(encrypted_terms, 2 * group, info.scale)?;
+            let count = self.decrypt_element(encrypted_terms, 2 * group + 1, 1.0)?;
+            if count > 0.0 {
+                *mean = Some(sum / count);
+            }
+        }
+        Ok((means[0], means[1]))
+    }
+
+    /// Decrypts a single element of an encrypted vector
+    ///
+    /// Only the requested ciphertext is deserialized and decrypted, so one record can be
+    /// revealed without decrypting the rest of the vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    /// * `index` - The position of the element to decrypt
+    /// * `scale` - The scaling factor used during encryption
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the decrypted value, or an error if the index is out of bounds
+    #[allow(dead_code)]
+    pub fn decrypt_element(
+        &self,
+        encrypted: &EncryptedVector,
+        index: usize,
+        scale: f64,
+    ) -> Result<f64, EncryptionError> {
+        self.check_key_fingerprint(encrypted)?;
+        let ciphertext: SignedRadixCiphertext =
+            bincode::deserialize(&encrypted.ciphertext_bytes_at(index)?)?;
+        let decrypted_value: i64 = self.client_key.decrypt_signed_radix(&ciphertext);
+        Ok(decrypted_value as f64 / scale)
+    }
+
+    /// Decrypts a contiguous range of elements of a vector of encrypted floating-point values
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    /// * `range` - The positions of the elements to decrypt
+    /// * `scale` - The scaling factor used during encryption
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the decrypted values, or an error if the range is out of bounds
+    #[allow(dead_code)]
+    pub fn decrypt_range(
+        &self,
+        encrypted: &EncryptedVector,
+        range: Range<usize>,
+        scale: f64,
+    ) -> Result<Vec<f64>, EncryptionError> {
+        if range.start > range.end || range.end > encrypted.data.len() {
+            return Err(EncryptionError::InvalidInput(format!(
+                "Range {:?} is out of bounds for a vector of length {}",
+                range,
+                encrypted.data.len()
+            )));
+        }
+
+        range
+            .map(|index| self.decrypt_element(encrypted, index, scale))
+            .collect()
+    }
+
+    /// Decrypts a single element of a vector of encrypted boolean values
+    ///
+    /// Like `decrypt_bool_vector`, the decrypted value must be exactly 0 or 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    /// * `index` - The position of the element to decrypt
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the decrypted boolean, or an error if the index is out of
+    /// bounds or the value is neither 0 nor 1
+    #[allow(dead_code)]
+    pub fn decrypt_bool_element(
+        &self,
+        encrypted: &EncryptedVector,
+        index: usize,
+    ) -> Result<bool, EncryptionError> {
+        self.check_key_fingerprint(encrypted)?;
+        let ciphertext: SignedRadixCiphertext =
+            bincode::deserialize(&encrypted.ciphertext_bytes_at(index)?)?;
+        match self.client_key.decrypt_signed_radix::<i64>(&ciphertext) {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(InvalidBoolError { index, value }.into()),
+        }
+    }
+
+    /// Decrypts a vector of encrypted boolean values
+    ///
+    /// Every decrypted value must be exactly 0 or 1, so that a vector which was
+    /// accidentally summed or corrupted is rejected instead of decoding to plausible
+    /// booleans. Use `decrypt_bool_vector_with_mode` with `BoolDecoding::Lenient` to
+    /// treat any non-zero value as `true` instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the decrypted boolean values, or an `InvalidBoolError` for the
+    /// first value that is neither 0 nor 1
+    #[allow(dead_code)]
+    pub fn decrypt_bool_vector(
+        &self,
+        encrypted: &EncryptedVector,
+    ) -> Result<Vec<bool>, EncryptionError> {
+        self.decrypt_bool_vector_with_mode(encrypted, BoolDecoding::default())
+    }
+
+    /// Decrypts a vector of encrypted boolean values with an explicit decoding mode
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted` - An `EncryptedVector` containing the encrypted values
+    /// * `mode` - Whether values other than 0 and 1 are rejected or decoded as `true`
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the decrypted boolean values, or an `InvalidBoolError` if strict
+    /// decoding finds a value that is neither 0 nor 1
+    #[allow(dead_code)]
+    pub fn decrypt_bool_vector_with_mode(
+        &self,
+        encrypted: &EncryptedVector,
+        mode: BoolDecoding,
+    ) -> Result<Vec<bool>, EncryptionError> {
+        self.check_key_fingerprint(encrypted)?;
+        encrypted
+            .ciphertext_bytes()?
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let ciphertext: SignedRadixCiphertext = bincode::deserialize(data)?;
+                let value: i64 = self.client_key.decrypt_signed_radix(&ciphertext);
+                match (mode, value) {
+                    (_, 0) => Ok(false),
+                    (_, 1) => Ok(true),
+                    (BoolDecoding::Lenient, _) => Ok(true),
+                    (BoolDecoding::Strict, _) => Err(InvalidBoolError { index, value }.into()),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a reference to the server key
+    ///
+    /// # Returns
+    ///
+    /// A reference to the `ServerKey` used for homomorphic operations
+    pub fn server_key(&self) -> &ServerKey {
+        &self.server_key
+    }
+
+    /// Returns a shared handle to the server key
+    ///
+    /// The handle can be moved to other threads or outlive this instance without copying
+    /// the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Arc` pointing to the `ServerKey` used by this instance and its clones
+    #[allow(dead_code)]
+    pub fn server_key_arc(&self) -> Arc<ServerKey> {
+        Arc::clone(&self.server_key)
+    }
+
+    /// Serializes the client key to bytes
+    ///
+    /// The bytes can be kept in any secret store, such as a vault or browser storage,
+    /// instead of a file. The client key decrypts data and must stay private.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the serialized client key, or an error if serialization fails
+    #[allow(dead_code)]
+    pub fn client_key_to_bytes(&self) -> Result<Vec<u8>, EncryptionError> {
+        Ok(bincode::serialize(self.client_key.as_ref())?)
+    }
+
+    /// Serializes the server key to bytes
+    ///
+    /// The server key only allows computing on ciphertexts, so it can be shared with the
+    /// party performing the computations.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the serialized server key, or an error if serialization fails
+    #[allow(dead_code)]
+    pub fn server_key_to_bytes(&self) -> Result<Vec<u8>, EncryptionError> {
+        Ok(bincode::serialize(self.server_key.as_ref())?)
+    }
+
+    /// Restores encryption keys serialized by `client_key_to_bytes` and `server_key_to_bytes`
+    ///
+    /// # Arguments
+    ///
+    /// * `client_key_bytes` - The serialized client key
+    /// * `server_key_bytes` - The serialized server key
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a new `Self` instance, or an error if the bytes are not valid keys
+    #[allow(dead_code)]
+    pub fn from_key_bytes(
+        client_key_bytes: &[u8],
+        server_key_bytes: &[u8],
+    ) -> Result<Self, EncryptionError> {
+        let client_key: tfhe::integer::ClientKey = bincode::deserialize(client_key_bytes)?;
+        let server_key: ServerKey = bincode::deserialize(server_key_bytes)?;
+
+        Ok(Self {
+            key_fingerprint: fingerprint_client_key(&client_key),
+            client_key: Arc::new(client_key),
+            server_key: Arc::new(server_key),
+        })
+    }
+
+    /// Saves the encryption keys to disk
+    ///
+    /// # Arguments
+    ///
+    /// * `client_key_path` - The path where the client key will be saved
+    /// * `server_key_path` - The path where the server key will be saved
+    ///
+    /// # Returns
+    ///
+    /// A Result containing () if successful, or an error if the keys could not be saved
+    #[cfg(feature = "native")]
+    #[allow(dead_code)]
+    pub fn save_keys(
+        &self,
+        client_key_path: &Path,
+        server_key_path: &Path,
+    ) -> Result<(), EncryptionError> {
+        // Save the client key
+        let mut client_key_file = File::create(client_key_path)?;
+        client_key_file.write_all(&self.client_key_to_bytes()?)?;
+
+        // Save the server key
+        let mut server_key_file = File::create(server_key_path)?;
+        server_key_file.write_all(&self.server_key_to_bytes()?)?;
+
+        Ok(())
+    }
+
+    /// Loads encryption keys from disk
+    ///
+    /// # Arguments
+    ///
+    /// * `client_key_path` - The path from which the client key will be loaded
+    /// * `server_key_path` - The path from which the server key will be loaded
     ///
     /// # Returns
     ///
     /// A Result containing a new `Self` instance if successful, or an error if the keys could not be loaded
+    #[cfg(feature = "native")]
     #[allow(dead_code)]
     pub fn load_keys(
         client_key_path: &Path,
         server_key_path: &Path,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, EncryptionError> {
         // Load the client key
         let mut client_key_file = File::open(client_key_path)?;
         let mut client_key_bytes = Vec::new();
         client_key_file.read_to_end(&mut client_key_bytes)?;
-        let client_key: tfhe::integer::ClientKey = bincode::deserialize(&client_key_bytes)?;
 
         // Load the server key
         let mut server_key_file = File::open(server_key_path)?;
         let mut server_key_bytes = Vec::new();
         server_key_file.read_to_end(&mut server_key_bytes)?;
-        let server_key: ServerKey = bincode::deserialize(&server_key_bytes)?;
 
-        Ok(Self {
-            client_key,
-            server_key,
-        })
+        Self::from_key_bytes(&client_key_bytes, &server_key_bytes)
     }
 }
+/// Encrypts the fields of a set of biosample records
+///
+/// Numeric fields are multiplied by their scale from `scales` before encryption and each
+/// vector records the scale it was encrypted with. Ages are whole years and are encrypted
+/// exactly with a scale of 1, whatever `scales` says. Collection dates are encrypted the
+/// same way as days since 1970-01-01 under `fields::COLLECTION_DAY`, and facility ids under
+/// `fields::FACILITY`. The marker is encrypted as a boolean vector, and blood types and
+/// facilities are one-hot encoded into one vector per value.
+///
+/// # Arguments
+///
+/// * `fhe` - The `BiosampleFHE` instance used for encryption
+/// * `records` - The biosample records to encrypt
+/// * `scales` - The scale of each numeric field
+///
+/// # Returns
+///
+/// A Result containing a map of field names to encrypted vectors together with the
+/// `EncryptionContext` describing them, or an error if a collection date is malformed or
+/// encryption fails
+#[allow(dead_code)]
 pub fn encrypt_biosample_data(
     fhe: &BiosampleFHE,
     records: &[BiosampleRecord],
-) -> Result<HashMap<String, EncryptedVector>, Box<dyn Error>> {
-    let mut encrypted_data = HashMap::new();
+    scales: &ScaleConfig,
+) -> Result<(HashMap<String, EncryptedVector>, EncryptionContext), EncryptionError> {
+    #[cfg(feature = "rayon")]
+    return encrypt_biosample_data_parallel(fhe, records, scales);
+    #[cfg(not(feature = "rayon"))]
+    encrypt_biosample_data_with_progress(fhe, records, scales, &mut |_, _| {})
+}
+
+/// Encrypted fields, blood types and context returned by `encrypt_biosample_data_grouped`
+pub type GroupedEncryptedData = (
+    HashMap<String, EncryptedVector>,
+    EncryptedCategorical,
+    EncryptionContext,
+);
+
+/// Encrypts the fields of a set of biosample records, keeping the blood types together
+///
+/// Behaves like `encrypt_biosample_data`, but instead of flattening the blood types into
+/// one `fields::blood_type_key` entry per type, returns them as a single
+/// `EncryptedCategorical`, which can be stored as a whole with `save_categorical`. The map
+/// and the context then describe the remaining fields.
+///
+/// # Arguments
+///
+/// * `fhe` - The `BiosampleFHE` instance used for encryption
+/// * `records` - The biosample records to encrypt
+/// * `scales` - The scale of each numeric field
+///
+/// # Returns
+///
+/// A Result containing a map of field names to encrypted vectors, the encrypted blood
+/// types with their categories in sorted order, and the `EncryptionContext` describing the
+/// map, or an error if a collection date is malformed or encryption fails
+#[allow(dead_code)]
+pub fn encrypt_biosample_data_grouped(
+    fhe: &BiosampleFHE,
+    records: &[BiosampleRecord],
+    scales: &ScaleConfig,
+) -> Result<GroupedEncryptedData, EncryptionError> {
+    let (mut encrypted_data, _) = encrypt_biosample_data(fhe, records, scales)?;
+
+    // The keys share a prefix, so sorting them sorts the blood types
+    let mut blood_type_keys: Vec<String> = encrypted_data
+        .keys()
+        .filter(|key| key.starts_with(fields::BLOOD_TYPE_PREFIX))
+        .cloned()
+        .collect();
+    blood_type_keys.sort();
+
+    let mut blood_types = EncryptedCategorical {
+        categories: Vec::with_capacity(blood_type_keys.len()),
+        vectors: Vec::with_capacity(blood_type_keys.len()),
+    };
+    for key in blood_type_keys {
+        let category = key
+            .trim_start_matches(fields::BLOOD_TYPE_PREFIX)
+            .to_string();
+        if let Some(vector) = encrypted_data.remove(&key) {
+            blood_types.categories.push(category);
+            blood_types.vectors.push(vector);
+        }
+    }
+
+    let context = EncryptionContext::new(records.len(), &encrypted_data);
+    Ok((encrypted_data, blood_types, context))
+}
+
+/// Encrypts the fields of a set of biosample records, reporting progress as it goes
+///
+/// Behaves like `encrypt_biosample_data`. Progress is counted over every encrypted value
+/// across all fields, including one indicator per record for each blood type.
+///
+/// # Arguments
+///
+/// * `fhe` - The `BiosampleFHE` instance used for encryption
+/// * `records` - The biosample records to encrypt
+/// * `scales` - The scale of each numeric field
+/// * `progress` - A callback receiving the number of values encrypted so far and the total
+///
+/// # Returns
+///
+/// A Result containing a map of field names to encrypted vectors together with the
+/// `EncryptionContext` describing them, or an error if a collection date is malformed or
+/// encryption fails
+pub fn encrypt_biosample_data_with_progress(
+    fhe: &BiosampleFHE,
+    records: &[BiosampleRecord],
+    scales: &ScaleConfig,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<(HashMap<String, EncryptedVector>, EncryptionContext), EncryptionError> {
+    let columns = plain_columns(fhe, records, scales)?;
+
+    // Each column's progress is offset by the values of the columns already encrypted
+    let total = columns.iter().map(PlainColumn::num_values).sum();
+    let mut offset = 0;
+    let mut encrypted_data = HashMap::new();
+    for column in &columns {
+        let (key, vector) = column.encrypt(fhe, &mut |done, _| progress(offset + done, total))?;
+        encrypted_data.insert(key, vector);
+        offset += column.num_values();
+    }
+
+    let context = EncryptionContext::new(records.len(), &encrypted_data);
+    Ok((encrypted_data, context))
+}
+
+/// Encrypts the fields of a set of biosample records, one column per thread
+///
+/// Behaves like `encrypt_biosample_data`, but encrypts the columns (age, glucose,
+/// cholesterol, marker, collection day and each blood type and facility indicator) in
+/// parallel on the rayon thread pool. The resulting map is identical to the serial one.
+///
+/// # Arguments
+///
+/// * `fhe` - The `BiosampleFHE` instance used for encryption
+/// * `records` - The biosample records to encrypt
+/// * `scales` - The scale of each numeric field
+///
+/// # Returns
+///
+/// A Result containing a map of field names to encrypted vectors together with the
+/// `EncryptionContext` describing them, or an error if a collection date is malformed or
+/// a value cannot be encrypted
+#[cfg(feature = "rayon")]
+pub fn encrypt_biosample_data_parallel(
+    fhe: &BiosampleFHE,
+    records: &[BiosampleRecord],
+    scales: &ScaleConfig,
+) -> Result<(HashMap<String, EncryptedVector>, EncryptionContext), EncryptionError> {
+    use rayon::prelude::*;
+
+    let columns = plain_columns(fhe, records, scales)?;
+    let encrypted_data = columns
+        .par_iter()
+        .map(|column| column.encrypt(fhe, &mut |_, _| {}))
+        .collect::<Result<HashMap<String, EncryptedVector>, _>>()?;
+
+    let context = EncryptionContext::new(records.len(), &encrypted_data);
+    Ok((encrypted_data, context))
+}
+
+/// One column of a biosample dataset, extracted from the records and ready to be encrypted
+enum PlainColumn {
+    /// Numeric values, encrypted under `key` after multiplying them by `scale`
+    Numeric {
+        key: &'static str,
+        values: Vec<f64>,
+        scale: f64,
+    },
+    /// Integer values, encrypted exactly under `key` with a scale of 1
+    Integer { key: &'static str, values: Vec<i64> },
+    /// Boolean values, encrypted under `key`
+    Boolean { key: String, values: Vec<bool> },
+}
+
+impl PlainColumn {
+    /// Returns the number of values in the column
+    fn num_values(&self) -> usize {
+        match self {
+            PlainColumn::Numeric { values, .. } => values.len(),
+            PlainColumn::Integer { values, .. } => values.len(),
+            PlainColumn::Boolean { values, .. } => values.len(),
+        }
+    }
+
+    /// Encrypts the column
+    ///
+    /// # Arguments
+    ///
+    /// * `fhe` - The `BiosampleFHE` instance used for encryption
+    /// * `progress` - A callback receiving the number of values encrypted so far and the total
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the key of the column together with its encrypted vector, or an
+    /// error if a numeric value cannot be scaled to an integer
+    fn encrypt(
+        &self,
+        fhe: &BiosampleFHE,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<(String, EncryptedVector), EncryptionError> {
+        Ok(match self {
+            PlainColumn::Numeric { key, values, scale } => (
+                key.to_string(),
+                fhe.encrypt_f64_vector_with_progress(values, *scale, progress)?,
+            ),
+            PlainColumn::Integer { key, values } => (
+                key.to_string(),
+                fhe.encrypt_i64_vector_with_progress(values, progress)?,
+            ),
+            PlainColumn::Boolean { key, values } => (
+                key.clone(),
+                fhe.encrypt_bool_vector_with_progress(values, progress),
+            ),
+        })
+    }
+}
+
+/// One-hot encodes a categorical field into one boolean column per category
+///
+/// Categories are sorted, matching the order of `BiosampleFHE::encrypt_categorical`.
+///
+/// # Arguments
+///
+/// * `values` - The category of each record
+/// * `key` - Builds the key of a category's column from the category
+///
+/// # Returns
+///
+/// The indicator column of each category
+fn one_hot_columns(values: &[String], key: fn(&str) -> String) -> Vec<PlainColumn> {
+    let mut categories: Vec<&String> = values
+        .iter()
+        .collect::<std::collections::HashSet<_>>()
+        .into_iter()
+        .collect();
+    categories.sort();
+
+    categories
+        .into_iter()
+        .map(|category| PlainColumn::Boolean {
+            key: key(category),
+            values: values.iter().map(|v| v == category).collect(),
+        })
+        .collect()
+}
+
+/// Extracts the columns of a set of biosample records in the order they are encrypted
+///
+/// # Arguments
+///
+/// * `fhe` - The `BiosampleFHE` instance the columns will be encrypted with
+/// * `records` - The biosample records
+/// * `scales` - The scale of each numeric field
+///
+/// # Returns
+///
+/// A Result containing the columns, or an error if a collection date is malformed or
+/// outside the range the integers of `fhe` can hold
+fn plain_columns(
+    fhe: &BiosampleFHE,
+    records: &[BiosampleRecord],
+    scales: &ScaleConfig,
+) -> Result<Vec<PlainColumn>, EncryptionError> {
+    let numeric = |key: &'static str, value: fn(&BiosampleRecord) -> f64| PlainColumn::Numeric {
+        key,
+        values: records.iter().map(value).collect(),
+        scale: scales.scale_for(key),
+    };
+
+    // Collection dates are encrypted as day ordinals, so date ranges become integer ranges
+    let max_day = fhe.parameters_info().max_value;
+    let days = records
+        .iter()
+        .map(|r| {
+            let day = collection_day(&r.collection_date)
+                .map_err(|e| EncryptionError::InvalidInput(e.to_string()))?;
+            if day < 0 || day > max_day {
+                return Err(EncryptionError::InvalidInput(format!(
+                    "Collection date {} is outside the encryptable range",
+                    r.collection_date
+                )));
+            }
+            Ok(day)
+        })
+        .collect::<Result<Vec<i64>, EncryptionError>>()?;
+
+    // Ages, day ordinals and facility ids are whole numbers, so they are encrypted exactly
+    // without scaling
+    let mut columns = vec![
+        PlainColumn::Integer {
+            key: fields::AGE,
+            values: records.iter().map(|r| r.age as i64).collect(),
+        },
+        numeric(fields::GLUCOSE, |r| r.glucose_level),
+        numeric(fields::CHOLESTEROL, |r| r.cholesterol_level),
+        PlainColumn::Boolean {
+            key: fields::MARKER.to_string(),
+            values: records.iter().map(|r| r.marker_alpha).collect(),
+        },
+        PlainColumn::Integer {
+            key: fields::COLLECTION_DAY,
+            values: days,
+        },
+        PlainColumn::Integer {
+            key: fields::FACILITY,
+            values: records.iter().map(|r| r.facility_id as i64).collect(),
+        },
+    ];
 
-    // Extract and scale the numerical data
-    let scale = 100.0; // Scale for floating-point values
-
-    // Encrypt age field
-    let ages: Vec<f64> = records.iter().map(|r| r.age as f64).collect();
-    encrypted_data.insert("age".to_string(), fhe.encrypt_f64_vector(&ages, scale));
-
-    // Encrypt glucose levels
-    let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
-    encrypted_data.insert(
-        "glucose".to_string(),
-        fhe.encrypt_f64_vector(&glucose, scale),
-    );
-
-    // Encrypt cholesterol levels
-    let cholesterol: Vec<f64> = records.iter().map(|r| r.cholesterol_level).collect();
-    encrypted_data.insert(
-        "cholesterol".to_string(),
-        fhe.encrypt_f64_vector(&cholesterol, scale),
-    );
-
-    // Encrypt marker (boolean) field
-    let marker: Vec<bool> = records.iter().map(|r| r.marker_alpha).collect();
-    encrypted_data.insert("marker".to_string(), fhe.encrypt_bool_vector(&marker));
-
-    // For categorical data, we can use the encrypt_categorical method
-    // Blood types
+    // Blood types and facilities are one-hot encoded for counts and group-by computations
     let blood_types: Vec<String> = records.iter().map(|r| r.blood_type.clone()).collect();
-    let encrypted_blood_types = fhe.encrypt_categorical(&blood_types);
+    columns.extend(one_hot_columns(&blood_types, fields::blood_type_key));
+    let facilities: Vec<String> = records.iter().map(|r| r.facility_id.to_string()).collect();
+    columns.extend(one_hot_columns(&facilities, fields::facility_key));
 
-    // Store each blood type vector seperately.
-    for (i, blood_type) in encrypted_blood_types.categories.iter().enumerate() {
-        let key = format!("blood_type_{}", blood_type);
-        encrypted_data.insert(key, encrypted_blood_types.vectors[i].clone());
-    }
+    Ok(columns)
+}
 
-    Ok(encrypted_data)
+/// Invokes a progress callback every `PROGRESS_INTERVAL` values and after the last one
+///
+/// # Arguments
+///
+/// * `done` - The number of values processed so far
+/// * `total` - The total number of values to process
+/// * `progress` - The callback to invoke
+fn report_progress(done: usize, total: usize, progress: &mut dyn FnMut(usize, usize)) {
+    if done.is_multiple_of(PROGRESS_INTERVAL) || done == total {
+        progress(done, total);
+    }
 }
 
 #[cfg(test)]
@@ -411,6 +3095,39 @@ mod tests {
         assert!(!std::ptr::addr_of!(fhe.server_key).is_null());
     }
 
+    #[cfg(feature = "deterministic-keys")]
+    #[test]
+    fn test_from_seed_is_deterministic() {
+        let seed = [7u8; 32];
+        let test_values = vec![1.5, -2.25, 30.0];
+        let scale = 100.0;
+
+        let fhe1 = BiosampleFHE::from_seed(seed);
+        let encrypted1 = fhe1.encrypt_f64_vector(&test_values, scale).unwrap();
+        let fhe2 = BiosampleFHE::from_seed(seed);
+        let encrypted2 = fhe2.encrypt_f64_vector(&test_values, scale).unwrap();
+
+        // The same seed reproduces the same ciphertext bytes
+        assert_eq!(encrypted1.data, encrypted2.data);
+
+        // Each instance decrypts the other's ciphertexts
+        for decrypted in [
+            fhe1.decrypt_f64_vector(&encrypted2, scale),
+            fhe2.decrypt_f64_vector(&encrypted1, scale),
+        ] {
+            for (original, decrypted_val) in test_values.iter().zip(decrypted.iter()) {
+                assert!((original - decrypted_val).abs() < 0.01);
+            }
+        }
+
+        // A different seed gives a different key
+        let other = BiosampleFHE::from_seed([8u8; 32]);
+        assert_ne!(
+            other.encrypt_f64_vector(&test_values, scale).unwrap().data,
+            encrypted1.data
+        );
+    }
+
     #[test]
     fn test_encrypt_decrypt_f64_vector() {
         let fhe = BiosampleFHE::new();
@@ -418,7 +3135,7 @@ mod tests {
         let scale = 100.0;
 
         // Encrypt the values
-        let encrypted = fhe.encrypt_f64_vector(&test_values, scale);
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
         
         // Verify encrypted vector structure
         assert_eq!(encrypted.length, test_values.len());
@@ -435,13 +3152,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_encrypt_f64_vector_with_progress() {
+        let fhe = BiosampleFHE::new();
+        let test_values: Vec<f64> = (0..250).map(|i| i as f64 / 10.0).collect();
+        let scale = 100.0;
+
+        // Record every (done, total) pair the callback receives
+        let mut calls: Vec<(usize, usize)> = Vec::new();
+        let encrypted = fhe
+            .encrypt_f64_vector_with_progress(&test_values, scale, &mut |done, total| {
+                calls.push((done, total))
+            })
+            .unwrap();
+
+        // Invoked at every PROGRESS_INTERVAL values and once at the end
+        assert_eq!(calls, vec![(100, 250), (200, 250), (250, 250)]);
+        assert_eq!(calls.last(), Some(&(test_values.len(), test_values.len())));
+
+        // The result is the same as without a callback
+        let decrypted = fhe.decrypt_f64_vector(&encrypted, scale);
+        for (original, decrypted_val) in test_values.iter().zip(decrypted.iter()) {
+            assert!((original - decrypted_val).abs() < 0.01);
+        }
+    }
+
     #[test]
     fn test_encrypt_decrypt_f64_vector_empty() {
         let fhe = BiosampleFHE::new();
         let test_values: Vec<f64> = vec![];
         let scale = 100.0;
 
-        let encrypted = fhe.encrypt_f64_vector(&test_values, scale);
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
         assert_eq!(encrypted.length, 0);
         assert_eq!(encrypted.data.len(), 0);
 
@@ -449,13 +3191,66 @@ mod tests {
         assert_eq!(decrypted.len(), 0);
     }
 
+    #[test]
+    fn test_encrypt_f64_vector_rejects_non_finite_values() {
+        let fhe = BiosampleFHE::new();
+
+        // 1e6 is finite but wraps around a 16-bit integer once scaled by 100
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1e300, 1e6, -1e6] {
+            let error = fhe
+                .encrypt_f64_vector(&[95.5, 120.0, value], 100.0)
+                .err()
+                .unwrap();
+            assert!(error.to_string().contains("index 2"), "{}", error);
+        }
+        assert!(fhe.encrypt_f64_vector(&[1.0], f64::NAN).is_err());
+
+        // The bounds of the encrypted integer range are still accepted
+        let max_value = fhe.parameters_info().max_value;
+        let bounds = [max_value as f64, (-max_value - 1) as f64];
+        let encrypted = fhe.encrypt_f64_vector(&bounds, 1.0).unwrap();
+        assert_eq!(fhe.decrypt_f64_vector(&encrypted, 1.0), bounds.to_vec());
+        assert!(fhe
+            .encrypt_f64_vector(&[max_value as f64 + 1.0], 1.0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_i64_vector_keeps_ages_exact() {
+        let fhe = BiosampleFHE::new();
+        let records = create_test_records();
+        let ages: Vec<i64> = records.iter().map(|r| r.age as i64).collect();
+
+        let encrypted = fhe.encrypt_i64_vector(&ages).unwrap();
+        assert_eq!(encrypted.length, ages.len());
+        assert_eq!(encrypted.scale, 1.0);
+        assert_eq!(fhe.decrypt_i64_vector(&encrypted), ages);
+
+        // Values outside the ciphertext range are rejected rather than wrapped
+        let max_value = fhe.parameters_info().max_value;
+        let bounds = [-max_value - 1, max_value];
+        let encrypted_bounds = fhe.encrypt_i64_vector(&bounds).unwrap();
+        assert_eq!(fhe.decrypt_i64_vector(&encrypted_bounds), bounds.to_vec());
+        for value in [max_value + 1, -max_value - 2] {
+            let error = fhe.encrypt_i64_vector(&[0, value]).err().unwrap();
+            assert!(error.to_string().contains("index 1"), "{}", error);
+        }
+
+        // encrypt_biosample_data takes the integer path for ages whatever the configured scale
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::new(100.0)).unwrap();
+        assert_eq!(context.scale_for(fields::AGE).unwrap(), 1.0);
+        assert_eq!(fhe.decrypt_i64_vector(&encrypted_data[fields::AGE]), ages);
+        assert_eq!(encrypted_data[fields::GLUCOSE].scale, 100.0);
+    }
+
     #[test]
     fn test_encrypt_decrypt_f64_vector_large_values() {
         let fhe = BiosampleFHE::new();
         let test_values = vec![1000.0, -500.0, 999.99];
         let scale = 10.0;
 
-        let encrypted = fhe.encrypt_f64_vector(&test_values, scale);
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
         let decrypted = fhe.decrypt_f64_vector(&encrypted, scale);
         
         for (original, decrypted_val) in test_values.iter().zip(decrypted.iter()) {
@@ -464,6 +3259,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_decrypt_element_matches_full_decryption() {
+        let fhe = BiosampleFHE::new();
+        let test_values = vec![1.5, -2.25, 3.0, 0.75];
+        let scale = 100.0;
+
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
+        let decrypted = fhe.decrypt_f64_vector(&encrypted, scale);
+
+        for (index, &value) in decrypted.iter().enumerate() {
+            let element = fhe.decrypt_element(&encrypted, index, scale).unwrap();
+            assert_eq!(element, value);
+        }
+        let range = fhe.decrypt_range(&encrypted, 1..3, scale).unwrap();
+        assert_eq!(range, decrypted[1..3]);
+        let empty = fhe.decrypt_range(&encrypted, 2..2, scale).unwrap();
+        assert!(empty.is_empty());
+
+        let flags = vec![true, false];
+        let encrypted_flags = fhe.encrypt_bool_vector(&flags);
+        assert!(fhe.decrypt_bool_element(&encrypted_flags, 0).unwrap());
+        assert!(!fhe.decrypt_bool_element(&encrypted_flags, 1).unwrap());
+    }
+
+    #[test]
+    fn test_decrypt_element_out_of_bounds() {
+        let fhe = BiosampleFHE::new();
+        let encrypted = fhe.encrypt_f64_vector(&[1.0, 2.0], 100.0).unwrap();
+
+        assert!(matches!(
+            fhe.decrypt_element(&encrypted, 2, 100.0),
+            Err(EncryptionError::IndexOutOfBounds {
+                index: 2,
+                length: 2
+            })
+        ));
+        assert!(fhe.decrypt_bool_element(&encrypted, 5).is_err());
+        assert!(fhe.decrypt_range(&encrypted, 1..3, 100.0).is_err());
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 2..1;
+        assert!(fhe.decrypt_range(&encrypted, reversed, 100.0).is_err());
+    }
+
     #[test]
     fn test_encrypt_decrypt_bool_vector() {
         let fhe = BiosampleFHE::new();
@@ -477,7 +3315,7 @@ mod tests {
         assert_eq!(encrypted.data.len(), test_values.len());
 
         // Decrypt the values
-        let decrypted = fhe.decrypt_bool_vector(&encrypted);
+        let decrypted = fhe.decrypt_bool_vector(&encrypted).unwrap();
         
         // Verify decrypted values match original
         assert_eq!(decrypted, test_values);
@@ -492,7 +3330,7 @@ mod tests {
         assert_eq!(encrypted.length, 0);
         assert_eq!(encrypted.data.len(), 0);
 
-        let decrypted = fhe.decrypt_bool_vector(&encrypted);
+        let decrypted = fhe.decrypt_bool_vector(&encrypted).unwrap();
         assert_eq!(decrypted.len(), 0);
     }
 
@@ -502,7 +3340,7 @@ mod tests {
         let test_values = vec![true; 5];
 
         let encrypted = fhe.encrypt_bool_vector(&test_values);
-        let decrypted = fhe.decrypt_bool_vector(&encrypted);
+        let decrypted = fhe.decrypt_bool_vector(&encrypted).unwrap();
         
         assert_eq!(decrypted, test_values);
     }
@@ -513,11 +3351,43 @@ mod tests {
         let test_values = vec![false; 5];
 
         let encrypted = fhe.encrypt_bool_vector(&test_values);
-        let decrypted = fhe.decrypt_bool_vector(&encrypted);
+        let decrypted = fhe.decrypt_bool_vector(&encrypted).unwrap();
         
         assert_eq!(decrypted, test_values);
     }
 
+    #[test]
+    fn test_encrypt_bool_vector_is_compact() {
+        let fhe = BiosampleFHE::new();
+        let test_values = vec![true, false, true, false];
+
+        let encrypted = fhe.encrypt_bool_vector(&test_values);
+        assert_eq!(encrypted.kind, VectorKind::Boolean);
+
+        // The same 0/1 values encrypted at the full integer width
+        let numeric: Vec<f64> = test_values
+            .iter()
+            .map(|&v| if v { 1.0 } else { 0.0 })
+            .collect();
+        let full_width = fhe.encrypt_f64_vector(&numeric, 1.0).unwrap();
+        assert_eq!(full_width.kind, VectorKind::Numeric);
+
+        let bool_size: usize = encrypted.data.iter().map(|d| d.len()).sum();
+        let full_size: usize = full_width.data.iter().map(|d| d.len()).sum();
+        assert!(
+            bool_size * 4 < full_size,
+            "Boolean vector is {} bytes, full width is {} bytes",
+            bool_size,
+            full_size
+        );
+
+        // The kind tag survives serialization
+        let serialized = serde_json::to_string(&encrypted).unwrap();
+        let deserialized: EncryptedVector = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.kind, VectorKind::Boolean);
+        assert_eq!(fhe.decrypt_bool_vector(&deserialized).unwrap(), test_values);
+    }
+
     #[test]
     fn test_encrypt_categorical() {
         let fhe = BiosampleFHE::new();
@@ -585,12 +3455,96 @@ mod tests {
             .position(|x| x == "Type1").unwrap();
         
         // Decrypt the corresponding vector
-        let type1_vector = fhe.decrypt_bool_vector(&encrypted_categorical.vectors[type1_index]);
+        let type1_vector = fhe
+            .decrypt_bool_vector(&encrypted_categorical.vectors[type1_index])
+            .unwrap();
         
         // Should be [true, false, true] for "Type1"
         assert_eq!(type1_vector, vec![true, false, true]);
     }
 
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_encrypted_categorical_save_load_roundtrip() {
+        let fhe = BiosampleFHE::new();
+        let test_values = vec!["A+".to_string(), "O-".to_string(), "A+".to_string()];
+        let encrypted_categorical = fhe.encrypt_categorical(&test_values);
+
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path().join("blood_type");
+        encrypted_categorical.save(&dir).unwrap();
+        let loaded = EncryptedCategorical::load(&dir).unwrap();
+
+        assert_eq!(loaded.categories, encrypted_categorical.categories);
+        for (category, vector) in loaded.categories.iter().zip(loaded.vectors.iter()) {
+            let expected: Vec<bool> = test_values.iter().map(|v| v == category).collect();
+            assert_eq!(fhe.decrypt_bool_vector(vector).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_grouped_blood_types_roundtrip_through_a_file() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(8, 42).unwrap();
+        let (encrypted_data, blood_types, context) =
+            encrypt_biosample_data_grouped(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+        // The blood types are no longer flattened into the map
+        assert!(!encrypted_data
+            .keys()
+            .any(|key| key.starts_with(fields::BLOOD_TYPE_PREFIX)));
+        assert!(context.field(fields::AGE).is_ok());
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("blood_types.bin");
+        save_categorical(&blood_types, &path).unwrap();
+        let loaded = load_categorical(&path).unwrap();
+
+        let mut expected_categories: Vec<String> =
+            records.iter().map(|r| r.blood_type.clone()).collect();
+        expected_categories.sort();
+        expected_categories.dedup();
+        assert_eq!(loaded.categories, expected_categories);
+
+        // Each record is flagged in exactly the vector of its own blood type
+        for (category, vector) in loaded.categories.iter().zip(loaded.vectors.iter()) {
+            let expected: Vec<bool> = records.iter().map(|r| &r.blood_type == category).collect();
+            assert_eq!(fhe.decrypt_bool_vector(vector).unwrap(), expected);
+        }
+
+        // A file whose vectors do not match its categories is rejected
+        let mut truncated = loaded.clone();
+        truncated.vectors.pop();
+        save_categorical(&truncated, &path).unwrap();
+        assert!(load_categorical(&path).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_encrypted_categorical_load_rejects_category_count_mismatch() {
+        let fhe = BiosampleFHE::new();
+        let test_values = vec!["A+".to_string(), "O-".to_string()];
+        let temp_dir = tempdir().unwrap();
+        fhe.encrypt_categorical(&test_values)
+            .save(temp_dir.path())
+            .unwrap();
+
+        // List a third category that has no vector
+        let manifest_path = temp_dir.path().join(CATEGORICAL_MANIFEST_FILE);
+        let mut manifest: CategoricalManifest =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        manifest.categories.push("B+".to_string());
+        manifest.vector_lengths.push(test_values.len());
+        std::fs::write(&manifest_path, serde_json::to_string(&manifest).unwrap()).unwrap();
+
+        let error = EncryptedCategorical::load(temp_dir.path()).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Manifest lists 3 categories but the directory contains 2 vectors"
+        );
+    }
+
     #[test]
     fn test_server_key_access() {
         let fhe = BiosampleFHE::new();
@@ -601,6 +3555,91 @@ mod tests {
     }
 
     #[test]
+    fn test_with_params_roundtrip() {
+        let params = PARAM_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64;
+        let fhe = BiosampleFHE::with_params(params).unwrap();
+        assert_eq!(fhe.parameters(), params.into());
+
+        let test_values = vec![1.5, -2.25, 3.0];
+        let scale = 100.0;
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
+        assert_eq!(fhe.decrypt_f64_vector(&encrypted, scale), test_values);
+
+        // The parameters travel with the serialized client key
+        let restored = BiosampleFHE::from_key_bytes(
+            &fhe.client_key_to_bytes().unwrap(),
+            &fhe.server_key_to_bytes().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(restored.parameters(), params.into());
+        assert_eq!(restored.decrypt_f64_vector(&encrypted, scale), test_values);
+    }
+
+    #[test]
+    fn test_parameters_info() {
+        let info = BiosampleFHE::new().parameters_info();
+        assert_eq!(info.message_modulus, 4);
+        assert_eq!(info.carry_modulus, 4);
+        assert_eq!(info.num_blocks, FHE_INT_BITS);
+        assert_eq!(info.max_value, i16::MAX as i64);
+        assert_eq!(info.security_level, "128-bit, failure probability 2^-128");
+
+        // A full-range value leaves no room for a second one
+        assert_eq!(info.max_additions, 1);
+        assert_eq!(info.max_additions_for(100), 327);
+        assert_eq!(info.max_additions_for(-100), 327);
+        assert_eq!(info.max_additions_for(0), usize::MAX);
+
+        let params = PARAM_MESSAGE_2_CARRY_2_KS_PBS_TUNIFORM_2M64;
+        let info = BiosampleFHE::with_params(params).unwrap().parameters_info();
+        assert_eq!(info.security_level, "128-bit, failure probability 2^-64");
+    }
+
+    #[test]
+    fn test_with_params_rejects_unsupported_block_size() {
+        let params = tfhe::shortint::parameters::PARAM_MESSAGE_3_CARRY_3_KS_PBS_GAUSSIAN_2M128;
+        assert!(BiosampleFHE::with_params(params).is_err());
+    }
+
+    #[test]
+    fn test_key_bytes_roundtrip() {
+        let fhe = BiosampleFHE::new();
+        let test_values = vec![1.5, -2.25, 3.0];
+        let scale = 100.0;
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
+
+        let client_key_bytes: Vec<u8> = fhe.client_key_to_bytes().unwrap();
+        let server_key_bytes: Vec<u8> = fhe.server_key_to_bytes().unwrap();
+        let restored = BiosampleFHE::from_key_bytes(&client_key_bytes, &server_key_bytes).unwrap();
+
+        // The restored client key decrypts what the original encrypted
+        assert_eq!(restored.decrypt_f64_vector(&encrypted, scale), test_values);
+
+        // The restored server key computes on the original ciphertexts
+        let ciphertexts: Vec<SignedRadixCiphertext> = encrypted
+            .data
+            .iter()
+            .map(|data| bincode::deserialize(data).unwrap())
+            .collect();
+        let sum = restored
+            .server_key()
+            .add_parallelized(&ciphertexts[0], &ciphertexts[2]);
+        let sum =
+            EncryptedVector::new(vec![bincode::serialize(&sum).unwrap()], VectorKind::Numeric);
+        assert_eq!(fhe.decrypt_f64_vector(&sum, scale), vec![4.5]);
+    }
+
+    #[test]
+    fn test_from_key_bytes_rejects_invalid_bytes() {
+        let fhe = BiosampleFHE::new();
+        let client_key_bytes = fhe.client_key_to_bytes().unwrap();
+
+        assert!(BiosampleFHE::from_key_bytes(&[1, 2, 3], &[4, 5, 6]).is_err());
+        assert!(BiosampleFHE::from_key_bytes(&client_key_bytes, &client_key_bytes).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
     fn test_save_and_load_keys() {
         let fhe = BiosampleFHE::new();
         
@@ -627,7 +3666,7 @@ mod tests {
         let test_values = vec![1.0, 2.0, 3.0];
         let scale = 100.0;
         
-        let encrypted = loaded_fhe.encrypt_f64_vector(&test_values, scale);
+        let encrypted = loaded_fhe.encrypt_f64_vector(&test_values, scale).unwrap();
         let decrypted = loaded_fhe.decrypt_f64_vector(&encrypted, scale);
         
         for (original, decrypted_val) in test_values.iter().zip(decrypted.iter()) {
@@ -636,6 +3675,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "native")]
     fn test_save_keys_invalid_path() {
         let fhe = BiosampleFHE::new();
         
@@ -647,6 +3687,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "native")]
     fn test_load_keys_nonexistent_files() {
         let nonexistent_path = Path::new("nonexistent_key.bin");
         let result = BiosampleFHE::load_keys(nonexistent_path, nonexistent_path);
@@ -659,10 +3700,10 @@ mod tests {
         let fhe = BiosampleFHE::new();
         let test_records = create_test_records();
         
-        let encrypted_result = encrypt_biosample_data(&fhe, &test_records);
+        let encrypted_result = encrypt_biosample_data(&fhe, &test_records, &ScaleConfig::default());
         assert!(encrypted_result.is_ok());
         
-        let encrypted_data = encrypted_result.unwrap();
+        let (encrypted_data, _) = encrypted_result.unwrap();
         
         // Verify all expected fields are present
         assert!(encrypted_data.contains_key("age"));
@@ -687,15 +3728,40 @@ mod tests {
         assert_eq!(encrypted_data["marker"].length, test_records.len());
     }
 
+    #[test]
+    fn test_encrypt_biosample_data_with_progress() {
+        let fhe = BiosampleFHE::new();
+        let test_records = create_test_records();
+
+        let mut invocations = 0;
+        let mut last_call = (0, 0);
+        encrypt_biosample_data_with_progress(
+            &fhe,
+            &test_records,
+            &ScaleConfig::default(),
+            &mut |done, total| {
+                invocations += 1;
+                last_call = (done, total);
+            },
+        )
+        .unwrap();
+
+        // Five fields, three distinct blood types and two facilities, each finishing with
+        // its own report
+        let total = test_records.len() * (5 + 3 + 2);
+        assert_eq!(invocations, 5 + 3 + 2);
+        assert_eq!(last_call, (total, total));
+    }
+
     #[test]
     fn test_encrypt_biosample_data_empty() {
         let fhe = BiosampleFHE::new();
         let test_records: Vec<BiosampleRecord> = vec![];
         
-        let encrypted_result = encrypt_biosample_data(&fhe, &test_records);
+        let encrypted_result = encrypt_biosample_data(&fhe, &test_records, &ScaleConfig::default());
         assert!(encrypted_result.is_ok());
         
-        let encrypted_data = encrypted_result.unwrap();
+        let (encrypted_data, _) = encrypted_result.unwrap();
         
         // Should still have the basic fields, but with zero length
         assert!(encrypted_data.contains_key("age"));
@@ -709,22 +3775,48 @@ mod tests {
         assert_eq!(encrypted_data["marker"].length, 0);
     }
 
+    #[test]
+    fn test_sorted_encrypted_data_has_a_stable_layout() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(4, 21).unwrap();
+        let (first, first_context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+        let (second, second_context) =
+            encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+        // Ciphertexts are randomized, so compare everything but their bytes
+        let layout = |data: &HashMap<String, EncryptedVector>| -> Vec<(String, usize, VectorKind)> {
+            to_sorted_vec(data)
+                .into_iter()
+                .map(|(field, vector)| (field.to_string(), vector.length, vector.kind))
+                .collect()
+        };
+        let first_layout = layout(&first);
+        assert_eq!(first_layout, layout(&second));
+        assert!(first_layout.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(first_layout.len(), first.len());
+
+        // The context holds no ciphertexts, so its sorted fields serialize identically
+        assert_eq!(
+            bincode::serialize(&to_sorted_vec(&first_context.fields)).unwrap(),
+            bincode::serialize(&to_sorted_vec(&second_context.fields)).unwrap()
+        );
+    }
+
     #[test]
     fn test_encrypt_biosample_data_roundtrip() {
         let fhe = BiosampleFHE::new();
         let test_records = create_test_records();
         
         // Encrypt the data
-        let encrypted_data = encrypt_biosample_data(&fhe, &test_records).unwrap();
+        let (encrypted_data, _) =
+            encrypt_biosample_data(&fhe, &test_records, &ScaleConfig::default()).unwrap();
         
-        // Decrypt and verify age data
+        // Decrypt and verify age data, which is encrypted as exact integers
         let scale = 100.0;
-        let decrypted_ages = fhe.decrypt_f64_vector(&encrypted_data["age"], scale);
-        let expected_ages: Vec<f64> = test_records.iter().map(|r| r.age as f64).collect();
-        
-        for (expected, actual) in expected_ages.iter().zip(decrypted_ages.iter()) {
-            assert!((expected - actual).abs() < 0.01);
-        }
+        let decrypted_ages = fhe.decrypt_i64_vector(&encrypted_data["age"]);
+        let expected_ages: Vec<i64> = test_records.iter().map(|r| r.age as i64).collect();
+        assert_eq!(decrypted_ages, expected_ages);
         
         // Decrypt and verify glucose data
         let decrypted_glucose = fhe.decrypt_f64_vector(&encrypted_data["glucose"], scale);
@@ -735,12 +3827,112 @@ mod tests {
         }
         
         // Decrypt and verify marker data
-        let decrypted_marker = fhe.decrypt_bool_vector(&encrypted_data["marker"]);
+        let decrypted_marker = fhe.decrypt_bool_vector(&encrypted_data["marker"]).unwrap();
         let expected_marker: Vec<bool> = test_records.iter().map(|r| r.marker_alpha).collect();
         
         assert_eq!(decrypted_marker, expected_marker);
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_encryption_matches_serial() {
+        let fhe = BiosampleFHE::new();
+        let test_records = create_test_records();
+        let scales = ScaleConfig::default();
+
+        let (serial, serial_context) =
+            encrypt_biosample_data_with_progress(&fhe, &test_records, &scales, &mut |_, _| {})
+                .unwrap();
+        let (parallel, parallel_context) =
+            encrypt_biosample_data_parallel(&fhe, &test_records, &scales).unwrap();
+
+        // Ciphertexts are randomized, so compare the maps by what they decrypt to
+        assert_eq!(serial_context, parallel_context);
+        let mut keys: Vec<&String> = serial.keys().collect();
+        keys.sort();
+        let mut parallel_keys: Vec<&String> = parallel.keys().collect();
+        parallel_keys.sort();
+        assert_eq!(keys, parallel_keys);
+
+        for key in keys {
+            let (expected, actual) = (&serial[key], &parallel[key]);
+            assert_eq!(expected.length, actual.length, "{}", key);
+            assert_eq!(expected.kind, actual.kind, "{}", key);
+            assert_eq!(expected.scale, actual.scale, "{}", key);
+            match expected.kind {
+                VectorKind::Boolean => assert_eq!(
+                    fhe.decrypt_bool_vector(expected).unwrap(),
+                    fhe.decrypt_bool_vector(actual).unwrap(),
+                    "{}",
+                    key
+                ),
+                _ => assert_eq!(
+                    fhe.decrypt_f64_vector(expected, expected.scale),
+                    fhe.decrypt_f64_vector(actual, actual.scale),
+                    "{}",
+                    key
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_encrypt_biosample_data_with_scale_config() {
+        let fhe = BiosampleFHE::new();
+        let test_records = create_test_records();
+        let scales = ScaleConfig::new(10.0).with_scale(fields::AGE, 1.0);
+
+        let (encrypted_data, context) =
+            encrypt_biosample_data(&fhe, &test_records, &scales).unwrap();
+
+        // Each numeric vector records the scale it was encrypted with
+        assert_eq!(encrypted_data[fields::AGE].scale, 1.0);
+        assert_eq!(encrypted_data[fields::GLUCOSE].scale, 10.0);
+        assert_eq!(encrypted_data[fields::CHOLESTEROL].scale, 10.0);
+
+        // And so does the context, together with the lengths
+        assert_eq!(context.record_count, test_records.len());
+        assert_eq!(context.fields.len(), encrypted_data.len());
+        assert_eq!(context.scale_for(fields::AGE).unwrap(), 1.0);
+        let glucose_info = context.field(fields::GLUCOSE).unwrap();
+        assert_eq!(glucose_info.length, test_records.len());
+        assert!(context.field("unknown").is_err());
+
+        // Decrypting with the recorded scale recovers the values to the scale's precision
+        let age = &encrypted_data[fields::AGE];
+        let decrypted_ages = fhe.decrypt_f64_vector(age, age.scale);
+        let expected_ages: Vec<f64> = test_records.iter().map(|r| r.age as f64).collect();
+        assert_eq!(decrypted_ages, expected_ages);
+
+        let glucose = &encrypted_data[fields::GLUCOSE];
+        let decrypted_glucose = fhe.decrypt_f64_vector(glucose, glucose.scale);
+        for (record, actual) in test_records.iter().zip(decrypted_glucose.iter()) {
+            assert!((record.glucose_level - actual).abs() <= 0.05);
+        }
+    }
+
+    #[test]
+    fn test_decrypt_mean_with_context() {
+        let fhe = BiosampleFHE::new();
+        let ages = vec![30.0, 45.0, 51.0];
+        let encrypted_data = HashMap::from([(
+            fields::AGE.to_string(),
+            fhe.encrypt_f64_vector(&ages, 1.0).unwrap(),
+        )]);
+        let context = EncryptionContext::new(ages.len(), &encrypted_data);
+
+        // The mean of all ages, with the extra precision a computation would give it
+        let mean = fhe.encrypt_f64_vector(&[42.0], 100.0).unwrap();
+        assert_eq!(
+            fhe.decrypt_mean(&mean, fields::AGE, &context).unwrap(),
+            42.0
+        );
+        assert!(matches!(
+            fhe.decrypt_mean(&mean, fields::GLUCOSE, &context),
+            Err(EncryptionError::MissingField(field)) if field == fields::GLUCOSE
+        ));
+    }
+
     #[test]
     fn test_encrypt_biosample_data_with_generated_data() {
         let fhe = BiosampleFHE::new();
@@ -748,10 +3940,11 @@ mod tests {
         // Generate test data using the data generator
         let generated_records = generate_biosample_data(10, 12345).unwrap();
         
-        let encrypted_result = encrypt_biosample_data(&fhe, &generated_records);
+        let encrypted_result =
+            encrypt_biosample_data(&fhe, &generated_records, &ScaleConfig::default());
         assert!(encrypted_result.is_ok());
         
-        let encrypted_data = encrypted_result.unwrap();
+        let (encrypted_data, _) = encrypted_result.unwrap();
         
         // Verify all vectors have the correct length
         for (key, vector) in &encrypted_data {
@@ -766,7 +3959,7 @@ mod tests {
         let test_values = vec![1.0, 2.0, 3.0];
         let scale = 100.0;
         
-        let encrypted = fhe.encrypt_f64_vector(&test_values, scale);
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
         
         // Test that EncryptedVector can be serialized and deserialized
         let serialized = serde_json::to_string(&encrypted).unwrap();
@@ -782,6 +3975,123 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_verify_integrity_detects_flipped_byte() {
+        let fhe = BiosampleFHE::new();
+        let encrypted = fhe.encrypt_f64_vector(&[1.0, 2.0, 3.0], 100.0).unwrap();
+        assert!(encrypted.verify_integrity());
+
+        // Flip a byte in the middle of the serialized vector, inside the ciphertext payload
+        let mut bytes = encrypted.to_bytes().unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+
+        let corrupted: EncryptedVector = bincode::deserialize(&bytes).unwrap();
+        assert!(!corrupted.verify_integrity());
+        assert!(EncryptedVector::from_bytes_checked(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_try_decrypt_rejects_corrupted_ciphertext() {
+        let fhe = BiosampleFHE::new();
+        let mut encrypted = fhe.encrypt_f64_vector(&[1.0, 2.0, 3.0], 100.0).unwrap();
+        assert_eq!(
+            fhe.try_decrypt_f64_vector(&encrypted, 100.0).unwrap(),
+            vec![1.0, 2.0, 3.0]
+        );
+
+        // A truncated ciphertext can no longer be deserialized
+        encrypted.data[1].truncate(8);
+        assert!(fhe.try_decrypt_f64_vector(&encrypted, 100.0).is_err());
+        assert!(fhe.decrypt_bool_vector(&encrypted).is_err());
+    }
+
+    #[test]
+    fn test_decrypting_with_another_key_is_a_key_mismatch() {
+        let fhe = BiosampleFHE::new();
+        let other = BiosampleFHE::new();
+        assert_ne!(fhe.key_fingerprint(), other.key_fingerprint());
+
+        let encrypted = fhe.encrypt_f64_vector(&[1.0, 2.0, 3.0], 100.0).unwrap();
+        assert_eq!(encrypted.key_fingerprint, Some(fhe.key_fingerprint()));
+        assert!(matches!(
+            other.try_decrypt_f64_vector(&encrypted, 100.0),
+            Err(EncryptionError::KeyMismatch(_))
+        ));
+        assert!(matches!(
+            other.decrypt_element(&encrypted, 0, 100.0),
+            Err(EncryptionError::KeyMismatch(_))
+        ));
+        let markers = fhe.encrypt_bool_vector(&[true, false]);
+        assert!(matches!(
+            other.decrypt_bool_vector(&markers),
+            Err(EncryptionError::KeyMismatch(_))
+        ));
+        let ages = fhe.encrypt_i64_vector(&[42, 57]).unwrap();
+        assert!(matches!(
+            other.try_decrypt_i64_vector(&ages),
+            Err(EncryptionError::KeyMismatch(_))
+        ));
+        let record = generate_biosample_data(1, 7).unwrap().remove(0);
+        assert!(matches!(
+            other.decrypt_record(&fhe.encrypt_record(&record).unwrap()),
+            Err(EncryptionError::KeyMismatch(_))
+        ));
+
+        // The fingerprint survives serialization and belongs to the key, not the instance
+        let restored = EncryptedVector::from_bytes_checked(&encrypted.to_bytes().unwrap()).unwrap();
+        let reloaded = BiosampleFHE::from_key_bytes(
+            &fhe.client_key_to_bytes().unwrap(),
+            &fhe.server_key_to_bytes().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(reloaded.key_fingerprint(), fhe.key_fingerprint());
+        assert_eq!(
+            reloaded.try_decrypt_f64_vector(&restored, 100.0).unwrap(),
+            vec![1.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_checked_roundtrip() {
+        let fhe = BiosampleFHE::new();
+        let test_values = vec![true, false, true];
+        let encrypted = fhe.encrypt_bool_vector(&test_values);
+
+        let bytes = encrypted.to_bytes().unwrap();
+        let restored = EncryptedVector::from_bytes_checked(&bytes).unwrap();
+        assert_eq!(restored.checksum, encrypted.checksum);
+        assert_eq!(fhe.decrypt_bool_vector(&restored).unwrap(), test_values);
+    }
+
+    #[test]
+    fn test_encrypted_record_roundtrips_through_serde() {
+        let fhe = BiosampleFHE::new();
+        let record = generate_biosample_data(1, 7).unwrap().remove(0);
+
+        let encrypted = fhe.encrypt_record(&record).unwrap();
+        let bytes = bincode::serialize(&encrypted).unwrap();
+        let restored: EncryptedRecord = bincode::deserialize(&bytes).unwrap();
+        let decrypted = fhe.decrypt_record(&restored).unwrap();
+
+        assert_eq!(decrypted.patient_id, record.patient_id);
+        assert_eq!(decrypted.age, record.age);
+        assert_eq!(decrypted.gender, record.gender);
+        assert_eq!(decrypted.blood_type, record.blood_type);
+        assert!((decrypted.glucose_level - record.glucose_level).abs() < 0.01);
+        assert!((decrypted.cholesterol_level - record.cholesterol_level).abs() < 0.01);
+        assert_eq!(decrypted.marker_alpha, record.marker_alpha);
+        assert_eq!(decrypted.collection_date, record.collection_date);
+        assert_eq!(decrypted.facility_id, record.facility_id);
+
+        let mut malformed = record;
+        malformed.collection_date = "2023/01/02".to_string();
+        assert!(matches!(
+            fhe.encrypt_record(&malformed),
+            Err(EncryptionError::InvalidInput(_))
+        ));
+    }
+
     #[test]
     fn test_encrypted_categorical_serialization() {
         let fhe = BiosampleFHE::new();
@@ -803,6 +4113,106 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_vector_roundtrip() {
+        let fhe = BiosampleFHE::new();
+        let records = generate_biosample_data(20, 42).unwrap();
+        let glucose: Vec<f64> = records.iter().map(|r| r.glucose_level).collect();
+        let scale = 100.0;
+
+        let encrypted = fhe.encrypt_f64_vector(&glucose, scale).unwrap();
+        let compressed = encrypted.compress(3).unwrap();
+        assert!(compressed.compressed);
+        assert_eq!(compressed.length, encrypted.length);
+
+        // Report how much the payload shrank
+        let original_size: usize = encrypted.data.iter().map(|d| d.len()).sum();
+        let compressed_size: usize = compressed.data.iter().map(|d| d.len()).sum();
+        println!(
+            "Compressed {} bytes to {} bytes (ratio {:.2})",
+            original_size,
+            compressed_size,
+            original_size as f64 / compressed_size as f64
+        );
+
+        // The compression flag must survive serialization so readers auto-detect it
+        let serialized = serde_json::to_string(&compressed).unwrap();
+        let deserialized: EncryptedVector = serde_json::from_str(&serialized).unwrap();
+        assert!(deserialized.compressed);
+
+        // Compressed vectors decrypt identically to the originals
+        let decrypted_original = fhe.decrypt_f64_vector(&encrypted, scale);
+        let decrypted_compressed = fhe.decrypt_f64_vector(&deserialized, scale);
+        assert_eq!(decrypted_original, decrypted_compressed);
+
+        // Decompression restores the original payloads
+        let decompressed = compressed.decompress().unwrap();
+        assert!(!decompressed.compressed);
+        assert_eq!(decompressed.data, encrypted.data);
+    }
+
+    #[test]
+    fn test_encrypt_f64_vector_chunked_roundtrip() {
+        let fhe = BiosampleFHE::new();
+        let test_values = vec![1.5, -2.25, 3.0, 40.75, 0.0];
+        let scale = 100.0;
+
+        // Stream the values through an in-memory sink two at a time
+        let mut sink: Vec<u8> = Vec::new();
+        let written = fhe
+            .encrypt_f64_vector_chunked(&test_values, scale, 2, &mut sink)
+            .unwrap();
+        assert_eq!(written, test_values.len());
+
+        // Reading the stream back in chunks should yield every value in order
+        let mut reader = EncryptedVectorReader::new(sink.as_slice());
+        let mut decrypted = Vec::new();
+        while let Some(chunk) = reader.read_chunk(2).unwrap() {
+            assert!(chunk.length <= 2);
+            assert_eq!(chunk.scale, scale);
+            assert_eq!(chunk.key_fingerprint, Some(fhe.key_fingerprint()));
+            decrypted.extend(fhe.decrypt_f64_vector(&chunk, scale));
+        }
+
+        assert_eq!(decrypted.len(), test_values.len());
+        for (original, decrypted_val) in test_values.iter().zip(decrypted.iter()) {
+            assert!((original - decrypted_val).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_read_chunk_rejects_truncated_frames() {
+        // A length prefix far beyond the stream is not allocated up front
+        let mut stream = (u64::MAX - 1).to_le_bytes().to_vec();
+        stream.extend([1, 2, 3]);
+        let mut reader = EncryptedVectorReader::new(stream.as_slice());
+        assert!(matches!(
+            reader.read_chunk(2),
+            Err(EncryptionError::Integrity(_))
+        ));
+
+        let fhe = BiosampleFHE::new();
+        let mut sink: Vec<u8> = Vec::new();
+        fhe.encrypt_f64_vector_chunked(&[1.0, 2.0], 100.0, 2, &mut sink)
+            .unwrap();
+        sink.truncate(sink.len() - 1);
+        let mut reader = EncryptedVectorReader::new(sink.as_slice());
+        assert!(matches!(
+            reader.read_chunk(2),
+            Err(EncryptionError::Integrity(_))
+        ));
+    }
+
+    #[test]
+    fn test_encrypt_f64_vector_chunked_zero_chunk_size() {
+        let fhe = BiosampleFHE::new();
+        let mut sink: Vec<u8> = Vec::new();
+
+        let result = fhe.encrypt_f64_vector_chunked(&[1.0], 100.0, 0, &mut sink);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_biosample_fhe_clone() {
         let fhe = BiosampleFHE::new();
@@ -812,8 +4222,8 @@ mod tests {
         let test_values = vec![1.0, 2.0, 3.0];
         let scale = 100.0;
         
-        let encrypted_original = fhe.encrypt_f64_vector(&test_values, scale);
-        let encrypted_clone = fhe_clone.encrypt_f64_vector(&test_values, scale);
+        let encrypted_original = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
+        let encrypted_clone = fhe_clone.encrypt_f64_vector(&test_values, scale).unwrap();
         
         // Both should be able to decrypt their own encrypted data
         let decrypted_original = fhe.decrypt_f64_vector(&encrypted_original, scale);
@@ -828,13 +4238,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_clones_share_keys() {
+        let fhe = BiosampleFHE::new();
+        let fhe_clone = fhe.clone();
+
+        // Cloning shares the key allocations instead of copying them
+        assert!(Arc::ptr_eq(
+            &fhe.server_key_arc(),
+            &fhe_clone.server_key_arc()
+        ));
+        assert!(Arc::ptr_eq(&fhe.client_key, &fhe_clone.client_key));
+
+        // Each clone decrypts what the other encrypted
+        let test_values = vec![4.0, -1.5];
+        let scale = 100.0;
+        let encrypted_original = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
+        let encrypted_clone = fhe_clone.encrypt_f64_vector(&test_values, scale).unwrap();
+        assert_eq!(
+            fhe_clone.decrypt_f64_vector(&encrypted_original, scale),
+            test_values
+        );
+        assert_eq!(fhe.decrypt_f64_vector(&encrypted_clone, scale), test_values);
+    }
+
     #[test]
     fn test_edge_case_zero_values() {
         let fhe = BiosampleFHE::new();
         let test_values = vec![0.0; 5];
         let scale = 100.0;
         
-        let encrypted = fhe.encrypt_f64_vector(&test_values, scale);
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
         let decrypted = fhe.decrypt_f64_vector(&encrypted, scale);
         
         for decrypted_val in decrypted.iter() {
@@ -848,7 +4282,7 @@ mod tests {
         let test_values = vec![-1.0, -2.5, -10.0];
         let scale = 100.0;
         
-        let encrypted = fhe.encrypt_f64_vector(&test_values, scale);
+        let encrypted = fhe.encrypt_f64_vector(&test_values, scale).unwrap();
         let decrypted = fhe.decrypt_f64_vector(&encrypted, scale);
         
         for (original, decrypted_val) in test_values.iter().zip(decrypted.iter()) {
@@ -0,0 +1,53 @@
+//! Names of the encrypted biosample fields
+//! The encryption side stores each encrypted column under one of these keys and the
+//! computation side and the demo look them up with the same constants, so the two
+//! sides cannot drift apart.
+
+/// Key of the encrypted patient ages
+pub const AGE: &str = "age";
+
+/// Key of the encrypted glucose levels
+pub const GLUCOSE: &str = "glucose";
+
+/// Key of the encrypted cholesterol levels
+pub const CHOLESTEROL: &str = "cholesterol";
+
+/// Key of the encrypted marker alpha indicators
+pub const MARKER: &str = "marker";
+
+/// Key of the encrypted collection dates, as days since 1970-01-01
+pub const COLLECTION_DAY: &str = "collection_day";
+
+/// Key of the encrypted facility ids, as exact integers
+///
+/// Unlike the `FACILITY_PREFIX` keys, it does not end with an underscore, so it is never
+/// mistaken for a one-hot facility vector.
+pub const FACILITY: &str = "facility";
+
+/// Prefix of the keys of the one-hot encoded blood type vectors
+pub const BLOOD_TYPE_PREFIX: &str = "blood_type_";
+
+/// Prefix of the keys of the one-hot encoded facility vectors
+pub const FACILITY_PREFIX: &str = "facility_";
+
+/// Returns the key of the one-hot vector for a blood type
+///
+/// # Arguments
+/// * `blood_type` - The blood type, e.g. "A+"
+///
+/// # Returns
+/// The key under which the blood type's encrypted indicators are stored
+pub fn blood_type_key(blood_type: &str) -> String {
+    format!("{}{}", BLOOD_TYPE_PREFIX, blood_type)
+}
+
+/// Returns the key of the one-hot vector for a facility
+///
+/// # Arguments
+/// * `facility_id` - The facility id, e.g. "3"
+///
+/// # Returns
+/// The key under which the facility's encrypted indicators are stored
+pub fn facility_key(facility_id: &str) -> String {
+    format!("{}{}", FACILITY_PREFIX, facility_id)
+}
@@ -5,7 +5,12 @@
 
 pub mod computations;
 pub mod data_generator;
+pub mod dp;
 pub mod encryption;
+pub mod fields;
+#[cfg(feature = "native")]
+pub mod report;
+#[cfg(feature = "visualization")]
 pub mod visualization;
 
 // You can also re-export specific items if you want to make them easier to access, e.g.:
@@ -1,27 +1,49 @@
 mod computations;
 mod data_generator;
 mod encryption;
+mod fields;
+mod report;
+#[cfg(feature = "visualization")]
 mod visualization;
 
 // Required libraries
-use std::collections::HashMap; // HashMap is used for storing key-value pairs
+use std::collections::{BTreeMap, HashMap}; // Maps are used for storing key-value pairs
 use std::error::Error; // Error trait is used for handling errors
-use std::fs; // fs module is used for file system operations
+use std::fs::{self, File}; // fs module is used for file system operations
+use std::io::{BufReader, BufWriter, Write}; // Buffered I/O is used for streaming ciphertexts
 use std::path::{Path, PathBuf}; // Path and PathBuf are used for handling file paths
-use std::time::Instant; // Instant is used for measuring time
+use std::time::{Duration, Instant}; // Instant and Duration are used for measuring time
 
-use clap::{ArgAction, Parser}; // clap is used for command-line argument parsing
+use chrono::NaiveDate; // NaiveDate is used for the base date of generated data
+use clap::parser::ValueSource; // ValueSource tells command-line flags from defaults
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum}; // clap is used for command-line argument parsing
+use serde::{Deserialize, Serialize}; // Used for the config file and the JSON report
 
 // Importing the modules
-use computations::{compute_encrypted_mean, run_biosample_analysis, verify_computation};
-use data_generator::{generate_biosample_data, load_biosample_data, save_biosample_data};
-use encryption::{encrypt_biosample_data, BiosampleFHE};
+use computations::{
+    compute_encrypted_category_counts, compute_encrypted_mean_chunked,
+    compute_encrypted_mean_exact, run_biosample_analysis, set_num_threads, verify,
+    verify_computation_with_context, AnalysisResult, Tolerance,
+};
+use data_generator::{
+    generate_biosample_data_with_config, load_biosample_data, save_biosample_data, BiosampleRecord,
+    GeneratorConfig, COLLECTION_DATE_FORMAT, REPRODUCIBLE_BASE_DATE,
+};
+use encryption::{
+    encrypt_biosample_data_with_progress, BiosampleFHE, EncryptedVector, ScaleConfig,
+};
+use report::save_results_csv;
+#[cfg(feature = "visualization")]
 use visualization::{plot_comparison, plot_performance_metrics, visualize_fhe_workflow};
 
 /// FHE Demo for secure computation on biosample data
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
+    /// Read settings from this TOML file; flags given on the command line take precedence
+    #[clap(long)]
+    config: Option<PathBuf>,
+
     /// Number of biosample records to generate
     #[clap(short, long, default_value_t = 1000)]
     samples: usize,
@@ -30,6 +52,11 @@ struct Args {
     #[clap(short, long, default_value_t = 42)]
     seed: u64,
 
+    /// First possible collection date (YYYY-MM-DD) of generated records, so that a seed
+    /// yields the same data whenever it runs
+    #[clap(long, default_value = REPRODUCIBLE_BASE_DATE)]
+    base_date: String,
+
     /// Regenerate data even if it exists
     #[clap(short, long, action=ArgAction::SetTrue)]
     regenerate: bool,
@@ -41,6 +68,288 @@ struct Args {
     /// Output directory for visualization
     #[clap(short, long, default_value = "outputs")]
     output_dir: String,
+
+    /// Encrypt numeric fields in chunks of this many values, streaming the
+    /// ciphertexts through the data directory to bound peak memory
+    #[clap(long)]
+    chunk_size: Option<usize>,
+
+    /// Number of threads for encryption and the computations, one per core by default
+    /// (or as set by RAYON_NUM_THREADS)
+    #[clap(long)]
+    threads: Option<usize>,
+
+    /// Write the results and phase timings as JSON to this file
+    #[clap(long)]
+    json_out: Option<PathBuf>,
+
+    /// Write the plaintext and decrypted results as CSV to this file
+    #[clap(long)]
+    csv_out: Option<PathBuf>,
+
+    /// Load the client key from this file instead of generating new keys
+    #[clap(long)]
+    client_key: Option<PathBuf>,
+
+    /// Load the server key from this file instead of generating new keys
+    #[clap(long)]
+    server_key: Option<PathBuf>,
+
+    /// Save the keys to this directory so later runs can load them
+    #[clap(long)]
+    save_keys: Option<PathBuf>,
+
+    /// Comma-separated statistics to compute, decrypt and print
+    #[clap(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values = ["age", "glucose", "cholesterol", "blood_type_counts", "full_analysis"]
+    )]
+    metrics: Vec<Metric>,
+}
+
+/// Name of the client key file written by `--save-keys`
+const CLIENT_KEY_FILE: &str = "client_key.bin";
+
+/// Name of the server key file written by `--save-keys`
+const SERVER_KEY_FILE: &str = "server_key.bin";
+
+/// Statistics the demo can compute, selected with `--metrics`
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+enum Metric {
+    /// Average age
+    Age,
+    /// Average glucose level
+    Glucose,
+    /// Average cholesterol level
+    Cholesterol,
+    /// Number of patients with each blood type
+    BloodTypeCounts,
+    /// The complete analysis, including variances and the high cholesterol count
+    FullAnalysis,
+}
+
+/// Encrypted mean of a field, ready to be decrypted for verification
+enum EncryptedMean {
+    /// Divided on the server, to `MEAN_PRECISION` extra digits
+    Divided(EncryptedVector),
+    /// Quotient and remainder of the sum by the number of records
+    Exact(EncryptedVector),
+}
+
+/// Settings read from a `--config` file
+///
+/// Each key mirrors the command-line flag of the same name, e.g. `output_dir` for
+/// `--output-dir`, and `metrics` is a list such as `["age", "glucose"]`. Unknown keys are
+/// rejected so that typos do not go unnoticed.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    samples: Option<usize>,       // Number of biosample records to generate
+    seed: Option<u64>,            // Random seed for reproducibility
+    base_date: Option<String>,    // First possible collection date of generated records
+    regenerate: Option<bool>,     // Regenerate data even if it exists
+    no_visualize: Option<bool>,   // Skip visualization generation
+    output_dir: Option<String>,   // Output directory for visualization
+    chunk_size: Option<usize>,    // Chunk size for streamed encryption
+    threads: Option<usize>,       // Number of threads for encryption and the computations
+    json_out: Option<PathBuf>,    // File to write the JSON report to
+    csv_out: Option<PathBuf>,     // File to write the results CSV to
+    client_key: Option<PathBuf>,  // File to load the client key from
+    server_key: Option<PathBuf>,  // File to load the server key from
+    save_keys: Option<PathBuf>,   // Directory to save the keys to
+    metrics: Option<Vec<Metric>>, // Statistics to compute, decrypt and print
+}
+
+/// Builds the effective settings from the parsed command line and its `--config` file
+///
+/// A flag given on the command line takes precedence over the config file, and a key in
+/// the config file over the flag's built-in default.
+///
+/// # Arguments
+/// * `matches` - The parsed command-line arguments
+///
+/// # Returns
+/// * `Result<Args, Box<dyn Error>>` - The effective settings, or an error if the config
+///   file cannot be read or parsed, or only one of the two key files is given
+fn load_args(matches: &ArgMatches) -> Result<Args, Box<dyn Error>> {
+    let mut args = Args::from_arg_matches(matches)?;
+    if let Some(path) = args.config.clone() {
+        apply_config(&mut args, &path, matches)?;
+    }
+
+    // Keys are only loaded as a pair
+    match (&args.client_key, &args.server_key) {
+        (Some(_), None) => Err("--client-key requires --server-key".into()),
+        (None, Some(_)) => Err("--server-key requires --client-key".into()),
+        _ => Ok(args),
+    }
+}
+
+/// Fills the settings that were not given on the command line from a config file
+///
+/// # Arguments
+/// * `args` - The settings parsed from the command line
+/// * `path` - The TOML config file
+/// * `matches` - The parsed command-line arguments, to tell given flags from defaults
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - An error if the file cannot be read or parsed
+fn apply_config(args: &mut Args, path: &Path, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config: FileConfig = toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+
+    // Only fill in the settings that were not given on the command line
+    macro_rules! fill_from_config {
+        ($($field:ident),*) => {$(
+            if let Some(value) = config.$field {
+                if matches.value_source(stringify!($field)) != Some(ValueSource::CommandLine) {
+                    args.$field = value.into();
+                }
+            }
+        )*};
+    }
+    fill_from_config!(
+        samples,
+        seed,
+        base_date,
+        regenerate,
+        no_visualize,
+        output_dir,
+        chunk_size,
+        threads,
+        json_out,
+        csv_out,
+        client_key,
+        server_key,
+        save_keys,
+        metrics
+    );
+    Ok(())
+}
+
+/// Accuracy of one metric in the JSON report
+#[derive(Serialize, Debug)]
+struct MetricReport {
+    plaintext: f64,             // Result computed on plaintext data
+    decrypted: f64,             // Decrypted result of the encrypted computation
+    abs_error: f64,             // Absolute difference between the two
+    percent_error: Option<f64>, // Error relative to the plaintext result, if it is not zero
+}
+
+/// Results and timings written by `--json-out`
+#[derive(Serialize, Debug)]
+struct JsonReport {
+    records: usize,                          // Number of biosample records
+    metrics: BTreeMap<String, MetricReport>, // Accuracy of each metric, by name
+    timings: BTreeMap<String, f64>,          // Duration of each phase, in seconds
+}
+
+/// Writes the accuracy of each metric and the phase timings to a JSON file
+///
+/// # Arguments
+/// * `path` - The file to write
+/// * `records` - The number of biosample records
+/// * `plaintext_results` - The results computed on plaintext data, by metric
+/// * `decrypted_results` - The decrypted results, by the same metrics
+/// * `timings` - The duration of each phase, by name
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - An error if a metric has no decrypted result or the
+///   file cannot be written
+fn write_json_report(
+    path: &Path,
+    records: usize,
+    plaintext_results: &HashMap<String, f64>,
+    decrypted_results: &HashMap<String, f64>,
+    timings: &[(&str, Duration)],
+) -> Result<(), Box<dyn Error>> {
+    let metrics = plaintext_results
+        .iter()
+        .map(|(name, &plaintext)| {
+            let decrypted = *decrypted_results
+                .get(name)
+                .ok_or_else(|| format!("No decrypted result for {}", name))?;
+            let check = verify(decrypted, plaintext, Tolerance::Absolute(0.0));
+            let report = MetricReport {
+                plaintext,
+                decrypted,
+                abs_error: check.abs_error,
+                percent_error: check.rel_error.map(|rel_error| rel_error * 100.0),
+            };
+            Ok((name.clone(), report))
+        })
+        .collect::<Result<BTreeMap<_, _>, Box<dyn Error>>>()?;
+
+    let report = JsonReport {
+        records,
+        metrics,
+        timings: timings
+            .iter()
+            .map(|(phase, duration)| (phase.to_string(), duration.as_secs_f64()))
+            .collect(),
+    };
+    serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &report)?;
+    Ok(())
+}
+
+/// Reads the plaintext value of one numeric field from a record
+type FieldAccessor = fn(&BiosampleRecord) -> f64;
+
+/// Reads the encrypted mean of one numeric field from the full analysis
+type AnalysisAccessor = fn(&AnalysisResult) -> &Option<EncryptedVector>;
+
+/// Computes an encrypted mean by streaming a numeric column through disk in chunks.
+///
+/// The values are encrypted `chunk_size` at a time into `path`, and the sum is then
+/// folded back from the file chunk by chunk and divided into the mean, so at most one
+/// chunk of ciphertexts is held in memory at any point.
+///
+/// # Returns
+///
+/// Returns the encrypted mean in a serialized form, or an error if encryption,
+/// file I/O, or the computation fails.
+fn compute_streamed_mean(
+    fhe: &BiosampleFHE,
+    values: &[f64],
+    scale: f64,
+    chunk_size: usize,
+    path: &Path,
+) -> Result<EncryptedVector, Box<dyn Error>> {
+    // Encrypt the column chunk by chunk into the file
+    let mut sink = BufWriter::new(File::create(path)?);
+    fhe.encrypt_f64_vector_chunked(values, scale, chunk_size, &mut sink)?;
+    sink.flush()?;
+    drop(sink);
+
+    // Fold the mean back from the file chunk by chunk
+    let mut source = BufReader::new(File::open(path)?);
+    Ok(compute_encrypted_mean_chunked(
+        &mut source,
+        chunk_size,
+        fhe.server_key(),
+    )?)
+}
+
+/// Redraws a progress bar on the current line of the terminal
+///
+/// # Arguments
+/// * `done` - The number of values processed so far
+/// * `total` - The total number of values to process
+fn print_progress_bar(done: usize, total: usize) {
+    const BAR_WIDTH: usize = 40;
+    let filled = done * BAR_WIDTH / total.max(1);
+    print!(
+        "\r  [{}{}] {}/{} values",
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        done,
+        total
+    );
+    let _ = std::io::stdout().flush();
 }
 
 /// Main function for the FHE Demo application.
@@ -62,8 +371,11 @@ fn main() -> Result<(), Box<dyn Error>> {
         env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "info"),
     );
 
-    // Parse command-line arguments
-    let args = Args::parse();
+    // Parse command-line arguments and the config file they point to
+    let args = load_args(&Args::command().get_matches())?;
+    if let Some(threads) = args.threads {
+        set_num_threads(threads)?;
+    }
 
     println!("{}", "=".repeat(80));
     println!(
@@ -84,7 +396,13 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let records = if !data_file.exists() || args.regenerate {
         println!("\n[1/5] Generating synthetic biosample data...");
-        let records = generate_biosample_data(args.samples, args.seed)?;
+        let base_date = NaiveDate::parse_from_str(&args.base_date, COLLECTION_DATE_FORMAT)
+            .map_err(|e| format!("Invalid --base-date {}: {}", args.base_date, e))?;
+        let config = GeneratorConfig {
+            base_date: Some(base_date),
+            ..GeneratorConfig::default()
+        };
+        let records = generate_biosample_data_with_config(args.samples, args.seed, &config)?;
         save_biosample_data(&records, &data_file)?;
         records
     } else {
@@ -111,11 +429,40 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Initialize FHE encryption and encrypt data
     println!("\n[2/5] Encrypting biosample data using FHE...");
     let encryption_start = Instant::now();
-    let fhe = BiosampleFHE::new();
+    let fhe = match (&args.client_key, &args.server_key) {
+        (Some(client_key), Some(server_key)) => {
+            println!(
+                "Loading FHE keys from {} and {}...",
+                client_key.display(),
+                server_key.display()
+            );
+            BiosampleFHE::load_keys(client_key, server_key)?
+        }
+        _ => {
+            println!("Generating new FHE keys...");
+            BiosampleFHE::new()
+        }
+    };
+    if let Some(key_dir) = &args.save_keys {
+        fs::create_dir_all(key_dir)?;
+        fhe.save_keys(
+            &key_dir.join(CLIENT_KEY_FILE),
+            &key_dir.join(SERVER_KEY_FILE),
+        )?;
+        println!("Keys saved to {}", key_dir.display());
+    }
+    println!("FHE parameters:\n{}", fhe.parameters_info());
+
+    // Ages are encrypted as exact integers, the other fields at the default scale
+    let scales = ScaleConfig::default();
 
     // Encrypt the biosample data
     println!("Encrypting numerical and categorical data...");
-    let encrypted_data = encrypt_biosample_data(&fhe, &records)?;
+    let (encrypted_data, context) =
+        encrypt_biosample_data_with_progress(&fhe, &records, &scales, &mut |done, total| {
+            print_progress_bar(done, total)
+        })?;
+    println!();
 
     let encryption_time = encryption_start.elapsed();
     println!(
@@ -130,38 +477,105 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Track performance metrics
     let mut performance_metrics = HashMap::new();
 
-    // Average Age
-    println!("Computing average age...");
-    let start = Instant::now();
-    let encrypted_avg_age = match encrypted_data.get("age") {
-        Some(age_data) => compute_encrypted_mean(age_data, fhe.server_key())?,
-        None => return Err("Age data not found".into()),
-    };
-    performance_metrics.insert("Average Age".to_string(), start.elapsed());
-
-    // Average Glucose Level
-    println!("Computing average glucose level...");
-    let start = Instant::now();
-    let encrypted_avg_glucose = match encrypted_data.get("glucose_level") {
-        Some(glucose_data) => compute_encrypted_mean(glucose_data, fhe.server_key())?,
-        None => return Err("Glucose data not found".into()),
-    };
-    performance_metrics.insert("Average Glucose Level".to_string(), start.elapsed());
-
-    // Average Cholesterol Level
-    println!("Computing average cholesterol level...");
-    let start = Instant::now();
-    let encrypted_avg_cholesterol = match encrypted_data.get("cholesterol_level") {
-        Some(cholesterol_data) => compute_encrypted_mean(cholesterol_data, fhe.server_key())?,
-        None => return Err("Cholesterol data not found".into()),
-    };
-    performance_metrics.insert("Average Cholesterol Level".to_string(), start.elapsed());
+    if let Some(chunk_size) = args.chunk_size {
+        println!(
+            "Streaming numeric fields in chunks of {} values...",
+            chunk_size
+        );
+    }
 
-    // Run full analysis
-    println!("Running complete biosample analysis...");
-    let start = Instant::now();
-    let _encrypted_results = run_biosample_analysis(&encrypted_data, fhe.server_key())?;
-    performance_metrics.insert("Full Analysis".to_string(), start.elapsed());
+    // Run the full analysis first, so the averages and counts below can reuse its results
+    let mut analysis_results = AnalysisResult::default();
+    if args.metrics.contains(&Metric::FullAnalysis) {
+        println!("Running complete biosample analysis...");
+        let start = Instant::now();
+        analysis_results = run_biosample_analysis(&encrypted_data, fhe.server_key())?;
+        performance_metrics.insert("Full Analysis".to_string(), start.elapsed());
+    }
+
+    // Averages of the selected numeric fields, with the label they are reported under
+    let averages: [(Metric, &str, &str, FieldAccessor, AnalysisAccessor); 3] = [
+        (
+            Metric::Age,
+            "Average Age",
+            fields::AGE,
+            |r| r.age as f64,
+            |a| &a.avg_age,
+        ),
+        (
+            Metric::Glucose,
+            "Average Glucose Level",
+            fields::GLUCOSE,
+            |r| r.glucose_level,
+            |a| &a.avg_glucose,
+        ),
+        (
+            Metric::Cholesterol,
+            "Average Cholesterol Level",
+            fields::CHOLESTEROL,
+            |r| r.cholesterol_level,
+            |a| &a.avg_cholesterol,
+        ),
+    ];
+
+    let mut encrypted_results = Vec::new();
+    for (metric, label, field, value, analysed) in averages {
+        if !args.metrics.contains(&metric) {
+            continue;
+        }
+
+        // The full analysis already summed the field, so do not sum it again
+        if let (None, Some(encrypted_avg)) = (args.chunk_size, analysed(&analysis_results)) {
+            println!("Reusing {} from the full analysis...", label.to_lowercase());
+            let encrypted_avg = EncryptedMean::Divided(encrypted_avg.clone());
+            encrypted_results.push((label, field, value, encrypted_avg));
+            continue;
+        }
+
+        println!("Computing {}...", label.to_lowercase());
+        let start = Instant::now();
+        let encrypted_avg = match (args.chunk_size, encrypted_data.get(field)) {
+            (Some(chunk_size), _) => {
+                let values: Vec<f64> = records.iter().map(value).collect();
+                let path = data_dir.join(format!("encrypted_{}.bin", field));
+                let scale = context.scale_for(field)?;
+                EncryptedMean::Divided(compute_streamed_mean(
+                    &fhe, &values, scale, chunk_size, &path,
+                )?)
+            }
+            // The whole field is in memory, so keep the exact quotient and remainder
+            (None, Some(data)) => EncryptedMean::Exact(compute_encrypted_mean_exact(
+                data,
+                records.len(),
+                fhe.server_key(),
+            )?),
+            (None, None) => return Err(format!("{} data not found", label).into()),
+        };
+        performance_metrics.insert(label.to_string(), start.elapsed());
+        encrypted_results.push((label, field, value, encrypted_avg));
+    }
+
+    // Blood type counts
+    let mut encrypted_blood_type_counts = HashMap::new();
+    if args.metrics.contains(&Metric::BloodTypeCounts)
+        && !analysis_results.blood_type_counts.is_empty()
+    {
+        println!("Reusing blood type counts from the full analysis...");
+        encrypted_blood_type_counts = analysis_results
+            .blood_type_counts
+            .iter()
+            .map(|(blood_type, count)| {
+                let key = format!("{}{}", fields::BLOOD_TYPE_PREFIX, blood_type);
+                (key, count.clone())
+            })
+            .collect();
+    } else if args.metrics.contains(&Metric::BloodTypeCounts) {
+        println!("Counting blood types...");
+        let start = Instant::now();
+        encrypted_blood_type_counts =
+            compute_encrypted_category_counts(&encrypted_data, fhe.server_key())?;
+        performance_metrics.insert("Blood Type Counts".to_string(), start.elapsed());
+    }
 
     let computation_time = computation_start.elapsed();
     println!(
@@ -172,68 +586,149 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Decrypt and verify results
     println!("\n[4/5] Decrypting and verifying results...");
 
-    // Calculate plaintext_results for verification
-    let scale = 100.0;
-    let plaintext_results = {
-        let mut results = HashMap::new();
-
-        // Average Age
-        let avg_age = records.iter().map(|r| r.age as f64).sum::<f64>() / records.len() as f64;
-        results.insert("Average Age".to_string(), avg_age);
-
-        // Average Glucose Level
-        let avg_glucose =
-            records.iter().map(|r| r.glucose_level).sum::<f64>() / records.len() as f64;
-        results.insert("Average Glucose Level".to_string(), avg_glucose);
-
-        // Average Cholesterol Level
-        let avg_cholesterol =
-            records.iter().map(|r| r.cholesterol_level).sum::<f64>() / records.len() as f64;
-        results.insert("Average Cholesterol Level".to_string(), avg_cholesterol);
-
-        results
-    };
-
     // Decrypt results
     let decryption_start = Instant::now();
 
-    let mut encrypted_result_map = HashMap::new();
-    encrypted_result_map.insert("Average Age".to_string(), encrypted_avg_age);
-    encrypted_result_map.insert("Average Glucose Level".to_string(), encrypted_avg_glucose);
-    encrypted_result_map.insert(
-        "Average Cholesterol Level".to_string(),
-        encrypted_avg_cholesterol,
-    );
-
+    let mut plaintext_results = HashMap::new();
     let mut decrypted_results = HashMap::new();
-    for (key, enc_result) in &encrypted_result_map {
+    for (key, field, value, enc_result) in &encrypted_results {
         println!("Decrypting {}...", key);
 
-        // Decrypt
-        let decrypted_raw = fhe.decrypt_f64_vector(enc_result, scale);
-
-        // Process the result
-        let decrypted = decrypted_raw[0] / records.len() as f64;
-        decrypted_results.insert(key.clone(), decrypted);
-
-        // Get plaintext result for verification
-        let plaintext = plaintext_results[key];
-        let is_verified = verify_computation(decrypted, plaintext, 0.05);
-        let error = (decrypted - plaintext).abs();
-        let error_pct = if plaintext != 0.0 {
-            error / plaintext * 100.0
-        } else {
-            0.0
+        // Rebuild the exact mean when the server kept the remainder of the division
+        let decrypted = match enc_result {
+            EncryptedMean::Exact(exact) => fhe.decrypt_exact_mean(exact, records.len())?.value(),
+            EncryptedMean::Divided(mean) => fhe.decrypt_mean(mean, field, &context)?,
         };
+        decrypted_results.insert(key.to_string(), decrypted);
+
+        // Calculate the plaintext result for verification
+        let plaintext = records.iter().map(value).sum::<f64>() / records.len() as f64;
+        plaintext_results.insert(key.to_string(), plaintext);
+        let check = verify_computation_with_context(decrypted, plaintext, field, &context)?;
 
         println!("Plaintext result: {:.2}", plaintext);
         println!("Decrypted result: {:.2}", decrypted);
         println!(
             "Verification status: {}",
-            if is_verified { "PASS" } else { "FAIL" }
+            if check.passed { "PASS" } else { "FAIL" }
         );
-        println!("Error: {:.2}", error);
-        println!("Error percentage: {:.2}%", error_pct);
+        println!(
+            "Error: {:.4} (tolerance {:.4})",
+            check.abs_error, check.tolerance_used
+        );
+        match check.rel_error {
+            Some(rel_error) => println!("Error percentage: {:.2}%", rel_error * 100.0),
+            None => println!("Error percentage: n/a"),
+        }
+    }
+
+    let mut blood_type_keys: Vec<&String> = encrypted_blood_type_counts.keys().collect();
+    blood_type_keys.sort();
+    for key in blood_type_keys {
+        let blood_type = key.trim_start_matches(fields::BLOOD_TYPE_PREFIX);
+        let label = format!("Blood Type {} Count", blood_type);
+        println!("Decrypting {}...", label);
+
+        // Counts are plain integers, so they decrypt with a scale of 1
+        let decrypted = fhe.decrypt_element(&encrypted_blood_type_counts[key], 0, 1.0)?;
+        let plaintext = records
+            .iter()
+            .filter(|r| r.blood_type == blood_type)
+            .count() as f64;
+        // Counts must match exactly
+        let check = verify(decrypted, plaintext, Tolerance::Absolute(0.0));
+        println!("Plaintext result: {:.0}", plaintext);
+        println!("Decrypted result: {:.0}", decrypted);
+        println!(
+            "Verification status: {}",
+            if check.passed { "PASS" } else { "FAIL" }
+        );
+        plaintext_results.insert(label.clone(), plaintext);
+        decrypted_results.insert(label, decrypted);
+    }
+
+    // Results of the full analysis, decrypted in one go
+    let analysis = analysis_results.decrypt(&fhe, &context)?;
+
+    // Glucose-cholesterol correlation from the covariance and variance terms of the analysis
+    if let (Some(covariance), Some(correlation)) = (
+        analysis.cov_glucose_cholesterol,
+        analysis.glucose_cholesterol_correlation(),
+    ) {
+        println!("Decrypting Glucose-Cholesterol Correlation...");
+        println!("Covariance: {:.2}", covariance);
+        println!("Glucose-cholesterol correlation: {:.3}", correlation);
+    }
+
+    // Glucose-age index, the mean per-record product of glucose level and age over 1000
+    if let Some(product_mean) = analysis.mean_glucose_age_product {
+        let plaintext = records
+            .iter()
+            .map(|r| r.glucose_level * r.age as f64)
+            .sum::<f64>()
+            / records.len() as f64;
+        let check = verify(product_mean, plaintext, Tolerance::Relative(0.01));
+        println!("Decrypting Glucose-Age Index...");
+        println!("Plaintext result: {:.3}", plaintext / 1000.0);
+        println!("Decrypted result: {:.3}", product_mean / 1000.0);
+        println!(
+            "Verification status: {}",
+            if check.passed { "PASS" } else { "FAIL" }
+        );
+    }
+
+    // Average glucose of marker-positive and marker-negative patients
+    if let Some((positive, negative)) = analysis.glucose_by_marker {
+        println!("Decrypting Glucose by Marker Alpha...");
+        for (label, mean) in [("positives", positive), ("negatives", negative)] {
+            match mean {
+                Some(mean) => println!("Average glucose among marker_alpha {}: {:.2}", label, mean),
+                None => println!("No marker_alpha {}", label),
+            }
+        }
+    }
+
+    // Number of marker-positive patients
+    if let Some(count) = analysis.marker_positive_count {
+        println!("Decrypting Marker Alpha Count...");
+        println!(
+            "Marker alpha positives: {:.0} of {} ({:.1}%)",
+            count,
+            records.len(),
+            count / records.len() as f64 * 100.0
+        );
+    }
+
+    // Average cholesterol of each blood type
+    if !analysis.cholesterol_by_blood_type.is_empty() {
+        println!("Decrypting Cholesterol by Blood Type...");
+        let mut blood_type_means: Vec<_> = analysis.cholesterol_by_blood_type.iter().collect();
+        blood_type_means.sort_by_key(|(blood_type, _)| *blood_type);
+        println!("{:<12} {:>18}", "Blood Type", "Avg Cholesterol");
+        for (blood_type, mean) in blood_type_means {
+            // Blood types without patients have no mean
+            match mean {
+                Some(mean) => println!("{:<12} {:>18.2}", blood_type, mean),
+                None => println!("{:<12} {:>18}", blood_type, "-"),
+            }
+        }
+    }
+
+    // Number of blood types present, counted without revealing which
+    if let Some(distinct) = analysis.distinct_blood_types {
+        println!("Decrypting Distinct Blood Types...");
+        println!("Distinct blood types: {:.0}", distinct);
+    }
+
+    // Number of records of each facility
+    if !analysis.facility_counts.is_empty() {
+        println!("Decrypting Facility Counts...");
+        let mut facility_counts: Vec<_> = analysis.facility_counts.iter().collect();
+        facility_counts.sort_by_key(|(facility, _)| facility.parse::<u32>().unwrap_or(u32::MAX));
+        println!("{:<12} {:>18}", "Facility", "Records");
+        for (facility, count) in facility_counts {
+            println!("{:<12} {:>18.0}", facility, count);
+        }
     }
 
     let decryption_time = decryption_start.elapsed();
@@ -243,6 +738,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     );
 
     // Generate visiualizations
+    #[cfg(feature = "visualization")]
     if !args.no_visualize {
         println!("\n[5/5] Generating visualizations...");
 
@@ -250,13 +746,16 @@ fn main() -> Result<(), Box<dyn Error>> {
         fs::create_dir_all(&output_dir)?;
 
         // Plot comparison of plaintext and encrypted results
-        println!("Plotting comparison of plaintext and encrypted results...");
-        plot_comparison(
-            &plaintext_results,
-            &decrypted_results,
-            "FHE vs Plaintext Computation Results",
-            &output_dir.join("results_comparision.png"),
-        )?;
+        if !plaintext_results.is_empty() {
+            println!("Plotting comparison of plaintext and encrypted results...");
+            plot_comparison(
+                &plaintext_results,
+                &decrypted_results,
+                "FHE vs Plaintext Computation Results",
+                &output_dir.join("results_comparision.png"),
+                None,
+            )?;
+        }
 
         // Plot performance metrics
         println!("  Creating performance metrics chart...");
@@ -267,6 +766,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             &perf_metrics,
             "FHE Operation Performance",
             &output_dir.join("performance_metrics.png"),
+            None,
         )?;
 
         // Plot FHE workflow
@@ -277,6 +777,28 @@ fn main() -> Result<(), Box<dyn Error>> {
     } else {
         println!("\n[5/5] Visualization skipped");
     }
+    #[cfg(not(feature = "visualization"))]
+    println!("\n[5/5] Visualization skipped, built without the visualization feature");
+
+    // Machine-readable results
+    if let Some(json_path) = &args.json_out {
+        write_json_report(
+            json_path,
+            records.len(),
+            &plaintext_results,
+            &decrypted_results,
+            &[
+                ("encryption", encryption_time),
+                ("computation", computation_time),
+                ("decryption", decryption_time),
+            ],
+        )?;
+        println!("\nResults written to {}", json_path.display());
+    }
+    if let Some(csv_path) = &args.csv_out {
+        save_results_csv(&plaintext_results, &decrypted_results, csv_path)?;
+        println!("Results written to {}", csv_path.display());
+    }
 
     // Summary
     println!("\n{}", "=".repeat(80));
@@ -301,10 +823,15 @@ fn main() -> Result<(), Box<dyn Error>> {
     );
     println!("\nAccuracy:");
     for key in plaintext_results.keys() {
-        let error_pct = (decrypted_results[key] - plaintext_results[key]).abs()
-            / plaintext_results[key]
-            * 100.0;
-        println!("  - {}: {:.2}% error", key, error_pct);
+        let check = verify(
+            decrypted_results[key],
+            plaintext_results[key],
+            Tolerance::Absolute(0.0),
+        );
+        match check.rel_error {
+            Some(rel_error) => println!("  - {}: {:.2}% error", key, rel_error * 100.0),
+            None => println!("  - {}: {:.4} absolute error", key, check.abs_error),
+        }
     }
 
     println!("\n{}", "=".repeat(80));
@@ -313,3 +840,84 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_flag_selects_subset() {
+        let args = Args::try_parse_from(["demo", "--metrics", "age,blood_type_counts"]).unwrap();
+        assert_eq!(args.metrics, vec![Metric::Age, Metric::BloodTypeCounts]);
+
+        // Without the flag every statistic is computed
+        let args = Args::try_parse_from(["demo"]).unwrap();
+        assert_eq!(args.metrics.len(), Metric::value_variants().len());
+    }
+
+    #[test]
+    fn test_config_file_fills_flags_not_given_on_command_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo.toml");
+        fs::write(
+            &path,
+            "samples = 50\nseed = 7\nno_visualize = true\noutput_dir = \"charts\"\n\
+             metrics = [\"age\", \"glucose\"]\n",
+        )
+        .unwrap();
+
+        let matches = Args::command()
+            .try_get_matches_from(["demo", "--config", path.to_str().unwrap(), "--seed", "9"])
+            .unwrap();
+        let args = load_args(&matches).unwrap();
+        assert_eq!(args.samples, 50);
+        assert_eq!(args.seed, 9);
+        assert!(args.no_visualize);
+        assert_eq!(args.output_dir, "charts");
+        assert_eq!(args.metrics, vec![Metric::Age, Metric::Glucose]);
+
+        // Settings missing from both keep their defaults
+        assert!(!args.regenerate);
+        assert_eq!(args.chunk_size, None);
+
+        // Unknown keys are rejected
+        fs::write(&path, "sample = 50\n").unwrap();
+        let matches = Args::command()
+            .try_get_matches_from(["demo", "--config", path.to_str().unwrap()])
+            .unwrap();
+        let error = load_args(&matches).err().unwrap();
+        assert!(error.to_string().contains("sample"), "{}", error);
+    }
+
+    #[test]
+    fn test_key_files_must_be_given_as_a_pair() {
+        for flag in ["--client-key", "--server-key"] {
+            let matches = Args::command()
+                .try_get_matches_from(["demo", flag, "keys/key.bin"])
+                .unwrap();
+            let error = load_args(&matches).err().unwrap();
+            assert!(error.to_string().starts_with(flag), "{}", error);
+        }
+
+        let matches = Args::command()
+            .try_get_matches_from([
+                "demo",
+                "--client-key",
+                "keys/client_key.bin",
+                "--server-key",
+                "keys/server_key.bin",
+            ])
+            .unwrap();
+        assert!(load_args(&matches).is_ok());
+    }
+
+    #[test]
+    fn test_metrics_flag_rejects_typos() {
+        let error = Args::try_parse_from(["demo", "--metrics", "age,glucos"])
+            .err()
+            .unwrap();
+        let message = error.to_string();
+        assert!(message.contains("glucos"), "{}", message);
+        assert!(message.contains("possible values"), "{}", message);
+    }
+}
@@ -4,6 +4,9 @@ mod proptests {
     use proptest::prelude::*;
     use std::collections::HashSet;
 
+    /// Seed for the keys used by every property, so a failing case reproduces exactly
+    const PROPTEST_KEY_SEED: [u8; 32] = [42; 32];
+
     // Custom strategies for generating test data with smaller sizes for performance
     
     /// Strategy for generating reasonable f64 values for biosample data
@@ -57,12 +60,12 @@ mod proptests {
             values in f64_vector(),
             scale in scale_factor()
         ) {
-            let fhe = BiosampleFHE::new();
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
             
             // Skip if scale is too small to avoid precision issues
             prop_assume!(scale >= 1.0);
             
-            let encrypted = fhe.encrypt_f64_vector(&values, scale);
+            let encrypted = fhe.encrypt_f64_vector(&values, scale).unwrap();
             let decrypted = fhe.decrypt_f64_vector(&encrypted, scale);
             
             // Verify length preservation
@@ -83,10 +86,10 @@ mod proptests {
         /// Property: Boolean vector encryption/decryption should be perfectly reversible
         #[test]
         fn prop_bool_encrypt_decrypt_roundtrip(values in bool_vector()) {
-            let fhe = BiosampleFHE::new();
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
             
             let encrypted = fhe.encrypt_bool_vector(&values);
-            let decrypted = fhe.decrypt_bool_vector(&encrypted);
+            let decrypted = fhe.decrypt_bool_vector(&encrypted).unwrap();
             
             // Boolean encryption should be exact
             prop_assert_eq!(encrypted.length, values.len());
@@ -96,7 +99,7 @@ mod proptests {
         /// Property: Categorical encryption should preserve category information
         #[test]
         fn prop_categorical_encrypt_preserves_categories(values in categorical_vector()) {
-            let fhe = BiosampleFHE::new();
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
             
             let encrypted_categorical = fhe.encrypt_categorical(&values);
             
@@ -119,11 +122,11 @@ mod proptests {
         /// Property: Empty vectors should be handled correctly
         #[test]
         fn prop_empty_vectors_handled_correctly(scale in scale_factor()) {
-            let fhe = BiosampleFHE::new();
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
             
             // Test empty f64 vector
             let empty_f64: Vec<f64> = vec![];
-            let encrypted_f64 = fhe.encrypt_f64_vector(&empty_f64, scale);
+            let encrypted_f64 = fhe.encrypt_f64_vector(&empty_f64, scale).unwrap();
             let decrypted_f64 = fhe.decrypt_f64_vector(&encrypted_f64, scale);
             
             prop_assert_eq!(encrypted_f64.length, 0);
@@ -132,7 +135,7 @@ mod proptests {
             // Test empty bool vector
             let empty_bool: Vec<bool> = vec![];
             let encrypted_bool = fhe.encrypt_bool_vector(&empty_bool);
-            let decrypted_bool = fhe.decrypt_bool_vector(&encrypted_bool);
+            let decrypted_bool = fhe.decrypt_bool_vector(&encrypted_bool).unwrap();
             
             prop_assert_eq!(encrypted_bool.length, 0);
             prop_assert_eq!(decrypted_bool.len(), 0);
@@ -151,8 +154,8 @@ mod proptests {
             prop_assume!(scale >= 1.0);
             prop_assume!(!values.is_empty()); // Skip empty vectors for this test
             
-            let fhe = BiosampleFHE::new();
-            let encrypted = fhe.encrypt_f64_vector(&values, scale);
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
+            let encrypted = fhe.encrypt_f64_vector(&values, scale).unwrap();
             
             // Test JSON serialization
             let serialized = serde_json::to_string(&encrypted);
@@ -180,12 +183,12 @@ mod proptests {
         fn prop_scale_factor_affects_precision(
             values in prop::collection::vec(-10.0..10.0, 1..3), // Smaller range and size
         ) {
-            let fhe = BiosampleFHE::new();
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
             let scale1 = 1.0;
             let scale2 = 100.0;
             
-            let encrypted1 = fhe.encrypt_f64_vector(&values, scale1);
-            let encrypted2 = fhe.encrypt_f64_vector(&values, scale2);
+            let encrypted1 = fhe.encrypt_f64_vector(&values, scale1).unwrap();
+            let encrypted2 = fhe.encrypt_f64_vector(&values, scale2).unwrap();
             
             let decrypted1 = fhe.decrypt_f64_vector(&encrypted1, scale1);
             let decrypted2 = fhe.decrypt_f64_vector(&encrypted2, scale2);
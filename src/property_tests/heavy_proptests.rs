@@ -0,0 +1,166 @@
+#[cfg(all(test, feature = "rayon"))]
+mod proptests {
+    //! Round-trip properties at realistic sizes, too slow for every test run
+    //!
+    //! The properties are ignored by default; run them with
+    //! `cargo test --release --lib heavy_proptests -- --ignored`. Each case encrypts a
+    //! dataset of up to `MAX_RECORDS` records with `encrypt_biosample_data_parallel`,
+    //! one column per thread.
+    use crate::data_generator::BiosampleRecord;
+    use crate::encryption::*;
+    use crate::fields;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    /// Seed for the keys used by every property, so a failing case reproduces exactly
+    const PROPTEST_KEY_SEED: [u8; 32] = [42; 32];
+
+    /// Largest number of records in a generated dataset
+    const MAX_RECORDS: usize = 64;
+
+    /// Strategy for generating reasonable f64 values for biosample data
+    fn reasonable_f64() -> impl Strategy<Value = f64> {
+        prop_oneof![
+            -100.0..100.0,
+            // Edge cases
+            Just(0.0),
+            Just(-0.0),
+            // Small values
+            -1.0..1.0,
+        ]
+    }
+
+    /// Strategy for generating the glucose level and marker of up to `MAX_RECORDS` records
+    fn record_values() -> impl Strategy<Value = Vec<(f64, bool)>> {
+        prop::collection::vec((reasonable_f64(), any::<bool>()), 0..=MAX_RECORDS)
+    }
+
+    /// Strategy for generating scale factors
+    fn scale_factor() -> impl Strategy<Value = f64> {
+        prop_oneof![Just(1.0), Just(10.0), Just(100.0),]
+    }
+
+    /// Encrypts records holding the given glucose levels and markers, one column per thread
+    ///
+    /// # Arguments
+    ///
+    /// * `fhe` - The `BiosampleFHE` instance used for encryption
+    /// * `values` - The glucose level and marker of each record
+    /// * `scale` - The scale of the numeric fields
+    ///
+    /// # Returns
+    ///
+    /// A map of field names to encrypted vectors
+    fn encrypt_records(
+        fhe: &BiosampleFHE,
+        values: &[(f64, bool)],
+        scale: f64,
+    ) -> HashMap<String, EncryptedVector> {
+        let records: Vec<BiosampleRecord> = values
+            .iter()
+            .enumerate()
+            .map(|(i, &(glucose_level, marker_alpha))| BiosampleRecord {
+                patient_id: format!("P{:06}", i + 1),
+                age: 45,
+                gender: "Female".to_string(),
+                blood_type: "O+".to_string(),
+                glucose_level,
+                cholesterol_level: glucose_level,
+                marker_alpha,
+                collection_date: "2024-01-01".to_string(),
+                facility_id: 1,
+            })
+            .collect();
+        let scales = ScaleConfig {
+            default: scale,
+            scales: HashMap::new(),
+        };
+
+        let (encrypted_data, _) = encrypt_biosample_data_parallel(fhe, &records, &scales).unwrap();
+        encrypted_data
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        /// Property: F64 vector encryption/decryption should be reversible
+        #[test]
+        #[ignore]
+        fn prop_f64_encrypt_decrypt_roundtrip_large(
+            values in record_values(),
+            scale in scale_factor()
+        ) {
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
+            let glucose: Vec<f64> = values.iter().map(|&(glucose, _)| glucose).collect();
+
+            let encrypted_data = encrypt_records(&fhe, &values, scale);
+            let encrypted = &encrypted_data[fields::GLUCOSE];
+            let decrypted = fhe.decrypt_f64_vector(encrypted, scale);
+
+            // Verify length preservation
+            prop_assert_eq!(encrypted.length, glucose.len());
+            prop_assert_eq!(decrypted.len(), glucose.len());
+
+            // Verify values are approximately equal (accounting for floating point precision)
+            for (original, decrypted_val) in glucose.iter().zip(decrypted.iter()) {
+                let tolerance = 1.0 / scale + 0.01; // Scale-dependent tolerance
+                prop_assert!(
+                    (original - decrypted_val).abs() < tolerance,
+                    "Original: {}, Decrypted: {}, Tolerance: {}",
+                    original, decrypted_val, tolerance
+                );
+            }
+        }
+
+        /// Property: Boolean vector encryption/decryption should be perfectly reversible
+        #[test]
+        #[ignore]
+        fn prop_bool_encrypt_decrypt_roundtrip_large(values in record_values()) {
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
+            let markers: Vec<bool> = values.iter().map(|&(_, marker)| marker).collect();
+
+            let encrypted_data = encrypt_records(&fhe, &values, 1.0);
+            let encrypted = &encrypted_data[fields::MARKER];
+            let decrypted = fhe.decrypt_bool_vector(encrypted).unwrap();
+
+            // Boolean encryption should be exact
+            prop_assert_eq!(encrypted.length, markers.len());
+            prop_assert_eq!(decrypted, markers);
+        }
+
+        /// Property: Encrypted vectors should be serializable and deserializable
+        #[test]
+        #[ignore]
+        fn prop_encrypted_vector_serialization_large(
+            values in record_values(),
+            scale in scale_factor()
+        ) {
+            prop_assume!(!values.is_empty()); // Skip empty vectors for this test
+
+            let fhe = BiosampleFHE::from_seed(PROPTEST_KEY_SEED);
+            let glucose: Vec<f64> = values.iter().map(|&(glucose, _)| glucose).collect();
+            let encrypted_data = encrypt_records(&fhe, &values, scale);
+            let encrypted = &encrypted_data[fields::CHOLESTEROL];
+
+            // Test JSON serialization
+            let serialized = serde_json::to_string(encrypted);
+            prop_assert!(serialized.is_ok());
+
+            let deserialized: Result<EncryptedVector, _> = serde_json::from_str(&serialized.unwrap());
+            prop_assert!(deserialized.is_ok());
+
+            let deserialized = deserialized.unwrap();
+            prop_assert_eq!(encrypted.length, deserialized.length);
+            prop_assert_eq!(encrypted.data.len(), deserialized.data.len());
+
+            // Verify that deserialized data can be decrypted correctly
+            let decrypted = fhe.decrypt_f64_vector(&deserialized, scale);
+            prop_assert_eq!(decrypted.len(), glucose.len());
+
+            for (original, decrypted_val) in glucose.iter().zip(decrypted.iter()) {
+                let tolerance = 1.0 / scale + 0.01;
+                prop_assert!((original - decrypted_val).abs() < tolerance);
+            }
+        }
+    }
+}
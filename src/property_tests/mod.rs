@@ -1 +1,3 @@
-pub mod encryption_proptests;
\ No newline at end of file
+pub mod encryption_proptests;
+pub mod heavy_proptests;
+pub mod verification_proptests;
\ No newline at end of file
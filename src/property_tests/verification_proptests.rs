@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod proptests {
+    use crate::computations::*;
+    use proptest::prelude::*;
+
+    /// Strategy for generating any of the supported tolerances
+    fn tolerance() -> impl Strategy<Value = Tolerance> {
+        prop_oneof![
+            (0.0..10.0).prop_map(Tolerance::Absolute),
+            (0.0..1.0).prop_map(Tolerance::Relative),
+            (0.0..10.0, 0.0..1.0)
+                .prop_map(|(absolute, relative)| Tolerance::Both { absolute, relative }),
+        ]
+    }
+
+    proptest! {
+        /// Property: With a zero expectation there is no relative error, and the check
+        /// passes exactly when the absolute error is within the bound
+        #[test]
+        fn prop_zero_expectation_uses_absolute_error(
+            decrypted in -100.0..100.0f64,
+            tolerance in tolerance(),
+        ) {
+            let report = verify(decrypted, 0.0, tolerance);
+
+            prop_assert_eq!(report.rel_error, None);
+            prop_assert_eq!(report.abs_error, decrypted.abs());
+            prop_assert_eq!(report.passed, decrypted.abs() <= report.tolerance_used);
+            // A relative bound alone still accepts some error around zero
+            prop_assert!(report.tolerance_used >= 0.0);
+            if let Tolerance::Relative(relative) = tolerance {
+                prop_assert_eq!(report.tolerance_used, relative);
+            }
+        }
+
+        /// Property: Negating both values gives the same report, so negative
+        /// expectations are checked like positive ones
+        #[test]
+        fn prop_negative_expectation_mirrors_positive(
+            expected in 0.001..1000.0f64,
+            offset in -10.0..10.0f64,
+            tolerance in tolerance(),
+        ) {
+            let positive = verify(expected + offset, expected, tolerance);
+            let negative = verify(-(expected + offset), -expected, tolerance);
+
+            prop_assert_eq!(positive, negative);
+            prop_assert!(negative.rel_error.unwrap() >= 0.0);
+            prop_assert!(negative.tolerance_used >= 0.0);
+        }
+
+        /// Property: A result within the relative tolerance of a negative expectation passes
+        #[test]
+        fn prop_within_relative_tolerance_of_negative_expectation_passes(
+            expected in -1000.0..-0.001f64,
+            relative in 0.01..0.5f64,
+            fraction in -0.99..0.99f64,
+        ) {
+            let decrypted = expected * (1.0 + fraction * relative);
+            let report = verify(decrypted, expected, Tolerance::Relative(relative));
+
+            prop_assert!(report.passed);
+            prop_assert!(report.rel_error.unwrap() <= relative);
+        }
+
+        /// Property: NaN on either side never passes, whatever the tolerance
+        #[test]
+        fn prop_nan_never_passes(value in -1000.0..1000.0f64, tolerance in tolerance()) {
+            let nan_decrypted = verify(f64::NAN, value, tolerance);
+            prop_assert!(!nan_decrypted.passed);
+            prop_assert!(nan_decrypted.abs_error.is_nan());
+
+            let nan_expected = verify(value, f64::NAN, tolerance);
+            prop_assert!(!nan_expected.passed);
+            prop_assert!(nan_expected.abs_error.is_nan());
+
+            prop_assert!(!verify(f64::NAN, f64::NAN, tolerance).passed);
+            prop_assert!(!verify_computation(f64::NAN, value, 0.5));
+        }
+    }
+}
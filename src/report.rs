@@ -0,0 +1,94 @@
+//! Spreadsheet exports of decrypted results
+//! The demo prints how close each decrypted result is to its plaintext counterpart; this
+//! module writes the same comparison to a CSV file, one row per metric.
+// Required libraries
+use csv::Writer; // For writing the CSV file
+use serde::Serialize; // For serializing the rows
+use std::collections::HashMap; // For maps of named results
+use std::error::Error; // For error handling
+use std::path::Path; // For path handling
+
+/// One row of the results CSV
+#[derive(Serialize, Debug)]
+struct ResultRow<'a> {
+    metric: &'a str, // Name of the result
+    plaintext: f64,  // Result computed on plaintext data
+    decrypted: f64,  // Decrypted result of the encrypted computation
+    error: f64,      // Absolute difference between the two
+}
+
+/// Saves plaintext and decrypted results side by side to a CSV file
+///
+/// The file has the header `metric,plaintext,decrypted,error`, where `error` is the
+/// absolute difference between the two results, and one row per metric sorted by name so
+/// that the same results always produce the same file.
+///
+/// # Arguments
+/// * `plaintext` - The results computed on plaintext data, by metric
+/// * `decrypted` - The decrypted results, by the same metrics
+/// * `path` - The file path where the CSV will be written
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok(()) on success, or an error if a metric has no
+///   decrypted result or the file cannot be written
+pub fn save_results_csv(
+    plaintext: &HashMap<String, f64>,
+    decrypted: &HashMap<String, f64>,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut metrics: Vec<&String> = plaintext.keys().collect();
+    metrics.sort();
+
+    let mut wtr = Writer::from_path(path)?;
+    for metric in metrics {
+        let plaintext = plaintext[metric];
+        let decrypted = *decrypted
+            .get(metric)
+            .ok_or_else(|| format!("No decrypted result for {}", metric))?;
+        wtr.serialize(ResultRow {
+            metric,
+            plaintext,
+            decrypted,
+            error: (decrypted - plaintext).abs(),
+        })?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_csv_has_header_and_sorted_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.csv");
+        let plaintext = HashMap::from([
+            ("Average Glucose".to_string(), 101.25),
+            ("Average Age".to_string(), 45.5),
+            ("Blood Type A+".to_string(), 12.0),
+        ]);
+        let decrypted = HashMap::from([
+            ("Average Glucose".to_string(), 101.0),
+            ("Average Age".to_string(), 45.5),
+            ("Blood Type A+".to_string(), 12.0),
+        ]);
+
+        save_results_csv(&plaintext, &decrypted, &path).unwrap();
+
+        let mut rdr = csv::Reader::from_path(&path).unwrap();
+        let header: Vec<String> = rdr.headers().unwrap().iter().map(String::from).collect();
+        assert_eq!(header, ["metric", "plaintext", "decrypted", "error"]);
+        let rows: Vec<csv::StringRecord> = rdr.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), plaintext.len());
+        let metrics: Vec<&str> = rows.iter().map(|row| &row[0]).collect();
+        assert_eq!(metrics, ["Average Age", "Average Glucose", "Blood Type A+"]);
+        assert_eq!(&rows[1][3], "0.25");
+
+        // Every plaintext result needs a decrypted counterpart
+        let missing = HashMap::from([("Average Age".to_string(), 45.5)]);
+        assert!(save_results_csv(&plaintext, &missing, &path).is_err());
+    }
+}
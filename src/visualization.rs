@@ -1,19 +1,54 @@
 /// This module visualizes the data using the plotters library.
 // Required libraries
 use plotters::prelude::*; // A plotting library for Rust
+use plotters::style::text_anchor::{HPos, Pos, VPos}; // Anchors for positioning labels
 use std::collections::HashMap; // A collection type that stores key-value pairs
 use std::error::Error; // A trait for error handling
 use std::path::Path; // A type that represents a file path
 use std::time::Duration; // A type that represents a span of time
 
+/// Colors and font used by the comparison and performance charts
+#[derive(Clone, Debug)]
+pub struct ChartTheme {
+    pub plaintext_color: RGBColor,   // Color of the plaintext bars
+    pub encrypted_color: RGBColor,   // Color of the encrypted bars
+    pub performance_color: RGBColor, // Color of the performance bars
+    pub background: RGBColor,        // Background color of the chart
+    pub font: String,                // Font family of the caption and labels
+}
+
+impl Default for ChartTheme {
+    fn default() -> Self {
+        ChartTheme {
+            plaintext_color: BLUE,
+            encrypted_color: RED,
+            performance_color: GREEN,
+            background: WHITE,
+            font: "sans-serif".to_string(),
+        }
+    }
+}
+
+/// Creates a bar chart comparing plaintext and encrypted results
+///
+/// # Arguments
+/// * `plaintext_results` - The results computed on plaintext data, by name
+/// * `encrypted_results` - The decrypted results of the encrypted computations, by name
+/// * `title` - The title of the chart
+/// * `output_path` - The path of the PNG file to write
+/// * `theme` - The colors and font to use, or `None` for the default theme
 pub fn plot_comparison(
     plaintext_results: &HashMap<String, f64>,
     encrypted_results: &HashMap<String, f64>,
     title: &str,
     output_path: &Path,
+    theme: Option<&ChartTheme>,
 ) -> Result<(), Box<dyn Error>> {
+    let default_theme = ChartTheme::default();
+    let theme = theme.unwrap_or(&default_theme);
+
     let root = BitMapBackend::new(output_path, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
+    root.fill(&theme.background)?;
 
     let max_value = plaintext_results
         .values()
@@ -21,86 +56,132 @@ pub fn plot_comparison(
         .fold(0.0f64, |a, &b| a.max(b))
         * 1.2;
 
+    // Sort the categories once, so each bar pair and its label refer to the same category
+    let mut categories: Vec<&String> = plaintext_results.keys().collect();
+    categories.sort();
+
     let mut chart = ChartBuilder::on(&root)
-        .caption(title, ("sans-serif", 20).into_font())
+        .caption(title, (theme.font.as_str(), 20).into_font())
         .margin(10)
         .x_label_area_size(40)
         .y_label_area_size(60)
-        .build_cartesian_2d(0.0..plaintext_results.len() as f64, 0.0..max_value)?;
+        .build_cartesian_2d(0.0..categories.len() as f64, 0.0..max_value)?;
 
     chart
         .configure_mesh()
-        .x_labels(plaintext_results.len())
+        .x_labels(categories.len())
         .x_label_formatter(&|x| {
-            plaintext_results
-                .keys()
-                .nth(*x as usize)
-                .cloned()
+            categories
+                .get(*x as usize)
+                .map(|category| category.to_string())
                 .unwrap_or_default()
         })
         .y_desc("Value")
         .draw()?;
 
     // Draw plaintext bars
-    chart.draw_series(plaintext_results.values().enumerate().map(|(i, &value)| {
+    chart.draw_series(categories.iter().enumerate().map(|(i, &key)| {
+        let value = plaintext_results[key];
         let x0 = i as i32;
         let _x1 = x0 + 1;
         let bar_width = 0.3;
 
         Rectangle::new(
             [(x0 as f64 + 0.2, 0.0), (x0 as f64 + 0.2 + bar_width, value)],
-            BLUE.filled(),
+            theme.plaintext_color.filled(),
         )
     }))?;
 
     // Draw encrypted bars
-    chart.draw_series(encrypted_results.values().enumerate().map(|(i, &value)| {
+    chart.draw_series(categories.iter().enumerate().filter_map(|(i, &key)| {
+        let value = *encrypted_results.get(key)?;
         let x0 = i as i32;
         let _x1 = x0 + 1;
         let bar_width = 0.3;
 
-        Rectangle::new(
+        Some(Rectangle::new(
             [(x0 as f64 + 0.5, 0.0), (x0 as f64 + 0.5 + bar_width, value)],
-            RED.filled(),
-        )
+            theme.encrypted_color.filled(),
+        ))
+    }))?;
+
+    // Label each pair of bars with the percent error, above the taller bar
+    let label_style = TextStyle::from((theme.font.as_str(), 14).into_font())
+        .color(&BLACK)
+        .pos(Pos::new(HPos::Center, VPos::Bottom));
+    chart.draw_series(categories.iter().enumerate().filter_map(|(i, &key)| {
+        let plaintext = plaintext_results[key];
+        let encrypted = *encrypted_results.get(key)?;
+        let error = percent_error(plaintext, encrypted)?;
+        Some(Text::new(
+            format!("{:.2}%", error),
+            (i as f64 + 0.5, plaintext.max(encrypted)),
+            label_style.clone(),
+        ))
     }))?;
 
     // Add legend
     chart
         .configure_series_labels()
-        .background_style(WHITE.mix(0.8))
+        .background_style(theme.background.mix(0.8))
         .border_style(BLACK)
         .draw()?;
 
     chart
         .draw_series(std::iter::once(PathElement::new(
             vec![(0.0, 0.0), (0.3, 0.0)],
-            BLUE,
+            theme.plaintext_color,
         )))?
         .label("Plaintext")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], theme.plaintext_color));
 
     chart
         .draw_series(std::iter::once(PathElement::new(
             vec![(0.0, 0.0), (0.3, 0.0)],
-            RED,
+            theme.encrypted_color,
         )))?
         .label("Encrypted (FHE)")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], theme.encrypted_color));
 
     root.present()?;
 
     Ok(())
 }
 
+/// Returns the error of an encrypted result relative to its plaintext counterpart
+///
+/// # Arguments
+/// * `plaintext` - The result computed on plaintext data
+/// * `encrypted` - The decrypted result of the encrypted computation
+///
+/// # Returns
+/// * The absolute error as a percentage of the plaintext result, or `None` if the plaintext
+///   result is zero
+fn percent_error(plaintext: f64, encrypted: f64) -> Option<f64> {
+    if plaintext == 0.0 {
+        return None;
+    }
+    Some((encrypted - plaintext).abs() / plaintext.abs() * 100.0)
+}
+
 /// Creates a bar chart showing performance metrics
+///
+/// # Arguments
+/// * `metrics` - The duration of each operation, by name
+/// * `title` - The title of the chart
+/// * `output_path` - The path of the PNG file to write
+/// * `theme` - The colors and font to use, or `None` for the default theme
 pub fn plot_performance_metrics(
     metrics: &HashMap<String, Duration>,
     title: &str,
     output_path: &Path,
+    theme: Option<&ChartTheme>,
 ) -> Result<(), Box<dyn Error>> {
+    let default_theme = ChartTheme::default();
+    let theme = theme.unwrap_or(&default_theme);
+
     let root = BitMapBackend::new(output_path, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
+    root.fill(&theme.background)?;
 
     let max_duration = metrics
         .values()
@@ -109,7 +190,7 @@ pub fn plot_performance_metrics(
     let max_secs = max_duration.as_secs_f64() * 1.2; // 20% margin
 
     let mut chart = ChartBuilder::on(&root)
-        .caption(title, ("sans-serif", 20).into_font())
+        .caption(title, (theme.font.as_str(), 20).into_font())
         .margin(10)
         .x_label_area_size(40)
         .y_label_area_size(60)
@@ -131,7 +212,7 @@ pub fn plot_performance_metrics(
 
         Rectangle::new(
             [(x0 as f64 + 0.2, 0.0), (x0 as f64 + 0.2 + bar_width, secs)],
-            GREEN.filled(),
+            theme.performance_color.filled(),
         )
     }))?;
 
@@ -140,7 +221,7 @@ pub fn plot_performance_metrics(
         let secs = duration.as_secs_f64();
         let label = format!("{:.2}s", secs);
 
-        let style = TextStyle::from(("sans-serif", 15).into_font()).color(&BLACK);
+        let style = TextStyle::from((theme.font.as_str(), 15).into_font()).color(&BLACK);
 
         root.draw_text(
             &label,
@@ -157,6 +238,101 @@ pub fn plot_performance_metrics(
     Ok(())
 }
 
+/// Creates a line chart of how the duration of each phase scales with the dataset size
+///
+/// Each phase is drawn as one line over the dataset sizes, with a marker at every
+/// measurement, so a phase measured at a single size still shows up as a point. Phases
+/// are listed in the legend in alphabetical order.
+///
+/// # Arguments
+/// * `data` - The duration of each phase, by name, for each dataset size
+/// * `title` - The title of the chart
+/// * `output_path` - The path of the PNG file to write
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - An error if the chart cannot be drawn
+#[allow(dead_code)]
+pub fn plot_performance_scaling(
+    data: &[(usize, HashMap<String, Duration>)],
+    title: &str,
+    output_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut points: Vec<(usize, &HashMap<String, Duration>)> = data
+        .iter()
+        .map(|(size, metrics)| (*size, metrics))
+        .collect();
+    points.sort_by_key(|(size, _)| *size);
+    let mut phases: Vec<&String> = points
+        .iter()
+        .flat_map(|(_, metrics)| metrics.keys())
+        .collect();
+    phases.sort();
+    phases.dedup();
+
+    // A single size would make an empty x range, so pad it on both sides
+    let min_size = points.first().map_or(0, |(size, _)| *size) as f64;
+    let max_size = points.last().map_or(0, |(size, _)| *size) as f64;
+    let (min_size, max_size) = if min_size < max_size {
+        (min_size, max_size)
+    } else {
+        (min_size - 1.0, max_size + 1.0)
+    };
+    let max_secs = points
+        .iter()
+        .flat_map(|(_, metrics)| metrics.values())
+        .fold(0.0f64, |a, b| a.max(b.as_secs_f64()))
+        .max(f64::EPSILON)
+        * 1.2; // 20% margin
+
+    let root = BitMapBackend::new(output_path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 20).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(min_size..max_size, 0.0..max_secs)?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Dataset size (records)")
+        .y_desc("Time (seconds)")
+        .draw()?;
+
+    for (i, phase) in phases.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let series: Vec<(f64, f64)> = points
+            .iter()
+            .filter_map(|(size, metrics)| {
+                metrics
+                    .get(*phase)
+                    .map(|duration| (*size as f64, duration.as_secs_f64()))
+            })
+            .collect();
+
+        chart
+            .draw_series(LineSeries::new(series.clone(), color.stroke_width(2)))?
+            .label(phase.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        chart.draw_series(
+            series
+                .into_iter()
+                .map(|point| Circle::new(point, 4, color.filled())),
+        )?;
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    root.present()?;
+
+    Ok(())
+}
+
 /// Creates a visualization of the FHE workflow
 pub fn visualize_fhe_workflow(output_path: &Path) -> Result<(), Box<dyn Error>> {
     let root = BitMapBackend::new(output_path, (1000, 700)).into_drawing_area();
@@ -370,3 +546,349 @@ pub fn visualize_fhe_workflow(output_path: &Path) -> Result<(), Box<dyn Error>>
 
     Ok(())
 }
+
+/// Creates a scatter plot of plaintext values against their decrypted counterparts
+///
+/// Each record is drawn as a point with its plaintext value on the x axis and its
+/// decrypted value on the y axis, next to the line y = x. Points on the line were
+/// decrypted exactly; the vertical distance to it is the encoding error.
+///
+/// # Arguments
+/// * `plaintext` - The plaintext value of each record
+/// * `decrypted` - The decrypted value of each record, in the same order
+/// * `title` - The title of the chart
+/// * `output_path` - The path of the PNG file to write
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - An error if the slices have different lengths or the
+///   chart cannot be drawn
+#[allow(dead_code)]
+pub fn plot_scatter_accuracy(
+    plaintext: &[f64],
+    decrypted: &[f64],
+    title: &str,
+    output_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if plaintext.len() != decrypted.len() {
+        return Err(format!(
+            "Length mismatch: {} plaintext values but {} decrypted values",
+            plaintext.len(),
+            decrypted.len()
+        )
+        .into());
+    }
+
+    // Both axes share one range so that the diagonal is at 45 degrees
+    let (min_value, max_value) = plaintext
+        .iter()
+        .chain(decrypted.iter())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let (min_value, max_value) = if min_value <= max_value {
+        let margin = ((max_value - min_value) * 0.05).max(1.0);
+        (min_value - margin, max_value + margin)
+    } else {
+        (0.0, 1.0)
+    };
+
+    let root = BitMapBackend::new(output_path, (800, 800)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 20).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(min_value..max_value, min_value..max_value)?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Plaintext")
+        .y_desc("Decrypted")
+        .draw()?;
+
+    // Draw the y = x reference line
+    chart
+        .draw_series(LineSeries::new(
+            vec![(min_value, min_value), (max_value, max_value)],
+            BLACK,
+        ))?
+        .label("y = x")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK));
+
+    // Draw one point per record
+    chart
+        .draw_series(
+            plaintext
+                .iter()
+                .zip(decrypted.iter())
+                .map(|(&x, &y)| Circle::new((x, y), 3, BLUE.filled())),
+        )?
+        .label("Records")
+        .legend(|(x, y)| Circle::new((x + 10, y), 3, BLUE.filled()));
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+/// Computes the five-number summary of a series: minimum, first quartile, median, third
+/// quartile and maximum
+///
+/// Quartiles are interpolated linearly between the closest ranks, so series with fewer
+/// than four values still get a summary; a single value yields five equal numbers.
+///
+/// # Arguments
+/// * `values` - The values of the series, in any order
+///
+/// # Returns
+/// * The summary, or `None` if the series is empty
+fn five_number_summary(values: &[f64]) -> Option<[f64; 5]> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let quantile = |q: f64| {
+        let position = q * (sorted.len() - 1) as f64;
+        let (below, above) = (position.floor() as usize, position.ceil() as usize);
+        sorted[below] + (sorted[above] - sorted[below]) * (position - below as f64)
+    };
+    Some([
+        sorted[0],
+        quantile(0.25),
+        quantile(0.5),
+        quantile(0.75),
+        sorted[sorted.len() - 1],
+    ])
+}
+
+/// Creates a box-and-whisker plot with one box per named series
+///
+/// Each box spans the first to the third quartile with a line at the median, and its
+/// whiskers reach the minimum and maximum. Series are drawn in alphabetical order; empty
+/// series keep their place on the axis but have no box.
+///
+/// # Arguments
+/// * `series` - The values of each series, by name
+/// * `title` - The title of the chart
+/// * `output_path` - The path of the PNG file to write
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - An error if the chart cannot be drawn
+#[allow(dead_code)]
+pub fn plot_boxplot(
+    series: &HashMap<String, Vec<f64>>,
+    title: &str,
+    output_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut names: Vec<&String> = series.keys().collect();
+    names.sort();
+    let summaries: Vec<Option<[f64; 5]>> = names
+        .iter()
+        .map(|name| five_number_summary(&series[*name]))
+        .collect();
+
+    let (min_value, max_value) = summaries
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), summary| {
+            (lo.min(summary[0]), hi.max(summary[4]))
+        });
+    let (min_value, max_value) = if min_value <= max_value {
+        let margin = ((max_value - min_value) * 0.1).max(1.0);
+        (min_value - margin, max_value + margin)
+    } else {
+        (0.0, 1.0)
+    };
+
+    let root = BitMapBackend::new(output_path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 20).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..names.len().max(1) as f64, min_value..max_value)?;
+
+    chart
+        .configure_mesh()
+        .x_labels(names.len())
+        .x_label_formatter(&|x| {
+            names
+                .get(*x as usize)
+                .map(|n| n.to_string())
+                .unwrap_or_default()
+        })
+        .y_desc("Value")
+        .draw()?;
+
+    for (i, summary) in summaries.iter().enumerate() {
+        let Some([min, q1, median, q3, max]) = *summary else {
+            continue;
+        };
+        let (left, centre, right) = (i as f64 + 0.25, i as f64 + 0.5, i as f64 + 0.75);
+
+        // Box from the first to the third quartile
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [(left, q1), (right, q3)],
+            BLUE.mix(0.3).filled(),
+        )))?;
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [(left, q1), (right, q3)],
+            BLUE,
+        )))?;
+
+        // Median line, whiskers and their caps
+        let lines = [
+            vec![(left, median), (right, median)],
+            vec![(centre, q3), (centre, max)],
+            vec![(centre, q1), (centre, min)],
+            vec![(centre - 0.1, max), (centre + 0.1, max)],
+            vec![(centre - 0.1, min), (centre + 0.1, min)],
+        ];
+        chart.draw_series(
+            lines
+                .into_iter()
+                .map(|points| PathElement::new(points, BLACK)),
+        )?;
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plot_scatter_accuracy_writes_png() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("scatter.png");
+        let plaintext = [85.51, 110.27, 104.0];
+        let decrypted = [85.5, 110.3, 104.0];
+
+        plot_scatter_accuracy(&plaintext, &decrypted, "Glucose accuracy", &path).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+
+        // Every plaintext value needs a decrypted counterpart
+        assert!(plot_scatter_accuracy(&plaintext, &decrypted[..2], "Mismatch", &path).is_err());
+    }
+
+    #[test]
+    fn test_plot_comparison_labels_percent_errors() {
+        assert_eq!(percent_error(200.0, 201.0), Some(0.5));
+        assert_eq!(percent_error(-50.0, -49.0), Some(2.0));
+        assert_eq!(percent_error(0.0, 0.25), None);
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("comparison.png");
+        let plaintext = HashMap::from([
+            ("Average Glucose Level".to_string(), 200.0),
+            ("Zero Count".to_string(), 0.0),
+        ]);
+        let encrypted = HashMap::from([
+            ("Average Glucose Level".to_string(), 201.0),
+            ("Zero Count".to_string(), 0.0),
+        ]);
+        plot_comparison(&plaintext, &encrypted, "Results", &path, None).unwrap();
+
+        let png = std::fs::read(&path).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn test_charts_render_with_custom_theme() {
+        let dir = tempdir().unwrap();
+        let theme = ChartTheme {
+            plaintext_color: RGBColor(0, 84, 147),
+            encrypted_color: RGBColor(255, 147, 0),
+            performance_color: RGBColor(0, 143, 81),
+            background: RGBColor(250, 248, 240),
+            font: "serif".to_string(),
+        };
+
+        let plaintext = HashMap::from([("Average Age".to_string(), 45.2)]);
+        let encrypted = HashMap::from([("Average Age".to_string(), 45.0)]);
+        let comparison = dir.path().join("comparison.png");
+        plot_comparison(&plaintext, &encrypted, "Results", &comparison, Some(&theme)).unwrap();
+        assert!(std::fs::metadata(&comparison).unwrap().len() > 0);
+
+        let metrics = HashMap::from([("Encryption".to_string(), Duration::from_millis(1500))]);
+        let performance = dir.path().join("performance.png");
+        plot_performance_metrics(&metrics, "Performance", &performance, Some(&theme)).unwrap();
+        assert!(std::fs::metadata(&performance).unwrap().len() > 0);
+    }
+
+    #[test]
+    fn test_plot_performance_scaling_writes_png() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("scaling.png");
+        let data = vec![
+            (
+                100,
+                HashMap::from([
+                    ("Encryption".to_string(), Duration::from_millis(1200)),
+                    ("Computation".to_string(), Duration::from_millis(3400)),
+                ]),
+            ),
+            (
+                500,
+                HashMap::from([
+                    ("Encryption".to_string(), Duration::from_millis(5800)),
+                    ("Computation".to_string(), Duration::from_millis(16100)),
+                ]),
+            ),
+        ];
+
+        plot_performance_scaling(&data, "Performance scaling", &path).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+
+        // A single dataset size is drawn as points
+        let single = dir.path().join("single.png");
+        plot_performance_scaling(&data[..1], "Performance scaling", &single).unwrap();
+        assert!(std::fs::metadata(&single).unwrap().len() > 0);
+    }
+
+    #[test]
+    fn test_five_number_summary() {
+        let summary = five_number_summary(&[7.0, 1.0, 3.0, 5.0, 9.0]).unwrap();
+        assert_eq!(summary, [1.0, 3.0, 5.0, 7.0, 9.0]);
+
+        // Short series interpolate between their values
+        assert_eq!(
+            five_number_summary(&[2.0, 4.0]).unwrap(),
+            [2.0, 2.5, 3.0, 3.5, 4.0]
+        );
+        assert_eq!(five_number_summary(&[6.0]).unwrap(), [6.0; 5]);
+        assert_eq!(five_number_summary(&[]), None);
+    }
+
+    #[test]
+    fn test_plot_boxplot_writes_png() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("boxplot.png");
+        let series = HashMap::from([
+            (
+                "Glucose".to_string(),
+                vec![85.5, 110.25, 104.0, 92.75, 131.0],
+            ),
+            ("Age".to_string(), vec![25.0, 70.0]),
+        ]);
+
+        plot_boxplot(&series, "Decrypted distributions", &path).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+    }
+}
@@ -0,0 +1,285 @@
+//! End-to-end tests of the demo pipeline
+//! These run the real binary on a small generated dataset so that drift between the
+//! keys written by the encryption side and the keys read by the demo is caught.
+#![cfg(feature = "native")]
+
+use std::fs::File;
+use std::process::Command;
+
+use fhe_mini_project::computations::{
+    compute_encrypted_mean_chunked, run_biosample_analysis, verify_computation_with_context,
+};
+use fhe_mini_project::data_generator::{generate_biosample_data, load_biosample_data};
+use fhe_mini_project::encryption::{encrypt_biosample_data, BiosampleFHE, ScaleConfig};
+use fhe_mini_project::fields;
+use tempfile::tempdir;
+
+#[test]
+fn test_encrypted_dataset_contains_analysed_fields() {
+    let fhe = BiosampleFHE::new();
+    let records = generate_biosample_data(20, 42).unwrap();
+    let (encrypted_data, _) =
+        encrypt_biosample_data(&fhe, &records, &ScaleConfig::default()).unwrap();
+
+    for key in [
+        fields::AGE,
+        fields::GLUCOSE,
+        fields::CHOLESTEROL,
+        fields::MARKER,
+        fields::COLLECTION_DAY,
+    ] {
+        assert!(encrypted_data.contains_key(key), "Missing field: {}", key);
+    }
+    for record in &records {
+        let key = fields::blood_type_key(&record.blood_type);
+        assert!(encrypted_data.contains_key(&key), "Missing field: {}", key);
+        let key = fields::facility_key(&record.facility_id.to_string());
+        assert!(encrypted_data.contains_key(&key), "Missing field: {}", key);
+    }
+}
+
+#[test]
+fn test_analysis_post_processing_uses_only_the_context() {
+    let fhe = BiosampleFHE::new();
+    // Variances square every value, so keep the dataset small
+    let records = generate_biosample_data(8, 7).unwrap();
+    let scales = ScaleConfig::new(10.0).with_scale(fields::AGE, 1.0);
+    let (encrypted_data, context) = encrypt_biosample_data(&fhe, &records, &scales).unwrap();
+    assert_eq!(context.record_count, records.len());
+
+    let results = run_biosample_analysis(&encrypted_data, fhe.server_key()).unwrap();
+
+    // Scales and record counts come from the context, not from this test
+    let n = records.len() as f64;
+    for (result, field, plaintext) in [
+        (
+            &results.avg_age,
+            fields::AGE,
+            records.iter().map(|r| r.age as f64).sum::<f64>() / n,
+        ),
+        (
+            &results.avg_glucose,
+            fields::GLUCOSE,
+            records.iter().map(|r| r.glucose_level).sum::<f64>() / n,
+        ),
+        (
+            &results.avg_cholesterol,
+            fields::CHOLESTEROL,
+            records.iter().map(|r| r.cholesterol_level).sum::<f64>() / n,
+        ),
+    ] {
+        let mean = fhe
+            .decrypt_mean(result.as_ref().unwrap(), field, &context)
+            .unwrap();
+        let check = verify_computation_with_context(mean, plaintext, field, &context).unwrap();
+        assert!(check.passed, "{}: {} vs {}", field, mean, plaintext);
+    }
+
+    for (result, field, values) in [
+        (
+            &results.var_glucose,
+            fields::GLUCOSE,
+            records.iter().map(|r| r.glucose_level).collect::<Vec<_>>(),
+        ),
+        (
+            &results.var_cholesterol,
+            fields::CHOLESTEROL,
+            records.iter().map(|r| r.cholesterol_level).collect(),
+        ),
+    ] {
+        let mean = values.iter().sum::<f64>() / n;
+        let plaintext = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        let (variance, _) = fhe
+            .decrypt_variance(result.as_ref().unwrap(), field, &context)
+            .unwrap();
+        let error = (variance - plaintext).abs() / plaintext;
+        assert!(error < 0.05, "{}: {} vs {}", field, variance, plaintext);
+    }
+}
+
+#[test]
+fn test_demo_pipeline_computes_all_averages() {
+    // The demo writes its data and outputs relative to the working directory
+    // and squares every glucose and cholesterol value, so keep the dataset small
+    let work_dir = tempdir().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_fhe_mini_project"))
+        .args(["--samples", "8", "--regenerate", "--no-visualize"])
+        .current_dir(work_dir.path())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "Demo failed\nstdout:\n{}\nstderr:\n{}",
+        stdout,
+        stderr
+    );
+
+    // Every average must have been computed and decrypted
+    for metric in [
+        "Average Age",
+        "Average Glucose Level",
+        "Average Cholesterol Level",
+    ] {
+        assert!(
+            stdout.contains(&format!("Decrypting {}...", metric)),
+            "{} was not computed\nstdout:\n{}",
+            metric,
+            stdout
+        );
+    }
+    assert!(stdout.contains("Glucose-cholesterol correlation"));
+    assert!(stdout.contains("Decrypting Glucose by Marker Alpha..."));
+    assert!(stdout.contains("Decrypting Marker Alpha Count..."));
+    assert!(stdout.contains("Decrypting Cholesterol by Blood Type..."));
+    assert!(stdout.contains("FHE Demo Complete!"));
+}
+
+#[test]
+fn test_demo_pipeline_computes_only_selected_metrics() {
+    let work_dir = tempdir().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_fhe_mini_project"))
+        .args([
+            "--samples",
+            "6",
+            "--regenerate",
+            "--no-visualize",
+            "--metrics",
+            "age,blood_type_counts",
+        ])
+        .current_dir(work_dir.path())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Demo failed\nstdout:\n{}", stdout);
+
+    assert!(stdout.contains("Decrypting Average Age..."));
+    assert!(stdout.contains("Counting blood types..."));
+    for skipped in [
+        "Average Glucose Level",
+        "Average Cholesterol Level",
+        "Running complete biosample analysis",
+    ] {
+        assert!(
+            !stdout.contains(skipped),
+            "{} was computed\nstdout:\n{}",
+            skipped,
+            stdout
+        );
+    }
+}
+
+#[test]
+fn test_demo_pipeline_writes_json_report() {
+    let work_dir = tempdir().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_fhe_mini_project"))
+        .args([
+            "--samples",
+            "6",
+            "--regenerate",
+            "--no-visualize",
+            "--metrics",
+            "age,blood_type_counts",
+            "--json-out",
+            "results.json",
+        ])
+        .current_dir(work_dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Demo failed\nstdout:\n{}", stdout);
+
+    let json = std::fs::read_to_string(work_dir.path().join("results.json")).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(report["records"], 6);
+    let metrics = report["metrics"].as_object().unwrap();
+    assert!(metrics.contains_key("Average Age"), "{}", json);
+    assert!(metrics.keys().any(|name| name.starts_with("Blood Type")));
+    for (metric, entry) in metrics {
+        for key in ["plaintext", "decrypted", "abs_error", "percent_error"] {
+            assert!(
+                entry[key].is_number(),
+                "{}.{} missing\n{}",
+                metric,
+                key,
+                json
+            );
+        }
+        assert!(entry["abs_error"].as_f64().unwrap() < 0.5, "{}", json);
+    }
+    for phase in ["encryption", "computation", "decryption"] {
+        assert!(report["timings"][phase].is_number(), "{} missing", phase);
+    }
+}
+
+#[test]
+fn test_demo_pipeline_reuses_saved_keys() {
+    let work_dir = tempdir().unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_fhe_mini_project"))
+            .args(args)
+            .current_dir(work_dir.path())
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        assert!(output.status.success(), "Demo failed\nstdout:\n{}", stdout);
+        stdout
+    };
+
+    // The first run generates the keys and saves them
+    let stdout = run(&[
+        "--samples",
+        "6",
+        "--regenerate",
+        "--no-visualize",
+        "--metrics",
+        "age",
+        "--save-keys",
+        "keys",
+    ]);
+    assert!(stdout.contains("Generating new FHE keys..."));
+
+    // The second run loads them and streams its ciphertexts into the data directory
+    let stdout = run(&[
+        "--no-visualize",
+        "--metrics",
+        "age",
+        "--chunk-size",
+        "3",
+        "--client-key",
+        "keys/client_key.bin",
+        "--server-key",
+        "keys/server_key.bin",
+    ]);
+    assert!(stdout.contains("Loading FHE keys"));
+    assert!(!stdout.contains("Generating new FHE keys..."));
+
+    // The saved keys decrypt the second run's artifacts
+    let keys = work_dir.path().join("keys");
+    let fhe = BiosampleFHE::load_keys(&keys.join("client_key.bin"), &keys.join("server_key.bin"))
+        .unwrap();
+    let mut artifact = File::open(work_dir.path().join("data/encrypted_age.bin")).unwrap();
+    let mean = compute_encrypted_mean_chunked(&mut artifact, 3, fhe.server_key()).unwrap();
+    let records = load_biosample_data(&work_dir.path().join("data/biosample_data.csv")).unwrap();
+    let expected = records.iter().map(|r| r.age as f64).sum::<f64>() / records.len() as f64;
+    let decrypted = fhe.decrypt_element(&mean, 0, mean.scale).unwrap();
+    assert!(
+        (decrypted - expected).abs() < 0.01,
+        "{} vs {}",
+        decrypted,
+        expected
+    );
+
+    // Keys are only loaded as a pair
+    let output = Command::new(env!("CARGO_BIN_EXE_fhe_mini_project"))
+        .args(["--no-visualize", "--client-key", "keys/client_key.bin"])
+        .current_dir(work_dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--client-key requires --server-key"));
+}